@@ -1,6 +1,11 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nocheat::analyzer::Analyzer;
+use nocheat::options::TrainingConfig;
 use nocheat::types::PlayerStats;
-use nocheat::{build_dataframe, df_to_ndarray, generate_default_model, train_model};
+use nocheat::{
+    build_dataframe, df_to_ndarray, generate_default_model, load_model_from_bytes, train_model,
+    train_model_to_writer,
+};
 use polars::prelude::{col, DataType, IntoLazy};
 use std::collections::HashMap;
 
@@ -28,10 +33,11 @@ fn make_dummy_stats(n: usize) -> Vec<PlayerStats> {
         result.push(PlayerStats {
             player_id: format!("player_{}", i),
             shots_fired: shots,
-            hits: hits,
-            headshots: headshots,
+            hits,
+            headshots,
             shot_timestamps_ms: None,
             training_label: None,
+            ..Default::default()
         });
     }
 
@@ -54,15 +60,16 @@ fn create_training_data(n: usize) -> (Vec<PlayerStats>, Vec<f64>) {
         hits.insert("rifle".to_string(), (100.0 * accuracy) as u32);
 
         let headshot_ratio = 0.1 + (i % 15) as f32 * 0.01; // 10-25% headshots
-        let headshots = ((100.0 * accuracy) as f32 * headshot_ratio) as u32;
+        let headshots = ((100.0 * accuracy) * headshot_ratio) as u32;
 
         players.push(PlayerStats {
             player_id: format!("normal_{}", i),
             shots_fired: shots,
-            hits: hits,
-            headshots: headshots,
+            hits,
+            headshots,
             shot_timestamps_ms: None,
             training_label: None,
+            ..Default::default()
         });
 
         labels.push(0.0);
@@ -80,15 +87,16 @@ fn create_training_data(n: usize) -> (Vec<PlayerStats>, Vec<f64>) {
         hits.insert("rifle".to_string(), (100.0 * accuracy) as u32);
 
         let headshot_ratio = 0.4 + (i % 40) as f32 * 0.01; // 40-80% headshots
-        let headshots = ((100.0 * accuracy) as f32 * headshot_ratio) as u32;
+        let headshots = ((100.0 * accuracy) * headshot_ratio) as u32;
 
         players.push(PlayerStats {
             player_id: format!("cheater_{}", i),
             shots_fired: shots,
-            hits: hits,
-            headshots: headshots,
+            hits,
+            headshots,
             shot_timestamps_ms: None,
             training_label: None,
+            ..Default::default()
         });
 
         labels.push(1.0);
@@ -135,7 +143,7 @@ fn bench_train_model(c: &mut Criterion) {
 
     c.bench_function("train_model_100", |b| {
         b.iter(|| {
-            let _ = train_model(
+            train_model(
                 black_box(training_data.clone()),
                 black_box(labels.clone()),
                 black_box(model_path.to_str().unwrap()),
@@ -154,7 +162,7 @@ fn bench_generate_default_model(c: &mut Criterion) {
 
     c.bench_function("generate_default_model", |b| {
         b.iter(|| {
-            let _ = generate_default_model(black_box(model_path.to_str().unwrap())).unwrap();
+            generate_default_model(black_box(model_path.to_str().unwrap())).unwrap();
         })
     });
 
@@ -162,11 +170,88 @@ fn bench_generate_default_model(c: &mut Criterion) {
     let _ = std::fs::remove_file(&model_path);
 }
 
+fn bench_dedupe_predictions(c: &mut Criterion) {
+    let (training_data, labels) = create_training_data(100);
+    let mut buffer = Vec::new();
+    train_model_to_writer(
+        training_data,
+        labels,
+        &TrainingConfig {
+            seed: Some(42),
+            ..Default::default()
+        },
+        &mut buffer,
+    )
+    .unwrap();
+
+    // 10,000 bot accounts sharing 20 distinct stat lines
+    let mut stats = make_dummy_stats(10_000);
+    for (i, player) in stats.iter_mut().enumerate() {
+        player.hits = make_dummy_stats(20)[i % 20].hits.clone();
+        player.headshots = 10 + (i % 20) as u32;
+    }
+
+    for dedupe in [false, true] {
+        let analyzer = Analyzer::builder()
+            .model(load_model_from_bytes(&buffer).unwrap())
+            .dedupe_predictions(dedupe)
+            .build()
+            .unwrap();
+        let name = if dedupe {
+            "analyze_duplicated_10000_deduped"
+        } else {
+            "analyze_duplicated_10000_naive"
+        };
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let _ = analyzer.analyze(black_box(stats.clone())).unwrap();
+            })
+        });
+    }
+}
+
+fn bench_feature_cache(c: &mut Criterion) {
+    // The same lobby of 10,000 players analyzed again and again
+    let stats = make_dummy_stats(10_000);
+    let (training_data, labels) = create_training_data(100);
+    let mut model_bytes = Vec::new();
+    train_model_to_writer(
+        training_data,
+        labels,
+        &TrainingConfig {
+            seed: Some(42),
+            ..Default::default()
+        },
+        &mut model_bytes,
+    )
+    .unwrap();
+
+    for capacity in [0, 10_000] {
+        let analyzer = Analyzer::builder()
+            .model(load_model_from_bytes(&model_bytes).unwrap())
+            .feature_cache(capacity)
+            .build()
+            .unwrap();
+        let name = if capacity > 0 {
+            "analyze_repeated_10000_cached"
+        } else {
+            "analyze_repeated_10000_uncached"
+        };
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let _ = analyzer.analyze(black_box(stats.clone())).unwrap();
+            })
+        });
+    }
+}
+
 criterion_group!(
     benches,
     bench_build_dataframe,
     bench_df_to_ndarray,
     bench_train_model,
-    bench_generate_default_model
+    bench_generate_default_model,
+    bench_dedupe_predictions,
+    bench_feature_cache
 );
 criterion_main!(benches);
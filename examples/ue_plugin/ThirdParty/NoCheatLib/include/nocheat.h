@@ -55,7 +55,7 @@ typedef struct AnalysisResult {
  * @param stats_json_len Length of the JSON buffer in bytes
  * @param out_json_ptr Pointer to a location where output buffer pointer will be stored
  * @param out_json_len Pointer to a location where output buffer length will be stored
- * @return 0 on success, negative values on error
+ * @return 0 on success, negative values on error (-7: more players than set_max_batch allows)
  */
 NOCHEAT_API int analyze_round(
     const unsigned char* stats_json_ptr,
@@ -64,6 +64,48 @@ NOCHEAT_API int analyze_round(
     size_t* out_json_len
 );
 
+/**
+ * Analyzes player statistics like analyze_round, skipping entries that are
+ * not valid PlayerStats instead of failing the whole batch
+ * @param stats_json_ptr Pointer to UTF-8 encoded JSON array of player stats
+ * @param stats_json_len Length of the JSON buffer in bytes
+ * @param out_json_ptr Pointer to a location where output buffer pointer will be stored;
+ *        the output is {"results": [...], "failed": [{"index": ..., "error": ...}]}
+ * @param out_json_len Pointer to a location where output buffer length will be stored
+ * @return 0 on success, negative values on error
+ */
+NOCHEAT_API int analyze_round_lenient(
+    const unsigned char* stats_json_ptr,
+    size_t stats_json_len,
+    unsigned char** out_json_ptr,
+    size_t* out_json_len
+);
+
+/**
+ * Called once per player result by analyze_round_cb
+ * @param result_json_ptr Pointer to one result serialized as a JSON object;
+ *        only valid during the call, copy it before returning
+ * @param result_json_len Length of the JSON object in bytes
+ */
+typedef void (*nocheat_result_callback)(
+    const unsigned char* result_json_ptr,
+    size_t result_json_len
+);
+
+/**
+ * Analyzes player statistics, streaming each result to a callback instead of
+ * allocating one output buffer
+ * @param stats_json_ptr Pointer to UTF-8 encoded JSON buffer containing player stats
+ * @param stats_json_len Length of the JSON buffer in bytes
+ * @param callback Function invoked once per player, in input order
+ * @return 0 on success, negative values on error (same codes as analyze_round)
+ */
+NOCHEAT_API int analyze_round_cb(
+    const unsigned char* stats_json_ptr,
+    size_t stats_json_len,
+    nocheat_result_callback callback
+);
+
 /**
  * Frees memory allocated by analyze_round
  * @param ptr Pointer to the buffer to free
@@ -74,6 +116,20 @@ NOCHEAT_API void free_buffer(
     size_t len
 );
 
+/**
+ * Limit the number of players analyze_round and analyze_round_cb analyze in
+ * one call; larger batches return -7 and must be split by the caller
+ * @param max_players Maximum number of players per call, or 0 for no limit (the default)
+ */
+NOCHEAT_API void set_max_batch(size_t max_players);
+
+/**
+ * Select indented (for debugging) or compact (the default) JSON output for
+ * every later analyze_round, analyze_round_lenient and analyze_round_cb call
+ * @param pretty Non-zero for indented JSON, 0 for compact JSON
+ */
+NOCHEAT_API void set_pretty_json(int pretty);
+
 /**
  * Set a custom path to load the model from
  * @param path_ptr Pointer to a UTF-8 encoded path string
@@ -89,6 +145,15 @@ NOCHEAT_API int set_model_path(
     size_t path_len
 );
 
+/**
+ * Validate that the current model loads and separates known samples
+ * @return 0 if every check passed, negative values on failure:
+ *         -1: Model couldn't be loaded
+ *         -2: Model misclassified a canonical sample
+ *         -3: Selftest couldn't run
+ */
+NOCHEAT_API int selftest_code(void);
+
 #ifdef __cplusplus
 }
 #endif
\ No newline at end of file
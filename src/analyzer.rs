@@ -0,0 +1,1640 @@
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::audit::AuditSink;
+use crate::collusion::{flag_team_outliers_by_key, team_key, TeamKey};
+use crate::inference::{CacheStats, FeatureCache, FeatureRow};
+use crate::latency::LatencyHistogram;
+use crate::model::{CheatModel, ModelMetadata};
+use crate::options::{AnalysisOptions, FeaturePrecision, Segment, Thresholds, ZeroDivision};
+use crate::types::{
+    AnalysisOutcome, AnalysisResponse, EntryError, Flag, LenientResponse, Metrics, PlayerResult,
+    PlayerStats, SweepResult,
+};
+
+/// Where an `Analyzer` gets its model from
+#[derive(Debug, Clone)]
+enum ModelSource {
+    /// The global model at the current model path (see `set_model_path`)
+    Current,
+    /// A model loaded when the analyzer was built
+    Loaded(Arc<CheatModel>),
+    /// A model that failed to load, analyzed with the heuristic fallback
+    Unavailable(String),
+}
+
+/// Custom flag rule registered with `AnalyzerBuilder::flag_rule`.
+///
+/// The predicate sees the engineered features of a player and the rule adds
+/// `flag` to the player's result when it returns true, which lets operators
+/// encode bespoke (e.g. compound) rules next to the built-in flags.
+#[derive(Clone)]
+pub struct FlagRule {
+    flag: String,
+    predicate: Arc<dyn Fn(&FeatureRow) -> bool + Send + Sync>,
+}
+
+impl FlagRule {
+    /// A rule adding `flag` to every player for which `predicate` holds
+    pub fn new(
+        flag: impl Into<String>,
+        predicate: impl Fn(&FeatureRow) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            flag: flag.into(),
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    /// Name of the flag added by the rule
+    pub fn flag(&self) -> &str {
+        &self.flag
+    }
+}
+
+impl std::fmt::Debug for FlagRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FlagRule")
+            .field("flag", &self.flag)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Model and thresholds of a region/platform segment, see `AnalyzerBuilder::segment`
+#[derive(Debug, Default)]
+pub struct SegmentConfig {
+    /// Model scoring the segment, or `None` to use the analyzer's model
+    pub model: Option<CheatModel>,
+    /// Thresholds of the segment, or `None` to use the analyzer's thresholds
+    pub thresholds: Option<Thresholds>,
+}
+
+/// A configured segment, ready to analyze
+#[derive(Debug, Clone)]
+struct SegmentRoute {
+    model: Option<Arc<CheatModel>>,
+    options: AnalysisOptions,
+}
+
+/// Share a model between several analyses, copying the load error if there is none
+fn share_model(model: &Result<Arc<CheatModel>>) -> Result<Arc<CheatModel>> {
+    match model {
+        Ok(model) => Ok(Arc::clone(model)),
+        Err(error) => Err(anyhow::anyhow!("{}", error)),
+    }
+}
+
+/// Configured analysis pipeline.
+///
+/// Collects the model, thresholds and output filters in one place instead of
+/// a growing set of `analyze_stats_with_*` functions. `analyze_stats` is
+/// equivalent to `Analyzer::default().analyze(stats)`.
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyzer::Analyzer;
+/// use nocheat::options::Thresholds;
+/// use nocheat::types::PlayerStats;
+///
+/// let analyzer = Analyzer::builder()
+///     .model_path("models/cheat_model.bin")
+///     .thresholds(Thresholds {
+///         high_hit_rate: 0.85,
+///         ..Default::default()
+///     })
+///     .only_flagged(true)
+///     .build()
+///     .expect("Failed to build analyzer");
+///
+/// let stats: Vec<PlayerStats> = Vec::new();
+/// let response = analyzer.analyze(stats).expect("Analysis failed");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Analyzer {
+    model: ModelSource,
+    options: AnalysisOptions,
+    only_flagged: bool,
+    segments: HashMap<Segment, SegmentRoute>,
+    rules: Vec<FlagRule>,
+    latency: Option<Arc<LatencyHistogram>>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self {
+            model: ModelSource::Current,
+            options: AnalysisOptions::default(),
+            only_flagged: false,
+            segments: HashMap::new(),
+            rules: Vec::new(),
+            latency: None,
+        }
+    }
+}
+
+impl Analyzer {
+    /// Start configuring an analyzer
+    pub fn builder() -> AnalyzerBuilder {
+        AnalyzerBuilder::default()
+    }
+
+    /// Options applied to every analysis
+    pub fn options(&self) -> &AnalysisOptions {
+        &self.options
+    }
+
+    /// Feature cache shared by the analyses of this analyzer, if enabled
+    pub fn feature_cache(&self) -> Option<&FeatureCache> {
+        self.options.feature_cache.as_deref()
+    }
+
+    /// The 50th, 95th and 99th percentiles of the analysis latency since the
+    /// analyzer was built or `reset_latency` was called.
+    ///
+    /// Every model pass counts once: one per call, or one per chunk of
+    /// `analyze_cancellable`. All three are zero when nothing was recorded or
+    /// tracking is disabled (see `AnalyzerBuilder::track_latency`).
+    pub fn latency_percentiles(&self) -> (Duration, Duration, Duration) {
+        self.latency.as_ref().map_or(
+            (Duration::ZERO, Duration::ZERO, Duration::ZERO),
+            |histogram| histogram.percentiles(),
+        )
+    }
+
+    /// Latency histogram of this analyzer, if tracking is enabled
+    pub fn latency_histogram(&self) -> Option<&LatencyHistogram> {
+        self.latency.as_deref()
+    }
+
+    /// Forget the latencies recorded so far, e.g. at the start of an SLO window
+    pub fn reset_latency(&self) {
+        if let Some(histogram) = &self.latency {
+            histogram.reset();
+        }
+    }
+
+    /// Analyze player statistics with this configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AnalysisResponse>` - The analysis results, restricted to flagged
+    ///   players when `only_flagged` is set
+    pub fn analyze(&self, stats: Vec<PlayerStats>) -> Result<AnalysisResponse> {
+        self.analyze_with_cache_stats(stats)
+            .map(|(response, _)| response)
+    }
+
+    /// Analyze the statistics of a single player.
+    ///
+    /// Shorthand for the common "check one player" case. `only_flagged` does
+    /// not apply, so a clean player still gets a result.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - The player's statistics
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PlayerResult>` - The result of the player
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::analyzer::Analyzer;
+    /// use nocheat::types::PlayerStats;
+    /// use std::collections::HashMap;
+    ///
+    /// let stats = PlayerStats {
+    ///     player_id: "player123".to_string(),
+    ///     shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+    ///     hits: HashMap::from([("rifle".to_string(), 50)]),
+    ///     headshots: 10,
+    ///     ..Default::default()
+    /// };
+    /// let result = Analyzer::default().analyze_player(stats).expect("Analysis failed");
+    /// println!("{}: {}", result.player_id, result.suspicion_score);
+    /// ```
+    pub fn analyze_player(&self, stats: PlayerStats) -> Result<PlayerResult> {
+        let player_id = stats.player_id.clone();
+        let (response, _) = self.run(vec![stats], self.model(), &mut Metrics::default())?;
+        response
+            .results
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Analysis of player '{}' produced no result", player_id))
+    }
+
+    /// Analyze player statistics, also reporting the prediction cache hit rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(AnalysisResponse, CacheStats)>` - The same response as
+    ///   `analyze`, and how many predictions were shared between players with
+    ///   identical model inputs (see `AnalysisOptions::dedupe_predictions`)
+    pub fn analyze_with_cache_stats(
+        &self,
+        stats: Vec<PlayerStats>,
+    ) -> Result<(AnalysisResponse, CacheStats)> {
+        let (mut response, cache) = self.run(stats, self.model(), &mut Metrics::default())?;
+
+        if self.only_flagged {
+            response.results.retain(PlayerResult::is_flagged);
+        }
+        Ok((response, cache))
+    }
+
+    /// Analyze player statistics, also reporting where the time went.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(AnalysisResponse, Metrics)>` - The same response as `analyze`,
+    ///   and the time spent building the DataFrame, computing features and
+    ///   scoring players
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::analyzer::Analyzer;
+    /// use nocheat::types::PlayerStats;
+    ///
+    /// let stats: Vec<PlayerStats> = Vec::new();
+    /// let (response, metrics) = Analyzer::default().analyze_timed(stats).expect("Analysis failed");
+    /// println!(
+    ///     "{} players in {:.1} ms ({:.1} ms inference)",
+    ///     response.results.len(),
+    ///     metrics.total_ms,
+    ///     metrics.inference_ms
+    /// );
+    /// ```
+    pub fn analyze_timed(&self, stats: Vec<PlayerStats>) -> Result<(AnalysisResponse, Metrics)> {
+        let started = Instant::now();
+        let mut metrics = Metrics::default();
+        let (mut response, _) = self.run(stats, self.model(), &mut metrics)?;
+
+        if self.only_flagged {
+            response.results.retain(PlayerResult::is_flagged);
+        }
+        metrics.total_ms = Metrics::elapsed_ms(started);
+        Ok((response, metrics))
+    }
+
+    /// Analyze a JSON array of PlayerStats, skipping the entries that are not
+    /// valid PlayerStats instead of failing the whole batch.
+    ///
+    /// The input must still be a syntactically valid JSON array; only entries
+    /// with missing or mistyped fields are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - UTF-8 encoded JSON array of PlayerStats objects
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LenientResponse>` - The results of the valid entries and the
+    ///   index and parse error of every skipped one
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::analyzer::Analyzer;
+    ///
+    /// let json = br#"[{"player_id": "a", "shots_fired": {}, "hits": {}, "headshots": 0},
+    ///                 {"player_id": "b"}]"#;
+    /// let response = Analyzer::default().analyze_json_lenient(json).expect("Invalid JSON");
+    /// assert_eq!(response.results.len(), 1);
+    /// assert_eq!(response.failed[0].index, 1);
+    /// ```
+    pub fn analyze_json_lenient(&self, json: &[u8]) -> Result<LenientResponse> {
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(json)?;
+        let mut stats = Vec::with_capacity(entries.len());
+        let mut failed = Vec::new();
+        for (index, entry) in entries.into_iter().enumerate() {
+            match serde_json::from_value::<PlayerStats>(entry) {
+                Ok(entry) => stats.push(entry),
+                Err(error) => failed.push(EntryError {
+                    index,
+                    error: error.to_string(),
+                }),
+            }
+        }
+        let results = self.analyze(stats)?.results;
+        Ok(LenientResponse { results, failed })
+    }
+
+    /// Score player statistics once and report who is flagged at each threshold.
+    ///
+    /// A player is flagged at a threshold when its suspicion score reaches it;
+    /// `only_flagged` does not apply. See `AnalysisResponse::sweep`.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    /// * `thresholds` - Suspicion thresholds to compare, e.g. `[0.5, 0.7, 0.9]`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SweepResult>` - The flagged count and players of every threshold
+    pub fn analyze_sweep(
+        &self,
+        stats: Vec<PlayerStats>,
+        thresholds: &[f32],
+    ) -> Result<SweepResult> {
+        let (response, _) = self.run(stats, self.model(), &mut Metrics::default())?;
+        Ok(response.sweep(thresholds))
+    }
+
+    /// Analyze player statistics, also reporting the inputs the model ignored.
+    ///
+    /// Fields the active model does not read are otherwise silently dropped,
+    /// which hides integration bugs such as a game sending timestamps or
+    /// attributes to a model trained without them. See
+    /// `ModelMetadata::ignored_fields` for which fields are reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(AnalysisResponse, Vec<String>)>` - The same response as
+    ///   `analyze`, and the optional fields provided in the batch that the
+    ///   model ignored (every one of them under the heuristic fallback)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::analyzer::Analyzer;
+    /// use nocheat::types::PlayerStats;
+    ///
+    /// let stats: Vec<PlayerStats> = Vec::new();
+    /// let (response, ignored_fields) = Analyzer::default()
+    ///     .analyze_with_diagnostics(stats)
+    ///     .expect("Analysis failed");
+    /// for field in ignored_fields {
+    ///     eprintln!("warning: the model ignores {}", field);
+    /// }
+    /// ```
+    pub fn analyze_with_diagnostics(
+        &self,
+        stats: Vec<PlayerStats>,
+    ) -> Result<(AnalysisResponse, Vec<String>)> {
+        let model = self.model();
+        let ignored_fields = match &model {
+            Ok(model) => model.metadata.ignored_fields(&stats),
+            Err(_) => ModelMetadata::legacy().ignored_fields(&stats),
+        };
+        let (mut response, _) = self.run(stats, model, &mut Metrics::default())?;
+
+        if self.only_flagged {
+            response.results.retain(PlayerResult::is_flagged);
+        }
+        Ok((response, ignored_fields))
+    }
+
+    /// Analyze player statistics in chunks, stopping early once `cancel` is set.
+    ///
+    /// The flag is checked before every chunk of `AnalysisOptions::chunk_size`
+    /// players, so a cancelled analysis returns the results of the chunks it
+    /// already finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    /// * `cancel` - Set by the caller (e.g. from another thread) to abort the analysis
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AnalysisOutcome>` - The full response, or the partial one if cancelled
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::analyzer::Analyzer;
+    /// use nocheat::types::PlayerStats;
+    /// use std::sync::atomic::AtomicBool;
+    ///
+    /// let cancel = AtomicBool::new(false);
+    /// let stats: Vec<PlayerStats> = Vec::new();
+    /// let outcome = Analyzer::default()
+    ///     .analyze_cancellable(stats, &cancel)
+    ///     .expect("Analysis failed");
+    /// if outcome.is_cancelled() {
+    ///     println!("Aborted, {} players analyzed", outcome.into_response().results.len());
+    /// }
+    /// ```
+    pub fn analyze_cancellable(
+        &self,
+        stats: Vec<PlayerStats>,
+        cancel: &AtomicBool,
+    ) -> Result<AnalysisOutcome> {
+        self.analyze_chunks(stats, cancel, &mut |_| {})
+    }
+
+    /// Chunked analysis behind `analyze_cancellable`; `after_chunk` receives
+    /// the number of players analyzed so far
+    pub(crate) fn analyze_chunks(
+        &self,
+        stats: Vec<PlayerStats>,
+        cancel: &AtomicBool,
+        after_chunk: &mut dyn FnMut(usize),
+    ) -> Result<AnalysisOutcome> {
+        let model = self.model();
+        let mut response = AnalysisResponse { results: vec![] };
+        let mut analyzed = 0;
+        for chunk in stats.chunks(self.options.chunk_size.max(1)) {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(AnalysisOutcome::Cancelled(response));
+            }
+            let (mut partial, _) =
+                self.run(chunk.to_vec(), share_model(&model), &mut Metrics::default())?;
+            if self.only_flagged {
+                partial.results.retain(PlayerResult::is_flagged);
+            }
+            response = response.merge(partial);
+            analyzed += chunk.len();
+            after_chunk(analyzed);
+        }
+        Ok(AnalysisOutcome::Completed(response))
+    }
+
+    /// Analyze players with `run_teams`, recording the latency when tracking
+    /// is enabled
+    fn run(
+        &self,
+        stats: Vec<PlayerStats>,
+        model: Result<Arc<CheatModel>>,
+        metrics: &mut Metrics,
+    ) -> Result<(AnalysisResponse, CacheStats)> {
+        let Some(histogram) = &self.latency else {
+            return self.run_teams(stats, model, metrics);
+        };
+        let started = Instant::now();
+        let outcome = self.run_teams(stats, model, metrics);
+        histogram.record(started.elapsed());
+        outcome
+    }
+
+    /// Analyze players with the custom rules, then compare every player with
+    /// its teammates when `AnalysisOptions::team_outlier_margin` is set
+    fn run_teams(
+        &self,
+        stats: Vec<PlayerStats>,
+        model: Result<Arc<CheatModel>>,
+        metrics: &mut Metrics,
+    ) -> Result<(AnalysisResponse, CacheStats)> {
+        if let Some(margin) = self.options.team_outlier_margin {
+            let teams: Vec<Option<TeamKey>> = stats.iter().map(team_key).collect();
+            let (mut response, cache) = self.run_rules(stats, model, metrics)?;
+            flag_team_outliers_by_key(&teams, &mut response.results, margin);
+            return Ok((response, cache));
+        }
+        self.run_rules(stats, model, metrics)
+    }
+
+    /// Analyze players as routed by `run_segments`, then add the flags of the
+    /// custom rules to every scored player
+    fn run_rules(
+        &self,
+        stats: Vec<PlayerStats>,
+        model: Result<Arc<CheatModel>>,
+        metrics: &mut Metrics,
+    ) -> Result<(AnalysisResponse, CacheStats)> {
+        if self.rules.is_empty() {
+            return self.run_segments(stats, model, metrics);
+        }
+        let rows: Vec<FeatureRow> = stats
+            .iter()
+            .map(|player| {
+                FeatureRow::from_stats_using(
+                    player,
+                    self.options.feature_precision,
+                    self.options.zero_division,
+                )
+            })
+            .collect();
+        let (mut response, cache) = self.run_segments(stats, model, metrics)?;
+        for (result, row) in response.results.iter_mut().zip(&rows) {
+            if result.has_flag(Flag::Whitelisted) {
+                continue;
+            }
+            for rule in self.rules.iter().filter(|rule| (rule.predicate)(row)) {
+                if !result.flags.contains(&rule.flag) {
+                    result.flags.push(rule.flag.clone());
+                }
+            }
+        }
+        Ok((response, cache))
+    }
+
+    /// Analyze players with `model` and the analyzer's options, except for the
+    /// players of a configured segment, which get the segment's model and
+    /// thresholds. Results keep the input order.
+    fn run_segments(
+        &self,
+        stats: Vec<PlayerStats>,
+        model: Result<Arc<CheatModel>>,
+        metrics: &mut Metrics,
+    ) -> Result<(AnalysisResponse, CacheStats)> {
+        if self.segments.is_empty() {
+            return crate::analyze_or_fallback(stats, model, &self.options, metrics);
+        }
+
+        // Group players by segment, None standing for the default route
+        let mut groups: Vec<(Option<&Segment>, Vec<usize>, Vec<PlayerStats>)> = Vec::new();
+        for (index, player) in stats.into_iter().enumerate() {
+            let segment = Segment::candidates(&player)
+                .iter()
+                .find_map(|candidate| self.segments.get_key_value(candidate))
+                .map(|(segment, _)| segment);
+            match groups.iter_mut().find(|(group, ..)| *group == segment) {
+                Some((_, indices, players)) => {
+                    indices.push(index);
+                    players.push(player);
+                }
+                None => groups.push((segment, vec![index], vec![player])),
+            }
+        }
+
+        let mut results = Vec::new();
+        let mut cache = CacheStats::default();
+        for (segment, indices, players) in groups {
+            let route = segment.map(|segment| &self.segments[segment]);
+            let group_model = match route.and_then(|route| route.model.as_ref()) {
+                Some(segment_model) => Ok(Arc::clone(segment_model)),
+                None => share_model(&model),
+            };
+            let options = route.map_or(&self.options, |route| &route.options);
+            let mut group_metrics = Metrics::default();
+            let (response, group_cache) =
+                crate::analyze_or_fallback(players, group_model, options, &mut group_metrics)?;
+            metrics.build_ms += group_metrics.build_ms;
+            metrics.feature_ms += group_metrics.feature_ms;
+            metrics.inference_ms += group_metrics.inference_ms;
+            cache.lookups += group_cache.lookups;
+            cache.hits += group_cache.hits;
+            results.extend(indices.into_iter().zip(response.results));
+        }
+        results.sort_by_key(|(index, _)| *index);
+        let results = results.into_iter().map(|(_, result)| result).collect();
+        Ok((AnalysisResponse { results }, cache))
+    }
+
+    fn model(&self) -> Result<Arc<CheatModel>> {
+        match &self.model {
+            ModelSource::Current => crate::current_model(),
+            ModelSource::Loaded(model) => Ok(Arc::clone(model)),
+            ModelSource::Unavailable(error) => Err(anyhow::anyhow!("{}", error)),
+        }
+    }
+
+    /// Analyze player statistics and record every flagged player in `sink`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Destination of the flagged results
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AnalysisResponse>` - The same response as `analyze`
+    pub fn analyze_and_audit(
+        &self,
+        sink: &mut dyn AuditSink,
+        stats: Vec<PlayerStats>,
+    ) -> Result<AnalysisResponse> {
+        let response = self.analyze(stats)?;
+        for result in response.results.iter().filter(|r| r.is_flagged()) {
+            sink.record(result)?;
+        }
+        Ok(response)
+    }
+
+    /// Analyze player statistics, first loading the replay of every player that references one.
+    ///
+    /// For players with a `replay_path`, the shots of the replay fill in
+    /// `shot_timestamps_ms` and `view_angles` when those are absent, enabling
+    /// the timing checks. Paths are resolved against `replay_root` and may not
+    /// leave it (see `replay::attach_replay`). A replay file that does not
+    /// exist is skipped and the player is analyzed from its counts alone.
+    ///
+    /// # Arguments
+    ///
+    /// * `replay_root` - Directory holding the replays players may reference
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AnalysisResponse>` - The same response as `analyze` on the
+    ///   completed stats, or an error when an existing replay lies outside
+    ///   `replay_root`, is too large or cannot be parsed
+    pub fn analyze_with_replays(
+        &self,
+        replay_root: &Path,
+        mut stats: Vec<PlayerStats>,
+    ) -> Result<AnalysisResponse> {
+        for player in &mut stats {
+            crate::replay::attach_replay(player, replay_root)?;
+        }
+        self.analyze(stats)
+    }
+
+    /// Analyze every JSON file of a `.tar` or `.tar.gz` archive.
+    ///
+    /// Each `.json` entry must hold a `Vec<PlayerStats>`; other entries are
+    /// skipped. Entries are analyzed in archive order and every result records
+    /// the name of the entry it came from in `source`. Gzip compression is
+    /// detected from the file contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the archive
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AnalysisResponse>` - The results of all entries, in archive order
+    pub fn analyze_archive(&self, path: &str) -> Result<AnalysisResponse> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let is_gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        let reader: Box<dyn Read> = if is_gzip {
+            Box::new(GzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+
+        let mut responses = Vec::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if !entry.header().entry_type().is_file() || !name.ends_with(".json") {
+                continue;
+            }
+
+            let stats: Vec<PlayerStats> = serde_json::from_reader(entry)
+                .map_err(|e| anyhow::anyhow!("Invalid JSON in {}: {}", name, e))?;
+            let mut response = self.analyze(stats)?;
+            for result in &mut response.results {
+                result.source = Some(name.clone());
+            }
+            responses.push(response);
+        }
+        Ok(AnalysisResponse::merge_all(responses))
+    }
+}
+
+/// Builder for `Analyzer`, created with `Analyzer::builder()`
+#[derive(Debug, Default)]
+pub struct AnalyzerBuilder {
+    model_path: Option<String>,
+    model: Option<CheatModel>,
+    options: AnalysisOptions,
+    only_flagged: bool,
+    segments: HashMap<Segment, SegmentConfig>,
+    rules: Vec<FlagRule>,
+    track_latency: bool,
+}
+
+impl AnalyzerBuilder {
+    /// Load the model from this path when building, instead of using the global model
+    pub fn model_path(mut self, path: &str) -> Self {
+        self.model_path = Some(path.to_string());
+        self
+    }
+
+    /// Use an already loaded model; takes precedence over `model_path`
+    pub fn model(mut self, model: CheatModel) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Thresholds used by the rule-based flags
+    pub fn thresholds(mut self, thresholds: Thresholds) -> Self {
+        self.options.thresholds = thresholds;
+        self
+    }
+
+    /// Replace all analysis options at once
+    pub fn options(mut self, options: AnalysisOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Clamp every `suspicion_score` to [0.0, 1.0] (enabled by default)
+    pub fn clamp_scores(mut self, clamp: bool) -> Self {
+        self.options.clamp_scores = clamp;
+        self
+    }
+
+    /// Fall back to the heuristics when the model cannot be loaded
+    pub fn heuristic_fallback(mut self, enabled: bool) -> Self {
+        self.options.heuristic_fallback = enabled;
+        self
+    }
+
+    /// Flag players whose `training_label` disagrees with the model
+    pub fn check_labels(mut self, enabled: bool) -> Self {
+        self.options.check_labels = enabled;
+        self
+    }
+
+    /// Precision of the feature computation; match the precision the model was trained with
+    pub fn feature_precision(mut self, precision: FeaturePrecision) -> Self {
+        self.options.feature_precision = precision;
+        self
+    }
+
+    /// Players that are never scored (see `AnalysisOptions::whitelist`)
+    pub fn whitelist(mut self, player_ids: HashSet<String>) -> Self {
+        self.options.whitelist = player_ids;
+        self
+    }
+
+    /// Score players with more hits than shots as cheaters without running the model
+    pub fn flag_impossible_hit_rate(mut self, enabled: bool) -> Self {
+        self.options.flag_impossible_hit_rate = enabled;
+        self
+    }
+
+    /// Cache the feature rows of up to `capacity` distinct players across
+    /// analyses (see `inference::FeatureCache`); 0 disables the cache, the
+    /// default
+    pub fn feature_cache(mut self, capacity: usize) -> Self {
+        self.options.feature_cache = (capacity > 0).then(|| Arc::new(FeatureCache::new(capacity)));
+        self
+    }
+
+    /// Run the model once per distinct feature vector of a batch (enabled by default)
+    pub fn dedupe_predictions(mut self, enabled: bool) -> Self {
+        self.options.dedupe_predictions = enabled;
+        self
+    }
+
+    /// Report the decision boundary distance of every model-scored player
+    pub fn include_margin(mut self, enabled: bool) -> Self {
+        self.options.include_margin = enabled;
+        self
+    }
+
+    /// Number of players analyzed between two cancellation checks
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.options.chunk_size = chunk_size;
+        self
+    }
+
+    /// Handling of zero denominators in the engineered features
+    pub fn zero_division(mut self, zero_division: ZeroDivision) -> Self {
+        self.options.zero_division = zero_division;
+        self
+    }
+
+    /// Suspicion score at or above which a player's `predicted_label` is true
+    pub fn label_threshold(mut self, threshold: f32) -> Self {
+        self.options.label_threshold = threshold;
+        self
+    }
+
+    /// Merge weapons whose names only differ in case or surrounding whitespace
+    pub fn normalize_weapons(mut self, enabled: bool) -> Self {
+        self.options.normalize_weapons = enabled;
+        self
+    }
+
+    /// Total shots below which a player's verdict is `Insufficient`
+    pub fn min_shots(mut self, min_shots: u32) -> Self {
+        self.options.min_shots = min_shots;
+        self
+    }
+
+    /// Maximum number of threads scoring a batch, or `None` to use every available core;
+    /// `Some(1)` scores it on the calling thread
+    pub fn max_threads(mut self, max_threads: Option<usize>) -> Self {
+        self.options.max_threads = max_threads;
+        self
+    }
+
+    /// Flag players scoring more than `margin` above their teammates' mean, or `None` to disable
+    pub fn team_outlier_margin(mut self, margin: Option<f32>) -> Self {
+        self.options.team_outlier_margin = margin;
+        self
+    }
+
+    /// Maximum number of distinct weapons per player, or `None` for no limit
+    pub fn max_weapons(mut self, max_weapons: Option<usize>) -> Self {
+        self.options.max_weapons = max_weapons;
+        self
+    }
+
+    /// Only return players for which `PlayerResult::is_flagged` holds
+    pub fn only_flagged(mut self, enabled: bool) -> Self {
+        self.only_flagged = enabled;
+        self
+    }
+
+    /// Score the players of a region/platform segment with its own model and thresholds.
+    ///
+    /// Players are routed by their `region` and `platform` fields to the most
+    /// specific matching segment (see `Segment`); players matching no segment
+    /// use the analyzer's model and thresholds. Options other than the
+    /// thresholds are shared by every segment.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::analyzer::{Analyzer, SegmentConfig};
+    /// use nocheat::load_model;
+    /// use nocheat::options::Segment;
+    ///
+    /// let console_model = load_model("models/console_model.bin").expect("Failed to load model");
+    /// let analyzer = Analyzer::builder()
+    ///     .model_path("models/cheat_model.bin")
+    ///     .segment(
+    ///         Segment::platform("console"),
+    ///         SegmentConfig {
+    ///             model: Some(console_model),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .build()
+    ///     .expect("Failed to build analyzer");
+    /// ```
+    pub fn segment(mut self, segment: Segment, config: SegmentConfig) -> Self {
+        self.segments.insert(segment, config);
+        self
+    }
+
+    /// Add a custom flag rule, evaluated on the features of every scored player.
+    ///
+    /// Rules run after the built-in analysis, in registration order, and
+    /// append their flag to the players they fire on; whitelisted players are
+    /// skipped. Custom flags count towards `PlayerResult::is_flagged` but do
+    /// not change the suspicion score.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::analyzer::{Analyzer, FlagRule};
+    ///
+    /// let analyzer = Analyzer::builder()
+    ///     .model_path("models/cheat_model.bin")
+    ///     .flag_rule(FlagRule::new("SharpShooter", |row| {
+    ///         row.hit_rate > 0.6 && row.headshot_rate > 0.4
+    ///     }))
+    ///     .build()
+    ///     .expect("Failed to build analyzer");
+    /// ```
+    pub fn flag_rule(mut self, rule: FlagRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Record the latency of every analysis in a histogram, read with
+    /// `Analyzer::latency_percentiles` (disabled by default). Clones of the
+    /// analyzer share the histogram.
+    pub fn track_latency(mut self, enabled: bool) -> Self {
+        self.track_latency = enabled;
+        self
+    }
+
+    /// Configure several segments at once (see `segment`)
+    pub fn segments(mut self, segments: HashMap<Segment, SegmentConfig>) -> Self {
+        self.segments.extend(segments);
+        self
+    }
+
+    /// Build the analyzer, loading the model from `model_path` if one was given.
+    ///
+    /// A model that fails to load is an error unless `heuristic_fallback` is
+    /// enabled, in which case the analyzer scores players heuristically. A
+    /// segment with neither a region nor a platform is an error.
+    pub fn build(self) -> Result<Analyzer> {
+        let mut segments = HashMap::new();
+        for (segment, config) in self.segments {
+            if segment == Segment::default() {
+                return Err(anyhow::anyhow!(
+                    "A segment needs a region or a platform; use model and thresholds for everyone else"
+                ));
+            }
+            let mut options = self.options.clone();
+            if let Some(thresholds) = config.thresholds {
+                options.thresholds = thresholds;
+            }
+            let model = config.model.map(Arc::new);
+            segments.insert(segment, SegmentRoute { model, options });
+        }
+
+        let model = match (self.model, self.model_path) {
+            (Some(model), _) => ModelSource::Loaded(Arc::new(model)),
+            (None, Some(path)) => match crate::load_model(&path) {
+                Ok(model) => ModelSource::Loaded(Arc::new(model)),
+                Err(e) if self.options.heuristic_fallback => {
+                    ModelSource::Unavailable(format!("Failed to load {}: {}", path, e))
+                }
+                Err(e) => return Err(e),
+            },
+            (None, None) => ModelSource::Current,
+        };
+
+        Ok(Analyzer {
+            model,
+            options: self.options,
+            only_flagged: self.only_flagged,
+            segments,
+            rules: self.rules,
+            latency: self
+                .track_latency
+                .then(|| Arc::new(LatencyHistogram::new())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::VERDICT_THRESHOLD;
+    use crate::types::{Assessment, Flag};
+
+    fn make_stats(player_id: &str, hits: u32, headshots: u32) -> PlayerStats {
+        PlayerStats {
+            player_id: player_id.to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), hits)]),
+            headshots,
+            ..Default::default()
+        }
+    }
+
+    fn trained_model() -> CheatModel {
+        let (training_data, labels) = crate::generate_synthetic_dataset();
+        let config = crate::options::TrainingConfig {
+            trees: 10,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        crate::train_model_to_writer(training_data, labels, &config, &mut buffer).unwrap();
+        CheatModel::from_bytes(&buffer).unwrap()
+    }
+
+    #[test]
+    fn test_builder_configures_analysis() {
+        let stats = vec![make_stats("normal", 50, 10), make_stats("cheater", 95, 60)];
+
+        let analyzer = Analyzer::builder().model(trained_model()).build().unwrap();
+        assert_eq!(analyzer.analyze(stats.clone()).unwrap().results.len(), 2);
+
+        // Only the cheater is above the stricter hit rate threshold
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .thresholds(Thresholds {
+                high_hit_rate: 0.9,
+                ..Default::default()
+            })
+            .only_flagged(true)
+            .build()
+            .unwrap();
+        let response = analyzer.analyze(stats).unwrap();
+        assert_eq!(response.results.len(), 1);
+        assert_eq!(response.results[0].player_id, "cheater");
+        assert!(response.results[0]
+            .flags
+            .contains(&"HighHitRate".to_string()));
+    }
+
+    #[test]
+    fn test_analyze_player_returns_single_result() {
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .only_flagged(true)
+            .build()
+            .unwrap();
+
+        let cheater = analyzer
+            .analyze_player(make_stats("cheater", 95, 60))
+            .unwrap();
+        assert_eq!(cheater.player_id, "cheater");
+        assert!(cheater.predicted_label);
+
+        // A clean player still gets a result despite only_flagged
+        let normal = analyzer
+            .analyze_player(make_stats("normal", 50, 10))
+            .unwrap();
+        assert_eq!(normal.player_id, "normal");
+        assert!(!normal.is_flagged());
+    }
+
+    #[test]
+    fn test_custom_flag_rule_fires() {
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .whitelist(HashSet::from(["pro".to_string()]))
+            .flag_rule(FlagRule::new("SharpShooter", |row| {
+                row.hit_rate > 0.6 && row.headshot_rate > 0.4
+            }))
+            .build()
+            .unwrap();
+        let stats = vec![
+            // Accurate but rarely aiming for the head: only one condition holds
+            make_stats("accurate", 70, 10),
+            make_stats("sharp", 70, 35),
+            make_stats("pro", 70, 35),
+        ];
+
+        let response = analyzer.analyze(stats).unwrap();
+        let sharp = |i: usize| {
+            response.results[i]
+                .flags
+                .contains(&"SharpShooter".to_string())
+        };
+        assert!(!sharp(0));
+        assert!(sharp(1));
+        assert!(response.results[1].is_flagged());
+        assert!(!sharp(2));
+    }
+
+    #[test]
+    fn test_team_outlier_flagged_among_clean_teammates() {
+        let on_team = |player_id: &str, hits: u32, headshots: u32, team: &str| PlayerStats {
+            team: Some(team.to_string()),
+            match_id: Some("match1".to_string()),
+            ..make_stats(player_id, hits, headshots)
+        };
+        let stats = vec![
+            on_team("red_1", 50, 10, "red"),
+            on_team("red_2", 45, 8, "red"),
+            on_team("red_cheater", 95, 60, "red"),
+            on_team("red_3", 55, 12, "red"),
+            // A team of cheaters has no outlier
+            on_team("blue_1", 95, 60, "blue"),
+            on_team("blue_2", 92, 55, "blue"),
+        ];
+        let outliers = |margin: Option<f32>| {
+            let analyzer = Analyzer::builder()
+                .model(trained_model())
+                .team_outlier_margin(margin)
+                .build()
+                .unwrap();
+            analyzer
+                .analyze(stats.clone())
+                .unwrap()
+                .results
+                .into_iter()
+                .filter(|result| result.has_flag(Flag::TeamOutlier))
+                .map(|result| result.player_id)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(outliers(None).is_empty());
+        assert_eq!(outliers(Some(0.5)), vec!["red_cheater".to_string()]);
+    }
+
+    #[test]
+    fn test_console_players_use_console_model() {
+        let mut console_model = trained_model();
+        console_model.metadata.version = "console".to_string();
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .segment(
+                Segment::platform("console"),
+                SegmentConfig {
+                    model: Some(console_model),
+                    thresholds: Some(Thresholds {
+                        high_hit_rate: 0.99,
+                        ..Default::default()
+                    }),
+                },
+            )
+            .build()
+            .unwrap();
+
+        let console = |player_id: &str, region: Option<&str>| PlayerStats {
+            platform: Some("console".to_string()),
+            region: region.map(str::to_string),
+            ..make_stats(player_id, 95, 60)
+        };
+        let stats = vec![
+            console("console_na", Some("na")),
+            make_stats("pc", 95, 60),
+            console("console", None),
+        ];
+        let response = analyzer.analyze(stats).unwrap();
+
+        let ids: Vec<&str> = response
+            .results
+            .iter()
+            .map(|r| r.player_id.as_str())
+            .collect();
+        assert_eq!(ids, ["console_na", "pc", "console"]);
+        for result in &response.results {
+            let is_console = result.player_id != "pc";
+            assert_eq!(
+                result.model_version.as_deref() == Some("console"),
+                is_console
+            );
+            assert_eq!(
+                result.flags.contains(&"HighHitRate".to_string()),
+                !is_console
+            );
+        }
+
+        let catch_all = Analyzer::builder()
+            .segment(Segment::default(), SegmentConfig::default())
+            .build();
+        assert!(catch_all.is_err());
+    }
+
+    #[test]
+    fn test_missing_model_path() {
+        let result = Analyzer::builder().model_path("does/not/exist.bin").build();
+        assert!(result.is_err());
+
+        let analyzer = Analyzer::builder()
+            .model_path("does/not/exist.bin")
+            .heuristic_fallback(true)
+            .build()
+            .unwrap();
+        let response = analyzer
+            .analyze(vec![make_stats("normal", 50, 10)])
+            .unwrap();
+        assert!(response.results[0]
+            .flags
+            .contains(&"HeuristicFallback".to_string()));
+    }
+
+    fn append<W: std::io::Write>(builder: &mut tar::Builder<W>, name: &str, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, data).unwrap();
+    }
+
+    #[test]
+    fn test_analyze_archive() {
+        let dir = std::env::temp_dir().join("nocheat_archive_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let tar_path = dir.join("matches.tar");
+        let gz_path = dir.join("matches.tar.gz");
+
+        let match_a = serde_json::to_vec(&vec![make_stats("normal", 50, 10)]).unwrap();
+        let match_b = serde_json::to_vec(&vec![
+            make_stats("cheater", 95, 60),
+            make_stats("other", 40, 5),
+        ])
+        .unwrap();
+        let mut builder = tar::Builder::new(File::create(&tar_path).unwrap());
+        append(&mut builder, "match_a.json", &match_a);
+        append(&mut builder, "notes.txt", b"not stats");
+        append(&mut builder, "match_b.json", &match_b);
+        builder.into_inner().unwrap();
+
+        let gz = flate2::write::GzEncoder::new(
+            File::create(&gz_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(gz);
+        append(&mut builder, "match_b.json", &match_b);
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let analyzer = Analyzer::builder().model(trained_model()).build().unwrap();
+
+        let response = analyzer
+            .analyze_archive(tar_path.to_str().unwrap())
+            .unwrap();
+        let sources: Vec<_> = response
+            .results
+            .iter()
+            .map(|r| (r.player_id.as_str(), r.source.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("normal", "match_a.json"),
+                ("cheater", "match_b.json"),
+                ("other", "match_b.json")
+            ]
+        );
+
+        let response = analyzer.analyze_archive(gz_path.to_str().unwrap()).unwrap();
+        assert_eq!(response.results.len(), 2);
+        assert!(response.results[0].suspicion_score >= VERDICT_THRESHOLD);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_headshot_rate_flagged_per_weapon_class() {
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .thresholds(Thresholds {
+                headshot_rate_by_class: HashMap::from([
+                    ("sniper".to_string(), 0.8),
+                    ("shotgun".to_string(), 0.3),
+                ]),
+                weapon_classes: HashMap::from([
+                    ("awp".to_string(), "sniper".to_string()),
+                    ("nova".to_string(), "shotgun".to_string()),
+                ]),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        // 60% headshots with either weapon
+        let with_weapon = |weapon: &str| PlayerStats {
+            player_id: weapon.to_string(),
+            shots_fired: HashMap::from([(weapon.to_string(), 100)]),
+            hits: HashMap::from([(weapon.to_string(), 50)]),
+            headshots: 30,
+            weapon_headshots: HashMap::from([(weapon.to_string(), 30)]),
+            ..Default::default()
+        };
+
+        let response = analyzer
+            .analyze(vec![with_weapon("nova"), with_weapon("awp")])
+            .unwrap();
+        assert!(response.results[0].has_flag(Flag::HighHeadshotRate));
+        assert!(!response.results[1].has_flag(Flag::HighHeadshotRate));
+    }
+
+    #[test]
+    fn test_replays_enable_timing_checks() {
+        let dir = std::env::temp_dir().join("nocheat_replay_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // A macro firing exactly every 50 ms
+        let replay_path = dir.join("round.replay");
+        let replay: String = (0..30)
+            .map(|i| format!("{},{}.0,0.0,{}\n", 1000 + i * 50, i, i % 2))
+            .collect();
+        std::fs::write(
+            &replay_path,
+            format!("# timestamp_ms,yaw,pitch,hit\n{}", replay),
+        )
+        .unwrap();
+
+        let with_replay = PlayerStats {
+            replay_path: Some(replay_path.to_str().unwrap().to_string()),
+            ..make_stats("with_replay", 50, 10)
+        };
+        let missing_replay = PlayerStats {
+            replay_path: Some("missing.replay".to_string()),
+            ..make_stats("missing_replay", 50, 10)
+        };
+
+        let analyzer = Analyzer::builder().model(trained_model()).build().unwrap();
+        let response = analyzer
+            .analyze_with_replays(&dir, vec![with_replay.clone(), missing_replay])
+            .unwrap();
+        assert!(response.results[0].has_flag(Flag::RoboticCadence));
+        assert!(!response.results[1].has_flag(Flag::RoboticCadence));
+
+        // The plain path ignores replays
+        let response = analyzer.analyze(vec![with_replay]).unwrap();
+        assert!(!response.results[0].has_flag(Flag::RoboticCadence));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_audit_records_only_flagged_players() {
+        let dir = std::env::temp_dir().join("nocheat_audit_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("flags.jsonl");
+
+        let analyzer = Analyzer::builder().model(trained_model()).build().unwrap();
+        let mut sink = crate::audit::JsonlAuditSink::open(&path).unwrap();
+        let response = analyzer
+            .analyze_and_audit(
+                &mut sink,
+                vec![make_stats("normal", 50, 10), make_stats("cheater", 95, 60)],
+            )
+            .unwrap();
+        assert_eq!(response.results.len(), 2);
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["player_id"], "cheater");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_cancel_after_first_chunk_returns_partial_results() {
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .chunk_size(2)
+            .build()
+            .unwrap();
+        let stats: Vec<PlayerStats> = (0..6)
+            .map(|i| make_stats(&format!("player{}", i), 50, 10))
+            .collect();
+
+        let cancel = AtomicBool::new(false);
+        let mut chunks = Vec::new();
+        let outcome = analyzer
+            .analyze_chunks(stats.clone(), &cancel, &mut |analyzed| {
+                chunks.push(analyzed);
+                cancel.store(true, Ordering::Relaxed);
+            })
+            .unwrap();
+        assert_eq!(chunks, vec![2]);
+        assert!(outcome.is_cancelled());
+        let ids: Vec<String> = outcome
+            .into_response()
+            .results
+            .into_iter()
+            .map(|r| r.player_id)
+            .collect();
+        assert_eq!(ids, vec!["player0", "player1"]);
+
+        let outcome = analyzer
+            .analyze_cancellable(stats, &AtomicBool::new(false))
+            .unwrap();
+        assert!(!outcome.is_cancelled());
+        assert_eq!(outcome.into_response().results.len(), 6);
+    }
+
+    #[test]
+    fn test_margin_reported_when_enabled() {
+        let stats = vec![make_stats("cheater", 95, 60)];
+        let default = Analyzer::builder().model(trained_model()).build().unwrap();
+        assert_eq!(
+            default.analyze(stats.clone()).unwrap().results[0].margin,
+            None
+        );
+
+        let detailed = Analyzer::builder()
+            .model(trained_model())
+            .include_margin(true)
+            .build()
+            .unwrap();
+        let margin = detailed.analyze(stats).unwrap().results[0].margin.unwrap();
+        assert!(margin > 0.0);
+    }
+
+    #[test]
+    fn test_predicted_label_follows_threshold() {
+        let stats = vec![make_stats("cheater", 95, 60), make_stats("clean", 45, 8)];
+        let labels = |threshold: f32| {
+            Analyzer::builder()
+                .model(trained_model())
+                .label_threshold(threshold)
+                .build()
+                .unwrap()
+                .analyze(stats.clone())
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|r| {
+                    assert_eq!(r.predicted_label, r.suspicion_score >= threshold);
+                    r.predicted_label
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(labels(0.5), vec![true, false]);
+        // Nothing reaches an unattainable threshold, everything reaches zero
+        assert_eq!(labels(1.5), vec![false, false]);
+        assert_eq!(labels(0.0), vec![true, true]);
+    }
+
+    #[test]
+    fn test_verdict_separates_insufficient_data_from_clean() {
+        let few_shots = PlayerStats {
+            shots_fired: HashMap::from([("rifle".to_string(), 2)]),
+            hits: HashMap::from([("rifle".to_string(), 1)]),
+            ..make_stats("few_shots", 0, 0)
+        };
+        let stats = vec![
+            make_stats("cheater", 95, 60),
+            make_stats("clean", 45, 8),
+            few_shots,
+        ];
+        let analyzer = Analyzer::builder().model(trained_model()).build().unwrap();
+        let verdicts: Vec<Assessment> = analyzer
+            .analyze(stats.clone())
+            .unwrap()
+            .results
+            .iter()
+            .map(|r| r.verdict)
+            .collect();
+        assert_eq!(
+            verdicts,
+            [
+                Assessment::Suspicious,
+                Assessment::Clean,
+                Assessment::Insufficient
+            ]
+        );
+
+        // Without a gate the score decides alone
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .min_shots(0)
+            .build()
+            .unwrap();
+        let response = analyzer.analyze(stats).unwrap();
+        assert_ne!(response.results[2].verdict, Assessment::Insufficient);
+    }
+
+    #[test]
+    fn test_timed_analysis_reports_stages() {
+        let stats: Vec<PlayerStats> = (0..200)
+            .map(|i| make_stats(&format!("player{}", i), 30 + i % 60, i % 20))
+            .collect();
+        let analyzer = Analyzer::builder().model(trained_model()).build().unwrap();
+        let (response, metrics) = analyzer.analyze_timed(stats).unwrap();
+        assert_eq!(response.results.len(), 200);
+
+        // Only the structure is checked; wall-clock durations vary with load
+        for stage in [metrics.build_ms, metrics.feature_ms, metrics.inference_ms] {
+            assert!(stage >= 0.0);
+        }
+        let stages = metrics.build_ms + metrics.feature_ms + metrics.inference_ms;
+        assert!(stages <= metrics.total_ms);
+    }
+
+    #[test]
+    fn test_diagnostics_report_ignored_fields() {
+        let mut stats = make_stats("player", 50, 10);
+        stats.shot_timestamps_ms = Some(vec![0, 120, 250].into());
+        stats
+            .attributes
+            .insert("map".to_string(), "dust".to_string());
+        let analyzer = Analyzer::builder().model(trained_model()).build().unwrap();
+        let (response, ignored) = analyzer
+            .analyze_with_diagnostics(vec![stats.clone(), make_stats("other", 40, 5)])
+            .unwrap();
+        assert_eq!(response.results.len(), 2);
+        assert_eq!(ignored, ["attributes.map", "shot_timestamps_ms"]);
+
+        // A model trained with a timestamp indicator reads them
+        let (training_data, labels) = crate::generate_synthetic_dataset();
+        let config = crate::options::TrainingConfig {
+            trees: 5,
+            seed: Some(7),
+            optional_features: vec!["shot_timestamps_ms".to_string()],
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        crate::train_model_to_writer(training_data, labels, &config, &mut buffer).unwrap();
+        let analyzer = Analyzer::builder()
+            .model(CheatModel::from_bytes(&buffer).unwrap())
+            .build()
+            .unwrap();
+        let (_, ignored) = analyzer.analyze_with_diagnostics(vec![stats]).unwrap();
+        assert_eq!(ignored, ["attributes.map"]);
+    }
+
+    #[test]
+    fn test_sweep_counts_do_not_increase_with_threshold() {
+        let stats: Vec<PlayerStats> = (0..40)
+            .map(|i| make_stats(&format!("player{}", i), 30 + i * 17 % 70, i % 40))
+            .collect();
+        let thresholds = [0.1, 0.3, 0.5, 0.7, 0.9];
+        // Calibrated, so scores spread between 0 and 1 instead of hard votes
+        let (training_data, labels) = crate::generate_synthetic_dataset();
+        let config = crate::options::TrainingConfig {
+            trees: 11,
+            seed: Some(7),
+            calibrate: true,
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        crate::train_model_to_writer(training_data, labels, &config, &mut buffer).unwrap();
+        let analyzer = Analyzer::builder()
+            .model(CheatModel::from_bytes(&buffer).unwrap())
+            .build()
+            .unwrap();
+        let sweep = analyzer.analyze_sweep(stats, &thresholds).unwrap();
+
+        assert_eq!(sweep.levels.len(), thresholds.len());
+        for pair in sweep.levels.windows(2) {
+            assert!(pair[1].flagged_count <= pair[0].flagged_count);
+            assert!(pair[1]
+                .flagged_players
+                .iter()
+                .all(|id| pair[0].flagged_players.contains(id)));
+        }
+        assert!(sweep.levels[0].flagged_count > sweep.levels[4].flagged_count);
+        assert_eq!(
+            sweep.levels[2].flagged_players.len(),
+            sweep.levels[2].flagged_count
+        );
+    }
+
+    #[test]
+    fn test_thread_count_does_not_change_results() {
+        let stats: Vec<PlayerStats> = (0..25)
+            .map(|i| make_stats(&format!("player{}", i), 30 + i * 3, i * 2))
+            .collect();
+        let scores = |max_threads: Option<usize>| {
+            Analyzer::builder()
+                .model(trained_model())
+                .max_threads(max_threads)
+                .build()
+                .unwrap()
+                .analyze(stats.clone())
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|r| (r.player_id, r.suspicion_score, r.flags))
+                .collect::<Vec<_>>()
+        };
+
+        let parallel = scores(Some(4));
+        assert_eq!(parallel.len(), 25);
+        assert_eq!(scores(Some(1)), parallel);
+        assert_eq!(scores(None), parallel);
+    }
+
+    #[test]
+    fn test_feature_cache_hit_returns_identical_results() {
+        let stats = vec![
+            make_stats("normal", 50, 10),
+            make_stats("cheater", 95, 60),
+            make_stats("twin", 50, 10),
+        ];
+        // Everything but the analysis time and model version
+        let scored = |response: AnalysisResponse| {
+            response
+                .results
+                .into_iter()
+                .map(|r| (r.player_id, r.suspicion_score, r.flags, r.flag_details))
+                .collect::<Vec<_>>()
+        };
+        let uncached = scored(
+            Analyzer::builder()
+                .model(trained_model())
+                .build()
+                .unwrap()
+                .analyze(stats.clone())
+                .unwrap(),
+        );
+
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .feature_cache(16)
+            .build()
+            .unwrap();
+        let first = analyzer.analyze(stats.clone()).unwrap();
+        let cache = analyzer.feature_cache().unwrap();
+        // The twin shares the normal player's totals, so only two rows are cached
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().hits, 0);
+
+        let second = analyzer.analyze(stats).unwrap();
+        assert_eq!(cache.stats().hits, 3);
+        assert_eq!(scored(first), uncached);
+        assert_eq!(scored(second), uncached);
+    }
+
+    #[test]
+    fn test_latency_percentiles_are_ordered() {
+        let analyzer = Analyzer::builder()
+            .model(trained_model())
+            .track_latency(true)
+            .build()
+            .unwrap();
+        let zero = (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+        assert_eq!(analyzer.latency_percentiles(), zero);
+
+        for i in 0..50 {
+            let stats: Vec<PlayerStats> = (0..=i)
+                .map(|j| make_stats(&format!("player{}", j), 40 + j % 50, j % 30))
+                .collect();
+            analyzer.analyze(stats).unwrap();
+        }
+        assert_eq!(analyzer.latency_histogram().unwrap().count(), 50);
+        let (p50, p95, p99) = analyzer.latency_percentiles();
+        assert!(p50 > Duration::ZERO);
+        assert!(p50 <= p95 && p95 <= p99);
+
+        analyzer.reset_latency();
+        assert_eq!(analyzer.latency_percentiles(), zero);
+    }
+}
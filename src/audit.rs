@@ -0,0 +1,295 @@
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::types::PlayerResult;
+
+/// Durable destination for flag decisions.
+///
+/// Unlike log output, a sink is meant to keep a permanent record of every
+/// flagged player so decisions can be reviewed or appealed later. Call
+/// `flush` before the process exits so no recorded result is lost.
+pub trait AuditSink {
+    /// Record a single analysis result
+    fn record(&mut self, result: &PlayerResult) -> Result<()>;
+
+    /// Persist every recorded result, e.g. on shutdown.
+    ///
+    /// The default does nothing, for sinks that keep no buffered data.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps the recorded results in memory, e.g. for tests or batch reports
+impl AuditSink for Vec<PlayerResult> {
+    fn record(&mut self, result: &PlayerResult) -> Result<()> {
+        self.push(result.clone());
+        Ok(())
+    }
+}
+
+/// Audit sink appending one JSON object per line to a file.
+///
+/// When `max_bytes` is set, the file is rotated before a line would grow it
+/// past that size: the current file is renamed to `<path>.1` (replacing any
+/// previous rotation) and a fresh file is started.
+///
+/// When `rate_limit` is set, at most that many results are written per
+/// interval, so a flag storm cannot flood the disk. Results beyond the limit
+/// are dropped and counted, and the count is written as a
+/// `{"dropped": <count>}` line when the next interval starts or on `flush`.
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyze_and_audit;
+/// use nocheat::audit::JsonlAuditSink;
+/// use std::time::Duration;
+///
+/// let mut sink = JsonlAuditSink::open("audit/flags.jsonl")
+///     .expect("Failed to open audit log")
+///     .max_bytes(10 * 1024 * 1024)
+///     .rate_limit(1000, Duration::from_secs(60));
+///
+/// let response = analyze_and_audit(&mut sink, vec![]).expect("Analysis failed");
+/// ```
+#[derive(Debug)]
+pub struct JsonlAuditSink {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: Option<u64>,
+    rate_limit: Option<RateLimit>,
+}
+
+/// Results written in the current interval of `JsonlAuditSink::rate_limit`
+#[derive(Debug)]
+struct RateLimit {
+    max_records: usize,
+    interval: Duration,
+    started: Instant,
+    recorded: usize,
+    dropped: u64,
+}
+
+impl JsonlAuditSink {
+    /// Open the audit file for appending, creating it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = Self::append_to(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+            max_bytes: None,
+            rate_limit: None,
+        })
+    }
+
+    /// Rotate the file once it would exceed this many bytes
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Write at most `max_records` results per `interval`, dropping the rest
+    pub fn rate_limit(mut self, max_records: usize, interval: Duration) -> Self {
+        self.rate_limit = Some(RateLimit {
+            max_records,
+            interval,
+            started: Instant::now(),
+            recorded: 0,
+            dropped: 0,
+        });
+        self
+    }
+
+    /// Path of the most recent rotated file
+    pub fn rotated_path(&self) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(".1");
+        PathBuf::from(name)
+    }
+
+    fn append_to(path: &Path) -> Result<File> {
+        Ok(OpenOptions::new().create(true).append(true).open(path)?)
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+        std::fs::rename(&self.path, self.rotated_path())?;
+        self.file = Self::append_to(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, mut line: Vec<u8>) -> Result<()> {
+        line.push(b'\n');
+        if let Some(max_bytes) = self.max_bytes {
+            if self.written > 0 && self.written + line.len() as u64 > max_bytes {
+                self.rotate()?;
+            }
+        }
+        self.file.write_all(&line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    /// Record a result at time `now`, which decides its rate-limit interval
+    fn record_at(&mut self, result: &PlayerResult, now: Instant) -> Result<()> {
+        if let Some(limit) = &mut self.rate_limit {
+            if now.saturating_duration_since(limit.started) >= limit.interval {
+                limit.started = now;
+                limit.recorded = 0;
+                self.write_dropped()?;
+            }
+        }
+        if let Some(limit) = &mut self.rate_limit {
+            if limit.recorded >= limit.max_records {
+                limit.dropped += 1;
+                return Ok(());
+            }
+            limit.recorded += 1;
+        }
+        self.write_line(serde_json::to_vec(result)?)
+    }
+
+    /// Write the number of results dropped since the last report, if any
+    fn write_dropped(&mut self) -> Result<()> {
+        let dropped = match &mut self.rate_limit {
+            Some(limit) if limit.dropped > 0 => std::mem::take(&mut limit.dropped),
+            _ => return Ok(()),
+        };
+        self.write_line(serde_json::to_vec(
+            &serde_json::json!({ "dropped": dropped }),
+        )?)
+    }
+}
+
+impl AuditSink for JsonlAuditSink {
+    fn record(&mut self, result: &PlayerResult) -> Result<()> {
+        self.record_at(result, Instant::now())
+    }
+
+    /// Write the count of dropped results, then flush the file and sync it to
+    /// disk, so the records survive a crash
+    fn flush(&mut self) -> Result<()> {
+        self.write_dropped()?;
+        self.file.flush()?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flagged(player_id: &str) -> PlayerResult {
+        PlayerResult {
+            player_id: player_id.to_string(),
+            suspicion_score: 0.9,
+            flags: vec!["HighHitRate".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_jsonl_sink_rotates_by_size() {
+        let dir = std::env::temp_dir().join("nocheat_audit_rotation_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("flags.jsonl");
+
+        let line_len = serde_json::to_vec(&flagged("p1")).unwrap().len() as u64 + 1;
+        let mut sink = JsonlAuditSink::open(&path).unwrap().max_bytes(line_len * 2);
+        for player_id in ["p1", "p2", "p3"] {
+            sink.record(&flagged(player_id)).unwrap();
+        }
+
+        let rotated = std::fs::read_to_string(sink.rotated_path()).unwrap();
+        let current = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(rotated.lines().count(), 2);
+        assert_eq!(current.lines().count(), 1);
+        assert!(current.contains("\"p3\""));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_jsonl_sink_drops_results_above_rate_limit() {
+        let dir = std::env::temp_dir().join("nocheat_audit_rate_limit_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("flags.jsonl");
+        let lines = || -> Vec<serde_json::Value> {
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        };
+
+        // Times are passed explicitly, so the test does not depend on the wall clock
+        let interval = Duration::from_secs(60);
+        let mut sink = JsonlAuditSink::open(&path).unwrap().rate_limit(2, interval);
+        let start = sink.rate_limit.as_ref().unwrap().started;
+        for player_id in ["p1", "p2", "p3", "p4", "p5"] {
+            sink.record_at(&flagged(player_id), start).unwrap();
+        }
+        assert_eq!(lines().len(), 2);
+
+        // The next interval reports the dropped results before writing again
+        let next = start + interval;
+        for player_id in ["p6", "p7", "p8"] {
+            sink.record_at(&flagged(player_id), next).unwrap();
+        }
+        sink.flush().unwrap();
+
+        let lines = lines();
+        let ids: Vec<&str> = lines
+            .iter()
+            .filter_map(|line| line["player_id"].as_str())
+            .collect();
+        assert_eq!(ids, ["p1", "p2", "p6", "p7"]);
+        assert_eq!(lines[2], serde_json::json!({ "dropped": 3 }));
+        assert_eq!(lines[5], serde_json::json!({ "dropped": 1 }));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_flushed_records_survive_reopen() {
+        let dir = std::env::temp_dir().join("nocheat_audit_flush_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("flags.jsonl");
+
+        let mut sink = JsonlAuditSink::open(&path).unwrap();
+        for player_id in ["p1", "p2"] {
+            sink.record(&flagged(player_id)).unwrap();
+        }
+        sink.flush().unwrap();
+        drop(sink);
+
+        let mut sink = JsonlAuditSink::open(&path).unwrap();
+        sink.record(&flagged("p3")).unwrap();
+        sink.flush().unwrap();
+
+        let ids: Vec<String> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let record: serde_json::Value = serde_json::from_str(line).unwrap();
+                record["player_id"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(ids, ["p1", "p2", "p3"]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
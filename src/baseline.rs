@@ -0,0 +1,671 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::inference::{FeatureRow, FEATURE_NAMES};
+use crate::options::{FeaturePrecision, Thresholds};
+use crate::types::{Assessment, Flag, FlagDetail, PlayerResult, PlayerStats};
+
+/// Percentiles reported by `compute_baseline`
+pub const BASELINE_PERCENTILES: [u8; 5] = [50, 75, 90, 95, 99];
+
+/// Distribution of one feature across a population.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FeatureBaseline {
+    /// Mean value
+    pub mean: f64,
+    /// Population standard deviation
+    pub std: f64,
+    /// Values at the percentiles of `BASELINE_PERCENTILES`, keyed by percentile
+    pub percentiles: BTreeMap<u8, f64>,
+}
+
+/// Feature distributions of a known-clean population, used to derive thresholds.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::baseline::compute_baseline;
+/// use nocheat::options::Thresholds;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
+///
+/// let clean: Vec<PlayerStats> = (0..20)
+///     .map(|i| PlayerStats {
+///         player_id: format!("player{}", i),
+///         shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+///         hits: HashMap::from([("rifle".to_string(), 40 + i)]),
+///         headshots: 10,
+///         ..Default::default()
+///     })
+///     .collect();
+///
+/// let baseline = compute_baseline(&clean).expect("Baseline failed");
+/// let thresholds = Thresholds::from_baseline(&baseline, 3.0);
+/// assert!(thresholds.high_hit_rate > 0.59);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Baseline {
+    /// Number of players the baseline was computed from
+    pub players: usize,
+    /// Distribution of every feature of `FEATURE_NAMES`, keyed by feature name
+    pub features: BTreeMap<String, FeatureBaseline>,
+}
+
+impl Baseline {
+    /// Distribution of a feature, if it is part of the baseline
+    pub fn feature(&self, name: &str) -> Option<&FeatureBaseline> {
+        self.features.get(name)
+    }
+}
+
+/// Compute the distribution of every feature over a population.
+///
+/// Features are computed with the same pipeline as the analysis. Undefined
+/// values (e.g. the hit rate of a player who fired no shots) are left out of
+/// the feature they belong to.
+///
+/// # Arguments
+///
+/// * `stats` - Statistics of players known to be legitimate
+///
+/// # Returns
+///
+/// * `Result<Baseline>` - The feature distributions, or an error if no player
+///   has a defined value for some feature
+pub fn compute_baseline(stats: &[PlayerStats]) -> Result<Baseline> {
+    let rows = crate::feature_rows(stats, FeaturePrecision::F32)?;
+
+    let mut features = BTreeMap::new();
+    for name in FEATURE_NAMES {
+        let values = sorted_feature_values(&rows, name);
+        if values.is_empty() {
+            return Err(anyhow::anyhow!("No defined values for feature {}", name));
+        }
+
+        let mut running = RunningStats::default();
+        values.iter().for_each(|&value| running.update(value));
+        let percentiles = BASELINE_PERCENTILES
+            .iter()
+            .map(|&p| (p, percentile(&values, p)))
+            .collect();
+        features.insert(
+            name.to_string(),
+            FeatureBaseline {
+                mean: running.mean,
+                std: running.std(),
+                percentiles,
+            },
+        );
+    }
+
+    Ok(Baseline {
+        players: stats.len(),
+        features,
+    })
+}
+
+/// Finite values of a feature, sorted ascending
+pub(crate) fn sorted_feature_values(rows: &[FeatureRow], name: &str) -> Vec<f64> {
+    let mut values: Vec<f64> = rows
+        .iter()
+        .filter_map(|row| row.get(name))
+        .filter(|v| v.is_finite())
+        .collect();
+    values.sort_by(f64::total_cmp);
+    values
+}
+
+/// Linearly interpolated percentile of sorted, non-empty values
+pub(crate) fn percentile(sorted: &[f64], p: u8) -> f64 {
+    let rank = p as f64 / 100.0 * (sorted.len() - 1) as f64;
+    let low = rank.floor() as usize;
+    let high = rank.ceil() as usize;
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+impl Thresholds {
+    /// Derive thresholds from a clean population's baseline.
+    ///
+    /// Each rate threshold is set `n_sigma` standard deviations above the
+    /// population mean of its feature. Thresholds without a matching feature
+    /// keep their defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `baseline` - Baseline of a known-clean population
+    /// * `n_sigma` - How many standard deviations above the mean a player must be
+    ///
+    /// # Returns
+    ///
+    /// * `Thresholds` - The derived thresholds
+    pub fn from_baseline(baseline: &Baseline, n_sigma: f64) -> Thresholds {
+        let above = |name: &str, default: f32| {
+            baseline
+                .feature(name)
+                .map(|f| (f.mean + n_sigma * f.std) as f32)
+                .unwrap_or(default)
+        };
+        let defaults = Thresholds::default();
+        Thresholds {
+            high_hit_rate: above("hit_rate", defaults.high_hit_rate),
+            high_headshot_rate: above("headshot_rate", defaults.high_headshot_rate),
+            ..defaults
+        }
+    }
+}
+
+/// Mean and variance of a stream of values, updated one value at a time.
+///
+/// `update` weighs every value equally (Welford's algorithm), giving the exact
+/// population mean and variance without storing the values; `push` weighs
+/// recent values more, for baselines that should follow a drifting population.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::baseline::RunningStats;
+///
+/// let mut stats = RunningStats::default();
+/// for value in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+///     stats.update(value);
+/// }
+/// assert_eq!(stats.mean, 5.0);
+/// assert_eq!(stats.std(), 2.0);
+/// assert_eq!(stats.z_score(9.0), 2.0);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RunningStats {
+    /// Number of values observed
+    pub count: u64,
+    /// Weighted mean of the observed values
+    pub mean: f64,
+    /// Weighted variance of the observed values
+    pub variance: f64,
+}
+
+impl RunningStats {
+    /// Add a value, giving it weight `alpha` against the history
+    pub fn push(&mut self, value: f64, alpha: f64) {
+        if self.count == 0 {
+            self.mean = value;
+            self.variance = 0.0;
+        } else {
+            let diff = value - self.mean;
+            let increment = alpha * diff;
+            self.mean += increment;
+            self.variance = (1.0 - alpha) * (self.variance + diff * increment);
+        }
+        self.count += 1;
+    }
+
+    /// Add a value with the same weight as every earlier one
+    pub fn update(&mut self, value: f64) {
+        // Welford's update is the weighted one with a weight of 1/n
+        self.push(value, 1.0 / (self.count + 1) as f64);
+    }
+
+    /// Weighted standard deviation
+    pub fn std(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// Standard deviations between `value` and the mean; infinite when the
+    /// values observed so far have no spread and `value` differs from them
+    pub fn z_score(&self, value: f64) -> f64 {
+        let diff = value - self.mean;
+        if diff == 0.0 {
+            return 0.0;
+        }
+        diff / self.std()
+    }
+}
+
+/// Self-tuning per-weapon hit rate baselines.
+///
+/// Normal accuracy differs wildly between weapons (a sniper rifle is far more
+/// accurate per shot than an SMG), so a single global hit rate threshold is
+/// either too strict for some weapons or too loose for others. This detector
+/// keeps a rolling mean and standard deviation of the hit rate of every
+/// weapon, learned from players known to be clean, and flags a player whose
+/// hit rate on a weapon is more than `n_sigma` deviations above that
+/// weapon's baseline. The struct is serializable so baselines can be
+/// persisted between sessions.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::baseline::WeaponBaselines;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
+///
+/// let player = |smg_hits: u32| PlayerStats {
+///     player_id: "player123".to_string(),
+///     shots_fired: HashMap::from([("smg".to_string(), 100)]),
+///     hits: HashMap::from([("smg".to_string(), smg_hits)]),
+///     ..Default::default()
+/// };
+///
+/// let mut baselines = WeaponBaselines::default();
+/// for round in 0..50 {
+///     baselines.observe(&player(25 + round % 10));
+/// }
+///
+/// assert_eq!(baselines.outlier_weapons(&player(60)), vec!["smg".to_string()]);
+/// assert!(baselines.outlier_weapons(&player(30)).is_empty());
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WeaponBaselines {
+    /// Weight of each new observation in (0, 1]; higher values adapt faster
+    pub alpha: f64,
+    /// Deviations above the weapon mean needed to flag a player
+    pub n_sigma: f64,
+    /// Observations a weapon needs before its baseline is used
+    pub min_samples: u64,
+    /// Minimum shots with a weapon for it to be observed or evaluated
+    pub min_shots: u32,
+    /// Baseline of every weapon observed so far
+    pub weapons: BTreeMap<String, RunningStats>,
+}
+
+impl Default for WeaponBaselines {
+    fn default() -> Self {
+        Self {
+            alpha: 0.05,
+            n_sigma: 3.0,
+            min_samples: 30,
+            min_shots: 10,
+            weapons: BTreeMap::new(),
+        }
+    }
+}
+
+impl WeaponBaselines {
+    /// Update the baselines with the weapons of a player known to be clean
+    pub fn observe(&mut self, stats: &PlayerStats) {
+        for (weapon, hit_rate) in self.weapon_hit_rates(stats) {
+            self.weapons
+                .entry(weapon)
+                .or_default()
+                .push(hit_rate, self.alpha.clamp(f64::EPSILON, 1.0));
+        }
+    }
+
+    /// Weapons on which the player's hit rate is an outlier, in sorted order.
+    ///
+    /// Weapons whose baseline has fewer than `min_samples` observations are skipped.
+    pub fn outlier_weapons(&self, stats: &PlayerStats) -> Vec<String> {
+        let mut outliers: Vec<String> = self.outliers(stats).map(|(weapon, _, _)| weapon).collect();
+        outliers.sort();
+        outliers
+    }
+
+    /// Add the `WeaponHitRateOutlier` flag to a player's result when any weapon is an outlier.
+    ///
+    /// The flag's detail reports the outlier weapon exceeding its bound the most.
+    pub fn flag(&self, stats: &PlayerStats, result: &mut PlayerResult) {
+        if result.has_flag(Flag::WeaponHitRateOutlier) {
+            return;
+        }
+        let worst = self
+            .outliers(stats)
+            .max_by(|a, b| (a.1 - a.2).total_cmp(&(b.1 - b.2)).then(b.0.cmp(&a.0)));
+        if let Some((weapon, hit_rate, bound)) = worst {
+            result.flags.push(Flag::WeaponHitRateOutlier.to_string());
+            result.flag_details.push(FlagDetail::new(
+                Flag::WeaponHitRateOutlier,
+                hit_rate as f32,
+                bound as f32,
+                format!(
+                    "{} hit rate {:.2} above baseline bound {:.2}",
+                    weapon, hit_rate, bound
+                ),
+            ));
+        }
+    }
+
+    /// Weapon, hit rate and outlier bound of every weapon above its bound
+    fn outliers<'a>(
+        &'a self,
+        stats: &'a PlayerStats,
+    ) -> impl Iterator<Item = (String, f64, f64)> + 'a {
+        self.weapon_hit_rates(stats)
+            .filter_map(|(weapon, hit_rate)| {
+                let baseline = self.weapons.get(&weapon)?;
+                let bound = baseline.mean + self.n_sigma * baseline.std();
+                (baseline.count >= self.min_samples && hit_rate > bound)
+                    .then_some((weapon, hit_rate, bound))
+            })
+    }
+
+    /// Hit rate of every weapon with at least `min_shots` shots
+    fn weapon_hit_rates<'a>(
+        &self,
+        stats: &'a PlayerStats,
+    ) -> impl Iterator<Item = (String, f64)> + 'a {
+        let min_shots = self.min_shots.max(1);
+        stats
+            .shots_fired
+            .iter()
+            .filter(move |(_, &shots)| shots >= min_shots)
+            .map(|(weapon, &shots)| {
+                let hits = stats.hits.get(weapon).copied().unwrap_or(0);
+                (weapon.clone(), hits as f64 / shots as f64)
+            })
+    }
+}
+
+/// Settings of `analyze_vs_self_with`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SelfBaselineConfig {
+    /// Standard deviations from the player's own mean needed to flag a feature
+    pub n_sigma: f64,
+    /// Historical rounds with a defined feature value needed to judge it
+    pub min_history: usize,
+    /// Floor of the historical standard deviation, so a perfectly steady
+    /// history does not turn tiny changes into huge z-scores
+    pub min_std: f64,
+}
+
+impl Default for SelfBaselineConfig {
+    fn default() -> Self {
+        Self {
+            n_sigma: 3.0,
+            min_history: 5,
+            min_std: 0.02,
+        }
+    }
+}
+
+/// Compare a player's current round against their own history with the
+/// default `SelfBaselineConfig`.
+///
+/// See `analyze_vs_self_with`.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::baseline::analyze_vs_self;
+/// use nocheat::types::{Flag, PlayerStats};
+/// use std::collections::HashMap;
+///
+/// let round = |hits: u32| PlayerStats {
+///     player_id: "player123".to_string(),
+///     shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+///     hits: HashMap::from([("rifle".to_string(), hits)]),
+///     headshots: hits / 4,
+///     ..Default::default()
+/// };
+/// let history: Vec<PlayerStats> = (0..10).map(|i| round(40 + i % 5)).collect();
+///
+/// assert!(analyze_vs_self(&round(85), &history).has_flag(Flag::DeviationFromSelf));
+/// assert!(!analyze_vs_self(&round(42), &history).has_flag(Flag::DeviationFromSelf));
+/// ```
+pub fn analyze_vs_self(current: &PlayerStats, history: &[PlayerStats]) -> PlayerResult {
+    analyze_vs_self_with(current, history, &SelfBaselineConfig::default())
+}
+
+/// Compare a player's current round against their own history.
+///
+/// Every feature of `FEATURE_NAMES` is turned into a z-score against the
+/// mean and standard deviation of the player's historical rounds, which
+/// catches a sudden cheat activation or a shared account that a population
+/// threshold misses for players far from the average. Features with fewer
+/// than `min_history` defined historical values are skipped, so a player
+/// without enough history gets a score of 0.0, no flag and an `Insufficient`
+/// verdict.
+///
+/// # Arguments
+///
+/// * `current` - Statistics of the round to check
+/// * `history` - Earlier rounds of the same player
+/// * `config` - Flagging settings
+///
+/// # Returns
+///
+/// * `PlayerResult` - The `DeviationFromSelf` flag with the largest z-score
+///   as its value when any feature is more than `n_sigma` deviations away,
+///   and a suspicion score of that z-score divided by `2 * n_sigma`, capped
+///   at 1.0, so reaching the bound scores 0.5
+pub fn analyze_vs_self_with(
+    current: &PlayerStats,
+    history: &[PlayerStats],
+    config: &SelfBaselineConfig,
+) -> PlayerResult {
+    let mut running = SelfHistory::default();
+    history.iter().for_each(|round| running.observe(round));
+    running.analyze(current, config)
+}
+
+/// Running feature statistics of one player's rounds.
+///
+/// Streaming counterpart of the `history` of `analyze_vs_self_with`: observe
+/// every round as it ends and compare the next one with `analyze`, without
+/// keeping past rounds around. The struct is serializable so it can be
+/// persisted per player.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::baseline::{SelfBaselineConfig, SelfHistory};
+/// use nocheat::types::{Flag, PlayerStats};
+/// use std::collections::HashMap;
+///
+/// let round = |hits: u32| PlayerStats {
+///     player_id: "player123".to_string(),
+///     shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+///     hits: HashMap::from([("rifle".to_string(), hits)]),
+///     headshots: hits / 4,
+///     ..Default::default()
+/// };
+/// let mut history = SelfHistory::default();
+/// for i in 0..10 {
+///     history.observe(&round(40 + i % 5));
+/// }
+///
+/// let result = history.analyze(&round(85), &SelfBaselineConfig::default());
+/// assert!(result.has_flag(Flag::DeviationFromSelf));
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SelfHistory {
+    /// Statistics of every feature of `FEATURE_NAMES` with a defined value
+    /// in some round, keyed by feature name
+    pub features: BTreeMap<String, RunningStats>,
+}
+
+impl SelfHistory {
+    /// Add the features of a finished round; undefined values are skipped
+    pub fn observe(&mut self, round: &PlayerStats) {
+        let row = FeatureRow::from_stats(round);
+        for name in FEATURE_NAMES {
+            if let Some(value) = row.get(name).filter(|v| v.is_finite()) {
+                self.features
+                    .entry(name.to_string())
+                    .or_default()
+                    .update(value);
+            }
+        }
+    }
+
+    /// Compare a round with the observed ones, see `analyze_vs_self_with`
+    pub fn analyze(&self, current: &PlayerStats, config: &SelfBaselineConfig) -> PlayerResult {
+        let current_row = FeatureRow::from_stats(current);
+
+        // Feature, value, historical mean and z-score of the largest deviation
+        let mut worst: Option<(&str, f64, f64, f64)> = None;
+        for name in FEATURE_NAMES {
+            let Some(value) = current_row.get(name).filter(|v| v.is_finite()) else {
+                continue;
+            };
+            let Some(stats) = self.features.get(name) else {
+                continue;
+            };
+            if (stats.count as usize) < config.min_history.max(1) {
+                continue;
+            }
+            let z = (value - stats.mean) / stats.std().max(config.min_std);
+            if worst.is_none_or(|(_, _, _, worst_z)| z.abs() > worst_z.abs()) {
+                worst = Some((name, value, stats.mean, z));
+            }
+        }
+        self_result(current, worst, config)
+    }
+}
+
+/// Result of `SelfHistory::analyze` given its largest deviation
+fn self_result(
+    current: &PlayerStats,
+    worst: Option<(&str, f64, f64, f64)>,
+    config: &SelfBaselineConfig,
+) -> PlayerResult {
+    let mut result = PlayerResult {
+        player_id: current.player_id.clone(),
+        analyzed_at: Some(crate::inference::unix_millis()),
+        verdict: match worst {
+            Some(_) => Assessment::Clean,
+            None => Assessment::Insufficient,
+        },
+        ..Default::default()
+    };
+    if let Some((name, value, mean, z)) = worst {
+        let score = (z.abs() / (2.0 * config.n_sigma)).min(1.0) as f32;
+        result.suspicion_score = score;
+        result.predicted_label = score >= crate::inference::VERDICT_THRESHOLD;
+        if result.predicted_label {
+            result.verdict = Assessment::Suspicious;
+        }
+        if z.abs() > config.n_sigma {
+            result.flags.push(Flag::DeviationFromSelf.to_string());
+            result.flag_details.push(FlagDetail::new(
+                Flag::DeviationFromSelf,
+                z.abs() as f32,
+                config.n_sigma as f32,
+                format!(
+                    "{} {:.2} is {:.1} deviations from the player's mean {:.2}",
+                    name, value, z, mean
+                ),
+            ));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_running_stats_match_batch_computation() {
+        let values: Vec<f64> = (0..500)
+            .map(|i| 1000.0 + ((i * 37) % 101) as f64 / 7.0)
+            .collect();
+        let mut running = RunningStats::default();
+        values.iter().for_each(|&value| running.update(value));
+
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        assert_eq!(running.count, 500);
+        assert!((running.mean - mean).abs() < 1e-9);
+        assert!((running.variance - variance).abs() < 1e-9);
+        assert!((running.z_score(mean + 2.0 * variance.sqrt()) - 2.0).abs() < 1e-9);
+        assert_eq!(running.z_score(running.mean), 0.0);
+    }
+
+    #[test]
+    fn test_thresholds_from_baseline() {
+        // Hit rates 0.40, 0.41, ..., 0.60 and headshot rates of 0.25
+        let stats: Vec<PlayerStats> = (0..21)
+            .map(|i| PlayerStats {
+                player_id: format!("player{}", i),
+                shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+                hits: HashMap::from([("rifle".to_string(), 40 + i)]),
+                headshots: (40 + i) / 4,
+                ..Default::default()
+            })
+            .chain(std::iter::once(PlayerStats {
+                player_id: "idle".to_string(),
+                ..Default::default()
+            }))
+            .collect();
+
+        let baseline = compute_baseline(&stats).unwrap();
+        assert_eq!(baseline.players, 22);
+        let hit_rate = baseline.feature("hit_rate").unwrap();
+        assert!((hit_rate.mean - 0.5).abs() < 1e-6);
+        assert!((hit_rate.std - 0.060553).abs() < 1e-5);
+        assert!((hit_rate.percentiles[&50] - 0.5).abs() < 1e-6);
+        assert!((hit_rate.percentiles[&90] - 0.58).abs() < 1e-6);
+
+        let thresholds = Thresholds::from_baseline(&baseline, 3.0);
+        assert!((thresholds.high_hit_rate - 0.681659).abs() < 1e-5);
+        assert!(thresholds.high_headshot_rate < 0.3);
+        assert_eq!(thresholds.aim_snap_min_events, 3);
+
+        assert!(compute_baseline(&[]).is_err());
+    }
+
+    #[test]
+    fn test_weapon_specific_baselines() {
+        let player = |weapon: &str, shots: u32, hits: u32| PlayerStats {
+            player_id: "player123".to_string(),
+            shots_fired: HashMap::from([(weapon.to_string(), shots)]),
+            hits: HashMap::from([(weapon.to_string(), hits)]),
+            ..Default::default()
+        };
+
+        // Clean snipers hit about 70% of their shots, clean SMG players about 30%
+        let mut baselines = WeaponBaselines::default();
+        for round in 0..100 {
+            baselines.observe(&player("sniper", 20, 12 + round % 5));
+            baselines.observe(&player("smg", 200, 50 + round % 20));
+        }
+        assert!(baselines.weapons["sniper"].mean > 0.6);
+        assert!(baselines.weapons["smg"].mean < 0.35);
+
+        // The same 75% hit rate is normal for a sniper but not for an SMG
+        let smg = player("smg", 200, 150);
+        let sniper = player("sniper", 20, 15);
+        assert_eq!(baselines.outlier_weapons(&smg), vec!["smg".to_string()]);
+        assert!(baselines.outlier_weapons(&sniper).is_empty());
+
+        let mut result = PlayerResult::default();
+        baselines.flag(&smg, &mut result);
+        assert!(result.has_flag(Flag::WeaponHitRateOutlier));
+
+        let restored: WeaponBaselines =
+            serde_json::from_str(&serde_json::to_string(&baselines).unwrap()).unwrap();
+        assert_eq!(restored, baselines);
+    }
+
+    #[test]
+    fn test_deviation_from_own_history() {
+        let round = |hits: u32, headshots: u32| PlayerStats {
+            player_id: "player123".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 200)]),
+            hits: HashMap::from([("rifle".to_string(), hits)]),
+            headshots,
+            ..Default::default()
+        };
+        // A consistent player: ~35% hit rate, ~20% headshot rate
+        let history: Vec<PlayerStats> = (0..20).map(|i| round(66 + i % 8, 13 + i % 3)).collect();
+
+        let steady = analyze_vs_self(&round(70, 14), &history);
+        assert!(!steady.has_flag(Flag::DeviationFromSelf));
+        assert!(steady.suspicion_score < 0.5);
+
+        // The same player suddenly hits 70% of their shots, mostly headshots
+        let anomalous = analyze_vs_self(&round(140, 90), &history);
+        assert!(anomalous.has_flag(Flag::DeviationFromSelf));
+        assert_eq!(anomalous.suspicion_score, 1.0);
+        assert!(anomalous.predicted_label);
+        assert_eq!(anomalous.flag_details[0].threshold, 3.0);
+
+        // Too little history to judge
+        let result = analyze_vs_self(&round(140, 90), &history[..3]);
+        assert!(result.flags.is_empty());
+        assert_eq!(result.suspicion_score, 0.0);
+        assert!(analyze_vs_self(&round(140, 90), &[]).flags.is_empty());
+    }
+}
@@ -1,5 +1,7 @@
+use nocheat::analyzer::Analyzer;
+use nocheat::options::{load_whitelist, TrainingConfig};
 use nocheat::types::PlayerStats;
-use nocheat::{generate_default_model, train_model};
+use nocheat::{generate_default_model, load_model, train_model, train_model_with_report};
 use std::env;
 use std::fs::File;
 use std::io::{self, BufReader, Read};
@@ -9,11 +11,63 @@ fn print_usage() {
     println!("NoCheat Model Trainer");
     println!("Usage:");
     println!("  train default <output_path>               Generate a default model");
-    println!("  train custom <training_data> <output_path> Train a model with custom data");
+    println!("  train custom <training_data> <output_path> [--cv <folds>] [--report <path>]");
+    println!(
+        "                                             Train a model with custom data, optionally"
+    );
+    println!(
+        "                                             cross-validated and with a JSON training report"
+    );
+    println!("  train analyze <stats_json> [whitelist]     Analyze stats with the default model");
+    println!("  train score <model> <stats_json> [--threshold <score>]");
+    println!(
+        "                                             Print flagged players; exits with 2 when"
+    );
+    println!(
+        "                                             a score exceeds the threshold (default 0.5)"
+    );
+    println!(
+        "  train dump <model> [max_trees]              Print the split rules of the first trees"
+    );
     println!();
     println!("Examples:");
     println!("  train default cheat_model.bin");
     println!("  train custom training_data.json cheat_model.bin");
+    println!(
+        "  train custom training_data.json cheat_model.bin --cv 5 --report training_report.json"
+    );
+    println!("  train analyze round.json whitelist.txt");
+    println!("  train score cheat_model.bin round.json --threshold 0.8");
+    println!("  train dump cheat_model.bin 3");
+}
+
+/// Options of the custom command
+#[derive(Default)]
+struct CustomOptions {
+    cv_folds: Option<usize>,
+    report_path: Option<String>,
+}
+
+/// Parse the `--cv <folds>` and `--report <path>` options of the custom command
+fn parse_custom_options(options: &[String]) -> Option<CustomOptions> {
+    let mut parsed = CustomOptions::default();
+    for pair in options.chunks(2) {
+        match pair {
+            [flag, value] if flag == "--cv" => parsed.cv_folds = Some(value.parse().ok()?),
+            [flag, value] if flag == "--report" => parsed.report_path = Some(value.clone()),
+            _ => return None,
+        }
+    }
+    Some(parsed)
+}
+
+/// Parse the `--threshold <score>` option of the score command
+fn parse_threshold(options: &[String]) -> Option<f32> {
+    match options {
+        [] => Some(0.5),
+        [flag, value] if flag == "--threshold" => value.parse().ok(),
+        _ => None,
+    }
 }
 
 fn main() -> io::Result<()> {
@@ -45,10 +99,13 @@ fn main() -> io::Result<()> {
         }
 
         "custom" => {
-            if args.len() != 4 {
-                print_usage();
-                process::exit(1);
-            }
+            let options = match args.get(4..).map(parse_custom_options) {
+                Some(Some(options)) => options,
+                _ => {
+                    print_usage();
+                    process::exit(1);
+                }
+            };
 
             let training_data_path = &args[2];
             let output_path = &args[3];
@@ -94,14 +151,164 @@ fn main() -> io::Result<()> {
                 labels.len()
             );
 
-            if let Err(e) = train_model(training_data, labels, output_path) {
-                eprintln!("Error training model: {}", e);
-                process::exit(1);
+            if options.cv_folds.is_none() && options.report_path.is_none() {
+                if let Err(e) = train_model(training_data, labels, output_path) {
+                    eprintln!("Error training model: {}", e);
+                    process::exit(1);
+                }
+            } else {
+                let model_file = File::create(output_path)?;
+                let report = match train_model_with_report(
+                    training_data,
+                    labels,
+                    &TrainingConfig::default(),
+                    options.cv_folds,
+                    model_file,
+                ) {
+                    Ok(report) => report,
+                    Err(e) => {
+                        eprintln!("Error training model: {}", e);
+                        process::exit(1);
+                    }
+                };
+                if let Some(cv) = &report.cross_validation {
+                    println!(
+                        "{}-fold cross-validation: accuracy {:.3}, precision {:.3}, recall {:.3}, F1 {:.3}",
+                        cv.folds, cv.accuracy, cv.precision, cv.recall, cv.f1
+                    );
+                }
+                if let Some(report_path) = &options.report_path {
+                    if let Err(e) = report.write(File::create(report_path)?) {
+                        eprintln!("Error writing training report: {}", e);
+                        process::exit(1);
+                    }
+                    println!("Training report saved to: {}", report_path);
+                }
             }
 
             println!("Model successfully trained and saved to: {}", output_path);
         }
 
+        "analyze" => {
+            if args.len() != 3 && args.len() != 4 {
+                print_usage();
+                process::exit(1);
+            }
+
+            let file = File::open(&args[2])?;
+            let stats: Vec<PlayerStats> = match serde_json::from_reader(BufReader::new(file)) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Error parsing player stats: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            // Optional whitelist file with one player_id per line
+            let mut builder = Analyzer::builder();
+            if let Some(whitelist_path) = args.get(3) {
+                match load_whitelist(whitelist_path) {
+                    Ok(whitelist) => builder = builder.whitelist(whitelist),
+                    Err(e) => {
+                        eprintln!("Error reading whitelist: {}", e);
+                        process::exit(1);
+                    }
+                }
+            }
+
+            let response = builder.build().and_then(|analyzer| analyzer.analyze(stats));
+            match response {
+                Ok(response) => match serde_json::to_string_pretty(&response) {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => {
+                        eprintln!("Error serializing results: {}", e);
+                        process::exit(1);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Error analyzing stats: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+
+        "score" => {
+            if args.len() < 4 {
+                print_usage();
+                process::exit(1);
+            }
+            let threshold = match parse_threshold(&args[4..]) {
+                Some(threshold) => threshold,
+                None => {
+                    print_usage();
+                    process::exit(1);
+                }
+            };
+
+            let file = File::open(&args[3])?;
+            let stats: Vec<PlayerStats> = match serde_json::from_reader(BufReader::new(file)) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Error parsing player stats: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            let response = Analyzer::builder()
+                .model_path(&args[2])
+                .build()
+                .and_then(|analyzer| analyzer.analyze(stats));
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    eprintln!("Error analyzing stats: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            let flagged: Vec<_> = response.results.iter().filter(|r| r.is_flagged()).collect();
+            println!("{:<24} {:>6}  FLAGS", "PLAYER", "SCORE");
+            for result in &flagged {
+                println!(
+                    "{:<24} {:>6.3}  {}",
+                    result.player_id,
+                    result.suspicion_score,
+                    result.flags.join(",")
+                );
+            }
+            println!(
+                "{} of {} players flagged",
+                flagged.len(),
+                response.results.len()
+            );
+
+            if response
+                .results
+                .iter()
+                .any(|r| r.suspicion_score > threshold)
+            {
+                process::exit(2);
+            }
+        }
+
+        "dump" => {
+            let max_trees = match args.get(3).map(|value| value.parse()) {
+                None => 1,
+                Some(Ok(max_trees)) if args.len() == 4 => max_trees,
+                _ => {
+                    print_usage();
+                    process::exit(1);
+                }
+            };
+
+            let result =
+                load_model(&args[2]).and_then(|model| model.dump_trees(io::stdout(), max_trees));
+            if let Err(e) = result {
+                eprintln!("Error dumping model: {}", e);
+                process::exit(1);
+            }
+        }
+
         _ => {
             print_usage();
             process::exit(1);
@@ -0,0 +1,298 @@
+//! Batch-level detection of players sharing the same cheat software.
+//!
+//! A cheat provider's software tends to fire with the same inter-shot timing
+//! on every account using it. Unlike the per-player checks of `inference`,
+//! these checks correlate players of one batch with each other, as does the
+//! comparison of a player's score with its teammates (`flag_team_outliers`).
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::types::{Flag, FlagDetail, PlayerResult, PlayerStats};
+
+/// Match and team of a player, the scope of `flag_team_outliers`
+pub(crate) type TeamKey = (Option<String>, String);
+
+/// Scope of a player in `flag_team_outliers`, `None` for players without a team
+pub(crate) fn team_key(stats: &PlayerStats) -> Option<TeamKey> {
+    Some((stats.match_id.clone(), stats.team.clone()?))
+}
+
+/// Raise `TeamOutlier` on players scoring far above their own teammates.
+///
+/// Catches a single cheater on an otherwise clean team. Players sharing a
+/// `team` and a `match_id` form a team; the score of each player is compared
+/// with the mean score of its teammates (excluding the player itself, so the
+/// outlier does not raise its own baseline), and the flag is added when it
+/// exceeds that mean by more than `margin`. Players without a team, alone in
+/// their team, or whitelisted are neither flagged nor counted.
+///
+/// # Arguments
+///
+/// * `stats` - Players of one batch
+/// * `results` - Their results, in the same order as `stats`
+/// * `margin` - Score above the teammates' mean at which a player is flagged
+///
+/// # Example
+///
+/// ```
+/// use nocheat::collusion::flag_team_outliers;
+/// use nocheat::types::{Flag, PlayerResult, PlayerStats};
+///
+/// let player = |id: &str| PlayerStats {
+///     player_id: id.to_string(),
+///     team: Some("red".to_string()),
+///     ..Default::default()
+/// };
+/// let stats = vec![player("a"), player("b"), player("c")];
+/// let mut results = vec![
+///     PlayerResult::new("a", 0.1, vec![]),
+///     PlayerResult::new("b", 0.2, vec![]),
+///     PlayerResult::new("c", 0.8, vec![]),
+/// ];
+///
+/// flag_team_outliers(&stats, &mut results, 0.4);
+/// assert!(results[2].has_flag(Flag::TeamOutlier));
+/// assert!(!results[0].has_flag(Flag::TeamOutlier));
+/// ```
+pub fn flag_team_outliers(stats: &[PlayerStats], results: &mut [PlayerResult], margin: f32) {
+    let teams: Vec<Option<TeamKey>> = stats.iter().map(team_key).collect();
+    flag_team_outliers_by_key(&teams, results, margin);
+}
+
+/// `flag_team_outliers` on the team of every result
+pub(crate) fn flag_team_outliers_by_key(
+    teams: &[Option<TeamKey>],
+    results: &mut [PlayerResult],
+    margin: f32,
+) {
+    let counted = |result: &PlayerResult| !result.has_flag(Flag::Whitelisted);
+    let mut totals: HashMap<&TeamKey, (f32, usize)> = HashMap::new();
+    for (team, result) in teams.iter().zip(results.iter()) {
+        if let Some(team) = team.as_ref().filter(|_| counted(result)) {
+            let total = totals.entry(team).or_default();
+            total.0 += result.suspicion_score;
+            total.1 += 1;
+        }
+    }
+
+    for (team, result) in teams.iter().zip(results.iter_mut()) {
+        let Some(team) = team.as_ref().filter(|_| counted(result)) else {
+            continue;
+        };
+        let (sum, count) = totals[team];
+        if count < 2 {
+            continue;
+        }
+        let teammates_mean = (sum - result.suspicion_score) / (count - 1) as f32;
+        let threshold = teammates_mean + margin;
+        if result.suspicion_score > threshold {
+            result.flags.push(Flag::TeamOutlier.to_string());
+            result.flag_details.push(FlagDetail::new(
+                Flag::TeamOutlier,
+                result.suspicion_score,
+                threshold,
+                format!(
+                    "score {:.2} above teammates' mean {:.2} by more than {:.2}",
+                    result.suspicion_score, teammates_mean, margin
+                ),
+            ));
+        }
+    }
+}
+
+/// Settings of `detect_timing_collusion_with`
+#[derive(Debug, Clone)]
+pub struct CollusionConfig {
+    /// Maximum Kolmogorov-Smirnov distance between two interval distributions
+    /// for the players to be grouped together
+    pub max_distance: f64,
+    /// Minimum number of inter-shot intervals a player needs to be compared
+    pub min_intervals: usize,
+}
+
+impl Default for CollusionConfig {
+    fn default() -> Self {
+        Self {
+            max_distance: 0.1,
+            min_intervals: 20,
+        }
+    }
+}
+
+/// Group players whose inter-shot timing is suspiciously similar.
+///
+/// Equivalent to `detect_timing_collusion_with` with the default `CollusionConfig`.
+///
+/// # Arguments
+///
+/// * `stats` - Players of one batch
+///
+/// # Returns
+///
+/// * `Vec<Vec<String>>` - Groups of at least two colluding player_ids
+///
+/// # Example
+///
+/// ```
+/// use nocheat::collusion::detect_timing_collusion;
+/// use nocheat::types::PlayerStats;
+///
+/// let scripted = |id: &str| PlayerStats {
+///     player_id: id.to_string(),
+///     shot_timestamps_ms: Some((0..40).map(|i| i * 87).collect::<Vec<u64>>().into()),
+///     ..Default::default()
+/// };
+///
+/// let groups = detect_timing_collusion(&[scripted("bot1"), scripted("bot2")]);
+/// assert_eq!(groups, vec![vec!["bot1".to_string(), "bot2".to_string()]]);
+/// ```
+pub fn detect_timing_collusion(stats: &[PlayerStats]) -> Vec<Vec<String>> {
+    detect_timing_collusion_with(stats, &CollusionConfig::default())
+}
+
+/// Group players whose inter-shot interval distributions are suspiciously similar.
+///
+/// The intervals between consecutive `shot_timestamps_ms` of every player
+/// with at least `min_intervals` intervals are compared pairwise with the
+/// two-sample Kolmogorov-Smirnov distance. Only players of the same
+/// `match_id` are compared (players without one form a scope of their own).
+/// Players closer than `max_distance` are linked, and every connected set of linked players forms
+/// a group. Player_ids within a group and the groups themselves are sorted.
+///
+/// # Arguments
+///
+/// * `stats` - Players of one batch
+/// * `config` - Similarity settings
+///
+/// # Returns
+///
+/// * `Vec<Vec<String>>` - Groups of at least two colluding player_ids
+pub fn detect_timing_collusion_with(
+    stats: &[PlayerStats],
+    config: &CollusionConfig,
+) -> Vec<Vec<String>> {
+    let players: Vec<(&PlayerStats, Vec<u64>)> = stats
+        .iter()
+        .filter_map(|stat| {
+            let intervals = sorted_intervals(&stat.shot_timestamps_ms.as_ref()?.all());
+            (intervals.len() >= config.min_intervals.max(1)).then_some((stat, intervals))
+        })
+        .collect();
+
+    // Union-find over the players of the batch
+    let mut parent: Vec<usize> = (0..players.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..players.len() {
+        for j in i + 1..players.len() {
+            if players[i].0.match_id == players[j].0.match_id
+                && ks_distance(&players[i].1, &players[j].1) <= config.max_distance
+            {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (i, (stat, _)) in players.iter().enumerate() {
+        let group = root(&mut parent, i);
+        groups
+            .entry(group)
+            .or_default()
+            .push(stat.player_id.clone());
+    }
+    let mut groups: Vec<Vec<String>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect();
+    groups.sort();
+    groups
+}
+
+/// Intervals between consecutive timestamps, sorted ascending
+fn sorted_intervals(timestamps: &[u64]) -> Vec<u64> {
+    let mut intervals: Vec<u64> = timestamps
+        .windows(2)
+        .map(|pair| pair[1].saturating_sub(pair[0]))
+        .collect();
+    intervals.sort_unstable();
+    intervals
+}
+
+/// Largest gap between the empirical CDFs of two sorted, non-empty samples
+fn ks_distance(a: &[u64], b: &[u64]) -> f64 {
+    let (mut i, mut j) = (0, 0);
+    let mut distance: f64 = 0.0;
+    while i < a.len() && j < b.len() {
+        let value = a[i].min(b[j]);
+        while i < a.len() && a[i] == value {
+            i += 1;
+        }
+        while j < b.len() && b[j] == value {
+            j += 1;
+        }
+        let gap = (i as f64 / a.len() as f64 - j as f64 / b.len() as f64).abs();
+        distance = distance.max(gap);
+    }
+    distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_intervals(player_id: &str, intervals: &[u64]) -> PlayerStats {
+        let mut timestamps = vec![1_000];
+        for interval in intervals {
+            timestamps.push(timestamps.last().unwrap() + interval);
+        }
+        PlayerStats {
+            player_id: player_id.to_string(),
+            shot_timestamps_ms: Some(timestamps.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_groups_players_sharing_a_timing_signature() {
+        // The cheat alternates between two fixed delays
+        let signature: Vec<u64> = (0..40).map(|i| if i % 2 == 0 { 83 } else { 117 }).collect();
+        let mut shifted = signature.clone();
+        shifted.rotate_left(1);
+        // Irregular human timing
+        let human: Vec<u64> = (0..40).map(|i| 150 + (i * 37 % 23) * 11).collect();
+
+        let stats = vec![
+            with_intervals("cheater_b", &shifted),
+            with_intervals("human", &human),
+            with_intervals("cheater_a", &signature),
+            with_intervals("too_short", &signature[..5]),
+        ];
+        assert_eq!(
+            detect_timing_collusion(&stats),
+            vec![vec!["cheater_a".to_string(), "cheater_b".to_string()]]
+        );
+    }
+
+    #[test]
+    fn test_players_of_different_matches_are_not_grouped() {
+        let signature: Vec<u64> = vec![100; 30];
+        let in_match = |player_id: &str, match_id: &str| PlayerStats {
+            match_id: Some(match_id.to_string()),
+            ..with_intervals(player_id, &signature)
+        };
+
+        let stats = vec![in_match("a", "match1"), in_match("b", "match2")];
+        assert!(detect_timing_collusion(&stats).is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+//! Monitoring of feature drift between the training data and live traffic.
+//!
+//! A model trained months ago degrades as the game meta shifts. The
+//! population stability index (PSI) of every feature compares the share of
+//! players per training decile with the share observed in a new batch:
+//! below 0.1 the population is stable, above 0.25 it has shifted enough to
+//! consider retraining.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::baseline::{percentile, sorted_feature_values};
+use crate::inference::FEATURE_NAMES;
+use crate::options::FeaturePrecision;
+use crate::types::PlayerStats;
+
+/// Lower bound of a bin share, so empty bins keep the PSI finite
+const MIN_SHARE: f64 = 1e-4;
+
+/// Training distribution of one feature, binned at its deciles
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FeatureBins {
+    /// Upper bounds of every bin but the last, ascending
+    pub edges: Vec<f64>,
+    /// Share of training players in every bin
+    pub expected: Vec<f64>,
+}
+
+impl FeatureBins {
+    fn fit(sorted: &[f64]) -> Self {
+        let mut edges: Vec<f64> = (1..10).map(|d| percentile(sorted, d * 10)).collect();
+        edges.dedup();
+        let expected = Self::shares(&edges, sorted);
+        Self { edges, expected }
+    }
+
+    /// Share of `values` falling in every bin
+    fn shares(edges: &[f64], values: &[f64]) -> Vec<f64> {
+        let mut counts = vec![0usize; edges.len() + 1];
+        for value in values {
+            counts[edges.partition_point(|edge| edge < value)] += 1;
+        }
+        counts
+            .iter()
+            .map(|&count| count as f64 / values.len().max(1) as f64)
+            .collect()
+    }
+
+    /// Population stability index of `values` against the training shares
+    fn psi(&self, values: &[f64]) -> f64 {
+        Self::shares(&self.edges, values)
+            .iter()
+            .zip(&self.expected)
+            .map(|(&actual, &expected)| {
+                let (actual, expected) = (actual.max(MIN_SHARE), expected.max(MIN_SHARE));
+                (actual - expected) * (actual / expected).ln()
+            })
+            .sum()
+    }
+}
+
+/// Drift of one feature in a batch
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FeatureDrift {
+    /// Population stability index against the training distribution
+    pub psi: f64,
+    /// Whether `psi` exceeds the monitor's threshold
+    pub drifted: bool,
+}
+
+/// Result of `DriftMonitor::drift_report`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DriftReport {
+    /// Number of players in the batch
+    pub players: usize,
+    /// Drift of every monitored feature, keyed by feature name
+    pub features: BTreeMap<String, FeatureDrift>,
+}
+
+impl DriftReport {
+    /// Whether any feature drifted
+    pub fn has_drift(&self) -> bool {
+        self.features.values().any(|drift| drift.drifted)
+    }
+
+    /// Names of the drifted features
+    pub fn drifted_features(&self) -> Vec<&str> {
+        self.features
+            .iter()
+            .filter(|(_, drift)| drift.drifted)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// One human-readable warning per drifted feature
+    pub fn warnings(&self) -> Vec<String> {
+        self.features
+            .iter()
+            .filter(|(_, drift)| drift.drifted)
+            .map(|(name, drift)| {
+                format!(
+                    "Feature {} drifted from the training data (PSI {:.3})",
+                    name, drift.psi
+                )
+            })
+            .collect()
+    }
+}
+
+/// Training feature distributions compared against new batches.
+///
+/// Fit it on the training data when a model is trained, store it next to the
+/// model (it is serializable), and check live batches with `drift_report`.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::drift::DriftMonitor;
+/// use nocheat::generate_synthetic_dataset;
+///
+/// let (training_data, _) = generate_synthetic_dataset();
+/// let monitor = DriftMonitor::fit(&training_data).expect("Failed to fit monitor");
+///
+/// let report = monitor.drift_report(&training_data).expect("Failed to compute drift");
+/// assert!(!report.has_drift());
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DriftMonitor {
+    /// PSI above which a feature counts as drifted (0.25 by default)
+    pub threshold: f64,
+    /// Training distribution of every feature of `FEATURE_NAMES`
+    pub features: BTreeMap<String, FeatureBins>,
+}
+
+impl DriftMonitor {
+    /// Record the feature distributions of the training data.
+    ///
+    /// # Arguments
+    ///
+    /// * `training_data` - The players the model was trained on
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DriftMonitor>` - The monitor, or an error if no player has a
+    ///   defined value for some feature
+    pub fn fit(training_data: &[PlayerStats]) -> Result<Self> {
+        let rows = crate::feature_rows(training_data, FeaturePrecision::F32)?;
+        let mut features = BTreeMap::new();
+        for name in FEATURE_NAMES {
+            let values = sorted_feature_values(&rows, name);
+            if values.is_empty() {
+                return Err(anyhow::anyhow!("No defined values for feature {}", name));
+            }
+            features.insert(name.to_string(), FeatureBins::fit(&values));
+        }
+        Ok(Self {
+            threshold: 0.25,
+            features,
+        })
+    }
+
+    /// Use a different PSI threshold
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Compare a batch with the training distributions.
+    ///
+    /// Undefined feature values (e.g. the hit rate of a player who fired no
+    /// shots) are left out, as during fitting.
+    ///
+    /// # Arguments
+    ///
+    /// * `batch` - Recent players to check
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DriftReport>` - The PSI of every feature
+    pub fn drift_report(&self, batch: &[PlayerStats]) -> Result<DriftReport> {
+        let rows = crate::feature_rows(batch, FeaturePrecision::F32)?;
+        let features = self
+            .features
+            .iter()
+            .map(|(name, bins)| {
+                let psi = bins.psi(&sorted_feature_values(&rows, name));
+                let drift = FeatureDrift {
+                    psi,
+                    drifted: psi > self.threshold,
+                };
+                (name.clone(), drift)
+            })
+            .collect();
+        Ok(DriftReport {
+            players: batch.len(),
+            features,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn players(hits: impl Iterator<Item = u32>) -> Vec<PlayerStats> {
+        hits.map(|h| PlayerStats {
+            player_id: format!("player{}", h),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), h)]),
+            headshots: 10,
+            ..Default::default()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_shifted_hit_rate_raises_psi() {
+        let monitor = DriftMonitor::fit(&players(30..70)).unwrap();
+
+        let stable = monitor.drift_report(&players(31..69)).unwrap();
+        assert!(stable.features["hit_rate"].psi < 0.1);
+        assert!(!stable.has_drift());
+
+        // The meta shifted towards higher accuracy
+        let shifted = monitor.drift_report(&players(55..95)).unwrap();
+        assert!(shifted.features["hit_rate"].psi > 0.25);
+        assert!(shifted.drifted_features().contains(&"hit_rate"));
+        assert_eq!(shifted.warnings().len(), shifted.drifted_features().len());
+    }
+}
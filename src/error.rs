@@ -0,0 +1,117 @@
+//! Typed errors for failures callers may want to inspect.
+//!
+//! Public functions still return `anyhow::Result`; errors of this module are
+//! wrapped in it and can be recovered with `anyhow::Error::downcast_ref`.
+
+use std::fmt;
+
+/// Step of the feature pipeline in which an error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureStage {
+    /// Building the DataFrame or computing an engineered column
+    Build,
+    /// Reading a column out of the DataFrame
+    Extract,
+}
+
+impl fmt::Display for FeatureStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FeatureStage::Build => "build",
+            FeatureStage::Extract => "extract",
+        })
+    }
+}
+
+/// Error raised by the crate with context the underlying library lacks.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "dataframe")]
+/// # {
+/// use nocheat::error::NoCheatError;
+/// use nocheat::{build_dataframe, df_to_ndarray};
+///
+/// let df = build_dataframe(&[]).unwrap();
+/// let error = df_to_ndarray(&df, &["aim_score"]).unwrap_err();
+/// match error.downcast_ref::<NoCheatError>() {
+///     Some(NoCheatError::Feature { column, .. }) => assert_eq!(column, "aim_score"),
+///     _ => panic!("unexpected error: {}", error),
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum NoCheatError {
+    /// A feature column could not be built or read
+    Feature {
+        /// Name of the column being built or read; columns built together
+        /// are listed comma-separated
+        column: String,
+        /// Step of the feature pipeline that failed
+        stage: FeatureStage,
+        /// Error reported by the DataFrame library
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// A model file exceeds `ModelLoadLimits::max_bytes`
+    ModelTooLarge {
+        /// Size of the file in bytes
+        size: u64,
+        /// Largest accepted size in bytes
+        limit: u64,
+    },
+    /// Deserializing a model took longer than `ModelLoadLimits::timeout`
+    ModelLoadTimeout {
+        /// Time allowed for deserialization
+        timeout: std::time::Duration,
+    },
+}
+
+impl NoCheatError {
+    /// Wrap a DataFrame error raised while handling `column`
+    #[cfg(feature = "dataframe")]
+    pub(crate) fn feature<E>(column: &str, stage: FeatureStage) -> impl FnOnce(E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let column = column.to_string();
+        move |source| NoCheatError::Feature {
+            column,
+            stage,
+            source: Box::new(source),
+        }
+    }
+}
+
+impl fmt::Display for NoCheatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoCheatError::Feature {
+                column,
+                stage,
+                source,
+            } => write!(
+                f,
+                "Feature column '{}' failed at the {} stage: {}",
+                column, stage, source
+            ),
+            NoCheatError::ModelTooLarge { size, limit } => write!(
+                f,
+                "Model file of {} bytes exceeds the limit of {} bytes",
+                size, limit
+            ),
+            NoCheatError::ModelLoadTimeout { timeout } => {
+                write!(f, "Model deserialization exceeded {:?}", timeout)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NoCheatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NoCheatError::Feature { source, .. } => Some(source.as_ref()),
+            NoCheatError::ModelTooLarge { .. } | NoCheatError::ModelLoadTimeout { .. } => None,
+        }
+    }
+}
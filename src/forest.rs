@@ -0,0 +1,537 @@
+//! Structural view of a serialized RandomForest.
+//!
+//! The `randomforest` crate keeps its trees private, so this module parses its
+//! byte format (big-endian column count and types, tree count, then every tree
+//! as a pre-order list of leaf/split nodes) into a plain tree structure. It also
+//! implements the compact encoding used by `CheatModel::serialize_compact`:
+//!
+//! ```text
+//! u16 column count | u8 column types | varint node count | nodes | u16 tree count | varint roots
+//! leaf:  u8 0 | f32 value
+//! split: u8 1 | u16 column | f32 threshold | varint left | varint right
+//! ```
+//!
+//! Compact nodes live in a shared pool where every distinct subtree is stored
+//! once and referenced by index, and all numbers are little-endian.
+//!
+//! Both formats may come from untrusted files, so parsing rejects trees deeper
+//! than `MAX_TREE_DEPTH` and forests expanding to more than `MAX_NODES` nodes.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Deepest tree accepted when parsing either format
+pub(crate) const MAX_TREE_DEPTH: usize = 1024;
+
+/// Most nodes, summed over all trees, accepted when parsing either format
+pub(crate) const MAX_NODES: usize = 1 << 22;
+
+/// A node of a decision tree
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Node {
+    /// Terminal node holding the predicted value
+    Leaf(f64),
+    /// Internal node sending inputs left when they satisfy the split
+    Split {
+        column: u16,
+        value: f64,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+/// Every tree of a forest along with its column types
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ForestLayout {
+    /// Raw column types (0 = numerical, 1 = categorical)
+    pub columns: Vec<u8>,
+    /// Root node of every tree
+    pub trees: Vec<Node>,
+}
+
+/// Bounds-checked reader over a byte buffer
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(anyhow::anyhow!("Truncated forest data"));
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16_be(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u16_le(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn f64_be(&mut self) -> Result<f64> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(f64::from_be_bytes(b))
+    }
+
+    fn f32_le(&mut self) -> Result<f32> {
+        let mut b = [0u8; 4];
+        b.copy_from_slice(self.take(4)?);
+        Ok(f32::from_le_bytes(b))
+    }
+
+    fn varint(&mut self) -> Result<usize> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow::anyhow!("Invalid varint in forest data"))
+    }
+}
+
+/// Append an unsigned LEB128 varint
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn too_deep() -> anyhow::Error {
+    anyhow::anyhow!("Forest tree exceeds the depth limit of {}", MAX_TREE_DEPTH)
+}
+
+fn too_many_nodes() -> anyhow::Error {
+    anyhow::anyhow!("Forest exceeds the limit of {} nodes", MAX_NODES)
+}
+
+impl Node {
+    /// Parse a node at `depth`, counting it and its descendants into `nodes`
+    fn parse(cursor: &mut Cursor, depth: usize, nodes: &mut usize) -> Result<Self> {
+        if depth >= MAX_TREE_DEPTH {
+            return Err(too_deep());
+        }
+        *nodes += 1;
+        if *nodes > MAX_NODES {
+            return Err(too_many_nodes());
+        }
+        match cursor.u8()? {
+            0 => Ok(Node::Leaf(cursor.f64_be()?)),
+            1 => {
+                let column = cursor.u16_be()?;
+                let value = cursor.f64_be()?;
+                let left = Box::new(Node::parse(cursor, depth + 1, nodes)?);
+                let right = Box::new(Node::parse(cursor, depth + 1, nodes)?);
+                Ok(Node::Split {
+                    column,
+                    value,
+                    left,
+                    right,
+                })
+            }
+            kind => Err(anyhow::anyhow!("Unknown node type {}", kind)),
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Node::Leaf(value) => {
+                out.push(0);
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+            Node::Split {
+                column,
+                value,
+                left,
+                right,
+            } => {
+                out.push(1);
+                out.extend_from_slice(&column.to_be_bytes());
+                out.extend_from_slice(&value.to_be_bytes());
+                left.write(out);
+                right.write(out);
+            }
+        }
+    }
+
+    fn write_text<W: Write>(
+        &self,
+        out: &mut W,
+        columns: &[u8],
+        names: &[String],
+        depth: usize,
+    ) -> Result<()> {
+        let indent = "  ".repeat(depth);
+        match self {
+            Node::Leaf(value) => writeln!(out, "{}predict {}", indent, value)?,
+            Node::Split {
+                column,
+                value,
+                left,
+                right,
+            } => {
+                let index = *column as usize;
+                let name = names
+                    .get(index)
+                    .cloned()
+                    .unwrap_or_else(|| format!("column_{}", index));
+                // Mirrors ColumnType::is_left: categorical columns split on equality
+                let op = if columns.get(index) == Some(&1) {
+                    "=="
+                } else {
+                    "<="
+                };
+                writeln!(out, "{}if {} {} {:.4}:", indent, name, op, value)?;
+                left.write_text(out, columns, names, depth + 1)?;
+                writeln!(out, "{}else:", indent)?;
+                right.write_text(out, columns, names, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the node to the compact pool, returning its index.
+    ///
+    /// Children are pooled first, so a node only references lower indices and
+    /// identical subtrees (after quantization) resolve to the same index.
+    fn pool(&self, pool: &mut CompactPool) -> usize {
+        let key = match self {
+            Node::Leaf(value) => (0, 0, (*value as f32).to_bits(), 0, 0),
+            Node::Split {
+                column,
+                value,
+                left,
+                right,
+            } => {
+                let left = left.pool(pool);
+                let right = right.pool(pool);
+                (1, *column, (*value as f32).to_bits(), left, right)
+            }
+        };
+        let next = pool.nodes.len();
+        *pool.index.entry(key).or_insert_with(|| {
+            pool.nodes.push(key);
+            next
+        })
+    }
+}
+
+/// Pooled compact node: (kind, column, f32 bits, left index, right index)
+type CompactNode = (u8, u16, u32, usize, usize);
+
+/// Deduplicated node pool of the compact encoding
+#[derive(Default)]
+struct CompactPool {
+    nodes: Vec<CompactNode>,
+    index: HashMap<CompactNode, usize>,
+}
+
+impl ForestLayout {
+    /// Parse the byte format written by `RandomForestClassifier::serialize`
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor { bytes };
+        let column_count = cursor.u16_be()? as usize;
+        let columns = cursor.take(column_count)?.to_vec();
+        let tree_count = cursor.u16_be()? as usize;
+        let mut nodes = 0;
+        let trees = (0..tree_count)
+            .map(|_| Node::parse(&mut cursor, 0, &mut nodes))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { columns, trees })
+    }
+
+    /// Write the byte format read by `RandomForestClassifier::deserialize`
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.columns.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.columns);
+        out.extend_from_slice(&(self.trees.len() as u16).to_be_bytes());
+        for tree in &self.trees {
+            tree.write(out);
+        }
+    }
+
+    /// Append the trees of `other`, which must use the same columns
+    pub fn merge(mut self, other: ForestLayout) -> Result<Self> {
+        if self.columns != other.columns {
+            return Err(anyhow::anyhow!(
+                "Cannot merge forests with different inputs ({} and {} columns)",
+                self.columns.len(),
+                other.columns.len()
+            ));
+        }
+        if self.trees.len() + other.trees.len() > u16::MAX as usize {
+            return Err(anyhow::anyhow!(
+                "Merged forest would exceed {} trees",
+                u16::MAX
+            ));
+        }
+        self.trees.extend(other.trees);
+        Ok(self)
+    }
+
+    /// Write the compact encoding, quantizing thresholds and leaves to `f32`
+    pub fn write_compact(&self, out: &mut Vec<u8>) {
+        let mut pool = CompactPool::default();
+        let roots: Vec<usize> = self.trees.iter().map(|tree| tree.pool(&mut pool)).collect();
+
+        out.extend_from_slice(&(self.columns.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.columns);
+        write_varint(out, pool.nodes.len());
+        for &(kind, column, bits, left, right) in &pool.nodes {
+            out.push(kind);
+            if kind == 1 {
+                out.extend_from_slice(&column.to_le_bytes());
+            }
+            out.extend_from_slice(&bits.to_le_bytes());
+            if kind == 1 {
+                write_varint(out, left);
+                write_varint(out, right);
+            }
+        }
+        out.extend_from_slice(&(roots.len() as u16).to_le_bytes());
+        for root in roots {
+            write_varint(out, root);
+        }
+    }
+
+    /// Distinct leaf values of all trees in ascending order, i.e. the classes of a classifier
+    pub fn classes(&self) -> Vec<f64> {
+        fn collect(node: &Node, out: &mut Vec<f64>) {
+            match node {
+                Node::Leaf(value) => out.push(*value),
+                Node::Split { left, right, .. } => {
+                    collect(left, out);
+                    collect(right, out);
+                }
+            }
+        }
+
+        let mut classes = Vec::new();
+        for tree in &self.trees {
+            collect(tree, &mut classes);
+        }
+        classes.sort_by(f64::total_cmp);
+        classes.dedup();
+        classes
+    }
+
+    /// Write the first `max_trees` trees as indented `if`/`else` rules, naming
+    /// columns after `names` (falling back to `column_{index}`)
+    pub fn write_text<W: Write>(
+        &self,
+        out: &mut W,
+        names: &[String],
+        max_trees: usize,
+    ) -> Result<()> {
+        for (index, tree) in self.trees.iter().take(max_trees).enumerate() {
+            writeln!(out, "tree {}:", index)?;
+            tree.write_text(out, &self.columns, names, 1)?;
+        }
+        let remaining = self.trees.len().saturating_sub(max_trees);
+        if remaining > 0 {
+            writeln!(out, "... {} more trees", remaining)?;
+        }
+        Ok(())
+    }
+
+    /// Number of split nodes testing every column, across all trees
+    pub fn split_counts(&self) -> Vec<usize> {
+        fn count(node: &Node, out: &mut [usize]) {
+            if let Node::Split {
+                column,
+                left,
+                right,
+                ..
+            } = node
+            {
+                if let Some(slot) = out.get_mut(*column as usize) {
+                    *slot += 1;
+                }
+                count(left, out);
+                count(right, out);
+            }
+        }
+
+        let mut counts = vec![0; self.columns.len()];
+        for tree in &self.trees {
+            count(tree, &mut counts);
+        }
+        counts
+    }
+
+    /// Parse the compact encoding written by `write_compact`.
+    ///
+    /// Pooled subtrees are expanded into every tree referencing them, so the
+    /// expanded size and depth are computed from the pool and checked against
+    /// the limits before any tree is built.
+    pub fn parse_compact(bytes: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor { bytes };
+        let column_count = cursor.u16_le()? as usize;
+        let columns = cursor.take(column_count)?.to_vec();
+
+        let node_count = cursor.varint()?;
+        let mut pool: Vec<CompactNode> = Vec::new();
+        // Expanded node count and depth of the subtree rooted at every pooled node
+        let mut sizes: Vec<(usize, usize)> = Vec::new();
+        for _ in 0..node_count {
+            let (node, size) = match cursor.u8()? {
+                0 => ((0, 0, cursor.f32_le()?.to_bits(), 0, 0), (1, 1)),
+                1 => {
+                    let column = cursor.u16_le()?;
+                    let bits = cursor.f32_le()?.to_bits();
+                    let left = cursor.varint()?;
+                    let right = cursor.varint()?;
+                    let (Some(l), Some(r)) = (sizes.get(left), sizes.get(right)) else {
+                        return Err(anyhow::anyhow!("Invalid child index in compact forest"));
+                    };
+                    let count = l.0.saturating_add(r.0).saturating_add(1);
+                    let depth = l.1.max(r.1) + 1;
+                    if count > MAX_NODES {
+                        return Err(too_many_nodes());
+                    }
+                    if depth > MAX_TREE_DEPTH {
+                        return Err(too_deep());
+                    }
+                    ((1, column, bits, left, right), (count, depth))
+                }
+                kind => return Err(anyhow::anyhow!("Unknown node type {}", kind)),
+            };
+            pool.push(node);
+            sizes.push(size);
+        }
+
+        let tree_count = cursor.u16_le()? as usize;
+        let roots = (0..tree_count)
+            .map(|_| {
+                let root = cursor.varint()?;
+                if root >= pool.len() {
+                    return Err(anyhow::anyhow!("Invalid root index in compact forest"));
+                }
+                Ok(root)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let total = roots
+            .iter()
+            .fold(0usize, |total, &root| total.saturating_add(sizes[root].0));
+        if total > MAX_NODES {
+            return Err(too_many_nodes());
+        }
+
+        fn expand(pool: &[CompactNode], index: usize) -> Node {
+            let (kind, column, bits, left, right) = pool[index];
+            if kind == 0 {
+                return Node::Leaf(f32::from_bits(bits) as f64);
+            }
+            Node::Split {
+                column,
+                value: f32::from_bits(bits) as f64,
+                left: Box::new(expand(pool, left)),
+                right: Box::new(expand(pool, right)),
+            }
+        }
+
+        let trees = roots.into_iter().map(|root| expand(&pool, root)).collect();
+        Ok(Self { columns, trees })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(column: u16, value: f64, left: Node, right: Node) -> Node {
+        Node::Split {
+            column,
+            value,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    #[test]
+    fn test_layout_roundtrip() {
+        let layout = ForestLayout {
+            columns: vec![0, 0],
+            trees: vec![
+                split(0, 0.75, Node::Leaf(0.0), Node::Leaf(1.0)),
+                split(
+                    1,
+                    0.5,
+                    split(0, 0.75, Node::Leaf(0.0), Node::Leaf(1.0)),
+                    Node::Leaf(1.0),
+                ),
+            ],
+        };
+
+        let mut full = Vec::new();
+        layout.write(&mut full);
+        assert_eq!(ForestLayout::parse(&full).unwrap(), layout);
+
+        // The shared subtree and leaves are pooled once
+        let mut compact = Vec::new();
+        layout.write_compact(&mut compact);
+        assert!(compact.len() < full.len());
+        assert_eq!(ForestLayout::parse_compact(&compact).unwrap(), layout);
+
+        assert!(ForestLayout::parse(&full[..full.len() - 1]).is_err());
+        assert_eq!(layout.classes(), vec![0.0, 1.0]);
+        assert_eq!(layout.split_counts(), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_self_doubling_compact_forest_is_rejected() {
+        // Node i splits into node i - 1 twice, doubling the expanded size each time
+        let mut compact = vec![1, 0, 0];
+        let nodes = 200;
+        write_varint(&mut compact, nodes);
+        compact.push(0);
+        compact.extend_from_slice(&1.0f32.to_le_bytes());
+        for i in 1..nodes {
+            compact.push(1);
+            compact.extend_from_slice(&0u16.to_le_bytes());
+            compact.extend_from_slice(&0.5f32.to_le_bytes());
+            write_varint(&mut compact, i - 1);
+            write_varint(&mut compact, i - 1);
+        }
+        compact.extend_from_slice(&1u16.to_le_bytes());
+        write_varint(&mut compact, nodes - 1);
+        assert!(compact.len() < 2000);
+
+        let error = ForestLayout::parse_compact(&compact).unwrap_err();
+        assert!(error.to_string().contains("nodes"), "{}", error);
+    }
+
+    #[test]
+    fn test_deep_forest_is_rejected() {
+        // A chain of splits deeper than the limit, in the full format
+        let mut full = vec![0, 1, 0, 0, 1];
+        for _ in 0..=MAX_TREE_DEPTH {
+            full.push(1);
+            full.extend_from_slice(&0u16.to_be_bytes());
+            full.extend_from_slice(&0.5f64.to_be_bytes());
+            full.push(0);
+            full.extend_from_slice(&0.0f64.to_be_bytes());
+        }
+        full.push(0);
+        full.extend_from_slice(&1.0f64.to_be_bytes());
+
+        let error = ForestLayout::parse(&full).unwrap_err();
+        assert!(error.to_string().contains("depth"), "{}", error);
+    }
+}
@@ -0,0 +1,1851 @@
+//! Minimal inference core that does not depend on Polars.
+//!
+//! The feature math in this module works on one `PlayerStats` at a time and
+//! produces exactly the same values as the DataFrame pipeline, so integrators
+//! that only need single-row inference can build the crate with
+//! `default-features = false` and skip the Polars dependency entirely.
+
+use crate::model::CheatModel;
+use crate::options::{AnalysisOptions, FeaturePrecision, Thresholds, ZeroDivision};
+use crate::types::{
+    Assessment, Flag, FlagDetail, PlayerResult, PlayerStats, ShotTimestamps, ViewAngleSample,
+};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+/// Names of the features fed to the model, in model input order
+pub const FEATURE_NAMES: [&str; 2] = ["hit_rate", "headshot_rate"];
+
+/// Optional inputs of `PlayerStats` that can be given a presence indicator
+/// feature (see `TrainingConfig::optional_features`)
+pub const OPTIONAL_FEATURES: [&str; 5] = [
+    "shot_timestamps_ms",
+    "input_timestamps_ms",
+    "view_angles",
+    "damage_dealt",
+    "total_engagements",
+];
+
+/// Numeric features that can be added to the model input with
+/// `TrainingConfig::extra_features`
+pub const EXTRA_FEATURES: [&str; 2] = ["damage_per_shot", "preaim_ratio"];
+
+/// An optional input of `PlayerStats` and the encoded columns reading it
+pub(crate) struct OptionalInput {
+    /// Field name of the input
+    pub name: &'static str,
+    /// Whether a player provided the input
+    pub provided: fn(&PlayerStats) -> bool,
+    /// Presence indicator and extra features computed from the input
+    pub columns: &'static [&'static str],
+}
+
+/// Every optional input of `PlayerStats` that can reach the model, either
+/// through a presence indicator of `OPTIONAL_FEATURES` or an extra feature of
+/// `EXTRA_FEATURES`. A new optional input must be listed here to be reported
+/// by `ModelMetadata::ignored_fields`.
+pub(crate) const OPTIONAL_INPUTS: [OptionalInput; 6] = [
+    OptionalInput {
+        name: "shot_timestamps_ms",
+        provided: |stats| stats.shot_timestamps_ms.is_some(),
+        columns: &["shot_timestamps_ms_present"],
+    },
+    OptionalInput {
+        name: "input_timestamps_ms",
+        provided: |stats| stats.input_timestamps_ms.is_some(),
+        columns: &["input_timestamps_ms_present"],
+    },
+    OptionalInput {
+        name: "view_angles",
+        provided: |stats| stats.view_angles.is_some(),
+        columns: &["view_angles_present"],
+    },
+    OptionalInput {
+        name: "damage_dealt",
+        provided: |stats| stats.damage_dealt.is_some(),
+        columns: &["damage_dealt_present", "damage_per_shot"],
+    },
+    OptionalInput {
+        name: "preaimed_engagements",
+        provided: |stats| stats.preaimed_engagements.is_some(),
+        columns: &["preaim_ratio"],
+    },
+    OptionalInput {
+        name: "total_engagements",
+        provided: |stats| stats.total_engagements.is_some(),
+        columns: &["total_engagements_present", "preaim_ratio"],
+    },
+];
+
+/// Whether a player provided the optional input with the given name.
+///
+/// Returns `None` for names not listed in `OPTIONAL_FEATURES`.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::is_present;
+/// use nocheat::types::PlayerStats;
+///
+/// let stats = PlayerStats {
+///     shot_timestamps_ms: Some(vec![0, 120, 250].into()),
+///     ..Default::default()
+/// };
+/// assert_eq!(is_present(&stats, "shot_timestamps_ms"), Some(true));
+/// assert_eq!(is_present(&stats, "view_angles"), Some(false));
+/// ```
+pub fn is_present(stats: &PlayerStats, name: &str) -> Option<bool> {
+    if !OPTIONAL_FEATURES.contains(&name) {
+        return None;
+    }
+    OPTIONAL_INPUTS
+        .iter()
+        .find(|input| input.name == name)
+        .map(|input| (input.provided)(stats))
+}
+
+/// Value of an extra feature of `EXTRA_FEATURES`, or `None` for other names.
+///
+/// Features of inputs the player did not provide encode as 0.0; add a
+/// presence indicator to tell them apart from a genuine zero.
+pub fn extra_feature(stats: &PlayerStats, name: &str) -> Option<f64> {
+    match name {
+        "damage_per_shot" => Some(damage_per_shot(stats).unwrap_or(0.0)),
+        "preaim_ratio" => Some(preaim_ratio(stats).unwrap_or(0.0)),
+        _ => None,
+    }
+}
+
+/// Damage dealt divided by total shots fired.
+///
+/// A strong aim-assist signal independent of the hit rate, since aimbots
+/// favor high-damage hit zones. Returns `None` when the damage is unknown or
+/// no shot was fired.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::damage_per_shot;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
+///
+/// let stats = PlayerStats {
+///     shots_fired: HashMap::from([("rifle".to_string(), 40)]),
+///     damage_dealt: Some(1000),
+///     ..Default::default()
+/// };
+/// assert_eq!(damage_per_shot(&stats), Some(25.0));
+/// ```
+pub fn damage_per_shot(stats: &PlayerStats) -> Option<f64> {
+    let damage = stats.damage_dealt?;
+    let shots: u32 = stats.shots_fired.values().sum();
+    (shots > 0).then(|| damage as f64 / shots as f64)
+}
+
+/// Fraction of engagements in which the crosshair was already on the enemy
+/// when it became visible.
+///
+/// A wallhack/ESP signal: the player knows where enemies will appear. Returns
+/// `None` when either count is unknown or there was no engagement.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::preaim_ratio;
+/// use nocheat::types::PlayerStats;
+///
+/// let stats = PlayerStats {
+///     preaimed_engagements: Some(9),
+///     total_engagements: Some(12),
+///     ..Default::default()
+/// };
+/// assert_eq!(preaim_ratio(&stats), Some(0.75));
+/// ```
+pub fn preaim_ratio(stats: &PlayerStats) -> Option<f64> {
+    let preaimed = stats.preaimed_engagements?;
+    let total = stats.total_engagements?;
+    (total > 0).then(|| preaimed as f64 / total as f64)
+}
+
+/// Suspicion score at or above which the model's verdict is "cheater"
+pub(crate) const VERDICT_THRESHOLD: f32 = 0.5;
+
+/// Engineered features for a single player.
+///
+/// By default ratios are computed in `f32` (matching the `Float32` casts of the
+/// DataFrame pipeline) and widened to `f64` for the model; see
+/// `FeatureRow::from_stats_with` for full `f64` precision.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::FeatureRow;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
+///
+/// let mut shots = HashMap::new();
+/// shots.insert("rifle".to_string(), 100);
+/// let mut hits = HashMap::new();
+/// hits.insert("rifle".to_string(), 50);
+///
+/// let stats = PlayerStats {
+///     player_id: "player123".to_string(),
+///     shots_fired: shots,
+///     hits: hits,
+///     headshots: 10,
+///     shot_timestamps_ms: None,
+///     training_label: None,
+///     ..Default::default()
+/// };
+///
+/// let row = FeatureRow::from_stats(&stats);
+/// assert!((row.hit_rate - 0.5).abs() < 1e-6);
+/// assert!((row.headshot_rate - 0.2).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureRow {
+    /// Total hits divided by total shots fired
+    pub hit_rate: f64,
+    /// Headshots divided by total hits
+    pub headshot_rate: f64,
+}
+
+impl FeatureRow {
+    /// Compute the features of a single player
+    pub fn from_stats(stats: &PlayerStats) -> Self {
+        Self::from_stats_with(stats, FeaturePrecision::F32)
+    }
+
+    /// Compute the features of a single player with the given precision.
+    ///
+    /// Ratios with a zero denominator are undefined (NaN or infinite), which
+    /// statistics over a population such as baselines skip as missing.
+    pub fn from_stats_with(stats: &PlayerStats, precision: FeaturePrecision) -> Self {
+        Self::compute(stats, precision, None)
+    }
+
+    /// Compute the features of a single player with the given precision and
+    /// handling of zero denominators, as done for model-scored players
+    pub fn from_stats_using(
+        stats: &PlayerStats,
+        precision: FeaturePrecision,
+        zero_division: ZeroDivision,
+    ) -> Self {
+        Self::compute(stats, precision, Some(zero_division))
+    }
+
+    /// Compute the features, dividing plainly when `zero_division` is `None`
+    pub(crate) fn compute(
+        stats: &PlayerStats,
+        precision: FeaturePrecision,
+        zero_division: Option<ZeroDivision>,
+    ) -> Self {
+        let shots: u32 = stats.shots_fired.values().sum();
+        let hits: u32 = stats.hits.values().sum();
+
+        match (precision, zero_division) {
+            (FeaturePrecision::F32, None) => Self {
+                hit_rate: (hits as f32 / shots as f32) as f64,
+                headshot_rate: (stats.headshots as f32 / hits as f32) as f64,
+            },
+            (FeaturePrecision::F64, None) => Self {
+                hit_rate: hits as f64 / shots as f64,
+                headshot_rate: stats.headshots as f64 / hits as f64,
+            },
+            (FeaturePrecision::F32, Some(division)) => Self {
+                hit_rate: division.ratio_f32(hits as f32, shots as f32) as f64,
+                headshot_rate: division.ratio_f32(stats.headshots as f32, hits as f32) as f64,
+            },
+            (FeaturePrecision::F64, Some(division)) => Self {
+                hit_rate: division.ratio(hits as f64, shots as f64),
+                headshot_rate: division.ratio(stats.headshots as f64, hits as f64),
+            },
+        }
+    }
+
+    /// Feature values in the order given by `FEATURE_NAMES`
+    pub fn to_vec(&self) -> Vec<f64> {
+        vec![self.hit_rate, self.headshot_rate]
+    }
+
+    /// Value of the feature with the given name, if it exists
+    pub fn get(&self, name: &str) -> Option<f64> {
+        match name {
+            "hit_rate" => Some(self.hit_rate),
+            "headshot_rate" => Some(self.headshot_rate),
+            _ => None,
+        }
+    }
+}
+
+/// Rule-based analysis of a player record that works without a trained model.
+///
+/// Used by `analyze_stats_with_options` when `heuristic_fallback` is enabled
+/// and no model can be loaded.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::Analyzable;
+/// use nocheat::options::Thresholds;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
+///
+/// let stats = PlayerStats {
+///     player_id: "player123".to_string(),
+///     shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+///     hits: HashMap::from([("rifle".to_string(), 95)]),
+///     headshots: 20,
+///     ..Default::default()
+/// };
+///
+/// // The hit rate check fires, the headshot rate check does not
+/// assert!(stats.is_suspicious(&Thresholds::default()));
+/// assert_eq!(stats.heuristic_score(&Thresholds::default()), 0.5);
+/// ```
+pub trait Analyzable {
+    /// Compute the engineered features of the record
+    fn extract_features(&self) -> FeatureRow;
+
+    /// Run every applicable heuristic check, returning its name and whether it fired.
+    ///
+    /// The default checks the hit rate and headshot rate of `extract_features`.
+    fn heuristic_checks(&self, thresholds: &Thresholds) -> Vec<(&'static str, bool)> {
+        let row = self.extract_features();
+        vec![
+            (
+                Flag::HighHitRate.as_str(),
+                row.hit_rate as f32 > thresholds.high_hit_rate,
+            ),
+            (
+                Flag::HighHeadshotRate.as_str(),
+                row.headshot_rate as f32 > thresholds.high_headshot_rate,
+            ),
+        ]
+    }
+
+    /// Whether at least one heuristic check fired
+    fn is_suspicious(&self, thresholds: &Thresholds) -> bool {
+        self.heuristic_checks(thresholds)
+            .iter()
+            .any(|(_, fired)| *fired)
+    }
+
+    /// Fraction of the heuristic checks that fired, in [0.0, 1.0]
+    fn heuristic_score(&self, thresholds: &Thresholds) -> f32 {
+        let checks = self.heuristic_checks(thresholds);
+        if checks.is_empty() {
+            return 0.0;
+        }
+        let fired = checks.iter().filter(|(_, fired)| *fired).count();
+        fired as f32 / checks.len() as f32
+    }
+}
+
+impl Analyzable for PlayerStats {
+    fn extract_features(&self) -> FeatureRow {
+        FeatureRow::from_stats(self)
+    }
+
+    /// Checks hit rate and headshot rate (per weapon class when
+    /// `weapon_headshots` is reported), plus aim snaps when view angles are
+    /// present, input cadence when enough timestamps are present and damage
+    /// per shot when damage is reported
+    fn heuristic_checks(&self, thresholds: &Thresholds) -> Vec<(&'static str, bool)> {
+        let row = self.extract_features();
+        let mut checks = vec![
+            (
+                Flag::HighHitRate.as_str(),
+                row.hit_rate as f32 > thresholds.high_hit_rate,
+            ),
+            (
+                Flag::HighHeadshotRate.as_str(),
+                high_headshot_rate(self, &row, thresholds),
+            ),
+        ];
+        if let Some(samples) = &self.view_angles {
+            let snaps = count_aim_snaps(
+                samples,
+                thresholds.aim_snap_window_ms,
+                thresholds.aim_snap_deg_per_sec,
+            );
+            checks.push((
+                Flag::AimSnap.as_str(),
+                snaps >= thresholds.aim_snap_min_events,
+            ));
+        }
+        if let Some(cv) = cadence_variation(self, thresholds) {
+            checks.push((
+                Flag::RoboticCadence.as_str(),
+                cv < thresholds.robotic_cadence_max_cv,
+            ));
+        }
+        if let Some(damage) = damage_per_shot(self) {
+            checks.push((
+                Flag::HighDamageEfficiency.as_str(),
+                damage as f32 > thresholds.high_damage_per_shot,
+            ));
+        }
+        if let Some(ratio) = preaim_ratio(self) {
+            checks.push((
+                Flag::PreAim.as_str(),
+                ratio as f32 > thresholds.high_preaim_ratio,
+            ));
+        }
+        checks
+    }
+}
+
+/// Weapon key used by `ToPlayerStats` when per-weapon counts are unknown
+pub const AGGREGATE_WEAPON: &str = "all";
+
+/// Conversion of game-specific player records into `PlayerStats`.
+///
+/// The default `to_player_stats` rebuilds the counts from the ratios of
+/// `Analyzable::extract_features` and the total number of shots, storing them
+/// under the single weapon `"all"`. Override it when the record keeps
+/// per-weapon counts or view angles that should be preserved.
+///
+/// # Example
+///
+/// A typical FPS record only needs its features and shot count:
+///
+/// ```
+/// use nocheat::inference::{Analyzable, FeatureRow, ToPlayerStats};
+///
+/// struct FpsPlayerData {
+///     name: String,
+///     shots: u32,
+///     hits: u32,
+///     headshot_kills: u32,
+/// }
+///
+/// impl Analyzable for FpsPlayerData {
+///     fn extract_features(&self) -> FeatureRow {
+///         FeatureRow {
+///             hit_rate: self.hits as f64 / self.shots as f64,
+///             headshot_rate: self.headshot_kills as f64 / self.hits as f64,
+///         }
+///     }
+/// }
+///
+/// impl ToPlayerStats for FpsPlayerData {
+///     fn player_id(&self) -> String {
+///         self.name.clone()
+///     }
+///
+///     fn total_shots(&self) -> u32 {
+///         self.shots
+///     }
+/// }
+///
+/// let player = FpsPlayerData {
+///     name: "player123".to_string(),
+///     shots: 200,
+///     hits: 150,
+///     headshot_kills: 45,
+/// };
+///
+/// let stats = player.to_player_stats();
+/// assert_eq!(stats.shots_fired["all"], 200);
+/// assert_eq!(stats.hits["all"], 150);
+/// assert_eq!(stats.headshots, 45);
+/// ```
+pub trait ToPlayerStats: Analyzable {
+    /// Unique identifier of the player
+    fn player_id(&self) -> String;
+
+    /// Total shots fired, used to turn the extracted ratios back into counts
+    fn total_shots(&self) -> u32;
+
+    /// Convert the record into `PlayerStats`
+    fn to_player_stats(&self) -> PlayerStats {
+        let row = self.extract_features();
+        let shots = self.total_shots();
+        let hits = ratio_to_count(row.hit_rate, shots);
+        let headshots = ratio_to_count(row.headshot_rate, hits);
+
+        PlayerStats {
+            player_id: self.player_id(),
+            shots_fired: HashMap::from([(AGGREGATE_WEAPON.to_string(), shots)]),
+            hits: HashMap::from([(AGGREGATE_WEAPON.to_string(), hits)]),
+            headshots,
+            ..Default::default()
+        }
+    }
+}
+
+/// Turn a ratio of `total` back into a count, treating undefined ratios as 0
+fn ratio_to_count(ratio: f64, total: u32) -> u32 {
+    if !ratio.is_finite() {
+        return 0;
+    }
+    (ratio * total as f64).round().clamp(0.0, total as f64) as u32
+}
+
+/// Run inference for a single player without building a DataFrame.
+///
+/// # Arguments
+///
+/// * `model` - A loaded model (see `load_model`)
+/// * `stats` - Statistics of the player to analyze
+///
+/// # Returns
+///
+/// * `Result<PlayerResult>` - The suspicion score (clamped to [0.0, 1.0]) and flags for the player
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::inference::predict_one;
+/// use nocheat::load_model;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
+///
+/// let model = load_model("models/cheat_model.bin").expect("Failed to load model");
+///
+/// let mut shots = HashMap::new();
+/// shots.insert("rifle".to_string(), 100);
+/// let mut hits = HashMap::new();
+/// hits.insert("rifle".to_string(), 95);
+///
+/// let stats = PlayerStats {
+///     player_id: "player123".to_string(),
+///     shots_fired: shots,
+///     hits: hits,
+///     headshots: 70,
+///     shot_timestamps_ms: None,
+///     training_label: None,
+///     ..Default::default()
+/// };
+///
+/// let result = predict_one(&model, &stats).expect("Prediction failed");
+/// assert_eq!(result.player_id, "player123");
+/// ```
+pub fn predict_one(model: &CheatModel, stats: &PlayerStats) -> Result<PlayerResult> {
+    score_row(
+        model,
+        stats,
+        &FeatureRow::from_stats(stats),
+        &AnalysisOptions::default(),
+    )
+}
+
+/// Hit statistics of the prediction cache of a batch analysis.
+///
+/// See `AnalysisOptions::dedupe_predictions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Number of predictions looked up in the cache
+    pub lookups: usize,
+    /// Number of lookups answered without running the model
+    pub hits: usize,
+}
+
+impl CacheStats {
+    /// Fraction of lookups answered from the cache, 0.0 when nothing was looked up
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.lookups as f64
+    }
+}
+
+/// The inputs a feature row is computed from: totals of the player's
+/// statistics and the division settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct FeatureKey {
+    shots: u32,
+    hits: u32,
+    headshots: u32,
+    precision: FeaturePrecision,
+    /// Bits of the `ZeroDivision::Epsilon` constant, `None` for `Guard`
+    epsilon: Option<u64>,
+}
+
+impl FeatureKey {
+    /// Key of the feature row of `stats` under the given settings
+    pub fn new(
+        stats: &PlayerStats,
+        precision: FeaturePrecision,
+        zero_division: ZeroDivision,
+    ) -> Self {
+        Self {
+            shots: stats.shots_fired.values().sum(),
+            hits: stats.hits.values().sum(),
+            headshots: stats.headshots,
+            precision,
+            epsilon: match zero_division {
+                ZeroDivision::Guard => None,
+                ZeroDivision::Epsilon(eps) => Some(eps.to_bits()),
+            },
+        }
+    }
+}
+
+/// Feature rows memoized across analyses, keyed by the statistics they are
+/// computed from.
+///
+/// Analyzing the same players again, such as a lobby re-checked every round,
+/// skips the DataFrame build and feature computation of every player whose
+/// shot, hit and headshot totals did not change. Keys hold those totals
+/// exactly, so a hit always returns the row a fresh computation would give.
+///
+/// # Memory
+///
+/// The cache holds at most `capacity` rows and evicts the least recently used
+/// one when full. An entry takes about 100 bytes including its bookkeeping,
+/// so a capacity of 100,000 bounds the cache to roughly 10 MB.
+///
+/// The cache is safe to share between threads. See
+/// `AnalyzerBuilder::feature_cache`.
+#[derive(Debug)]
+pub struct FeatureCache {
+    capacity: usize,
+    state: Mutex<FeatureCacheState>,
+}
+
+#[derive(Debug, Default)]
+struct FeatureCacheState {
+    /// Cached rows with the tick of their last use
+    rows: HashMap<FeatureKey, (FeatureRow, u64)>,
+    /// Keys by tick of last use, oldest first
+    recency: BTreeMap<u64, FeatureKey>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl FeatureCache {
+    /// Create an empty cache holding at most `capacity` feature rows
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Maximum number of feature rows held
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of feature rows held
+    pub fn len(&self) -> usize {
+        self.lock().rows.len()
+    }
+
+    /// Whether the cache holds no feature rows
+    pub fn is_empty(&self) -> bool {
+        self.lock().rows.is_empty()
+    }
+
+    /// Lookups and hits since the cache was created or last cleared
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats
+    }
+
+    /// Drop every cached row and reset the statistics
+    pub fn clear(&self) {
+        *self.lock() = FeatureCacheState::default();
+    }
+
+    /// Lock the state; a panic while holding the lock cannot leave a row
+    /// half-written, so poisoning is ignored
+    fn lock(&self) -> MutexGuard<'_, FeatureCacheState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Cached row of `key`, marking it as recently used
+    pub(crate) fn get(&self, key: &FeatureKey) -> Option<FeatureRow> {
+        let mut guard = self.lock();
+        let state = &mut *guard;
+        state.stats.lookups += 1;
+        state.tick += 1;
+        let (row, used) = state.rows.get_mut(key)?;
+        state.recency.remove(used);
+        *used = state.tick;
+        state.recency.insert(state.tick, *key);
+        state.stats.hits += 1;
+        Some(*row)
+    }
+
+    /// Store the row of `key`, evicting the least recently used rows beyond
+    /// the capacity
+    pub(crate) fn insert(&self, key: FeatureKey, row: FeatureRow) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut guard = self.lock();
+        let state = &mut *guard;
+        state.tick += 1;
+        if let Some((_, used)) = state.rows.insert(key, (row, state.tick)) {
+            state.recency.remove(&used);
+        }
+        state.recency.insert(state.tick, key);
+        while state.rows.len() > self.capacity {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.rows.remove(&oldest);
+        }
+    }
+}
+
+/// How `align_features` changes a feature vector to fit a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureAlignment {
+    /// The vector already has the model's input length
+    Exact,
+    /// The vector is short by `missing` features, which are filled in
+    Padded {
+        /// Number of features appended
+        missing: usize,
+    },
+    /// The vector is long by `dropped` features, which are cut off
+    Truncated {
+        /// Number of trailing features removed
+        dropped: usize,
+    },
+}
+
+impl FeatureAlignment {
+    /// Alignment of a vector of `len` features to a model expecting `expected_len`
+    pub fn of(len: usize, expected_len: usize) -> Self {
+        if len < expected_len {
+            FeatureAlignment::Padded {
+                missing: expected_len - len,
+            }
+        } else if len > expected_len {
+            FeatureAlignment::Truncated {
+                dropped: len - expected_len,
+            }
+        } else {
+            FeatureAlignment::Exact
+        }
+    }
+}
+
+/// Fit a feature vector to the input length of a model.
+///
+/// Game modes that produce more or fewer features than the model was trained
+/// with can still share it: short vectors are padded with `fill`, long ones
+/// are truncated to `expected_len`. Missing or dropped features usually point
+/// at a mode the model was not trained for, so callers that want to warn
+/// about it can check `FeatureAlignment::of` first.
+///
+/// # Arguments
+///
+/// * `features` - The feature vector produced for a player
+/// * `expected_len` - Number of inputs of the model
+/// * `fill` - Value of the padded features
+///
+/// # Returns
+///
+/// * `Vec<f32>` - A vector of exactly `expected_len` features
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::{align_features, FeatureAlignment};
+///
+/// assert_eq!(align_features(vec![0.5], 3, -1.0), vec![0.5, -1.0, -1.0]);
+/// assert_eq!(align_features(vec![0.5, 0.2, 0.9], 2, 0.0), vec![0.5, 0.2]);
+/// assert_eq!(FeatureAlignment::of(1, 3), FeatureAlignment::Padded { missing: 2 });
+/// ```
+pub fn align_features(mut features: Vec<f32>, expected_len: usize, fill: f32) -> Vec<f32> {
+    features.resize(expected_len, fill);
+    features
+}
+
+/// Run the model, turning a panic inside the forest into an error
+pub(crate) fn predict_raw(model: &CheatModel, features: &[f64]) -> Result<f64> {
+    std::panic::catch_unwind(|| model.predict(features))
+        .map_err(|_| anyhow::anyhow!("Model prediction failed"))
+}
+
+/// Score a precomputed feature row and build the player's result
+pub(crate) fn score_row(
+    model: &CheatModel,
+    stats: &PlayerStats,
+    row: &FeatureRow,
+    options: &AnalysisOptions,
+) -> Result<PlayerResult> {
+    let features = model.encode(stats, row)?;
+    let raw_score = predict_raw(model, &features)?;
+    Ok(scored_result(
+        model, stats, row, &features, raw_score, options,
+    ))
+}
+
+/// Build the result of a player whose encoded input `features` the model
+/// predicted as `raw_score`
+pub(crate) fn scored_result(
+    model: &CheatModel,
+    stats: &PlayerStats,
+    row: &FeatureRow,
+    features: &[f64],
+    raw_score: f64,
+    options: &AnalysisOptions,
+) -> PlayerResult {
+    let mut score = raw_score as f32;
+    if options.clamp_scores {
+        score = score.clamp(0.0, 1.0);
+    }
+
+    let mut details = evaluate_flags(stats, row, &options.thresholds);
+    if options.check_labels {
+        if let Some(label) = stats.training_label {
+            let is_cheater = label >= VERDICT_THRESHOLD as f64;
+            if is_cheater != (score >= VERDICT_THRESHOLD) {
+                details.push(FlagDetail::new(
+                    Flag::LabelMismatch,
+                    label as f32,
+                    VERDICT_THRESHOLD,
+                    format!("training label {} disagrees with score {:.2}", label, score),
+                ));
+            }
+        }
+    }
+
+    let predicted_label = score >= options.label_threshold;
+    PlayerResult {
+        player_id: stats.player_id.clone(),
+        suspicion_score: score,
+        predicted_label,
+        verdict: verdict(stats, predicted_label, options),
+        flags: flag_names(&details),
+        top_weapons: top_weapons(stats, &details, &options.thresholds),
+        flag_details: details,
+        analyzed_at: Some(unix_millis()),
+        model_version: Some(model.metadata.version.clone()),
+        class_probabilities: model
+            .is_multiclass()
+            .then(|| model.class_probabilities(features)),
+        margin: options
+            .include_margin
+            .then(|| model.predict_margin(features)),
+        skill_adjusted_score: skill_adjusted_score(stats, score, options),
+        ..Default::default()
+    }
+}
+
+/// Assessment of a scored player: `Insufficient` below `AnalysisOptions::min_shots`,
+/// otherwise following the predicted label
+fn verdict(stats: &PlayerStats, predicted_label: bool, options: &AnalysisOptions) -> Assessment {
+    let shots: u32 = stats.shots_fired.values().sum();
+    if shots < options.min_shots {
+        Assessment::Insufficient
+    } else if predicted_label {
+        Assessment::Suspicious
+    } else {
+        Assessment::Clean
+    }
+}
+
+/// Suspicion score discounted by the performance expected at the player's skill rating
+fn skill_adjusted_score(stats: &PlayerStats, score: f32, options: &AnalysisOptions) -> Option<f32> {
+    let expected = options.skill_curve.expected(stats.skill_rating?);
+    if expected <= 0.0 {
+        return Some(score);
+    }
+    let adjusted = score / expected;
+    Some(if options.clamp_scores {
+        adjusted.clamp(0.0, 1.0)
+    } else {
+        adjusted
+    })
+}
+
+/// Current time in milliseconds since the Unix epoch
+pub(crate) fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Result of a whitelisted player, who is never scored
+pub(crate) fn whitelisted_row(stats: &PlayerStats) -> PlayerResult {
+    PlayerResult {
+        player_id: stats.player_id.clone(),
+        suspicion_score: 0.0,
+        flags: vec![Flag::Whitelisted.to_string()],
+        analyzed_at: Some(unix_millis()),
+        ..Default::default()
+    }
+}
+
+/// Result of a player whose hit rate exceeds 1.0, which no legitimate client can produce
+pub(crate) fn impossible_hit_rate_row(
+    stats: &PlayerStats,
+    row: &FeatureRow,
+    options: &AnalysisOptions,
+) -> PlayerResult {
+    let mut details = evaluate_flags(stats, row, &options.thresholds);
+    details.push(FlagDetail::new(
+        Flag::ImpossibleHitRate,
+        row.hit_rate as f32,
+        1.0,
+        format!("hit rate {:.2} above 1.00", row.hit_rate),
+    ));
+
+    let predicted_label = 1.0 >= options.label_threshold;
+    PlayerResult {
+        player_id: stats.player_id.clone(),
+        suspicion_score: 1.0,
+        predicted_label,
+        verdict: verdict(stats, predicted_label, options),
+        flags: flag_names(&details),
+        top_weapons: top_weapons(stats, &details, &options.thresholds),
+        flag_details: details,
+        analyzed_at: Some(unix_millis()),
+        ..Default::default()
+    }
+}
+
+/// Score a player with the `Analyzable` heuristics when no model is available
+pub(crate) fn heuristic_row(stats: &PlayerStats, options: &AnalysisOptions) -> PlayerResult {
+    let row = stats.extract_features();
+    let details = evaluate_flags(stats, &row, &options.thresholds);
+    let mut flags = flag_names(&details);
+    flags.push(Flag::HeuristicFallback.to_string());
+
+    let score = stats.heuristic_score(&options.thresholds);
+    let predicted_label = score >= options.label_threshold;
+    PlayerResult {
+        player_id: stats.player_id.clone(),
+        suspicion_score: score,
+        predicted_label,
+        verdict: verdict(stats, predicted_label, options),
+        skill_adjusted_score: skill_adjusted_score(stats, score, options),
+        top_weapons: top_weapons(stats, &details, &options.thresholds),
+        flags,
+        flag_details: details,
+        analyzed_at: Some(unix_millis()),
+        ..Default::default()
+    }
+}
+
+/// Maximum number of weapons listed in `PlayerResult::top_weapons`
+pub const TOP_WEAPONS: usize = 3;
+
+/// Part of the hit rate above `threshold` contributed by each weapon.
+///
+/// The excess `hit_rate - threshold` splits exactly into one term per weapon,
+/// `(hits - threshold * shots) / total_shots`, so a weapon with a high hit
+/// rate over many shots contributes more than one with a few lucky hits.
+///
+/// # Arguments
+///
+/// * `stats` - Statistics of the player
+/// * `threshold` - Hit rate the weapons are compared against, usually
+///   `Thresholds::high_hit_rate`
+///
+/// # Returns
+///
+/// * `Vec<(String, f32)>` - Every weapon with a positive contribution, from
+///   the largest to the smallest (ties by weapon name)
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::weapon_contributions;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
+///
+/// let stats = PlayerStats {
+///     shots_fired: HashMap::from([("awp".to_string(), 50), ("pistol".to_string(), 50)]),
+///     hits: HashMap::from([("awp".to_string(), 50), ("pistol".to_string(), 40)]),
+///     ..Default::default()
+/// };
+/// // 0.9 hit rate: the awp adds 0.1 above 0.8, the pistol exactly 0.8 adds nothing
+/// let contributions = weapon_contributions(&stats, 0.8);
+/// assert_eq!(contributions.len(), 1);
+/// assert_eq!(contributions[0].0, "awp");
+/// assert!((contributions[0].1 - 0.1).abs() < 1e-6);
+/// ```
+pub fn weapon_contributions(stats: &PlayerStats, threshold: f32) -> Vec<(String, f32)> {
+    let total_shots: u32 = stats.shots_fired.values().sum();
+    if total_shots == 0 {
+        return Vec::new();
+    }
+    let weapons: HashSet<&String> = stats.shots_fired.keys().chain(stats.hits.keys()).collect();
+    let mut contributions: Vec<(String, f32)> = weapons
+        .into_iter()
+        .filter_map(|weapon| {
+            let shots = stats.shots_fired.get(weapon).copied().unwrap_or(0) as f64;
+            let hits = stats.hits.get(weapon).copied().unwrap_or(0) as f64;
+            let contribution = (hits - threshold as f64 * shots) / total_shots as f64;
+            (contribution > 0.0).then(|| (weapon.clone(), contribution as f32))
+        })
+        .collect();
+    contributions.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    contributions
+}
+
+/// The `TOP_WEAPONS` largest weapon contributions when `HighHitRate` fired
+fn top_weapons(
+    stats: &PlayerStats,
+    details: &[FlagDetail],
+    thresholds: &Thresholds,
+) -> Vec<(String, f32)> {
+    if !details
+        .iter()
+        .any(|detail| detail.flag == Flag::HighHitRate)
+    {
+        return Vec::new();
+    }
+    let mut contributions = weapon_contributions(stats, thresholds.high_hit_rate);
+    contributions.truncate(TOP_WEAPONS);
+    contributions
+}
+
+/// Build the rule-based flags for a player along with their evidence
+pub(crate) fn evaluate_flags(
+    stats: &PlayerStats,
+    row: &FeatureRow,
+    thresholds: &Thresholds,
+) -> Vec<FlagDetail> {
+    let mut flags = Vec::new();
+    // Compare in f32, the precision the ratios were computed in
+    let hit_rate = row.hit_rate as f32;
+    if hit_rate > thresholds.high_hit_rate {
+        flags.push(FlagDetail::new(
+            Flag::HighHitRate,
+            hit_rate,
+            thresholds.high_hit_rate,
+            format!(
+                "hit rate {:.2} above {:.2}",
+                hit_rate, thresholds.high_hit_rate
+            ),
+        ));
+    }
+    // Only per weapon class; the overall headshot rate is left to the model
+    if let Some((class, rate)) = class_headshot_rates(stats, thresholds)
+        .into_iter()
+        .filter(|(_, class)| class.rate > class.threshold)
+        .max_by(|a, b| (a.1.rate - a.1.threshold).total_cmp(&(b.1.rate - b.1.threshold)))
+    {
+        flags.push(FlagDetail::new(
+            Flag::HighHeadshotRate,
+            rate.rate,
+            rate.threshold,
+            format!(
+                "{} headshot rate {:.2} above {:.2}",
+                class, rate.rate, rate.threshold
+            ),
+        ));
+    }
+    if let Some(samples) = &stats.view_angles {
+        let snaps = count_aim_snaps(
+            samples,
+            thresholds.aim_snap_window_ms,
+            thresholds.aim_snap_deg_per_sec,
+        );
+        if snaps >= thresholds.aim_snap_min_events {
+            flags.push(FlagDetail::new(
+                Flag::AimSnap,
+                snaps as f32,
+                thresholds.aim_snap_min_events as f32,
+                format!(
+                    "{} aim snaps, threshold {}",
+                    snaps, thresholds.aim_snap_min_events
+                ),
+            ));
+        }
+    }
+    if let Some(cv) = cadence_variation(stats, thresholds) {
+        if cv < thresholds.robotic_cadence_max_cv {
+            flags.push(FlagDetail::new(
+                Flag::RoboticCadence,
+                cv,
+                thresholds.robotic_cadence_max_cv,
+                format!(
+                    "input interval variation {:.3} below {:.3}",
+                    cv, thresholds.robotic_cadence_max_cv
+                ),
+            ));
+        }
+    }
+    if let Some(damage) = damage_per_shot(stats).map(|d| d as f32) {
+        if damage > thresholds.high_damage_per_shot {
+            flags.push(FlagDetail::new(
+                Flag::HighDamageEfficiency,
+                damage,
+                thresholds.high_damage_per_shot,
+                format!(
+                    "damage per shot {:.1} above {:.1}",
+                    damage, thresholds.high_damage_per_shot
+                ),
+            ));
+        }
+    }
+    if let Some(ratio) = preaim_ratio(stats).map(|r| r as f32) {
+        if ratio > thresholds.high_preaim_ratio {
+            flags.push(FlagDetail::new(
+                Flag::PreAim,
+                ratio,
+                thresholds.high_preaim_ratio,
+                format!(
+                    "pre-aimed engagement ratio {:.2} above {:.2}",
+                    ratio, thresholds.high_preaim_ratio
+                ),
+            ));
+        }
+    }
+    flags
+}
+
+/// Names of the flags of `details`, as stored in `PlayerResult::flags`
+fn flag_names(details: &[FlagDetail]) -> Vec<String> {
+    details
+        .iter()
+        .map(|detail| detail.flag.to_string())
+        .collect()
+}
+
+/// Variation of the player's input intervals (see `interval_variation`),
+/// below `robotic_cadence_max_cv` when too regular to be human.
+///
+/// Uses `input_timestamps_ms`, falling back to `shot_timestamps_ms`; with
+/// per-weapon shot timestamps, the most regular weapon counts. Returns `None`
+/// when there are fewer intervals than `robotic_cadence_min_intervals`.
+fn cadence_variation(stats: &PlayerStats, thresholds: &Thresholds) -> Option<f32> {
+    let variation = |timestamps: &[u64]| {
+        (timestamps.len() > thresholds.robotic_cadence_min_intervals)
+            .then(|| interval_variation(timestamps))
+            .flatten()
+    };
+    if let Some(timestamps) = &stats.input_timestamps_ms {
+        return variation(timestamps);
+    }
+    match stats.shot_timestamps_ms.as_ref()? {
+        ShotTimestamps::Flat(timestamps) => variation(timestamps),
+        ShotTimestamps::PerWeapon(weapons) => weapons
+            .values()
+            .filter_map(|timestamps| variation(timestamps))
+            .min_by(f32::total_cmp),
+    }
+}
+
+/// Headshot rate of one weapon class, see `class_headshot_rates`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassHeadshotRate {
+    /// Headshots of the weapons of the class divided by their hits
+    pub rate: f32,
+    /// Threshold of the class, from `Thresholds::headshot_rate_by_class`
+    /// or else `Thresholds::high_headshot_rate`
+    pub threshold: f32,
+}
+
+/// Headshot rate of every weapon class in `PlayerStats::weapon_headshots`.
+///
+/// Weapons are grouped by `Thresholds::weapon_classes`, and the headshots and
+/// hits of the weapons of a class are added up before dividing. Returns an
+/// empty map when no per-weapon headshots are reported.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::class_headshot_rates;
+/// use nocheat::options::Thresholds;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
+///
+/// let stats = PlayerStats {
+///     hits: HashMap::from([("awp".to_string(), 10), ("scout".to_string(), 10)]),
+///     weapon_headshots: HashMap::from([("awp".to_string(), 8), ("scout".to_string(), 4)]),
+///     ..Default::default()
+/// };
+/// let thresholds = Thresholds {
+///     headshot_rate_by_class: HashMap::from([("sniper".to_string(), 0.8)]),
+///     weapon_classes: HashMap::from([
+///         ("awp".to_string(), "sniper".to_string()),
+///         ("scout".to_string(), "sniper".to_string()),
+///     ]),
+///     ..Default::default()
+/// };
+/// let rates = class_headshot_rates(&stats, &thresholds);
+/// assert_eq!(rates["sniper"].rate, 0.6);
+/// assert_eq!(rates["sniper"].threshold, 0.8);
+/// ```
+pub fn class_headshot_rates(
+    stats: &PlayerStats,
+    thresholds: &Thresholds,
+) -> BTreeMap<String, ClassHeadshotRate> {
+    let mut totals: BTreeMap<&str, (u32, u32)> = BTreeMap::new();
+    for (weapon, &headshots) in &stats.weapon_headshots {
+        let class = thresholds
+            .weapon_classes
+            .get(weapon)
+            .map_or(weapon.as_str(), String::as_str);
+        let hits = stats.hits.get(weapon).copied().unwrap_or(0);
+        let total = totals.entry(class).or_insert((0, 0));
+        total.0 = total.0.saturating_add(headshots);
+        total.1 = total.1.saturating_add(hits);
+    }
+    totals
+        .into_iter()
+        .map(|(class, (headshots, hits))| {
+            let rate = ClassHeadshotRate {
+                rate: ZeroDivision::Guard.ratio_f32(headshots as f32, hits as f32),
+                threshold: thresholds
+                    .headshot_rate_by_class
+                    .get(class)
+                    .copied()
+                    .unwrap_or(thresholds.high_headshot_rate),
+            };
+            (class.to_string(), rate)
+        })
+        .collect()
+}
+
+/// Whether the headshot rate check fires: per weapon class when the player
+/// reports `weapon_headshots`, otherwise on the overall headshot rate
+fn high_headshot_rate(stats: &PlayerStats, row: &FeatureRow, thresholds: &Thresholds) -> bool {
+    if stats.weapon_headshots.is_empty() {
+        return row.headshot_rate as f32 > thresholds.high_headshot_rate;
+    }
+    class_headshot_rates(stats, thresholds)
+        .values()
+        .any(|class| class.rate > class.threshold)
+}
+
+/// Timing features of the shots of one weapon type, see `weapon_timing`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeaponTiming {
+    /// Number of timestamped shots
+    pub shots: usize,
+    /// Shots per second between the first and the last shot
+    pub fire_rate: f32,
+    /// Variation of the intervals between shots, see `interval_variation`
+    pub interval_variation: Option<f32>,
+}
+
+/// Timing features of each weapon type with per-weapon `shot_timestamps_ms`.
+///
+/// Weapons with fewer than two shots, or with every shot at the same
+/// millisecond, are left out. Returns an empty map for a flat timestamp list,
+/// which cannot be attributed to weapons.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::weapon_timing;
+/// use nocheat::types::{PlayerStats, ShotTimestamps};
+/// use std::collections::HashMap;
+///
+/// let stats = PlayerStats {
+///     shot_timestamps_ms: Some(ShotTimestamps::PerWeapon(HashMap::from([
+///         ("smg".to_string(), (0..11).map(|i| i * 100).collect()),
+///         ("sniper".to_string(), vec![0, 1500, 3000]),
+///     ]))),
+///     ..Default::default()
+/// };
+/// let timing = weapon_timing(&stats);
+/// assert_eq!(timing["smg"].fire_rate, 10.0);
+/// assert!(timing["sniper"].fire_rate < 1.0);
+/// ```
+pub fn weapon_timing(stats: &PlayerStats) -> HashMap<String, WeaponTiming> {
+    let Some(weapons) = stats
+        .shot_timestamps_ms
+        .as_ref()
+        .and_then(ShotTimestamps::per_weapon)
+    else {
+        return HashMap::new();
+    };
+    weapons
+        .iter()
+        .filter_map(|(weapon, timestamps)| {
+            let first = timestamps.iter().min()?;
+            let span_ms = timestamps.iter().max()? - first;
+            (span_ms > 0).then(|| {
+                let timing = WeaponTiming {
+                    shots: timestamps.len(),
+                    fire_rate: (timestamps.len() - 1) as f32 * 1000.0 / span_ms as f32,
+                    interval_variation: interval_variation(timestamps),
+                };
+                (weapon.clone(), timing)
+            })
+        })
+        .collect()
+}
+
+/// Coefficient of variation (standard deviation / mean) of the intervals
+/// between consecutive timestamps.
+///
+/// Human input is irregular, while scripts and macros fire on a fixed timer,
+/// so a value close to 0 indicates automation. Timestamps are expected in
+/// chronological order; returns `None` with fewer than two timestamps or when
+/// every timestamp is identical.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::interval_variation;
+///
+/// // A macro pressing a key exactly every 50 ms
+/// let timestamps: Vec<u64> = (0..30).map(|i| 1000 + i * 50).collect();
+/// assert_eq!(interval_variation(&timestamps), Some(0.0));
+/// ```
+pub fn interval_variation(timestamps: &[u64]) -> Option<f32> {
+    let intervals: Vec<f64> = timestamps
+        .windows(2)
+        .map(|pair| pair[1].saturating_sub(pair[0]) as f64)
+        .collect();
+    if intervals.is_empty() {
+        return None;
+    }
+    let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+    if mean <= 0.0 {
+        return None;
+    }
+    let variance =
+        intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
+    Some((variance.sqrt() / mean) as f32)
+}
+
+/// Angular distance in degrees between two view angles, wrapping yaw around 360°
+fn angular_distance(a: &ViewAngleSample, b: &ViewAngleSample) -> f32 {
+    let d_yaw = (b.yaw - a.yaw + 180.0).rem_euclid(360.0) - 180.0;
+    let d_pitch = b.pitch - a.pitch;
+    (d_yaw * d_yaw + d_pitch * d_pitch).sqrt()
+}
+
+/// Count hits preceded by a humanly-implausible view snap.
+///
+/// For every hit, the maximum angular velocity between consecutive samples
+/// ending within `window_ms` before (and including) the hit is computed; the
+/// hit counts as snapped when that velocity exceeds `max_deg_per_sec`.
+/// Samples sharing a timestamp are skipped as their velocity is undefined.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::inference::count_aim_snaps;
+/// use nocheat::types::ViewAngleSample;
+///
+/// let samples = vec![
+///     ViewAngleSample { timestamp_ms: 0, yaw: 0.0, pitch: 0.0, hit: false },
+///     // 90 degrees in 10 ms right before the hit
+///     ViewAngleSample { timestamp_ms: 10, yaw: 90.0, pitch: 0.0, hit: true },
+/// ];
+/// assert_eq!(count_aim_snaps(&samples, 100, 3600.0), 1);
+/// ```
+pub fn count_aim_snaps(samples: &[ViewAngleSample], window_ms: u64, max_deg_per_sec: f32) -> usize {
+    let mut snaps = 0;
+    for (i, hit) in samples.iter().enumerate().filter(|(_, s)| s.hit) {
+        let window_start = hit.timestamp_ms.saturating_sub(window_ms);
+        let peak = samples[..=i]
+            .windows(2)
+            .rev()
+            .take_while(|pair| pair[1].timestamp_ms >= window_start)
+            .filter(|pair| pair[1].timestamp_ms > pair[0].timestamp_ms)
+            .map(|pair| {
+                let dt_s = (pair[1].timestamp_ms - pair[0].timestamp_ms) as f32 / 1000.0;
+                angular_distance(&pair[0], &pair[1]) / dt_s
+            })
+            .fold(0.0f32, f32::max);
+
+        if peak > max_deg_per_sec {
+            snaps += 1;
+        }
+    }
+    snaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_stats(shots: u32, hits: u32, headshots: u32) -> PlayerStats {
+        let mut shots_fired = HashMap::new();
+        shots_fired.insert("rifle".to_string(), shots);
+        let mut hit_map = HashMap::new();
+        hit_map.insert("rifle".to_string(), hits);
+
+        PlayerStats {
+            player_id: "player".to_string(),
+            shots_fired,
+            hits: hit_map,
+            headshots,
+            shot_timestamps_ms: None,
+            training_label: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_feature_row_values() {
+        let row = FeatureRow::from_stats(&make_stats(150, 135, 50));
+        assert_eq!(row.hit_rate, (135.0f32 / 150.0f32) as f64);
+        assert_eq!(row.headshot_rate, (50.0f32 / 135.0f32) as f64);
+        assert_eq!(row.to_vec().len(), FEATURE_NAMES.len());
+    }
+
+    fn flags_for(stats: &PlayerStats) -> Vec<String> {
+        flag_names(&evaluate_flags(
+            stats,
+            &FeatureRow::from_stats(stats),
+            &Thresholds::default(),
+        ))
+    }
+
+    #[test]
+    fn test_high_hit_rate_flag() {
+        assert_eq!(
+            flags_for(&make_stats(100, 90, 10)),
+            vec!["HighHitRate".to_string()]
+        );
+
+        // Exactly at the threshold is not flagged
+        assert!(flags_for(&make_stats(100, 80, 10)).is_empty());
+    }
+
+    #[test]
+    fn test_top_weapons_name_the_dominant_weapon() {
+        let stats = PlayerStats {
+            shots_fired: HashMap::from([
+                ("awp".to_string(), 200),
+                ("rifle".to_string(), 100),
+                ("pistol".to_string(), 10),
+            ]),
+            hits: HashMap::from([
+                ("awp".to_string(), 198),
+                ("rifle".to_string(), 85),
+                ("pistol".to_string(), 10),
+            ]),
+            ..make_stats(0, 0, 0)
+        };
+        let result = heuristic_row(&stats, &AnalysisOptions::default());
+        assert!(result.has_flag(Flag::HighHitRate));
+
+        let weapons: Vec<&str> = result.top_weapons.iter().map(|(w, _)| w.as_str()).collect();
+        assert_eq!(weapons, ["awp", "rifle", "pistol"]);
+        let (_, awp) = result.top_weapons[0];
+        assert!(awp > 2.0 * result.top_weapons[1..].iter().map(|(_, c)| c).sum::<f32>());
+        // The contributions add up to the hit rate above the threshold
+        let excess = result.flag_detail(Flag::HighHitRate).unwrap().value - 0.8;
+        let total: f32 = result.top_weapons.iter().map(|(_, c)| c).sum();
+        assert!((total - excess).abs() < 1e-4);
+
+        // Nothing to explain without the flag
+        let clean = heuristic_row(&make_stats(100, 50, 10), &AnalysisOptions::default());
+        assert!(clean.top_weapons.is_empty());
+    }
+
+    #[test]
+    fn test_skill_adjusted_score_discounts_high_rank() {
+        let options = AnalysisOptions::default();
+        let ranked = |rating: f32| PlayerStats {
+            skill_rating: Some(rating),
+            ..make_stats(100, 90, 70)
+        };
+
+        let low = heuristic_row(&ranked(500.0), &options);
+        let high = heuristic_row(&ranked(3000.0), &options);
+        assert_eq!(low.suspicion_score, high.suspicion_score);
+        assert_eq!(low.skill_adjusted_score, Some(1.0));
+        assert_eq!(high.skill_adjusted_score, Some(0.5));
+
+        // Players without a rating get no adjusted score
+        let unranked = heuristic_row(&make_stats(100, 90, 70), &options);
+        assert_eq!(unranked.skill_adjusted_score, None);
+    }
+
+    #[test]
+    fn test_high_hit_rate_flag_carries_evidence() {
+        let result = heuristic_row(&make_stats(100, 90, 10), &AnalysisOptions::default());
+        let detail = result.flag_detail(Flag::HighHitRate).unwrap();
+        assert_eq!(detail.value, 0.9);
+        assert_eq!(detail.threshold, Thresholds::default().high_hit_rate);
+        assert_eq!(detail.message, "hit rate 0.90 above 0.80");
+
+        // Informational flags carry no detail
+        assert!(result.has_flag(Flag::HeuristicFallback));
+        assert_eq!(result.flag_details.len(), 1);
+    }
+
+    /// Build a sequence of 5 engagements, each a 50 ms approach ending in a hit
+    /// that turns the view by `deg_before_hit` in the final `ms_before_hit` ms
+    fn engagements(deg_before_hit: f32, ms_before_hit: u64) -> Vec<ViewAngleSample> {
+        let mut samples = Vec::new();
+        let mut yaw = 0.0;
+        for k in 0..5u64 {
+            let start = k * 1000;
+            // Slow tracking movement
+            for step in 0..5u64 {
+                yaw += 1.0;
+                samples.push(ViewAngleSample {
+                    timestamp_ms: start + step * 10,
+                    yaw,
+                    pitch: 0.0,
+                    hit: false,
+                });
+            }
+            yaw += deg_before_hit;
+            samples.push(ViewAngleSample {
+                timestamp_ms: start + 40 + ms_before_hit,
+                yaw,
+                pitch: 0.0,
+                hit: true,
+            });
+        }
+        samples
+    }
+
+    #[test]
+    fn test_aim_snap_detected() {
+        // 120 degrees in 8 ms = 15000 deg/s before every hit
+        let mut stats = make_stats(100, 50, 10);
+        stats.view_angles = Some(engagements(120.0, 8));
+
+        assert_eq!(
+            count_aim_snaps(stats.view_angles.as_ref().unwrap(), 100, 3600.0),
+            5
+        );
+        assert!(flags_for(&stats).contains(&"AimSnap".to_string()));
+    }
+
+    #[test]
+    fn test_smooth_aim_not_flagged() {
+        // 20 degrees in 40 ms = 500 deg/s, a normal human flick
+        let mut stats = make_stats(100, 50, 10);
+        stats.view_angles = Some(engagements(20.0, 40));
+
+        assert_eq!(
+            count_aim_snaps(stats.view_angles.as_ref().unwrap(), 100, 3600.0),
+            0
+        );
+        assert!(!flags_for(&stats).contains(&"AimSnap".to_string()));
+    }
+
+    #[test]
+    fn test_aim_snap_yaw_wraps_around() {
+        let samples = vec![
+            ViewAngleSample {
+                timestamp_ms: 0,
+                yaw: 359.0,
+                pitch: 0.0,
+                hit: false,
+            },
+            // Crossing 0 degrees is a 2 degree move, not 358
+            ViewAngleSample {
+                timestamp_ms: 10,
+                yaw: 1.0,
+                pitch: 0.0,
+                hit: true,
+            },
+        ];
+        assert_eq!(count_aim_snaps(&samples, 100, 3600.0), 0);
+    }
+
+    #[test]
+    fn test_heuristic_score_counts_fired_checks() {
+        let thresholds = Thresholds::default();
+        let clean = make_stats(100, 50, 10);
+        assert!(!clean.is_suspicious(&thresholds));
+        assert_eq!(clean.heuristic_score(&thresholds), 0.0);
+
+        // Hit rate and headshot rate both fire, aim snap does not
+        let mut cheater = make_stats(100, 95, 70);
+        cheater.view_angles = Some(engagements(20.0, 40));
+        assert_eq!(cheater.heuristic_checks(&thresholds).len(), 3);
+        assert!((cheater.heuristic_score(&thresholds) - 2.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[cfg(feature = "dataframe")]
+    #[test]
+    fn test_matches_dataframe_pipeline() {
+        let stats = vec![make_stats(100, 50, 10), make_stats(150, 135, 50)];
+        let df = crate::feature_dataframe(&stats, FeaturePrecision::F32).unwrap();
+        let features = crate::df_to_ndarray(&df, &FEATURE_NAMES).unwrap();
+
+        for (i, s) in stats.iter().enumerate() {
+            let row: Vec<f64> = features.row(i).iter().map(|&v| v as f64).collect();
+            assert_eq!(row, FeatureRow::from_stats(s).to_vec());
+        }
+    }
+
+    #[test]
+    fn test_f64_features_on_high_count_player() {
+        // Counts above 2^24 are not exactly representable in f32
+        let stats = make_stats(100_000_001, 87_654_321, 12_345_679);
+        let f32_row = FeatureRow::from_stats_with(&stats, FeaturePrecision::F32);
+        let f64_row = FeatureRow::from_stats_with(&stats, FeaturePrecision::F64);
+
+        assert_eq!(f64_row.hit_rate, 87_654_321.0 / 100_000_001.0);
+        assert_eq!(f64_row.headshot_rate, 12_345_679.0 / 87_654_321.0);
+        assert_ne!(f32_row.hit_rate, f64_row.hit_rate);
+        assert!((f32_row.hit_rate - f64_row.hit_rate).abs() < 1e-6);
+
+        #[cfg(feature = "dataframe")]
+        {
+            let df = crate::feature_dataframe(std::slice::from_ref(&stats), FeaturePrecision::F64)
+                .unwrap();
+            let hit_rate = df.column("hit_rate").unwrap().f64().unwrap().get(0);
+            assert_eq!(hit_rate, Some(f64_row.hit_rate));
+        }
+    }
+
+    /// Reference FPS record converted through the default `ToPlayerStats`
+    struct FpsPlayerData {
+        name: String,
+        shots: u32,
+        hits: u32,
+        headshot_kills: u32,
+    }
+
+    impl Analyzable for FpsPlayerData {
+        fn extract_features(&self) -> FeatureRow {
+            FeatureRow {
+                hit_rate: self.hits as f64 / self.shots as f64,
+                headshot_rate: self.headshot_kills as f64 / self.hits as f64,
+            }
+        }
+    }
+
+    impl ToPlayerStats for FpsPlayerData {
+        fn player_id(&self) -> String {
+            self.name.clone()
+        }
+
+        fn total_shots(&self) -> u32 {
+            self.shots
+        }
+    }
+
+    #[test]
+    fn test_fps_conversion_round_trips_counts() {
+        let player = FpsPlayerData {
+            name: "player123".to_string(),
+            shots: 137,
+            hits: 101,
+            headshot_kills: 33,
+        };
+        let stats = player.to_player_stats();
+
+        assert_eq!(stats.player_id, "player123");
+        assert_eq!(stats.shots_fired[AGGREGATE_WEAPON], 137);
+        assert_eq!(stats.hits[AGGREGATE_WEAPON], 101);
+        assert_eq!(stats.headshots, 33);
+        assert_eq!(
+            stats.heuristic_checks(&Thresholds::default()),
+            player.heuristic_checks(&Thresholds::default())
+        );
+
+        // A player without shots converts to zero counts
+        let idle = FpsPlayerData {
+            shots: 0,
+            hits: 0,
+            headshot_kills: 0,
+            ..player
+        };
+        assert_eq!(idle.to_player_stats().hits[AGGREGATE_WEAPON], 0);
+    }
+
+    #[test]
+    fn test_robotic_cadence_detected() {
+        let mut stats = make_stats(100, 50, 10);
+        stats.input_timestamps_ms = Some((0..30).map(|i| 1000 + i * 50).collect());
+        assert!(flags_for(&stats).contains(&"RoboticCadence".to_string()));
+
+        // Human jitter of a few tens of milliseconds is not flagged
+        stats.input_timestamps_ms = Some(
+            (0..30u64)
+                .scan(1000, |t, i| {
+                    *t += 120 + (i * 37) % 60;
+                    Some(*t)
+                })
+                .collect(),
+        );
+        assert!(!flags_for(&stats).contains(&"RoboticCadence".to_string()));
+
+        // Too few intervals to judge
+        stats.input_timestamps_ms = Some((0..10).map(|i| i * 50).collect());
+        assert!(!flags_for(&stats).contains(&"RoboticCadence".to_string()));
+    }
+
+    #[test]
+    fn test_per_weapon_timestamps() {
+        let mut stats: PlayerStats = serde_json::from_str(
+            r#"{"player_id": "p", "shots_fired": {"smg": 30, "sniper": 4}, "hits": {},
+                "headshots": 0, "shot_timestamps_ms": {
+                    "smg": [0, 80, 170, 240, 330, 400, 490, 560, 650, 720, 800],
+                    "sniper": [100, 1600, 3100, 4600]}}"#,
+        )
+        .unwrap();
+        let timing = weapon_timing(&stats);
+        assert_eq!(timing.len(), 2);
+        assert_eq!(timing["smg"].shots, 11);
+        assert!((timing["smg"].fire_rate - 12.5).abs() < 1e-4);
+        assert!((timing["sniper"].fire_rate - 2.0 / 3.0).abs() < 1e-4);
+        assert_eq!(timing["sniper"].interval_variation, Some(0.0));
+
+        // The old flat format still parses, but cannot be split by weapon
+        stats.shot_timestamps_ms = serde_json::from_str::<PlayerStats>(
+            r#"{"player_id": "p", "shots_fired": {},
+                "hits": {}, "headshots": 0, "shot_timestamps_ms": [0, 80, 170]}"#,
+        )
+        .unwrap()
+        .shot_timestamps_ms;
+        assert_eq!(stats.shot_timestamps_ms, Some(vec![0, 80, 170].into()));
+        assert!(weapon_timing(&stats).is_empty());
+    }
+
+    #[test]
+    fn test_high_damage_efficiency_flagged() {
+        // Few shots, each dealing far more damage than a legitimate player averages
+        let stats = PlayerStats {
+            player_id: "sniper".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 10)]),
+            hits: HashMap::from([("rifle".to_string(), 7)]),
+            headshots: 2,
+            damage_dealt: Some(950),
+            ..Default::default()
+        };
+        let thresholds = Thresholds::default();
+        let flags = evaluate_flags(&stats, &FeatureRow::from_stats(&stats), &thresholds);
+        assert_eq!(flag_names(&flags), vec!["HighDamageEfficiency".to_string()]);
+        assert_eq!(flags[0].value, 95.0);
+        assert_eq!(extra_feature(&stats, "damage_per_shot"), Some(95.0));
+
+        // Zero shots and unknown damage never divide by zero
+        let no_shots = PlayerStats {
+            damage_dealt: Some(500),
+            ..Default::default()
+        };
+        assert_eq!(damage_per_shot(&no_shots), None);
+        assert_eq!(
+            extra_feature(&PlayerStats::default(), "damage_per_shot"),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn test_high_preaim_ratio_flagged() {
+        let stats = PlayerStats {
+            preaimed_engagements: Some(18),
+            total_engagements: Some(20),
+            ..make_stats(100, 45, 10)
+        };
+        let thresholds = Thresholds::default();
+        let flags = evaluate_flags(&stats, &FeatureRow::from_stats(&stats), &thresholds);
+        assert_eq!(flag_names(&flags), vec!["PreAim".to_string()]);
+        assert_eq!(flags[0].value, 0.9);
+        assert_eq!(extra_feature(&stats, "preaim_ratio"), Some(0.9));
+
+        // Zero engagements never divide by zero
+        let no_engagements = PlayerStats {
+            preaimed_engagements: Some(0),
+            total_engagements: Some(0),
+            ..make_stats(100, 45, 10)
+        };
+        assert_eq!(preaim_ratio(&no_engagements), None);
+        assert!(evaluate_flags(
+            &no_engagements,
+            &FeatureRow::from_stats(&no_engagements),
+            &thresholds
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_headshot_threshold_per_weapon_class() {
+        let thresholds = Thresholds {
+            headshot_rate_by_class: HashMap::from([
+                ("sniper".to_string(), 0.8),
+                ("shotgun".to_string(), 0.3),
+            ]),
+            weapon_classes: HashMap::from([
+                ("awp".to_string(), "sniper".to_string()),
+                ("nova".to_string(), "shotgun".to_string()),
+            ]),
+            ..Default::default()
+        };
+        // 60% headshots with either weapon
+        let with_weapon = |weapon: &str| PlayerStats {
+            player_id: weapon.to_string(),
+            shots_fired: HashMap::from([(weapon.to_string(), 100)]),
+            hits: HashMap::from([(weapon.to_string(), 50)]),
+            headshots: 30,
+            weapon_headshots: HashMap::from([(weapon.to_string(), 30)]),
+            ..Default::default()
+        };
+        let headshot_check = |stats: &PlayerStats| {
+            stats
+                .heuristic_checks(&thresholds)
+                .into_iter()
+                .find(|(name, _)| *name == Flag::HighHeadshotRate.as_str())
+                .unwrap()
+                .1
+        };
+
+        assert!(!headshot_check(&with_weapon("awp")));
+        assert!(headshot_check(&with_weapon("nova")));
+
+        // Without per-weapon headshots the global threshold (0.6) applies
+        let mut overall = with_weapon("nova");
+        overall.weapon_headshots.clear();
+        assert!(!headshot_check(&overall));
+
+        // The model path reports the class and its threshold
+        let nova = with_weapon("nova");
+        let flags = evaluate_flags(&nova, &FeatureRow::from_stats(&nova), &thresholds);
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].flag, Flag::HighHeadshotRate);
+        assert_eq!((flags[0].value, flags[0].threshold), (0.6, 0.3));
+        assert!(flags[0].message.contains("shotgun"));
+        let awp = with_weapon("awp");
+        assert!(evaluate_flags(&awp, &FeatureRow::from_stats(&awp), &thresholds).is_empty());
+        assert!(
+            evaluate_flags(&overall, &FeatureRow::from_stats(&overall), &thresholds).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_optional_inputs_cover_every_encoded_column() {
+        let columns: Vec<&str> = OPTIONAL_INPUTS
+            .iter()
+            .flat_map(|input| input.columns.iter().copied())
+            .collect();
+        for name in OPTIONAL_FEATURES {
+            assert!(columns.contains(&format!("{}_present", name).as_str()));
+            assert_eq!(is_present(&PlayerStats::default(), name), Some(false));
+        }
+        for name in EXTRA_FEATURES {
+            assert!(columns.contains(&name));
+        }
+        assert_eq!(
+            is_present(&PlayerStats::default(), "preaimed_engagements"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_align_features_pads_and_truncates() {
+        // A mode reporting fewer features than the model takes
+        let padded = align_features(vec![0.6, 0.2], 4, -1.0);
+        assert_eq!(padded, vec![0.6, 0.2, -1.0, -1.0]);
+
+        // A mode reporting more
+        let truncated = align_features(vec![0.6, 0.2, 0.9, 0.4], 2, -1.0);
+        assert_eq!(truncated, vec![0.6, 0.2]);
+
+        assert_eq!(align_features(vec![0.6, 0.2], 2, -1.0), vec![0.6, 0.2]);
+
+        assert_eq!(
+            FeatureAlignment::of(2, 4),
+            FeatureAlignment::Padded { missing: 2 }
+        );
+        assert_eq!(
+            FeatureAlignment::of(4, 2),
+            FeatureAlignment::Truncated { dropped: 2 }
+        );
+        assert_eq!(FeatureAlignment::of(2, 2), FeatureAlignment::Exact);
+    }
+}
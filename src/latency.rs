@@ -0,0 +1,174 @@
+//! Latency histograms for SLO monitoring.
+//!
+//! Recording a latency must stay cheap next to the analysis it measures, so
+//! `LatencyHistogram` keeps a fixed set of atomic counters instead of the
+//! samples. Buckets are exact below 16 µs; above that, every power of two is
+//! split into 16 buckets, so a reported percentile is at most 6.25% above
+//! the true one.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Buckets per power of two, and number of exact buckets below the first one
+const SUB_BUCKETS: u64 = 16;
+/// log2 of `SUB_BUCKETS`
+const SUB_BUCKET_BITS: u32 = 4;
+/// Largest power of two tracked; longer latencies (over 12 days) count in the
+/// last bucket
+const MAX_EXPONENT: u32 = 39;
+const BUCKETS: usize = ((MAX_EXPONENT - SUB_BUCKET_BITS + 2) as u64 * SUB_BUCKETS) as usize;
+
+/// Histogram of latencies with microsecond resolution, safe to share
+/// between threads.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::latency::LatencyHistogram;
+/// use std::time::Duration;
+///
+/// let histogram = LatencyHistogram::new();
+/// for ms in 1..=100 {
+///     histogram.record(Duration::from_millis(ms));
+/// }
+/// let (p50, p95, p99) = histogram.percentiles();
+/// assert!(p50 <= p95 && p95 <= p99);
+/// assert!(p99 >= Duration::from_millis(99));
+/// ```
+pub struct LatencyHistogram {
+    counts: Vec<AtomicU64>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+}
+
+impl fmt::Debug for LatencyHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LatencyHistogram")
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one latency
+    pub fn record(&self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        self.counts[bucket(micros)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of latencies recorded since creation or the last reset
+    pub fn count(&self) -> u64 {
+        self.counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Latency below which `quantile` (in [0, 1]) of the recorded latencies
+    /// fall, rounded up to the end of its bucket; zero when nothing was
+    /// recorded
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        let counts: Vec<u64> = self
+            .counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(bucket_end(index));
+            }
+        }
+        Duration::from_micros(bucket_end(BUCKETS - 1))
+    }
+
+    /// The 50th, 95th and 99th percentiles, see `percentile`
+    pub fn percentiles(&self) -> (Duration, Duration, Duration) {
+        (
+            self.percentile(0.50),
+            self.percentile(0.95),
+            self.percentile(0.99),
+        )
+    }
+
+    /// Forget every recorded latency.
+    ///
+    /// Latencies recorded by other threads during the reset may be kept.
+    pub fn reset(&self) {
+        for count in &self.counts {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Bucket counting a latency of `micros`
+fn bucket(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let exponent = (63 - micros.leading_zeros()).min(MAX_EXPONENT);
+    if exponent == MAX_EXPONENT && micros >> MAX_EXPONENT > 1 {
+        return BUCKETS - 1;
+    }
+    let sub = (micros >> (exponent - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    ((exponent - SUB_BUCKET_BITS + 1) as u64 * SUB_BUCKETS + sub) as usize
+}
+
+/// Largest latency in microseconds counted by a bucket
+fn bucket_end(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = (index / SUB_BUCKETS - 1) as u32;
+    let start = (SUB_BUCKETS + index % SUB_BUCKETS) << shift;
+    start + (1 << shift) - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_within_bucket_error() {
+        let histogram = LatencyHistogram::new();
+        for micros in 1..=10_000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        assert_eq!(histogram.count(), 10_000);
+
+        let (p50, p95, p99) = histogram.percentiles();
+        for (percentile, exact) in [(p50, 5_000.0), (p95, 9_500.0), (p99, 9_900.0)] {
+            let micros = percentile.as_micros() as f64;
+            assert!(micros >= exact && micros <= exact * 1.0625, "{}", micros);
+        }
+
+        // Every bucket ends right before the next one starts
+        for index in 1..BUCKETS {
+            assert_eq!(bucket(bucket_end(index - 1)), index - 1);
+            assert_eq!(bucket(bucket_end(index - 1) + 1), index);
+        }
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.percentile(0.5), Duration::ZERO);
+    }
+}
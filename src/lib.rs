@@ -33,6 +33,7 @@ let player_stats = PlayerStats {
     headshots: 60,
     shot_timestamps_ms: None,
     training_label: None,
+    ..Default::default()
 };
 
 // Analyze the stats
@@ -45,16 +46,52 @@ if let Ok(response) = analysis {
 
 use anyhow::Result;
 use libc::{c_int, c_uchar, size_t};
+#[cfg(feature = "dataframe")]
 use ndarray::Array2;
 use once_cell::sync::Lazy;
+#[cfg(feature = "dataframe")]
 use polars::prelude::*;
-use randomforest::RandomForestClassifier;
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::{fs::File, ptr};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+pub mod analyzer;
+pub mod audit;
+pub mod baseline;
+pub mod collusion;
+pub mod drift;
+pub mod error;
+mod forest;
+pub mod inference;
+pub mod latency;
+pub mod model;
+pub mod options;
+pub mod policy;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod replay;
+pub mod reputation;
+pub mod shadow;
 pub mod types;
-use types::{AnalysisResponse, PlayerResult, PlayerStats};
+use analyzer::Analyzer;
+use audit::AuditSink;
+#[cfg(feature = "dataframe")]
+use error::{FeatureStage, NoCheatError};
+use inference::{CacheStats, FeatureCache, FeatureKey, FeatureRow};
+use model::{
+    CategoricalFeature, CheatModel, CrossValidation, Hyperparameters, ModelKind, ModelMetadata,
+    TrainingReport,
+};
+#[cfg(feature = "dataframe")]
+use options::SchemaSpec;
+use options::{
+    AnalysisOptions, BalanceStrategy, FeaturePrecision, ModelLoadLimits, RetryPolicy,
+    TrainingConfig, WindowConfig, ZeroDivision,
+};
+use types::{AnalysisOutcome, AnalysisResponse, Metrics, PlayerResult, PlayerStats, SweepResult};
 
 /// Public wrapper for statistical analysis of player data to detect cheating.
 ///
@@ -68,7 +105,9 @@ use types::{AnalysisResponse, PlayerResult, PlayerStats};
 ///
 /// # Returns
 ///
-/// * `Result<AnalysisResponse>` - The analysis results wrapped in a Result
+/// * `Result<AnalysisResponse>` - The analysis results wrapped in a Result.
+///   Every `suspicion_score` is guaranteed to lie in [0.0, 1.0], and every
+///   result records when it was analyzed and which model version produced it.
 ///
 /// # Example
 ///
@@ -90,29 +129,488 @@ use types::{AnalysisResponse, PlayerResult, PlayerStats};
 ///     headshots: 10,
 ///     shot_timestamps_ms: None,
 ///     training_label: None,
+///     ..Default::default()
 /// }];
 ///
 /// let results = analyze_stats(stats).expect("Analysis failed");
 /// assert_eq!(results.results.len(), 1);
 /// ```
 pub fn analyze_stats(stats: Vec<PlayerStats>) -> Result<AnalysisResponse> {
-    do_analysis(stats)
+    Analyzer::default().analyze(stats)
+}
+
+/// Analyze the statistics of a single player with the default analyzer.
+///
+/// See `Analyzer::analyze_player`.
+///
+/// # Arguments
+///
+/// * `stats` - The player's statistics
+///
+/// # Returns
+///
+/// * `Result<PlayerResult>` - The result of the player
+pub fn analyze_player(stats: PlayerStats) -> Result<PlayerResult> {
+    Analyzer::default().analyze_player(stats)
+}
+
+/// Analyze player statistics with the default analyzer, stopping early once `cancel` is set.
+///
+/// See `Analyzer::analyze_cancellable` for how cancellation works.
+///
+/// # Arguments
+///
+/// * `stats` - A vector of PlayerStats structures containing data to analyze
+/// * `cancel` - Set by the caller to abort the analysis between chunks
+///
+/// # Returns
+///
+/// * `Result<AnalysisOutcome>` - The full response, or the partial one if cancelled
+pub fn analyze_stats_cancellable(
+    stats: Vec<PlayerStats>,
+    cancel: &AtomicBool,
+) -> Result<AnalysisOutcome> {
+    Analyzer::default().analyze_cancellable(stats, cancel)
+}
+
+/// Score player statistics once with the default analyzer and report who is
+/// flagged at each suspicion threshold.
+///
+/// See `Analyzer::analyze_sweep`.
+///
+/// # Arguments
+///
+/// * `stats` - A vector of PlayerStats structures containing data to analyze
+/// * `thresholds` - Suspicion thresholds to compare, e.g. `[0.5, 0.7, 0.9]`
+///
+/// # Returns
+///
+/// * `Result<SweepResult>` - The flagged count and players of every threshold
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyze_sweep;
+/// use nocheat::types::PlayerStats;
+///
+/// let stats: Vec<PlayerStats> = Vec::new();
+/// let sweep = analyze_sweep(stats, &[0.5, 0.7, 0.9]).expect("Analysis failed");
+/// for level in &sweep.levels {
+///     println!("{:.1}: {} flagged", level.threshold, level.flagged_count);
+/// }
+/// ```
+pub fn analyze_sweep(stats: Vec<PlayerStats>, thresholds: &[f32]) -> Result<SweepResult> {
+    Analyzer::default().analyze_sweep(stats, thresholds)
+}
+
+/// Analyze player statistics with custom analysis options.
+///
+/// Behaves like `analyze_stats`, but lets advanced users tune the analysis,
+/// for example disabling the clamping of scores to [0.0, 1.0] to inspect the
+/// raw model output. See `Analyzer` for further configuration such as a
+/// custom model path or output filters.
+///
+/// # Arguments
+///
+/// * `stats` - A vector of PlayerStats structures containing data to analyze
+/// * `options` - Options controlling the analysis
+///
+/// # Returns
+///
+/// * `Result<AnalysisResponse>` - The analysis results wrapped in a Result
+pub fn analyze_stats_with_options(
+    stats: Vec<PlayerStats>,
+    options: &AnalysisOptions,
+) -> Result<AnalysisResponse> {
+    Analyzer::builder()
+        .options(options.clone())
+        .build()?
+        .analyze(stats)
+}
+
+/// Analyze with the loaded model, or with the heuristics if loading failed and
+/// the options allow it, recording the time spent in each stage in `metrics`
+pub(crate) fn analyze_or_fallback(
+    mut stats: Vec<PlayerStats>,
+    model: Result<Arc<CheatModel>>,
+    options: &AnalysisOptions,
+    metrics: &mut Metrics,
+) -> Result<(AnalysisResponse, CacheStats)> {
+    check_weapon_counts(&stats, options)?;
+    if options.normalize_weapons {
+        stats.iter_mut().for_each(PlayerStats::normalize_weapons);
+    }
+    match model {
+        Ok(model) => do_analysis_timed(stats, &model, options, metrics),
+        Err(_) if options.heuristic_fallback => {
+            let started = std::time::Instant::now();
+            let results = stats
+                .iter()
+                .map(|stat| {
+                    if options.whitelist.contains(&stat.player_id) {
+                        inference::whitelisted_row(stat)
+                    } else {
+                        inference::heuristic_row(stat, options)
+                    }
+                })
+                .collect();
+            metrics.inference_ms = Metrics::elapsed_ms(started);
+            Ok((AnalysisResponse { results }, CacheStats::default()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Reject a batch in which a player reports more weapons than `options.max_weapons`
+fn check_weapon_counts(stats: &[PlayerStats], options: &AnalysisOptions) -> Result<()> {
+    let Some(max_weapons) = options.max_weapons else {
+        return Ok(());
+    };
+    match stats.iter().find(|s| s.weapon_count() > max_weapons) {
+        Some(s) => Err(anyhow::anyhow!(
+            "Player {} reports {} weapons, more than the limit of {}",
+            s.player_id,
+            s.weapon_count(),
+            max_weapons
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Analyze players using only their most recent rounds as the feature basis.
+///
+/// Each entry of `histories` is one player's round history, ordered oldest
+/// first. The last `window.size` rounds of every player are aggregated and
+/// analyzed as a single record, so a recent change in behavior is not diluted
+/// by a long clean history. Players with fewer rounds than the window use all
+/// of their rounds; empty histories are skipped.
+///
+/// # Arguments
+///
+/// * `histories` - Per-player round histories, oldest round first
+/// * `window` - Size of the sliding window
+///
+/// # Returns
+///
+/// * `Result<AnalysisResponse>` - One result per non-empty history
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyze_windowed;
+/// use nocheat::options::WindowConfig;
+/// use nocheat::types::PlayerStats;
+///
+/// let history: Vec<PlayerStats> = Vec::new(); // rounds of one player, oldest first
+/// let response = analyze_windowed(vec![history], &WindowConfig { size: 3 })
+///     .expect("Analysis failed");
+/// ```
+pub fn analyze_windowed(
+    histories: Vec<Vec<PlayerStats>>,
+    window: &WindowConfig,
+) -> Result<AnalysisResponse> {
+    if window.size == 0 {
+        return Err(anyhow::anyhow!("Window size must be at least 1"));
+    }
+    let stats = histories
+        .iter()
+        .filter_map(|history| window.apply(history))
+        .collect();
+    analyze_stats(stats)
+}
+
+/// Combine the records of each player into one, scoped by match.
+///
+/// Records sharing both player_id and `match_id` are summed like the rounds
+/// of `analyze_windowed`; the same player in two different matches yields two
+/// records. Records without a match_id are grouped with each other. The
+/// output keeps the order in which each (match, player) pair first appears.
+///
+/// # Arguments
+///
+/// * `stats` - Per-round or partial records of any number of players
+///
+/// # Returns
+///
+/// * `Vec<PlayerStats>` - One record per player and match
+///
+/// # Example
+///
+/// ```
+/// use nocheat::aggregate_by_player;
+/// use nocheat::types::PlayerStats;
+///
+/// let round = |match_id: &str| PlayerStats {
+///     player_id: "player123".to_string(),
+///     headshots: 5,
+///     match_id: Some(match_id.to_string()),
+///     ..Default::default()
+/// };
+///
+/// let records = aggregate_by_player(&[round("m1"), round("m1"), round("m2")]);
+/// assert_eq!(records.len(), 2);
+/// assert_eq!(records[0].headshots, 10);
+/// ```
+pub fn aggregate_by_player(stats: &[PlayerStats]) -> Vec<PlayerStats> {
+    let mut groups: Vec<Vec<PlayerStats>> = Vec::new();
+    let mut index: HashMap<(Option<&str>, &str), usize> = HashMap::new();
+    for stat in stats {
+        let key = (stat.match_id.as_deref(), stat.player_id.as_str());
+        let group = *index.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(stat.clone());
+    }
+    groups
+        .iter()
+        .map(|rounds| PlayerStats::aggregate(rounds))
+        .collect()
+}
+
+/// Analyze every JSON file of a `.tar` or `.tar.gz` archive of match stats.
+///
+/// Each `.json` entry must hold a `Vec<PlayerStats>`; other entries are
+/// skipped. Every result records the name of the entry it came from in its
+/// `source` field.
+///
+/// # Arguments
+///
+/// * `path` - Path to the archive
+///
+/// # Returns
+///
+/// * `Result<AnalysisResponse>` - The results of all entries, in archive order
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyze_archive;
+///
+/// let response = analyze_archive("audits/2024-06.tar.gz").expect("Analysis failed");
+/// for result in &response.results {
+///     println!("{:?}: {} {}", result.source, result.player_id, result.suspicion_score);
+/// }
+/// ```
+pub fn analyze_archive(path: &str) -> Result<AnalysisResponse> {
+    Analyzer::default().analyze_archive(path)
+}
+
+/// Analyze player statistics and record every flagged player in an audit sink.
+///
+/// Only players for which `PlayerResult::is_flagged` holds are recorded.
+///
+/// # Arguments
+///
+/// * `sink` - Destination of the flagged results
+/// * `stats` - A vector of PlayerStats structures containing data to analyze
+///
+/// # Returns
+///
+/// * `Result<AnalysisResponse>` - The results of all players, flagged or not
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyze_and_audit;
+/// use nocheat::audit::JsonlAuditSink;
+///
+/// let mut sink = JsonlAuditSink::open("audit/flags.jsonl").expect("Failed to open audit log");
+/// let response = analyze_and_audit(&mut sink, vec![]).expect("Analysis failed");
+/// ```
+pub fn analyze_and_audit(
+    sink: &mut dyn AuditSink,
+    stats: Vec<PlayerStats>,
+) -> Result<AnalysisResponse> {
+    Analyzer::default().analyze_and_audit(sink, stats)
+}
+
+/// A loaded model together with the path and modification time of its file
+struct LoadedModel {
+    path: String,
+    modified: Option<std::time::SystemTime>,
+    model: Arc<CheatModel>,
+    /// Error of the last reload, when it failed and `model` was kept
+    reload_error: Option<String>,
 }
 
-/// Load pre-trained RandomForest model on first use
-static RF_MODEL: Lazy<RandomForestClassifier> =
-    Lazy::new(|| load_model(unsafe { CURRENT_MODEL_PATH }).expect("Failed to load RF model"));
+/// Model loaded from the current model path
+static LOADED_MODEL: Lazy<RwLock<Option<LoadedModel>>> = Lazy::new(|| RwLock::new(None));
 
 /// Path to the current model, can be updated via set_model_path
 static mut CURRENT_MODEL_PATH: &str = "models/cheat_model.bin";
 
-/// Deserialize RF from file
-fn load_model(path: &str) -> Result<RandomForestClassifier> {
-    let file = File::open(path)?;
-    // Use deserialize method provided by RandomForestClassifier
-    let rf = RandomForestClassifier::deserialize(file)
-        .map_err(|e| anyhow::anyhow!("Failed to deserialize model: {}", e))?;
-    Ok(rf)
+/// Maximum number of players per FFI analysis call, 0 for no limit (see `set_max_batch`)
+static MAX_BATCH: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether FFI calls write indented JSON (see `set_pretty_json`)
+static PRETTY_JSON: AtomicBool = AtomicBool::new(false);
+
+/// Serialize a value for the FFI, indented when `set_pretty_json` enabled it
+fn ffi_json<T: serde::Serialize>(value: &T) -> serde_json::Result<Vec<u8>> {
+    if PRETTY_JSON.load(Ordering::Relaxed) {
+        serde_json::to_vec_pretty(value)
+    } else {
+        serde_json::to_vec(value)
+    }
+}
+
+/// Whether a batch of `players` is above the limit set with `set_max_batch`
+fn exceeds_max_batch(players: usize) -> bool {
+    let max_batch = MAX_BATCH.load(Ordering::Relaxed);
+    max_batch > 0 && players > max_batch
+}
+
+/// Return the model at the current model path.
+///
+/// The model is loaded on first use and reloaded when the path changes or the
+/// file is modified. Reloads are retried with `RetryPolicy::default()` since a
+/// file may be read while it is still being written; if every attempt fails
+/// and a model was already loaded from the same path, that model is kept and
+/// the failure is reported by `last_reload_error`.
+pub(crate) fn current_model() -> Result<Arc<CheatModel>> {
+    let path = unsafe { CURRENT_MODEL_PATH };
+    cached_model(&LOADED_MODEL, path, &RetryPolicy::default())
+}
+
+/// Return the model of `path` from `cache`, loading it when the cache holds
+/// another path or an older version of the file (see `current_model`)
+fn cached_model(
+    cache: &RwLock<Option<LoadedModel>>,
+    path: &str,
+    policy: &RetryPolicy,
+) -> Result<Arc<CheatModel>> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let previous = match cache
+        .read()
+        .map_err(|_| anyhow::anyhow!("Model cache lock poisoned"))?
+        .as_ref()
+    {
+        Some(loaded) if loaded.path == path && loaded.modified == modified => {
+            return Ok(Arc::clone(&loaded.model));
+        }
+        Some(loaded) if loaded.path == path => Some(Arc::clone(&loaded.model)),
+        _ => None,
+    };
+
+    let model = if !std::path::Path::new(path).exists() {
+        Err(anyhow::anyhow!("{} does not exist", path))
+    } else {
+        load_model_with_retry(path, policy).map(Arc::new)
+    };
+    let (model, reload_error) = match (model, previous) {
+        (Ok(model), _) => (model, None),
+        // Keep serving the previous model; its file is only reloaded once modified again
+        (Err(e), Some(previous)) => (previous, Some(e.to_string())),
+        (Err(e), None) => return Err(e),
+    };
+    *cache
+        .write()
+        .map_err(|_| anyhow::anyhow!("Model cache lock poisoned"))? = Some(LoadedModel {
+        path: path.to_string(),
+        modified,
+        model: Arc::clone(&model),
+        reload_error,
+    });
+    Ok(model)
+}
+
+/// Error of the last failed reload of the model at the current model path.
+///
+/// When the model file is modified but cannot be loaded, e.g. because a
+/// deployment wrote a corrupt file, analysis keeps using the previously
+/// loaded model. This returns the error of every attempt of that reload, or
+/// `None` when the current model is up to date with its file.
+///
+/// # Example
+///
+/// ```no_run
+/// if let Some(error) = nocheat::last_reload_error() {
+///     eprintln!("serving a stale model: {}", error);
+/// }
+/// ```
+pub fn last_reload_error() -> Option<String> {
+    LOADED_MODEL
+        .read()
+        .ok()?
+        .as_ref()
+        .and_then(|loaded| loaded.reload_error.clone())
+}
+
+/// Deserialize a model from file
+///
+/// Accepts model containers written by `train_model` as well as bare forests
+/// written by earlier versions of the crate. Files larger than 256 MiB, or
+/// taking longer than 30 seconds to deserialize, are rejected (see
+/// `load_model_with_limits`).
+///
+/// # Arguments
+///
+/// * `path` - Path to a model written by `train_model` or `generate_default_model`
+///
+/// # Returns
+///
+/// * `Result<CheatModel>` - The loaded model and its metadata
+pub fn load_model(path: &str) -> Result<CheatModel> {
+    CheatModel::load(path)
+}
+
+/// Deserialize a model from file within explicit size and time limits
+///
+/// `load_model` applies `ModelLoadLimits::default()`; use this function to
+/// tighten or relax them.
+///
+/// # Arguments
+///
+/// * `path` - Path to a model written by `train_model` or `generate_default_model`
+/// * `limits` - Largest accepted file size and longest deserialization time
+///
+/// # Returns
+///
+/// * `Result<CheatModel>` - The loaded model, or a `NoCheatError::ModelTooLarge`
+///   or `NoCheatError::ModelLoadTimeout` error when a limit is exceeded
+pub fn load_model_with_limits(path: &str, limits: &ModelLoadLimits) -> Result<CheatModel> {
+    CheatModel::load_with_limits(path, limits)
+}
+
+/// Deserialize a model from file, retrying failed attempts with backoff
+///
+/// Useful when the file may be replaced while it is read, e.g. by a
+/// deployment writing a new model in place.
+///
+/// # Arguments
+///
+/// * `path` - Path to a model written by `train_model` or `generate_default_model`
+/// * `policy` - Number of attempts and backoff between them
+///
+/// # Returns
+///
+/// * `Result<CheatModel>` - The loaded model, or an error listing every failed attempt
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::load_model_with_retry;
+/// use nocheat::options::RetryPolicy;
+///
+/// let model = load_model_with_retry("models/cheat_model.bin", &RetryPolicy::default())
+///     .expect("Failed to load model");
+/// ```
+pub fn load_model_with_retry(path: &str, policy: &RetryPolicy) -> Result<CheatModel> {
+    policy.run(|| load_model(path))
+}
+
+/// Deserialize a model from an in-memory buffer
+///
+/// # Arguments
+///
+/// * `bytes` - A model as written by `train_model_to_writer`
+///
+/// # Returns
+///
+/// * `Result<CheatModel>` - The loaded model and its metadata
+pub fn load_model_from_bytes(bytes: &[u8]) -> Result<CheatModel> {
+    CheatModel::from_bytes(bytes)
 }
 
 /// Build a Polars DataFrame from PlayerStats
@@ -147,11 +645,13 @@ fn load_model(path: &str) -> Result<RandomForestClassifier> {
 ///     headshots: 10,
 ///     shot_timestamps_ms: None,
 ///     training_label: None,
+///     ..Default::default()
 /// }];
 ///
 /// let df = build_dataframe(&stats).expect("DataFrame creation failed");
 /// assert_eq!(df.height(), 1);
 /// ```
+#[cfg(feature = "dataframe")]
 pub fn build_dataframe(stats: &[PlayerStats]) -> Result<DataFrame> {
     let ids: Vec<&str> = stats.iter().map(|p| p.player_id.as_str()).collect();
     let shots: Vec<u32> = stats.iter().map(|p| p.shots_fired.values().sum()).collect();
@@ -163,7 +663,11 @@ pub fn build_dataframe(stats: &[PlayerStats]) -> Result<DataFrame> {
         "shots"     => shots,
         "hits"      => hits,
         "headshots" => headshots,
-    }?;
+    }
+    .map_err(NoCheatError::feature(
+        "player_id, shots, hits, headshots",
+        FeatureStage::Build,
+    ))?;
     Ok(df)
 }
 
@@ -203,6 +707,7 @@ pub fn build_dataframe(stats: &[PlayerStats]) -> Result<DataFrame> {
 ///     headshots: 10,
 ///     shot_timestamps_ms: None,
 ///     training_label: None,
+///     ..Default::default()
 /// }];
 ///
 /// let df = build_dataframe(&stats).expect("DataFrame creation failed");
@@ -218,12 +723,16 @@ pub fn build_dataframe(stats: &[PlayerStats]) -> Result<DataFrame> {
 /// assert_eq!(features.shape()[0], 1); // One row
 /// assert_eq!(features.shape()[1], 1); // One column
 /// ```
+#[cfg(feature = "dataframe")]
 pub fn df_to_ndarray(df: &DataFrame, cols: &[&str]) -> Result<Array2<f32>> {
     let n = df.height();
     let m = cols.len();
     let mut arr = Array2::<f32>::zeros((n, m));
     for (j, &col_name) in cols.iter().enumerate() {
-        let ca = df.column(col_name)?.f32()?;
+        let ca = df
+            .column(col_name)
+            .and_then(|series| series.f32())
+            .map_err(NoCheatError::feature(col_name, FeatureStage::Extract))?;
         for (i, v) in ca.into_no_null_iter().enumerate() {
             arr[(i, j)] = v;
         }
@@ -231,662 +740,3922 @@ pub fn df_to_ndarray(df: &DataFrame, cols: &[&str]) -> Result<Array2<f32>> {
     Ok(arr)
 }
 
-/// Core analysis function: feature engineering + RF inference
-fn do_analysis(stats: Vec<PlayerStats>) -> Result<AnalysisResponse> {
-    // Check if we can load the model (for debugging)
-    if !std::path::Path::new(unsafe { CURRENT_MODEL_PATH }).exists() {
-        return Err(anyhow::anyhow!("{} does not exist", unsafe {
-            CURRENT_MODEL_PATH
-        }));
-    }
-
-    // 1. DataFrame
-    let mut df = build_dataframe(&stats)?;
-
-    // 2. Compute features lazily - explicitly cast to Float32 to ensure correct types
-    let lf = df
-        .lazy()
-        .with_column(
-            (col("hits").cast(DataType::Float32) / col("shots").cast(DataType::Float32))
-                .alias("hit_rate"),
-        )
-        .with_column(
-            (col("headshots").cast(DataType::Float32) / col("hits").cast(DataType::Float32))
-                .alias("headshot_rate"),
-        );
-    df = lf.collect()?;
-
-    // 3. Extract features for RF
-    let features = df_to_ndarray(&df, &["hit_rate", "headshot_rate"])?;
-
-    // 4. Model inference - properly handle prediction for each row
-    let mut results = Vec::with_capacity(stats.len());
-    let hit_rates = df.column("hit_rate")?.f32()?;
-
-    for (i, stat) in stats.into_iter().enumerate() {
-        // Convert features to f64 array for each row as expected by RandomForestClassifier
-        let row_features: Vec<f64> = features.row(i).iter().map(|&v| v as f64).collect();
-
-        // Get prediction score (single f64 value)
-        let score = match std::panic::catch_unwind(|| RF_MODEL.predict(&row_features)) {
-            Ok(score) => score as f32,
-            Err(_) => return Err(anyhow::anyhow!("Model prediction failed")),
-        };
-
-        // Build flags
-        let mut flags = Vec::new();
-        if hit_rates.get(i).unwrap() > 0.8 {
-            flags.push("HighHitRate".to_string());
-        }
-
-        results.push(PlayerResult {
-            player_id: stat.player_id,
-            suspicion_score: score,
-            flags,
-        });
-    }
-
-    Ok(AnalysisResponse { results })
-}
-
-/// Train a new cheat detection model and save it to disk.
+/// Export the engineered features of a batch in NumPy `.npy` format.
 ///
-/// This function trains a RandomForestClassifier model using labeled training data
-/// and saves the resulting model to the specified path.
+/// Writes the `f32` feature matrix used for inference (one row per player,
+/// columns in `inference::FEATURE_NAMES` order) as a little-endian, C-order
+/// `.npy` file, and the player_ids in row order as a JSON array next to it,
+/// with the extension replaced by `.ids.json`.
 ///
 /// # Arguments
 ///
-/// * `training_data` - A vector of PlayerStats containing labeled training data
-/// * `labels` - A vector of binary labels (1.0 for cheaters, 0.0 for legitimate players)
-/// * `output_path` - Path where the trained model will be saved
+/// * `stats` - The players to export
+/// * `path` - Path of the `.npy` file to write
 ///
 /// # Returns
 ///
-/// * `Result<()>` - Ok if the model was trained and saved successfully
+/// * `Result<()>` - Success or an error if a file cannot be written
 ///
 /// # Example
 ///
 /// ```no_run
-/// use nocheat::{train_model};
+/// use nocheat::export_features_npy;
 /// use nocheat::types::PlayerStats;
-/// use std::collections::HashMap;
 ///
-/// // Create training data
-/// let mut training_data = Vec::new();
-/// let mut labels = Vec::new();
+/// let stats: Vec<PlayerStats> = Vec::new();
+/// // Writes features.npy and features.ids.json, loadable with numpy.load
+/// export_features_npy(&stats, "features.npy").expect("Export failed");
+/// ```
+#[cfg(feature = "dataframe")]
+pub fn export_features_npy(stats: &[PlayerStats], path: &str) -> Result<()> {
+    let df = feature_dataframe(stats, FeaturePrecision::F32)?;
+    let features = df_to_ndarray(&df, &inference::FEATURE_NAMES)?;
+    write_npy(&features, create_output_file(path)?)?;
+
+    let ids: Vec<&str> = stats.iter().map(|s| s.player_id.as_str()).collect();
+    let ids_path = std::path::Path::new(path).with_extension("ids.json");
+    serde_json::to_writer(create_output_file(ids_path)?, &ids)?;
+    Ok(())
+}
+
+/// Analyze player statistics and return the results as Arrow IPC bytes.
 ///
-/// // Example of a legitimate player
-/// let mut shots = HashMap::new();
-/// shots.insert("rifle".to_string(), 100);
-/// let mut hits = HashMap::new();
-/// hits.insert("rifle".to_string(), 50); // 50% accuracy is normal
+/// The IPC file holds one row per result with the columns `player_id`,
+/// `suspicion_score` and `flags` (a list of flag names), so Python (pyarrow,
+/// pandas, Polars) and DuckDB consumers can read it without parsing JSON.
 ///
-/// training_data.push(PlayerStats {
-///     player_id: "normal_player".to_string(),
-///     shots_fired: shots.clone(),
-///     hits: hits.clone(),
-///     headshots: 10, // 20% headshot ratio is normal
-///     shot_timestamps_ms: None,
-///     training_label: None,
-/// });
-/// labels.push(0.0); // Not a cheater
+/// # Arguments
 ///
-/// // Example of a cheating player
-/// let mut shots = HashMap::new();
-/// shots.insert("rifle".to_string(), 100);
-/// let mut hits = HashMap::new();
-/// hits.insert("rifle".to_string(), 95); // 95% accuracy is suspicious
+/// * `stats` - A vector of PlayerStats structures containing data to analyze
 ///
-/// training_data.push(PlayerStats {
-///     player_id: "cheater".to_string(),
-///     shots_fired: shots,
-///     hits: hits,
-///     headshots: 70, // 70% headshot ratio is very suspicious
-///     shot_timestamps_ms: None,
-///     training_label: None,
-/// });
-/// labels.push(1.0); // Labeled as a cheater
+/// # Returns
 ///
-/// // Train and save model
-/// train_model(training_data, labels, "cheat_model.bin").expect("Failed to train model");
+/// * `Result<Vec<u8>>` - The results in the Arrow IPC file format
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyze_to_arrow;
+/// use nocheat::types::PlayerStats;
+///
+/// let stats: Vec<PlayerStats> = Vec::new();
+/// let bytes = analyze_to_arrow(stats).expect("Analysis failed");
+/// std::fs::write("results.arrow", bytes).expect("Write failed");
 /// ```
-pub fn train_model(
-    training_data: Vec<PlayerStats>,
-    labels: Vec<f64>,
-    output_path: &str,
-) -> Result<()> {
-    // Validate inputs
-    if training_data.len() != labels.len() {
-        return Err(anyhow::anyhow!("Number of samples and labels must match"));
+#[cfg(feature = "dataframe")]
+pub fn analyze_to_arrow(stats: Vec<PlayerStats>) -> Result<Vec<u8>> {
+    let response = analyze_stats(stats)?;
+    let mut df = results_dataframe(&response.results)?;
+    let mut bytes = Vec::new();
+    IpcWriter::new(&mut bytes).finish(&mut df)?;
+    Ok(bytes)
+}
+
+/// Build a DataFrame with the id, score and flags of each result
+#[cfg(feature = "dataframe")]
+fn results_dataframe(results: &[PlayerResult]) -> Result<DataFrame> {
+    let ids: Vec<&str> = results.iter().map(|r| r.player_id.as_str()).collect();
+    let scores: Vec<f32> = results.iter().map(|r| r.suspicion_score).collect();
+    let flags: Vec<Series> = results
+        .iter()
+        .map(|r| Series::new("", r.flags.iter().map(String::as_str).collect::<Vec<_>>()))
+        .collect();
+    let mut flags = Series::new("flags", flags);
+    if results.is_empty() {
+        flags = flags.cast(&DataType::List(Box::new(DataType::String)))?;
     }
+    Ok(DataFrame::new(vec![
+        Series::new("player_id", ids),
+        Series::new("suspicion_score", scores),
+        flags,
+    ])?)
+}
 
-    if training_data.is_empty() {
-        return Err(anyhow::anyhow!("Training data cannot be empty"));
+/// Write a 2D `f32` array in NumPy `.npy` format (version 1.0)
+#[cfg(feature = "dataframe")]
+fn write_npy<W: Write>(array: &Array2<f32>, mut writer: W) -> Result<()> {
+    let (rows, cols) = array.dim();
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        rows, cols
+    );
+    // Magic, version and header length take 10 bytes; pad the header so the
+    // data starts on a 64-byte boundary, ending it with a newline
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for value in array.iter() {
+        writer.write_all(&value.to_le_bytes())?;
     }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Build the player DataFrame and add the engineered feature columns
+#[cfg(feature = "dataframe")]
+pub(crate) fn feature_dataframe(
+    stats: &[PlayerStats],
+    precision: FeaturePrecision,
+) -> Result<DataFrame> {
+    add_feature_columns(build_dataframe(stats)?, precision, None)
+}
 
-    // 1. Build DataFrame from training data
-    let mut df = build_dataframe(&training_data)?;
+/// Add the engineered feature columns to a player DataFrame
+#[cfg(feature = "dataframe")]
+fn add_feature_columns(
+    df: DataFrame,
+    precision: FeaturePrecision,
+    zero_division: Option<ZeroDivision>,
+) -> Result<DataFrame> {
+    let dtype = match precision {
+        FeaturePrecision::F32 => DataType::Float32,
+        FeaturePrecision::F64 => DataType::Float64,
+    };
+    let ratio = |numerator: &str, denominator: &str| {
+        let quotient = |divisor: Expr| col(numerator).cast(dtype.clone()) / divisor;
+        match zero_division {
+            None => quotient(col(denominator).cast(dtype.clone())),
+            Some(ZeroDivision::Guard) => when(col(denominator).eq(lit(0u32)))
+                .then(lit(0.0).cast(dtype.clone()))
+                .otherwise(quotient(col(denominator).cast(dtype.clone()))),
+            Some(ZeroDivision::Epsilon(eps)) => {
+                quotient(col(denominator).cast(dtype.clone()) + lit(eps).cast(dtype.clone()))
+            }
+        }
+    };
 
-    // 2. Add features using lazy evaluation
-    let lf = df
+    // Compute features lazily - explicitly cast to ensure correct types
+    let df = df
         .lazy()
-        .with_column(
-            (col("hits").cast(DataType::Float32) / col("shots").cast(DataType::Float32))
-                .alias("hit_rate"),
-        )
-        .with_column(
-            (col("headshots").cast(DataType::Float32) / col("hits").cast(DataType::Float32))
-                .alias("headshot_rate"),
-        );
-    df = lf.collect()?;
+        .with_column(ratio("hits", "shots").alias("hit_rate"))
+        .with_column(ratio("headshots", "hits").alias("headshot_rate"))
+        .collect()
+        .map_err(NoCheatError::feature(
+            &inference::FEATURE_NAMES.join(", "),
+            FeatureStage::Build,
+        ))?;
+    Ok(df)
+}
+
+/// Compute the feature rows of a batch through the DataFrame pipeline
+#[cfg(feature = "dataframe")]
+pub(crate) fn feature_rows(
+    stats: &[PlayerStats],
+    precision: FeaturePrecision,
+) -> Result<Vec<FeatureRow>> {
+    feature_rows_timed(stats, precision, None, &mut Metrics::default())
+}
+
+/// `feature_rows`, dividing by zero denominators as `zero_division` says (plainly
+/// when `None`) and recording the DataFrame build and feature times in `metrics`
+#[cfg(feature = "dataframe")]
+pub(crate) fn feature_rows_timed(
+    stats: &[PlayerStats],
+    precision: FeaturePrecision,
+    zero_division: Option<ZeroDivision>,
+    metrics: &mut Metrics,
+) -> Result<Vec<FeatureRow>> {
+    let started = std::time::Instant::now();
+    let df = build_dataframe(stats)?;
+    metrics.build_ms = Metrics::elapsed_ms(started);
+
+    let started = std::time::Instant::now();
+    let rows = dataframe_rows(
+        &add_feature_columns(df, precision, zero_division)?,
+        precision,
+    );
+    metrics.feature_ms = Metrics::elapsed_ms(started);
+    rows
+}
 
-    // 3. Extract features for training
-    let feature_cols = ["hit_rate", "headshot_rate"];
-    let features = df_to_ndarray(&df, &feature_cols)?;
+/// Read the feature rows out of a DataFrame with the engineered feature columns
+#[cfg(feature = "dataframe")]
+fn dataframe_rows(df: &DataFrame, precision: FeaturePrecision) -> Result<Vec<FeatureRow>> {
+    if precision == FeaturePrecision::F64 {
+        // Read the Float64 columns directly, skipping the f32 ndarray
+        let f64_column = |name: &str| {
+            df.column(name)
+                .and_then(|series| series.f64())
+                .map_err(NoCheatError::feature(name, FeatureStage::Extract))
+        };
+        let hit_rate = f64_column("hit_rate")?;
+        let headshot_rate = f64_column("headshot_rate")?;
+        return Ok(hit_rate
+            .into_no_null_iter()
+            .zip(headshot_rate.into_no_null_iter())
+            .map(|(hit_rate, headshot_rate)| FeatureRow {
+                hit_rate,
+                headshot_rate,
+            })
+            .collect());
+    }
 
-    // 4. Convert features to training format expected by RandomForest
-    let training_features: Vec<Vec<f64>> = features
+    let features = df_to_ndarray(df, &inference::FEATURE_NAMES)?;
+    Ok(features
         .rows()
         .into_iter()
-        .map(|row| row.iter().map(|&v| v as f64).collect())
+        .map(|row| FeatureRow {
+            hit_rate: row[0] as f64,
+            headshot_rate: row[1] as f64,
+        })
+        .collect())
+}
+
+/// Compute the feature rows of a batch one player at a time
+#[cfg(not(feature = "dataframe"))]
+pub(crate) fn feature_rows(
+    stats: &[PlayerStats],
+    precision: FeaturePrecision,
+) -> Result<Vec<FeatureRow>> {
+    feature_rows_timed(stats, precision, None, &mut Metrics::default())
+}
+
+/// `feature_rows`, dividing by zero denominators as `zero_division` says (plainly
+/// when `None`) and recording the feature time in `metrics`
+#[cfg(not(feature = "dataframe"))]
+pub(crate) fn feature_rows_timed(
+    stats: &[PlayerStats],
+    precision: FeaturePrecision,
+    zero_division: Option<ZeroDivision>,
+    metrics: &mut Metrics,
+) -> Result<Vec<FeatureRow>> {
+    let started = std::time::Instant::now();
+    let rows = stats
+        .iter()
+        .map(|s| FeatureRow::compute(s, precision, zero_division))
         .collect();
+    metrics.feature_ms = Metrics::elapsed_ms(started);
+    Ok(rows)
+}
 
-    // 5. Train RandomForest model using the example from the RandomForest repository
-    use randomforest::criterion::Gini;
-    use randomforest::table::TableBuilder;
+/// Collect the sorted categories of an attribute with Polars' `to_dummies`
+#[cfg(feature = "dataframe")]
+fn categorical_vocabulary(stats: &[PlayerStats], field: &str) -> Result<CategoricalFeature> {
+    let values: Vec<&str> = stats
+        .iter()
+        .filter_map(|p| p.attributes.get(field).map(String::as_str))
+        .collect();
+    let dummies = Series::new(field, values).to_dummies(None, false)?;
 
-    // Create a table builder
-    let mut table_builder = TableBuilder::new();
+    // Dummy columns are named `{field}_{category}` and sorted
+    let prefix = format!("{}_", field);
+    let categories = dummies
+        .get_column_names()
+        .iter()
+        .filter_map(|name| name.strip_prefix(prefix.as_str()))
+        .map(str::to_string)
+        .collect();
+    Ok(CategoricalFeature {
+        field: field.to_string(),
+        categories,
+    })
+}
 
-    // Add each row of features and its corresponding label
-    for (idx, features) in training_features.iter().enumerate() {
-        table_builder
-            .add_row(features, labels[idx])
-            .map_err(|e| anyhow::anyhow!("Failed to add row to table: {}", e))?;
+/// Collect the sorted categories of an attribute
+#[cfg(not(feature = "dataframe"))]
+fn categorical_vocabulary(stats: &[PlayerStats], field: &str) -> Result<CategoricalFeature> {
+    let categories: std::collections::BTreeSet<&String> = stats
+        .iter()
+        .filter_map(|p| p.attributes.get(field))
+        .collect();
+    Ok(CategoricalFeature {
+        field: field.to_string(),
+        categories: categories.into_iter().cloned().collect(),
+    })
+}
+
+/// `do_analysis_timed` without timing
+#[cfg(test)]
+fn do_analysis(
+    stats: Vec<PlayerStats>,
+    model: &CheatModel,
+    options: &AnalysisOptions,
+) -> Result<(AnalysisResponse, CacheStats)> {
+    do_analysis_timed(stats, model, options, &mut Metrics::default())
+}
+
+/// Core analysis function: feature engineering + RF inference.
+///
+/// Also reports how many predictions were shared between identical inputs,
+/// and records the time spent in each stage in `metrics`.
+fn do_analysis_timed(
+    stats: Vec<PlayerStats>,
+    model: &CheatModel,
+    options: &AnalysisOptions,
+    metrics: &mut Metrics,
+) -> Result<(AnalysisResponse, CacheStats)> {
+    model.check_options(options)?;
+
+    // 1. Feature engineering
+    let rows = match &options.feature_cache {
+        Some(cache) => cached_feature_rows(&stats, options, cache, metrics)?,
+        None => feature_rows_timed(
+            &stats,
+            options.feature_precision,
+            Some(options.zero_division),
+            metrics,
+        )?,
+    };
+
+    // 2. Model inference and flags for each row
+    let started = std::time::Instant::now();
+    let scored = score_batch(&stats, &rows, model, options);
+    metrics.inference_ms = Metrics::elapsed_ms(started);
+    scored
+}
+
+/// `feature_rows_timed`, computing only the rows missing from `cache`, once
+/// per distinct key, and caching them
+fn cached_feature_rows(
+    stats: &[PlayerStats],
+    options: &AnalysisOptions,
+    cache: &FeatureCache,
+    metrics: &mut Metrics,
+) -> Result<Vec<FeatureRow>> {
+    let keys: Vec<FeatureKey> = stats
+        .iter()
+        .map(|s| FeatureKey::new(s, options.feature_precision, options.zero_division))
+        .collect();
+    let mut rows: Vec<Option<FeatureRow>> = keys.iter().map(|key| cache.get(key)).collect();
+
+    // Compute each missing key once, from the totals it was built from
+    let mut seen = std::collections::HashSet::new();
+    let mut missing: Vec<FeatureKey> = Vec::new();
+    let mut misses = Vec::new();
+    for (i, key) in keys.iter().enumerate() {
+        if rows[i].is_none() && seen.insert(*key) {
+            missing.push(*key);
+            misses.push(PlayerStats {
+                shots_fired: stats[i].shots_fired.clone(),
+                hits: stats[i].hits.clone(),
+                headshots: stats[i].headshots,
+                ..Default::default()
+            });
+        }
+    }
+    if !misses.is_empty() {
+        let computed = feature_rows_timed(
+            &misses,
+            options.feature_precision,
+            Some(options.zero_division),
+            metrics,
+        )?;
+        let computed: HashMap<FeatureKey, FeatureRow> = missing.into_iter().zip(computed).collect();
+        for (row, key) in rows.iter_mut().zip(&keys) {
+            if row.is_none() {
+                *row = computed.get(key).copied();
+            }
+        }
+        for (key, row) in computed {
+            cache.insert(key, row);
+        }
     }
+    Ok(rows.into_iter().flatten().collect())
+}
 
-    // Build the table
-    let table = table_builder
-        .build()
-        .map_err(|e| anyhow::anyhow!("Failed to build table: {}", e))?;
+/// Score every feature row, predicting on up to `options.max_threads` threads
+/// or every available core when unset
+fn score_batch(
+    stats: &[PlayerStats],
+    rows: &[FeatureRow],
+    model: &CheatModel,
+    options: &AnalysisOptions,
+) -> Result<(AnalysisResponse, CacheStats)> {
+    /// Result of a player, or its encoded input and the index of that input
+    /// among the distinct inputs of the batch
+    enum Pending {
+        Done(PlayerResult),
+        Scored(Vec<f64>, usize),
+    }
+
+    // Encode every model-scored player first, so identical inputs are
+    // predicted once for the whole batch however it is split across threads
+    let mut inputs: Vec<Vec<f64>> = Vec::new();
+    let mut input_index: HashMap<Vec<u64>, usize> = HashMap::new();
+    let mut cache = CacheStats::default();
+    let mut pending = Vec::with_capacity(stats.len());
+    for (stat, row) in stats.iter().zip(rows) {
+        if options.whitelist.contains(&stat.player_id) {
+            pending.push(Pending::Done(inference::whitelisted_row(stat)));
+            continue;
+        }
+        if options.flag_impossible_hit_rate && row.hit_rate > 1.0 {
+            pending.push(Pending::Done(inference::impossible_hit_rate_row(
+                stat, row, options,
+            )));
+            continue;
+        }
+        let features = model.encode(stat, row)?;
+        let index = if options.dedupe_predictions {
+            cache.lookups += 1;
+            let key = features.iter().map(|f| f.to_bits()).collect();
+            let next = inputs.len();
+            let index = *input_index.entry(key).or_insert(next);
+            if index == next {
+                inputs.push(features.clone());
+            } else {
+                cache.hits += 1;
+            }
+            index
+        } else {
+            inputs.push(features.clone());
+            inputs.len() - 1
+        };
+        pending.push(Pending::Scored(features, index));
+    }
 
-    // Train the model using Gini impurity criterion
-    let forest = RandomForestClassifier::fit(Gini, table);
+    let scores = predict_inputs(&inputs, model, options)?;
+    let results = pending
+        .into_iter()
+        .zip(stats.iter().zip(rows))
+        .map(|(pending, (stat, row))| match pending {
+            Pending::Done(result) => result,
+            Pending::Scored(features, index) => {
+                inference::scored_result(model, stat, row, &features, scores[index], options)
+            }
+        })
+        .collect();
+    Ok((AnalysisResponse { results }, cache))
+}
 
-    // 6. Save model to file
-    let file = File::create(output_path)?;
-    if let Err(e) = forest.serialize(file) {
-        return Err(anyhow::anyhow!("Failed to serialize model: {}", e));
+/// Raw model output of every input, split in contiguous slices across at most
+/// `max_threads` threads (every available core by default) so outputs keep the
+/// input order. Batches with fewer inputs than threads are predicted on the
+/// calling thread.
+fn predict_inputs(
+    inputs: &[Vec<f64>],
+    model: &CheatModel,
+    options: &AnalysisOptions,
+) -> Result<Vec<f64>> {
+    let threads = options
+        .max_threads
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+        })
+        .max(1);
+    let predict = |inputs: &[Vec<f64>]| -> Result<Vec<f64>> {
+        inputs
+            .iter()
+            .map(|features| inference::predict_raw(model, features))
+            .collect()
+    };
+    if threads == 1 || inputs.len() < threads {
+        return predict(inputs);
     }
 
-    Ok(())
+    let slice_len = inputs.len().div_ceil(threads);
+    let outcomes: Vec<Result<Vec<f64>>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = inputs
+            .chunks(slice_len)
+            .map(|slice| scope.spawn(move || predict(slice)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("Scoring thread panicked")))
+            })
+            .collect()
+    });
+
+    let mut scores = Vec::with_capacity(inputs.len());
+    for outcome in outcomes {
+        scores.extend(outcome?);
+    }
+    Ok(scores)
 }
 
-/// Generate a default model based on built-in example data.
+/// Train a new cheat detection model and save it to disk.
 ///
-/// This is useful for getting started quickly with a basic model
-/// when you don't have enough training data yet.
+/// This function trains a RandomForestClassifier model using labeled training data
+/// and saves the resulting model to the specified path, creating missing parent
+/// directories.
 ///
 /// # Arguments
 ///
-/// * `output_path` - Path where the trained model will be saved
+/// * `training_data` - A vector of PlayerStats containing labeled training data
+/// * `labels` - A vector of labels in [0, 1] (1.0 for cheaters, 0.0 for legitimate
+///   players); soft labels are thresholded at `TrainingConfig::label_cutoff`
+/// * `output_path` - Path where the trained model will be saved; missing
+///   parent directories are created
 ///
 /// # Returns
 ///
-/// * `Result<()>` - Ok if the model was created and saved successfully
+/// * `Result<()>` - Ok if the model was trained and saved successfully
 ///
 /// # Example
 ///
 /// ```no_run
-/// use nocheat::generate_default_model;
+/// use nocheat::{train_model};
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
 ///
-/// // Generate a default model
-/// generate_default_model("cheat_model.bin").expect("Failed to generate default model");
+/// // Create training data
+/// let mut training_data = Vec::new();
+/// let mut labels = Vec::new();
+///
+/// // Example of a legitimate player
+/// let mut shots = HashMap::new();
+/// shots.insert("rifle".to_string(), 100);
+/// let mut hits = HashMap::new();
+/// hits.insert("rifle".to_string(), 50); // 50% accuracy is normal
+///
+/// training_data.push(PlayerStats {
+///     player_id: "normal_player".to_string(),
+///     shots_fired: shots.clone(),
+///     hits: hits.clone(),
+///     headshots: 10, // 20% headshot ratio is normal
+///     shot_timestamps_ms: None,
+///     training_label: None,
+///     ..Default::default()
+/// });
+/// labels.push(0.0); // Not a cheater
+///
+/// // Example of a cheating player
+/// let mut shots = HashMap::new();
+/// shots.insert("rifle".to_string(), 100);
+/// let mut hits = HashMap::new();
+/// hits.insert("rifle".to_string(), 95); // 95% accuracy is suspicious
+///
+/// training_data.push(PlayerStats {
+///     player_id: "cheater".to_string(),
+///     shots_fired: shots,
+///     hits: hits,
+///     headshots: 70, // 70% headshot ratio is very suspicious
+///     shot_timestamps_ms: None,
+///     training_label: None,
+///     ..Default::default()
+/// });
+/// labels.push(1.0); // Labeled as a cheater
+///
+/// // Train and save model
+/// train_model(training_data, labels, "cheat_model.bin").expect("Failed to train model");
 /// ```
-pub fn generate_default_model(output_path: &str) -> Result<()> {
-    // Create example training data
-    let mut training_data = Vec::new();
-    let mut labels = Vec::new();
-
-    // Generate several examples of legitimate players
-    for i in 0..50 {
-        let mut shots = HashMap::new();
-        let mut hits = HashMap::new();
-
-        // Random accuracy between 40-65%
-        let shot_count = 100 + i;
-        let accuracy = 0.4 + (i % 25) as f32 * 0.01;
-        let hit_count = (shot_count as f32 * accuracy) as u32;
-
-        shots.insert("rifle".to_string(), shot_count);
-        shots.insert("pistol".to_string(), shot_count / 2);
-        hits.insert("rifle".to_string(), hit_count);
-        hits.insert("pistol".to_string(), hit_count / 2);
-
-        // Normal headshot ratio 10-25%
-        let headshot_ratio = 0.1 + (i % 15) as f32 * 0.01;
-        let headshots = (hit_count as f32 * headshot_ratio) as u32;
-
-        training_data.push(PlayerStats {
-            player_id: format!("normal_player_{}", i),
-            shots_fired: shots,
-            hits,
-            headshots,
-            shot_timestamps_ms: None,
-            training_label: Some(0.0),
-        });
-
-        labels.push(0.0); // Not a cheater
-    }
-
-    // Generate several examples of cheating players
-    for i in 0..50 {
-        let mut shots = HashMap::new();
-        let mut hits = HashMap::new();
-
-        // Very high accuracy 80-98%
-        let shot_count = 100 + i;
-        let accuracy = 0.8 + (i % 18) as f32 * 0.01;
-        let hit_count = (shot_count as f32 * accuracy) as u32;
-
-        shots.insert("rifle".to_string(), shot_count);
-        shots.insert("pistol".to_string(), shot_count / 2);
-        hits.insert("rifle".to_string(), hit_count);
-        hits.insert("pistol".to_string(), hit_count / 2);
-
-        // High headshot ratio 40-80%
-        let headshot_ratio = 0.4 + (i % 40) as f32 * 0.01;
-        let headshots = (hit_count as f32 * headshot_ratio) as u32;
-
-        training_data.push(PlayerStats {
-            player_id: format!("cheater_{}", i),
-            shots_fired: shots,
-            hits,
-            headshots,
-            shot_timestamps_ms: None,
-            training_label: Some(1.0),
-        });
+pub fn train_model(
+    training_data: Vec<PlayerStats>,
+    labels: Vec<f64>,
+    output_path: &str,
+) -> Result<()> {
+    let file = create_output_file(output_path)?;
+    train_model_to_writer(training_data, labels, &TrainingConfig::default(), file)
+}
 
-        labels.push(1.0); // Labeled as a cheater
+/// Create a file for writing, creating its missing parent directories first
+fn create_output_file<P: AsRef<std::path::Path>>(path: P) -> Result<File> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            anyhow::anyhow!("Failed to create directory {}: {}", parent.display(), e)
+        })?;
     }
-
-    // Train and save the model
-    train_model(training_data, labels, output_path)
+    File::create(path).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", path.display(), e))
 }
 
-/// FFI: analyze a JSON buffer of PlayerStats; returns JSON buffer
+/// Train a new cheat detection model and write it to any writer.
 ///
-/// This function provides a C-compatible interface for the cheat detection system.
-/// It takes a JSON buffer containing player statistics, analyzes them, and returns
-/// the results as a JSON buffer.
+/// This is the writer-based counterpart of `train_model`, useful for streaming
+/// the model to remote storage or keeping it in memory. The path-based
+/// training functions are thin wrappers around it.
 ///
-/// # Safety
+/// # Arguments
 ///
-/// This function is unsafe because it deals with raw pointers and memory allocation
-/// across the FFI boundary. The caller is responsible for:
+/// * `training_data` - A vector of PlayerStats containing labeled training data
+/// * `labels` - A vector of labels in [0, 1] (1.0 for cheaters, 0.0 for legitimate
+///   players); soft labels are thresholded at `TrainingConfig::label_cutoff`
+/// * `config` - Hyperparameters of the RandomForest
+/// * `writer` - Destination of the serialized model
 ///
-/// - Ensuring the input pointers are valid and properly aligned
-/// - Freeing the returned buffer using the `free_buffer` function
+/// # Returns
 ///
-/// # Arguments
+/// * `Result<()>` - Ok if the model was trained and written successfully
 ///
-/// * `stats_json_ptr` - Pointer to a UTF-8 encoded JSON buffer
-/// * `stats_json_len` - Length of the JSON buffer in bytes
-/// * `out_json_ptr` - Pointer to a location where the output buffer pointer will be stored
-/// * `out_json_len` - Pointer to a location where the output buffer length will be stored
+/// # Example
 ///
-/// # Returns
+/// ```no_run
+/// use nocheat::{load_model_from_bytes, train_model_to_writer};
+/// use nocheat::options::TrainingConfig;
+/// use nocheat::types::PlayerStats;
+/// use std::collections::HashMap;
 ///
-/// * `0` on success
-/// * Negative values on various errors:
-///   * `-1` - Null pointer provided
-///   * `-2` - JSON parsing error
-///   * `-3` - Analysis error
-///   * `-4` - Serialization error
-///   * `-5` - Memory allocation error
-#[no_mangle]
-pub unsafe extern "C" fn analyze_round(
-    stats_json_ptr: *const c_uchar,
-    stats_json_len: size_t,
-    out_json_ptr: *mut *mut c_uchar,
-    out_json_len: *mut size_t,
-) -> c_int {
-    // safety: assume valid UTF-8 JSON
-    if stats_json_ptr.is_null() || out_json_ptr.is_null() || out_json_len.is_null() {
-        return -1;
+/// let mut training_data = Vec::new();
+/// let mut labels = Vec::new();
+/// for (player_id, hit_count, headshots, label) in [("normal", 50, 10, 0.0), ("cheater", 95, 70, 1.0)] {
+///     let mut shots = HashMap::new();
+///     shots.insert("rifle".to_string(), 100);
+///     let mut hits = HashMap::new();
+///     hits.insert("rifle".to_string(), hit_count);
+///
+///     training_data.push(PlayerStats {
+///         player_id: player_id.to_string(),
+///         shots_fired: shots,
+///         hits: hits,
+///         headshots: headshots,
+///         ..Default::default()
+///     });
+///     labels.push(label);
+/// }
+///
+/// // Train into an in-memory buffer instead of a file
+/// let mut buffer = Vec::new();
+/// train_model_to_writer(training_data, labels, &TrainingConfig::default(), &mut buffer)
+///     .expect("Failed to train model");
+///
+/// let model = load_model_from_bytes(&buffer).expect("Failed to load model");
+/// ```
+pub fn train_model_to_writer<W: Write>(
+    training_data: Vec<PlayerStats>,
+    labels: Vec<f64>,
+    config: &TrainingConfig,
+    writer: W,
+) -> Result<()> {
+    fit_training_data(&training_data, &labels, config)?.write(writer)
+}
+
+/// Train a new cheat detection model, write it to any writer and report on the run.
+///
+/// Trains exactly like `train_model_to_writer`. When `cv_folds` is set, the
+/// data is first cross-validated with that many folds (see `cross_validate`),
+/// which trains one extra model per fold.
+///
+/// # Arguments
+///
+/// * `training_data` - A vector of PlayerStats containing labeled training data
+/// * `labels` - A vector of labels in [0, 1] (1.0 for cheaters, 0.0 for legitimate players)
+/// * `config` - Hyperparameters of the RandomForest
+/// * `cv_folds` - Number of cross-validation folds, or `None` to skip cross-validation
+/// * `writer` - Destination of the serialized model
+///
+/// # Returns
+///
+/// * `Result<TrainingReport>` - Class counts, inputs, hyperparameters,
+///   cross-validated metrics and feature importances of the trained model
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::options::TrainingConfig;
+/// use nocheat::{generate_synthetic_dataset, train_model_with_report};
+/// use std::fs::File;
+///
+/// let (training_data, labels) = generate_synthetic_dataset();
+/// let model = File::create("cheat_model.bin").expect("Failed to create model file");
+/// let report =
+///     train_model_with_report(training_data, labels, &TrainingConfig::default(), Some(5), model)
+///         .expect("Failed to train model");
+///
+/// let file = File::create("training_report.json").expect("Failed to create report");
+/// report.write(file).expect("Failed to write report");
+/// ```
+pub fn train_model_with_report<W: Write>(
+    training_data: Vec<PlayerStats>,
+    labels: Vec<f64>,
+    config: &TrainingConfig,
+    cv_folds: Option<usize>,
+    writer: W,
+) -> Result<TrainingReport> {
+    let cross_validation = cv_folds
+        .map(|folds| cross_validate(&training_data, &labels, config, folds))
+        .transpose()?;
+    let model = fit_training_data(&training_data, &labels, config)?;
+    model.write(writer)?;
+
+    let cheaters = labels
+        .iter()
+        .filter(|&&label| label >= config.label_cutoff)
+        .count();
+    Ok(TrainingReport {
+        model_version: model.metadata.version.clone(),
+        class_counts: BTreeMap::from([
+            ("clean".to_string(), labels.len() - cheaters),
+            ("cheater".to_string(), cheaters),
+        ]),
+        feature_names: model.metadata.input_names(),
+        hyperparameters: Hyperparameters {
+            trees: config.trees,
+            seed: config.seed,
+            model_kind: config.model_kind,
+            label_cutoff: config.label_cutoff,
+            calibrate: config.calibrate,
+            min_feature_importance: config.min_feature_importance,
+        },
+        cross_validation,
+        feature_importances: model.feature_importances()?,
+    })
+}
+
+/// Estimate how well a model trained with `config` generalizes, with k-fold cross-validation.
+///
+/// Sample `i` is held out in fold `i % folds`, so the split is reproducible.
+/// A model is trained on the other folds and scores every held-out player;
+/// the metrics are computed over all held-out predictions.
+///
+/// # Arguments
+///
+/// * `training_data` - Labeled training data
+/// * `labels` - A vector of labels in [0, 1] (1.0 for cheaters, 0.0 for legitimate players)
+/// * `config` - Hyperparameters of the RandomForest
+/// * `folds` - Number of folds, at least 2 and at most the number of samples
+///
+/// # Returns
+///
+/// * `Result<CrossValidation>` - Accuracy, precision, recall and F1 score
+pub fn cross_validate(
+    training_data: &[PlayerStats],
+    labels: &[f64],
+    config: &TrainingConfig,
+    folds: usize,
+) -> Result<CrossValidation> {
+    validate_training_data(training_data, labels)?;
+    if folds < 2 || folds > training_data.len() {
+        return Err(anyhow::anyhow!(
+            "Cross-validation needs between 2 and {} folds, got {}",
+            training_data.len(),
+            folds
+        ));
     }
-    let input = std::slice::from_raw_parts(stats_json_ptr, stats_json_len);
-    let stats: Vec<PlayerStats> = match serde_json::from_slice(input) {
-        Ok(v) => v,
-        Err(_) => return -2,
+
+    let (mut true_positives, mut false_positives, mut false_negatives, mut correct) = (0, 0, 0, 0);
+    for fold in 0..folds {
+        let (held_out, train): (Vec<usize>, Vec<usize>) =
+            (0..training_data.len()).partition(|&i| i % folds == fold);
+        let model = fit_training_data(
+            &train
+                .iter()
+                .map(|&i| training_data[i].clone())
+                .collect::<Vec<_>>(),
+            &train.iter().map(|&i| labels[i]).collect::<Vec<_>>(),
+            config,
+        )?;
+        for i in held_out {
+            let predicted = inference::predict_one(&model, &training_data[i])?.suspicion_score
+                >= inference::VERDICT_THRESHOLD;
+            let actual = labels[i] >= config.label_cutoff;
+            match (predicted, actual) {
+                (true, true) => true_positives += 1,
+                (true, false) => false_positives += 1,
+                (false, true) => false_negatives += 1,
+                (false, false) => {}
+            }
+            correct += (predicted == actual) as usize;
+        }
+    }
+
+    let ratio = |numerator: usize, denominator: usize| {
+        if denominator == 0 {
+            0.0
+        } else {
+            numerator as f64 / denominator as f64
+        }
     };
-    match analyze_stats(stats) {
-        Ok(resp) => write_buffer(&resp, out_json_ptr, out_json_len),
-        Err(_) => -3,
+    let precision = ratio(true_positives, true_positives + false_positives);
+    let recall = ratio(true_positives, true_positives + false_negatives);
+    let f1 = if precision + recall > 0.0 {
+        2.0 * precision * recall / (precision + recall)
+    } else {
+        0.0
+    };
+    Ok(CrossValidation {
+        folds,
+        accuracy: ratio(correct, training_data.len()),
+        precision,
+        recall,
+        f1,
+    })
+}
+
+/// Validate the training data, encode it and fit a model as configured
+fn fit_training_data(
+    training_data: &[PlayerStats],
+    labels: &[f64],
+    config: &TrainingConfig,
+) -> Result<CheatModel> {
+    // Validate inputs
+    validate_training_data(training_data, labels)?;
+
+    // 1. Compute features for training, one-hot encoding the categorical fields
+    let categorical = config
+        .categorical_fields
+        .iter()
+        .map(|field| categorical_vocabulary(training_data, field))
+        .collect::<Result<Vec<_>>>()?;
+    let metadata = training_metadata(config, categorical);
+    let training_features = encode_training_data(training_data, &metadata, config)?;
+
+    // 2. Train the forest
+    fit_model(training_features, labels, metadata, config)
+}
+
+/// Number of copies of a full-confidence sample in `train_model_weighted`.
+///
+/// The forest has no notion of sample weight, so a sample of confidence `c`
+/// is repeated `round(c * LABEL_CONFIDENCE_RESOLUTION)` times instead.
+pub const LABEL_CONFIDENCE_RESOLUTION: usize = 4;
+
+/// Train a new cheat detection model whose samples are weighted by
+/// `PlayerStats::label_confidence` and write it to any writer.
+///
+/// Behaves like `train_model_to_writer`, except that a sample with low label
+/// confidence influences the model less than one with high confidence.
+/// Samples without a confidence count as fully confident (1.0), and samples
+/// whose confidence rounds to zero copies are left out.
+///
+/// # Arguments
+///
+/// * `training_data` - A vector of PlayerStats containing labeled training data,
+///   with optional `label_confidence` values in [0, 1]
+/// * `labels` - A vector of labels in [0, 1] (1.0 for cheaters, 0.0 for legitimate players)
+/// * `config` - Hyperparameters of the RandomForest
+/// * `writer` - Destination of the serialized model
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the model was trained and written successfully
+pub fn train_model_weighted<W: Write>(
+    training_data: Vec<PlayerStats>,
+    labels: Vec<f64>,
+    config: &TrainingConfig,
+    writer: W,
+) -> Result<()> {
+    validate_training_data(&training_data, &labels)?;
+    let copies = training_data
+        .iter()
+        .map(|stats| {
+            let confidence = stats.label_confidence.unwrap_or(1.0);
+            if !(0.0..=1.0).contains(&confidence) {
+                return Err(anyhow::anyhow!(
+                    "Label confidence of '{}' is {}; confidences must be in [0, 1]",
+                    stats.player_id,
+                    confidence
+                ));
+            }
+            Ok((confidence * LABEL_CONFIDENCE_RESOLUTION as f64).round() as usize)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let categorical = config
+        .categorical_fields
+        .iter()
+        .map(|field| categorical_vocabulary(&training_data, field))
+        .collect::<Result<Vec<_>>>()?;
+    let metadata = training_metadata(config, categorical);
+    let encoded = encode_training_data(&training_data, &metadata, config)?;
+
+    let mut training_features = Vec::new();
+    let mut weighted_labels = Vec::new();
+    for ((row, label), count) in encoded.into_iter().zip(labels).zip(copies) {
+        for _ in 0..count {
+            training_features.push(row.clone());
+            weighted_labels.push(label);
+        }
+    }
+    if training_features.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Every training sample has a label confidence of zero"
+        ));
     }
+
+    fit_model(training_features, &weighted_labels, metadata, config)?.write(writer)
 }
 
-/// Companion to free allocated buffer
+/// Train a new cheat detection model from a table of precomputed features
+/// and write it to any writer.
 ///
-/// This function must be called to free the memory allocated by `analyze_round`.
+/// `schema` names the feature columns, the label column and the optional
+/// player id column of `df`; every other column is ignored. The model reads
+/// the feature columns in the given order, so it can score live
+/// `PlayerStats` like any other model. The feature settings of `config`
+/// (`extra_features`, `categorical_fields`, `optional_features` and
+/// `feature_hashing`) do not apply; list the features in the schema instead.
 ///
-/// # Safety
+/// # Arguments
 ///
-/// This function is unsafe because it deals with raw pointers and memory deallocation.
-/// The caller must ensure that:
+/// * `df` - The training table, one player per row
+/// * `schema` - Roles of the columns of `df`
+/// * `config` - Hyperparameters of the RandomForest
+/// * `writer` - Destination of the serialized model
 ///
-/// - The pointer was previously allocated by `analyze_round`
-/// - The pointer has not already been freed
-/// - The length matches what was given in `out_json_len`
+/// # Returns
+///
+/// * `Result<()>` - Ok if the model was trained and written successfully; an
+///   error if a schema column is missing, a value is null or not numeric, or
+///   `config` sets a feature option
+#[cfg(feature = "dataframe")]
+pub fn train_model_from_dataframe<W: Write>(
+    df: &DataFrame,
+    schema: &SchemaSpec,
+    config: &TrainingConfig,
+    writer: W,
+) -> Result<()> {
+    schema.validate(&df.get_column_names())?;
+    if !config.extra_features.is_empty()
+        || !config.categorical_fields.is_empty()
+        || !config.optional_features.is_empty()
+        || config.feature_hashing.is_some()
+    {
+        return Err(anyhow::anyhow!(
+            "Feature options of TrainingConfig do not apply to tabular training; \
+             list the features in SchemaSpec::feature_cols"
+        ));
+    }
+    if df.height() == 0 {
+        return Err(anyhow::anyhow!("Training data cannot be empty"));
+    }
+
+    // Name of the player of a row for error messages
+    let ids = schema
+        .id_col
+        .as_ref()
+        .map(|name| df.column(name)?.cast(&DataType::String))
+        .transpose()?;
+    let player = |index: usize| match ids.as_ref().and_then(|ids| ids.str().ok()?.get(index)) {
+        Some(id) => format!("row {} ('{}')", index, id),
+        None => format!("row {}", index),
+    };
+    let numeric_column = |name: &str| -> Result<Vec<f64>> {
+        let values = df
+            .column(name)?
+            .cast(&DataType::Float64)
+            .map_err(|e| anyhow::anyhow!("Column '{}' is not numeric: {}", name, e))?;
+        values
+            .f64()?
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| {
+                value.ok_or_else(|| {
+                    anyhow::anyhow!("Column '{}' has no value at {}", name, player(index))
+                })
+            })
+            .collect()
+    };
+
+    let labels = numeric_column(&schema.label_col)?;
+    if let Some(index) = labels.iter().position(|label| !label.is_finite()) {
+        return Err(anyhow::anyhow!(
+            "Label of {} is {}; labels must be finite values in [0, 1]",
+            player(index),
+            labels[index]
+        ));
+    }
+    let mut training_features = vec![Vec::with_capacity(schema.feature_cols.len()); df.height()];
+    for name in &schema.feature_cols {
+        for (row, value) in training_features.iter_mut().zip(numeric_column(name)?) {
+            row.push(value);
+        }
+    }
+
+    let metadata = ModelMetadata {
+        version: config.version(),
+        feature_names: schema.feature_cols.clone(),
+        categorical: Vec::new(),
+        optional_features: Vec::new(),
+        selected_inputs: None,
+        calibration: None,
+        feature_hashing: None,
+        model_kind: config.model_kind,
+    };
+    fit_model(training_features, &labels, metadata, config)?.write(writer)
+}
+
+/// Train a new cheat detection model from a CSV file of precomputed features.
+///
+/// Reads the file, which must have a header row, and trains as
+/// `train_model_from_dataframe`.
 ///
 /// # Arguments
 ///
-/// * `ptr` - Pointer to the buffer to free
-/// * `len` - Length of the buffer in bytes
-#[no_mangle]
-pub unsafe extern "C" fn free_buffer(ptr: *mut c_uchar, len: size_t) {
-    if ptr.is_null() || len == 0 {
-        return;
+/// * `csv_path` - Path of the CSV training file
+/// * `schema` - Roles of the columns of the file
+/// * `config` - Hyperparameters of the RandomForest
+/// * `output_path` - Path where the trained model is written
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the model was trained and saved successfully
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::options::{SchemaSpec, TrainingConfig};
+/// use nocheat::train_model_from_csv;
+///
+/// let schema = SchemaSpec {
+///     feature_cols: vec!["hit_rate".to_string(), "headshot_rate".to_string()],
+///     label_col: "is_cheater".to_string(),
+///     id_col: Some("account".to_string()),
+/// };
+/// train_model_from_csv(
+///     "exports/labeled_rounds.csv",
+///     &schema,
+///     &TrainingConfig::default(),
+///     "models/cheat_model.bin",
+/// )
+/// .expect("Failed to train model");
+/// ```
+#[cfg(feature = "dataframe")]
+pub fn train_model_from_csv<P: AsRef<std::path::Path>>(
+    csv_path: P,
+    schema: &SchemaSpec,
+    config: &TrainingConfig,
+    output_path: &str,
+) -> Result<()> {
+    let csv_path = csv_path.as_ref();
+    let df = CsvReader::from_path(csv_path)
+        .and_then(|reader| reader.has_header(true).finish())
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", csv_path.display(), e))?;
+    train_model_from_dataframe(&df, schema, config, create_output_file(output_path)?)
+}
+
+/// Metadata of a model trained from scratch with `config`
+fn training_metadata(
+    config: &TrainingConfig,
+    categorical: Vec<CategoricalFeature>,
+) -> ModelMetadata {
+    let mut feature_names = ModelMetadata::legacy().feature_names;
+    feature_names.extend(config.extra_features.iter().cloned());
+    ModelMetadata {
+        version: config.version(),
+        feature_names,
+        categorical,
+        optional_features: config.optional_features.clone(),
+        selected_inputs: None,
+        calibration: None,
+        feature_hashing: config.feature_hashing.clone(),
+        model_kind: config.model_kind,
     }
-    let _ = Vec::from_raw_parts(ptr, len, len);
 }
 
-/// Serialize response and allocate C buffer
-fn write_buffer(
-    resp: &AnalysisResponse,
-    out_json_ptr: *mut *mut c_uchar,
-    out_json_len: *mut size_t,
-) -> c_int {
-    let json = match serde_json::to_vec(resp) {
-        Ok(j) => j,
-        Err(_) => return -4,
+/// Fit a model on encoded training rows, dropping the inputs the forest
+/// barely uses when `config.min_feature_importance` is set and calibrating
+/// its scores when `config.calibrate` is set
+fn fit_model(
+    training_features: Vec<Vec<f64>>,
+    labels: &[f64],
+    metadata: ModelMetadata,
+    config: &TrainingConfig,
+) -> Result<CheatModel> {
+    if config.calibrate && config.model_kind == ModelKind::Regressor {
+        return Err(anyhow::anyhow!(
+            "Calibration maps tree votes and cannot be used with a regressor"
+        ));
+    }
+    let labels = &config.hard_labels(labels)?;
+    let forest = fit_table(&training_features, labels, config)?;
+    let model = CheatModel::new(forest, metadata);
+    let (mut model, training_features) = match config.min_feature_importance {
+        Some(cutoff) => drop_unimportant_inputs(model, training_features, labels, config, cutoff)?,
+        None => (model, training_features),
     };
-    let len = json.len();
-    unsafe {
-        let buf = libc::malloc(len) as *mut c_uchar;
-        if buf.is_null() {
-            return -5;
+    if config.calibrate {
+        model.metadata.calibration =
+            Some(fit_calibration(&model, &training_features, labels, config)?);
+    }
+    Ok(model)
+}
+
+/// Retrain on the columns whose importance is at or above `cutoff`, returning
+/// the model and the training rows restricted to its inputs
+fn drop_unimportant_inputs(
+    model: CheatModel,
+    training_features: Vec<Vec<f64>>,
+    labels: &[f64],
+    config: &TrainingConfig,
+    cutoff: f64,
+) -> Result<(CheatModel, Vec<Vec<f64>>)> {
+    let importances = model.feature_importances()?;
+    let names = model.metadata.input_names();
+    let kept: Vec<usize> = (0..names.len())
+        .filter(|&i| importances.get(&names[i]).copied().unwrap_or_default() >= cutoff)
+        .collect();
+    if kept.is_empty() || kept.len() == names.len() {
+        return Ok((model, training_features));
+    }
+    let reduced: Vec<Vec<f64>> = training_features
+        .iter()
+        .map(|row| kept.iter().map(|&i| row[i]).collect())
+        .collect();
+    let metadata = ModelMetadata {
+        selected_inputs: Some(kept.iter().map(|&i| names[i].clone()).collect()),
+        ..model.metadata
+    };
+    let forest = fit_table(&reduced, labels, config)?;
+    Ok((CheatModel::new(forest, metadata), reduced))
+}
+
+/// Fit Platt scaling on out-of-fold cheater vote shares.
+///
+/// A forest is overconfident on its own training rows, so the rows are split
+/// in two folds and each fold is scored by a forest trained on the other.
+fn fit_calibration(
+    model: &CheatModel,
+    training_features: &[Vec<f64>],
+    labels: &[f64],
+    config: &TrainingConfig,
+) -> Result<model::PlattScaling> {
+    if model.is_multiclass() {
+        return Err(anyhow::anyhow!(
+            "Calibration requires binary cheater/legitimate labels"
+        ));
+    }
+    if training_features.len() < 4 {
+        return Err(anyhow::anyhow!(
+            "Calibration requires at least 4 training rows, got {}",
+            training_features.len()
+        ));
+    }
+
+    let mut scores = vec![0.0; training_features.len()];
+    for fold in 0..2 {
+        let (train, held_out): (Vec<usize>, Vec<usize>) =
+            (0..training_features.len()).partition(|i| i % 2 != fold);
+        let fold_features: Vec<Vec<f64>> = train
+            .iter()
+            .map(|&i| training_features[i].clone())
+            .collect();
+        let fold_labels: Vec<f64> = train.iter().map(|&i| labels[i]).collect();
+        let fold_model = CheatModel::from_forest(fit_table(&fold_features, &fold_labels, config)?);
+        for i in held_out {
+            scores[i] = fold_model.cheater_vote_share(&training_features[i]);
         }
-        ptr::copy_nonoverlapping(json.as_ptr(), buf, len);
-        *out_json_ptr = buf;
-        *out_json_len = len;
     }
-    0
+    model::PlattScaling::fit(&scores, labels)
 }
 
-/// Set the path to load a custom model
+/// Train a new cheat detection model from newline-delimited JSON and save it to a file.
 ///
-/// This function allows loading a custom model from a specified path.
-/// It's particularly useful when integrating with game engines like Unreal Engine
-/// where the default path may not be accessible or when you want to load different models.
+/// See `train_model_streaming_to_writer` for the input format.
 ///
-/// # Safety
+/// # Arguments
 ///
-/// This function is unsafe because it:
-/// - Modifies a global static variable that affects all future model loading
-/// - Takes a raw pointer that must be valid UTF-8 encoded path string
+/// * `reader` - Source of the NDJSON training rows
+/// * `output_path` - Path where the trained model will be saved; missing
+///   parent directories are created
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the model was trained and saved successfully
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::train_model_streaming;
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// let reader = BufReader::new(File::open("training_data.ndjson").expect("Failed to open data"));
+/// train_model_streaming(reader, "models/cheat_model.bin").expect("Failed to train model");
+/// ```
+pub fn train_model_streaming<R: BufRead>(reader: R, output_path: &str) -> Result<()> {
+    let file = create_output_file(output_path)?;
+    train_model_streaming_to_writer(reader, &TrainingConfig::default(), file)
+}
+
+/// Train a new cheat detection model from newline-delimited JSON and write it to any writer.
+///
+/// Every non-empty line holds one `PlayerStats` whose `training_label` is
+/// set. Lines are parsed and encoded one at a time, so only the encoded
+/// feature table is held in memory rather than the full player records,
+/// which lets corpora larger than RAM as JSON train on a single machine.
+///
+/// Categorical fields need the vocabulary of the whole dataset before the
+/// first row can be encoded, so `config.categorical_fields` must be empty.
 ///
 /// # Arguments
 ///
-/// * `path_ptr` - Pointer to a null-terminated UTF-8 encoded string containing the model path
-/// * `path_len` - Length of the path string in bytes (not including null terminator)
+/// * `reader` - Source of the NDJSON training rows
+/// * `config` - Hyperparameters of the RandomForest
+/// * `writer` - Destination of the serialized model
 ///
 /// # Returns
 ///
-/// * `0` on success
-/// * `-1` if the path pointer is null
-/// * `-2` if the path is not valid UTF-8
-/// * `-3` if the model file doesn't exist or can't be opened
-/// * `-4` if the model couldn't be deserialized (invalid format)
-#[no_mangle]
-pub unsafe extern "C" fn set_model_path(path_ptr: *const c_uchar, path_len: size_t) -> c_int {
-    // Check for null pointer
-    if path_ptr.is_null() {
-        return -1;
+/// * `Result<()>` - Ok if the model was trained and written successfully
+pub fn train_model_streaming_to_writer<R: BufRead, W: Write>(
+    reader: R,
+    config: &TrainingConfig,
+    writer: W,
+) -> Result<()> {
+    if !config.categorical_fields.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Categorical fields are not supported when training from a stream"
+        ));
     }
+    let metadata = training_metadata(config, Vec::new());
 
-    // Convert C string to Rust string slice
-    let path_bytes = std::slice::from_raw_parts(path_ptr, path_len);
-    let path_str = match std::str::from_utf8(path_bytes) {
-        Ok(s) => s,
-        Err(_) => return -2,
+    let mut training_features = Vec::new();
+    let mut labels = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let stats: PlayerStats = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("Invalid training row on line {}: {}", index + 1, e))?;
+        let label = stats.training_label.ok_or_else(|| {
+            anyhow::anyhow!("Training row on line {} has no training_label", index + 1)
+        })?;
+        let row = FeatureRow::from_stats_with(&stats, config.feature_precision);
+        training_features.push(metadata.encode(&stats, &row)?);
+        labels.push(label);
+    }
+    if training_features.is_empty() {
+        return Err(anyhow::anyhow!("Training data cannot be empty"));
+    }
+
+    fit_model(training_features, &labels, metadata, config)?.write(writer)
+}
+
+/// Continue training an existing model file with additional trees.
+///
+/// Warm-starts a nightly job from the current production model instead of
+/// training from scratch; see `train_model_warm_start_to_writer` for details.
+///
+/// # Arguments
+///
+/// * `existing_path` - Path of the model to extend
+/// * `new_data` - A vector of PlayerStats containing the new labeled data
+/// * `labels` - A vector of labels for `new_data`
+/// * `output_path` - Path where the extended model will be saved
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the model was extended and saved successfully
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::train_model_warm_start;
+/// use nocheat::types::PlayerStats;
+///
+/// let new_data: Vec<PlayerStats> = Vec::new(); // today's confirmed verdicts
+/// let labels: Vec<f64> = Vec::new();
+/// train_model_warm_start("models/cheat_model.bin", new_data, labels, "models/cheat_model.next.bin")
+///     .expect("Failed to extend model");
+/// ```
+pub fn train_model_warm_start(
+    existing_path: &str,
+    new_data: Vec<PlayerStats>,
+    labels: Vec<f64>,
+    output_path: &str,
+) -> Result<()> {
+    let existing = load_model(existing_path)?;
+    let file = create_output_file(output_path)?;
+    train_model_warm_start_to_writer(
+        &existing,
+        new_data,
+        labels,
+        &TrainingConfig::default(),
+        file,
+    )
+}
+
+/// Continue training a loaded model with additional trees and write the result.
+///
+/// `config.trees` new trees are fitted on `new_data` and appended to the
+/// trees of `existing`, which are kept unchanged. The new data is encoded with
+/// the metadata of `existing` (its features, category vocabularies and
+/// presence indicators), so `config.categorical_fields` and
+/// `config.optional_features` are ignored; only the version is replaced.
+///
+/// Because predictions are a majority vote over all trees, the weight of the
+/// new data grows with `config.trees` relative to the size of `existing`.
+/// With `config.seed` set, the new trees are deterministic, and so is the
+/// extended model for a given base model. Use a different seed per run so
+/// successive warm starts do not fit identical trees on identical data.
+///
+/// # Arguments
+///
+/// * `existing` - The model to extend
+/// * `new_data` - A vector of PlayerStats containing the new labeled data
+/// * `labels` - A vector of labels for `new_data`
+/// * `config` - Hyperparameters of the new trees
+/// * `writer` - Destination of the serialized model
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the model was extended and written successfully
+pub fn train_model_warm_start_to_writer<W: Write>(
+    existing: &CheatModel,
+    new_data: Vec<PlayerStats>,
+    labels: Vec<f64>,
+    config: &TrainingConfig,
+    writer: W,
+) -> Result<()> {
+    validate_training_data(&new_data, &labels)?;
+    if config.model_kind != existing.metadata.model_kind {
+        return Err(anyhow::anyhow!(
+            "Cannot extend a {:?} model with {:?} trees",
+            existing.metadata.model_kind,
+            config.model_kind
+        ));
+    }
+
+    // The calibration of the existing forest does not hold for the merged one
+    let metadata = ModelMetadata {
+        version: config.version(),
+        calibration: None,
+        ..existing.metadata.clone()
     };
+    let labels = config.hard_labels(&labels)?;
+    let forest = fit_encoded(&new_data, &labels, &metadata, config)?;
 
-    // Verify the model file exists and can be loaded
-    let path_exists = std::path::Path::new(path_str).exists();
-    if !path_exists {
-        return -3;
+    let mut existing_bytes = Vec::new();
+    existing
+        .forest()
+        .serialize(&mut existing_bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize model: {}", e))?;
+    let mut new_bytes = Vec::new();
+    forest
+        .serialize(&mut new_bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize model: {}", e))?;
+
+    let merged = forest::ForestLayout::parse(&existing_bytes)?
+        .merge(forest::ForestLayout::parse(&new_bytes)?)?;
+    let mut merged_bytes = Vec::new();
+    merged.write(&mut merged_bytes);
+    let merged = randomforest::RandomForestClassifier::deserialize(merged_bytes.as_slice())
+        .map_err(|e| anyhow::anyhow!("Failed to deserialize model: {}", e))?;
+
+    CheatModel::new(merged, metadata).write(writer)
+}
+
+/// Reject training data that is empty, does not match its labels or has a
+/// NaN or infinite label
+fn validate_training_data(training_data: &[PlayerStats], labels: &[f64]) -> Result<()> {
+    if training_data.len() != labels.len() {
+        return Err(anyhow::anyhow!("Number of samples and labels must match"));
+    }
+
+    if training_data.is_empty() {
+        return Err(anyhow::anyhow!("Training data cannot be empty"));
+    }
+
+    if let Some(index) = labels.iter().position(|label| !label.is_finite()) {
+        return Err(anyhow::anyhow!(
+            "Label of sample {} ('{}') is {}; labels must be finite values in [0, 1]",
+            index,
+            training_data[index].player_id,
+            labels[index]
+        ));
+    }
+    Ok(())
+}
+
+/// Encode the training data with `metadata` and fit a forest on it
+fn fit_encoded(
+    training_data: &[PlayerStats],
+    labels: &[f64],
+    metadata: &ModelMetadata,
+    config: &TrainingConfig,
+) -> Result<randomforest::RandomForestClassifier> {
+    let training_features = encode_training_data(training_data, metadata, config)?;
+    fit_table(&training_features, labels, config)
+}
+
+/// Encode every training player into a model input row
+fn encode_training_data(
+    training_data: &[PlayerStats],
+    metadata: &ModelMetadata,
+    config: &TrainingConfig,
+) -> Result<Vec<Vec<f64>>> {
+    training_data
+        .iter()
+        .zip(feature_rows(training_data, config.feature_precision)?.iter())
+        .map(|(stats, row)| metadata.encode(stats, row))
+        .collect()
+}
+
+/// Fit a forest on encoded training rows
+fn fit_table(
+    training_features: &[Vec<f64>],
+    labels: &[f64],
+    config: &TrainingConfig,
+) -> Result<randomforest::RandomForestClassifier> {
+    // Train RandomForest model using the example from the RandomForest repository
+    use randomforest::criterion::{Gini, Mse};
+    use randomforest::table::TableBuilder;
+
+    // Create a table builder
+    let mut table_builder = TableBuilder::new();
+
+    // Add each row of features and its corresponding label
+    for (idx, features) in training_features.iter().enumerate() {
+        table_builder
+            .add_row(features, labels[idx])
+            .map_err(|e| anyhow::anyhow!("Failed to add row to table: {}", e))?;
+    }
+
+    // Build the table
+    let table = table_builder
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build table: {}", e))?;
+
+    match config.model_kind {
+        // Train the model using Gini impurity criterion
+        ModelKind::Classifier => Ok(config.forest_options()?.fit(Gini, table)),
+        // Regressors share the serialized forest format of classifiers, so
+        // the trees are stored in a classifier and read by `model_kind`
+        ModelKind::Regressor => {
+            let forest = config.regressor_options()?.fit(Mse, table);
+            let mut bytes = Vec::new();
+            forest
+                .serialize(&mut bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to serialize model: {}", e))?;
+            randomforest::RandomForestClassifier::deserialize(bytes.as_slice())
+                .map_err(|e| anyhow::anyhow!("Failed to deserialize model: {}", e))
+        }
+    }
+}
+
+/// Generate a default model based on built-in example data.
+///
+/// This is useful for getting started quickly with a basic model
+/// when you don't have enough training data yet.
+///
+/// # Arguments
+///
+/// * `output_path` - Path where the trained model will be saved; missing
+///   parent directories are created
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the model was created and saved successfully
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::generate_default_model;
+///
+/// // Generate a default model
+/// generate_default_model("cheat_model.bin").expect("Failed to generate default model");
+/// ```
+pub fn generate_default_model(output_path: &str) -> Result<()> {
+    // Train and save the model on the built-in example data
+    let (training_data, labels) = generate_synthetic_dataset();
+    train_model(training_data, labels, output_path)
+}
+
+/// Generate the default model only if no file exists at `path` yet.
+///
+/// Convenient on first run: an existing model, default or custom, is never
+/// touched. The model is written to a temporary file next to `path` and then
+/// linked into place, which fails instead of overwriting when another caller
+/// got there first, so concurrent callers never observe a partially written
+/// model and all of them succeed.
+///
+/// # Arguments
+///
+/// * `path` - Path of the model; missing parent directories are created
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the model exists when the function returns
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::{ensure_default_model, load_model};
+///
+/// ensure_default_model("models/cheat_model.bin").expect("Failed to create model");
+/// let model = load_model("models/cheat_model.bin").expect("Failed to load model");
+/// ```
+pub fn ensure_default_model(path: &str) -> Result<()> {
+    let target = std::path::Path::new(path);
+    if target.exists() {
+        return Ok(());
+    }
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let mut temp_name = target.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(".{}.{}.tmp", std::process::id(), nanos));
+    let temp = target.with_file_name(temp_name);
+
+    let (training_data, labels) = generate_synthetic_dataset();
+    let written = create_output_file(&temp).and_then(|file| {
+        train_model_to_writer(training_data, labels, &TrainingConfig::default(), file)
+    });
+    // Unlike a rename, a hard link never replaces an existing file
+    let linked = written.and_then(|()| match std::fs::hard_link(&temp, target) {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => Err(anyhow::anyhow!(
+            "Failed to create {}: {}",
+            target.display(),
+            e
+        )),
+        _ => Ok(()),
+    });
+    let _ = std::fs::remove_file(&temp);
+    linked
+}
+
+/// Balance a training set so that cheaters and legitimate players are equally many.
+///
+/// Labels of 0.5 or more count as cheaters (the default
+/// `TrainingConfig::label_cutoff`); labels are returned unchanged. Undersampling
+/// keeps the selected rows in their original order; oversampling keeps every
+/// row and appends the duplicates. The same seed always gives the same result.
+///
+/// # Arguments
+///
+/// * `training_data` - A vector of PlayerStats containing labeled training data
+/// * `labels` - The labels of `training_data`
+/// * `strategy` - Whether to drop majority rows or duplicate minority rows
+/// * `seed` - Seed of the random selection
+///
+/// # Returns
+///
+/// * `Result<(Vec<PlayerStats>, Vec<f64>)>` - The balanced players and their
+///   labels, or an error when the lengths differ or a class has no rows
+///
+/// # Example
+///
+/// ```
+/// use nocheat::{balance_dataset, generate_synthetic_dataset};
+/// use nocheat::options::BalanceStrategy;
+///
+/// let (mut training_data, mut labels) = generate_synthetic_dataset();
+/// training_data.truncate(60); // 50 legitimate players, 10 cheaters
+/// labels.truncate(60);
+///
+/// let (balanced, labels) =
+///     balance_dataset(training_data, labels, BalanceStrategy::Undersample, 42).unwrap();
+/// assert_eq!(balanced.len(), 20);
+/// assert_eq!(labels.iter().filter(|&&l| l == 1.0).count(), 10);
+/// ```
+pub fn balance_dataset(
+    training_data: Vec<PlayerStats>,
+    labels: Vec<f64>,
+    strategy: BalanceStrategy,
+    seed: u64,
+) -> Result<(Vec<PlayerStats>, Vec<f64>)> {
+    validate_training_data(&training_data, &labels)?;
+    let (cheaters, legitimate): (Vec<usize>, Vec<usize>) =
+        (0..labels.len()).partition(|&i| labels[i] >= inference::VERDICT_THRESHOLD as f64);
+    if cheaters.is_empty() || legitimate.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Cannot balance a dataset with {} cheaters and {} legitimate players",
+            cheaters.len(),
+            legitimate.len()
+        ));
+    }
+    let (minority, majority) = if cheaters.len() < legitimate.len() {
+        (cheaters, legitimate)
+    } else {
+        (legitimate, cheaters)
+    };
+
+    let mut rng = SplitMix64(seed);
+    let selected = match strategy {
+        BalanceStrategy::Undersample => {
+            // Partial Fisher-Yates shuffle picking minority.len() majority rows
+            let mut pool = majority;
+            for i in 0..minority.len() {
+                let j = i + rng.below(pool.len() - i);
+                pool.swap(i, j);
+            }
+            pool.truncate(minority.len());
+            let mut selected = minority;
+            selected.extend(pool);
+            selected.sort_unstable();
+            selected
+        }
+        BalanceStrategy::Oversample => {
+            let duplicates: Vec<usize> = (minority.len()..majority.len())
+                .map(|_| minority[rng.below(minority.len())])
+                .collect();
+            let mut selected: Vec<usize> = (0..labels.len()).collect();
+            selected.extend(duplicates);
+            selected
+        }
+    };
+
+    Ok(selected
+        .into_iter()
+        .map(|i| (training_data[i].clone(), labels[i]))
+        .unzip())
+}
+
+/// Seeded SplitMix64 generator, for reproducible sampling
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform index below `n`, which must be positive
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Uniform value in [0, 1), from the top 53 bits
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Generate the built-in synthetic training data used by `generate_default_model`.
+///
+/// Produces 50 legitimate players (40-65% accuracy, 10-25% headshots) followed
+/// by 50 cheaters (80-98% accuracy, 40-80% headshots), with matching labels.
+///
+/// # Returns
+///
+/// * `(Vec<PlayerStats>, Vec<f64>)` - The players and their labels
+///
+/// # Example
+///
+/// ```
+/// use nocheat::generate_synthetic_dataset;
+///
+/// let (training_data, labels) = generate_synthetic_dataset();
+/// assert_eq!(training_data.len(), labels.len());
+/// assert_eq!(labels.iter().filter(|&&l| l == 1.0).count(), 50);
+/// ```
+pub fn generate_synthetic_dataset() -> (Vec<PlayerStats>, Vec<f64>) {
+    // Create example training data
+    let mut training_data = Vec::new();
+    let mut labels = Vec::new();
+
+    // Generate several examples of legitimate players
+    for i in 0..50 {
+        let mut shots = HashMap::new();
+        let mut hits = HashMap::new();
+
+        // Random accuracy between 40-65%
+        let shot_count = 100 + i;
+        let accuracy = 0.4 + (i % 25) as f32 * 0.01;
+        let hit_count = (shot_count as f32 * accuracy) as u32;
+
+        shots.insert("rifle".to_string(), shot_count);
+        shots.insert("pistol".to_string(), shot_count / 2);
+        hits.insert("rifle".to_string(), hit_count);
+        hits.insert("pistol".to_string(), hit_count / 2);
+
+        // Normal headshot ratio 10-25%
+        let headshot_ratio = 0.1 + (i % 15) as f32 * 0.01;
+        let headshots = (hit_count as f32 * headshot_ratio) as u32;
+
+        training_data.push(PlayerStats {
+            player_id: format!("normal_player_{}", i),
+            shots_fired: shots,
+            hits,
+            headshots,
+            shot_timestamps_ms: None,
+            training_label: Some(0.0),
+            ..Default::default()
+        });
+
+        labels.push(0.0); // Not a cheater
+    }
+
+    // Generate several examples of cheating players
+    for i in 0..50 {
+        let mut shots = HashMap::new();
+        let mut hits = HashMap::new();
+
+        // Very high accuracy 80-98%
+        let shot_count = 100 + i;
+        let accuracy = 0.8 + (i % 18) as f32 * 0.01;
+        let hit_count = (shot_count as f32 * accuracy) as u32;
+
+        shots.insert("rifle".to_string(), shot_count);
+        shots.insert("pistol".to_string(), shot_count / 2);
+        hits.insert("rifle".to_string(), hit_count);
+        hits.insert("pistol".to_string(), hit_count / 2);
+
+        // High headshot ratio 40-80%
+        let headshot_ratio = 0.4 + (i % 40) as f32 * 0.01;
+        let headshots = (hit_count as f32 * headshot_ratio) as u32;
+
+        training_data.push(PlayerStats {
+            player_id: format!("cheater_{}", i),
+            shots_fired: shots,
+            hits,
+            headshots,
+            shot_timestamps_ms: None,
+            training_label: Some(1.0),
+            ..Default::default()
+        });
+
+        labels.push(1.0); // Labeled as a cheater
+    }
+
+    (training_data, labels)
+}
+
+/// Suspicion score separating the canonical clean and cheater samples in `selftest`
+const SELFTEST_THRESHOLD: f32 = 0.5;
+
+/// Outcome of a single `selftest` check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelftestCheck {
+    /// Short name of the check
+    pub name: String,
+    /// Whether the check passed
+    pub passed: bool,
+    /// Human-readable detail (observed value or error)
+    pub detail: String,
+}
+
+/// Report produced by `selftest`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelftestReport {
+    /// Every check that was run, in order
+    pub checks: Vec<SelftestCheck>,
+}
+
+impl SelftestReport {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        !self.checks.is_empty() && self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Validate that the current model loads and separates known samples.
+///
+/// Intended as a startup self-check for integrators. It loads the model from
+/// the current model path and runs three checks:
+///
+/// 1. `model_loads` - the model file can be loaded
+/// 2. `cheater_flagged` - a canonical cheater from `generate_synthetic_dataset`
+///    scores at or above 0.5
+/// 3. `clean_not_flagged` - a canonical legitimate player scores below 0.5
+///
+/// # Returns
+///
+/// * `Result<SelftestReport>` - Pass/fail per check; the prediction checks are
+///   skipped when the model cannot be loaded
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::selftest;
+///
+/// let report = selftest().expect("Selftest could not run");
+/// if !report.passed() {
+///     for check in report.checks.iter().filter(|c| !c.passed) {
+///         eprintln!("{} failed: {}", check.name, check.detail);
+///     }
+/// }
+/// ```
+pub fn selftest() -> Result<SelftestReport> {
+    selftest_model(unsafe { CURRENT_MODEL_PATH })
+}
+
+/// Run the `selftest` checks against the model stored at `path`
+pub fn selftest_model(path: &str) -> Result<SelftestReport> {
+    let mut checks = Vec::new();
+
+    let model = match load_model(path) {
+        Ok(model) => {
+            checks.push(SelftestCheck {
+                name: "model_loads".to_string(),
+                passed: true,
+                detail: path.to_string(),
+            });
+            model
+        }
+        Err(e) => {
+            checks.push(SelftestCheck {
+                name: "model_loads".to_string(),
+                passed: false,
+                detail: e.to_string(),
+            });
+            return Ok(SelftestReport { checks });
+        }
+    };
+
+    // Canonical samples: the first legitimate player and the last cheater
+    let (samples, _) = generate_synthetic_dataset();
+    let clean = &samples[0];
+    let cheater = &samples[samples.len() - 1];
+
+    let cheater_score = inference::predict_one(&model, cheater)?.suspicion_score;
+    checks.push(SelftestCheck {
+        name: "cheater_flagged".to_string(),
+        passed: cheater_score >= SELFTEST_THRESHOLD,
+        detail: format!(
+            "score {:.3} (expected >= {})",
+            cheater_score, SELFTEST_THRESHOLD
+        ),
+    });
+
+    let clean_score = inference::predict_one(&model, clean)?.suspicion_score;
+    checks.push(SelftestCheck {
+        name: "clean_not_flagged".to_string(),
+        passed: clean_score < SELFTEST_THRESHOLD,
+        detail: format!(
+            "score {:.3} (expected < {})",
+            clean_score, SELFTEST_THRESHOLD
+        ),
+    });
+
+    Ok(SelftestReport { checks })
+}
+
+/// FFI: run `selftest` against the current model
+///
+/// # Returns
+///
+/// * `0` if every check passed
+/// * `-1` if the model could not be loaded
+/// * `-2` if the model loaded but misclassified a canonical sample
+/// * `-3` if the selftest could not run
+#[no_mangle]
+pub extern "C" fn selftest_code() -> c_int {
+    match selftest() {
+        Ok(report) if report.passed() => 0,
+        Ok(report) if report.checks.len() == 1 => -1,
+        Ok(_) => -2,
+        Err(_) => -3,
+    }
+}
+
+/// FFI: analyze a JSON buffer of PlayerStats; returns JSON buffer
+///
+/// This function provides a C-compatible interface for the cheat detection system.
+/// It takes a JSON buffer containing player statistics, analyzes them, and returns
+/// the results as a JSON buffer. With a multi-class model every result also
+/// carries a `class_probabilities` object mapping each class label to its
+/// share of the tree votes.
+///
+/// # Safety
+///
+/// This function is unsafe because it deals with raw pointers and memory allocation
+/// across the FFI boundary. The caller is responsible for:
+///
+/// - Ensuring the input pointers are valid and properly aligned
+/// - Freeing the returned buffer using the `free_buffer` function
+///
+/// # Arguments
+///
+/// * `stats_json_ptr` - Pointer to a UTF-8 encoded JSON buffer
+/// * `stats_json_len` - Length of the JSON buffer in bytes
+/// * `out_json_ptr` - Pointer to a location where the output buffer pointer will be stored
+/// * `out_json_len` - Pointer to a location where the output buffer length will be stored
+///
+/// # Returns
+///
+/// * `0` on success
+/// * Negative values on various errors:
+///   * `-1` - Null pointer provided
+///   * `-2` - JSON parsing error
+///   * `-3` - Analysis error
+///   * `-4` - Serialization error
+///   * `-5` - Memory allocation error
+///   * `-7` - More players than the limit set with `set_max_batch`; split the batch
+#[no_mangle]
+pub unsafe extern "C" fn analyze_round(
+    stats_json_ptr: *const c_uchar,
+    stats_json_len: size_t,
+    out_json_ptr: *mut *mut c_uchar,
+    out_json_len: *mut size_t,
+) -> c_int {
+    // safety: assume valid UTF-8 JSON
+    if stats_json_ptr.is_null() || out_json_ptr.is_null() || out_json_len.is_null() {
+        return -1;
+    }
+    let input = std::slice::from_raw_parts(stats_json_ptr, stats_json_len);
+    let stats: Vec<PlayerStats> = match serde_json::from_slice(input) {
+        Ok(v) => v,
+        Err(_) => return -2,
+    };
+    if exceeds_max_batch(stats.len()) {
+        return -7;
+    }
+    match analyze_stats(stats) {
+        Ok(resp) => write_buffer(&resp, out_json_ptr, out_json_len),
+        Err(_) => -3,
+    }
+}
+
+/// FFI: analyze a JSON buffer of PlayerStats, skipping invalid entries; returns JSON buffer
+///
+/// Lenient counterpart of `analyze_round`: entries of the input array that are
+/// not valid PlayerStats are skipped instead of failing the batch (see
+/// `Analyzer::analyze_json_lenient`). The output is a JSON object with the
+/// `results` of the valid entries and the `failed` entries as
+/// `{"index": ..., "error": ...}` objects.
+///
+/// # Safety
+///
+/// Same contract as `analyze_round`: the input pointer must be valid for
+/// `stats_json_len` bytes and the returned buffer must be freed with `free_buffer`.
+///
+/// # Arguments
+///
+/// * `stats_json_ptr` - Pointer to a UTF-8 encoded JSON array
+/// * `stats_json_len` - Length of the JSON buffer in bytes
+/// * `out_json_ptr` - Pointer to a location where the output buffer pointer will be stored
+/// * `out_json_len` - Pointer to a location where the output buffer length will be stored
+///
+/// # Returns
+///
+/// * `0` on success, even when some entries were skipped
+/// * Negative values on various errors:
+///   * `-1` - Null pointer provided
+///   * `-3` - Input is not a JSON array, or analysis error
+///   * `-4` - Serialization error
+///   * `-5` - Memory allocation error
+///   * `-7` - More entries, valid or not, than the limit set with
+///     `set_max_batch`; split the batch
+#[no_mangle]
+pub unsafe extern "C" fn analyze_round_lenient(
+    stats_json_ptr: *const c_uchar,
+    stats_json_len: size_t,
+    out_json_ptr: *mut *mut c_uchar,
+    out_json_len: *mut size_t,
+) -> c_int {
+    if stats_json_ptr.is_null() || out_json_ptr.is_null() || out_json_len.is_null() {
+        return -1;
+    }
+    let input = std::slice::from_raw_parts(stats_json_ptr, stats_json_len);
+    // Count the entries without building them; invalid entries count too
+    let entries = match serde_json::from_slice::<Vec<serde::de::IgnoredAny>>(input) {
+        Ok(entries) => entries.len(),
+        Err(_) => return -3,
+    };
+    if exceeds_max_batch(entries) {
+        return -7;
+    }
+    match Analyzer::default().analyze_json_lenient(input) {
+        Ok(resp) => write_buffer(&resp, out_json_ptr, out_json_len),
+        Err(_) => -3,
+    }
+}
+
+/// FFI: analyze a protobuf-encoded `PlayerStatsBatch`; returns an encoded `AnalysisResponse`
+///
+/// Protocol Buffers counterpart of `analyze_round` (see `proto/nocheat.proto`),
+/// only available with the `proto` feature.
+///
+/// # Safety
+///
+/// Same contract as `analyze_round`: the input pointer must be valid for
+/// `input_len` bytes and the returned buffer must be freed with `free_buffer`.
+///
+/// # Arguments
+///
+/// * `input_ptr` - Pointer to an encoded `PlayerStatsBatch`
+/// * `input_len` - Length of the input in bytes
+/// * `out_ptr` - Pointer to a location where the output buffer pointer will be stored
+/// * `out_len` - Pointer to a location where the output buffer length will be stored
+///
+/// # Returns
+///
+/// * `0` on success
+/// * Negative values on various errors:
+///   * `-1` - Null pointer provided
+///   * `-3` - Invalid input or analysis error
+///   * `-5` - Memory allocation error
+///   * `-7` - More players than the limit set with `set_max_batch`; split the batch
+#[cfg(feature = "proto")]
+#[no_mangle]
+pub unsafe extern "C" fn analyze_round_proto(
+    input_ptr: *const c_uchar,
+    input_len: size_t,
+    out_ptr: *mut *mut c_uchar,
+    out_len: *mut size_t,
+) -> c_int {
+    if input_ptr.is_null() || out_ptr.is_null() || out_len.is_null() {
+        return -1;
+    }
+    let input = std::slice::from_raw_parts(input_ptr, input_len);
+    let stats = match proto::decode_batch(input) {
+        Ok(stats) => stats,
+        Err(_) => return -3,
+    };
+    if exceeds_max_batch(stats.len()) {
+        return -7;
+    }
+    match proto::analyze_decoded(&Analyzer::default(), stats) {
+        Ok(output) => write_bytes(&output, out_ptr, out_len),
+        Err(_) => -3,
+    }
+}
+
+/// Callback receiving one serialized `PlayerResult` per invocation
+pub type ResultCallback = extern "C" fn(*const c_uchar, size_t);
+
+/// FFI: analyze a JSON buffer of PlayerStats, streaming each result to a callback
+///
+/// Works like `analyze_round`, but instead of allocating one buffer for the
+/// whole response, `callback` is invoked once per player, in input order,
+/// with that player's result serialized as a JSON object. Nothing needs to be
+/// freed by the caller.
+///
+/// # Safety
+///
+/// The input pointer must be valid for `stats_json_len` bytes. The buffer
+/// passed to `callback` is only valid during the call: the callback must copy
+/// the data before returning and must not free it.
+///
+/// # Arguments
+///
+/// * `stats_json_ptr` - Pointer to a UTF-8 encoded JSON buffer
+/// * `stats_json_len` - Length of the JSON buffer in bytes
+/// * `callback` - Function called with the pointer and length of every result
+///
+/// # Returns
+///
+/// * `0` on success
+/// * Negative values on various errors:
+///   * `-1` - Null pointer provided
+///   * `-2` - JSON parsing error
+///   * `-3` - Analysis error
+///   * `-4` - Serialization error
+///   * `-7` - More players than the limit set with `set_max_batch`; split the batch
+#[no_mangle]
+pub unsafe extern "C" fn analyze_round_cb(
+    stats_json_ptr: *const c_uchar,
+    stats_json_len: size_t,
+    callback: Option<ResultCallback>,
+) -> c_int {
+    let callback = match callback {
+        Some(callback) if !stats_json_ptr.is_null() => callback,
+        _ => return -1,
+    };
+    let input = std::slice::from_raw_parts(stats_json_ptr, stats_json_len);
+    let stats: Vec<PlayerStats> = match serde_json::from_slice(input) {
+        Ok(v) => v,
+        Err(_) => return -2,
+    };
+    if exceeds_max_batch(stats.len()) {
+        return -7;
+    }
+    match analyze_stats(stats) {
+        Ok(resp) => stream_results(&resp, callback),
+        Err(_) => -3,
+    }
+}
+
+/// Serialize every result on its own and hand it to the callback
+fn stream_results(resp: &AnalysisResponse, callback: ResultCallback) -> c_int {
+    for result in &resp.results {
+        let json = match ffi_json(result) {
+            Ok(json) => json,
+            Err(_) => return -4,
+        };
+        callback(json.as_ptr(), json.len());
+    }
+    0
+}
+
+/// Companion to free allocated buffer
+///
+/// This function must be called to free the memory allocated by `analyze_round`.
+///
+/// # Safety
+///
+/// This function is unsafe because it deals with raw pointers and memory deallocation.
+/// The caller must ensure that:
+///
+/// - The pointer was previously allocated by `analyze_round`
+/// - The pointer has not already been freed
+/// - The length matches what was given in `out_json_len`
+///
+/// # Arguments
+///
+/// * `ptr` - Pointer to the buffer to free
+/// * `len` - Length of the buffer in bytes
+#[no_mangle]
+pub unsafe extern "C" fn free_buffer(ptr: *mut c_uchar, len: size_t) {
+    if ptr.is_null() || len == 0 {
+        return;
+    }
+    let _ = Vec::from_raw_parts(ptr, len, len);
+}
+
+/// Serialize response and allocate C buffer
+fn write_buffer<T: serde::Serialize>(
+    resp: &T,
+    out_json_ptr: *mut *mut c_uchar,
+    out_json_len: *mut size_t,
+) -> c_int {
+    let json = match ffi_json(resp) {
+        Ok(j) => j,
+        Err(_) => return -4,
+    };
+    write_bytes(&json, out_json_ptr, out_json_len)
+}
+
+/// Copy bytes into a newly allocated C buffer
+fn write_bytes(bytes: &[u8], out_ptr: *mut *mut c_uchar, out_len: *mut size_t) -> c_int {
+    let len = bytes.len();
+    unsafe {
+        let buf = libc::malloc(len) as *mut c_uchar;
+        if buf.is_null() {
+            return -5;
+        }
+        ptr::copy_nonoverlapping(bytes.as_ptr(), buf, len);
+        *out_ptr = buf;
+        *out_len = len;
+    }
+    0
+}
+
+/// FFI: limit the number of players `analyze_round`, `analyze_round_cb`,
+/// `analyze_round_lenient` and `analyze_round_proto` analyze in one call
+///
+/// Protects a service from a single huge batch: calls with more players than
+/// `max_players` return `-7` without analyzing anything, telling the caller
+/// to split the batch. The limit applies to every later call from any thread.
+///
+/// # Arguments
+///
+/// * `max_players` - Maximum number of players per call, or `0` for no limit (the default)
+#[no_mangle]
+pub extern "C" fn set_max_batch(max_players: size_t) {
+    MAX_BATCH.store(max_players, Ordering::Relaxed);
+}
+
+/// FFI: select indented or compact JSON output
+///
+/// Indented JSON is easier to read while debugging an integration; compact
+/// JSON (the default) is smaller and faster to produce in production. The
+/// setting applies to every later `analyze_round`, `analyze_round_lenient`
+/// and `analyze_round_cb` call from any thread.
+///
+/// # Arguments
+///
+/// * `pretty` - Non-zero for indented JSON, `0` for compact JSON
+#[no_mangle]
+pub extern "C" fn set_pretty_json(pretty: c_int) {
+    PRETTY_JSON.store(pretty != 0, Ordering::Relaxed);
+}
+
+/// Set the path to load a custom model
+///
+/// This function allows loading a custom model from a specified path.
+/// It's particularly useful when integrating with game engines like Unreal Engine
+/// where the default path may not be accessible or when you want to load different models.
+/// The new model replaces the cached one on the next analysis, and is reloaded
+/// whenever its file is modified afterwards.
+///
+/// # Safety
+///
+/// This function is unsafe because it:
+/// - Modifies a global static variable that affects all future model loading
+/// - Takes a raw pointer that must be valid UTF-8 encoded path string
+///
+/// # Arguments
+///
+/// * `path_ptr` - Pointer to a null-terminated UTF-8 encoded string containing the model path
+/// * `path_len` - Length of the path string in bytes (not including null terminator)
+///
+/// # Returns
+///
+/// * `0` on success
+/// * `-1` if the path pointer is null
+/// * `-2` if the path is not valid UTF-8
+/// * `-3` if the model file doesn't exist or can't be opened
+/// * `-4` if the model couldn't be deserialized (invalid format)
+#[no_mangle]
+pub unsafe extern "C" fn set_model_path(path_ptr: *const c_uchar, path_len: size_t) -> c_int {
+    // Check for null pointer
+    if path_ptr.is_null() {
+        return -1;
+    }
+
+    // Convert C string to Rust string slice
+    let path_bytes = std::slice::from_raw_parts(path_ptr, path_len);
+    let path_str = match std::str::from_utf8(path_bytes) {
+        Ok(s) => s,
+        Err(_) => return -2,
+    };
+
+    // Verify the model file exists and can be loaded
+    let path_exists = std::path::Path::new(path_str).exists();
+    if !path_exists {
+        return -3;
+    }
+
+    // Try to load the model to verify it works
+    match load_model(path_str) {
+        Ok(_) => {
+            // Update the global model path
+            let path_string = String::from(path_str);
+            let path_box: Box<str> = path_string.into_boxed_str();
+            CURRENT_MODEL_PATH = Box::leak(path_box);
+            0
+        }
+        Err(_) => -4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+    use std::fs;
+
+    #[cfg(feature = "dataframe")]
+    fn create_test_stats() -> Vec<PlayerStats> {
+        let mut shots1 = HashMap::new();
+        shots1.insert("rifle".to_string(), 100);
+        let mut hits1 = HashMap::new();
+        hits1.insert("rifle".to_string(), 50);
+
+        let mut shots2 = HashMap::new();
+        shots2.insert("rifle".to_string(), 100);
+        shots2.insert("pistol".to_string(), 50);
+        let mut hits2 = HashMap::new();
+        hits2.insert("rifle".to_string(), 90); // suspicious hit rate
+        hits2.insert("pistol".to_string(), 45); // suspicious hit rate
+
+        vec![
+            PlayerStats {
+                player_id: "normal_player".to_string(),
+                shots_fired: shots1,
+                hits: hits1,
+                headshots: 10,
+                shot_timestamps_ms: None,
+                training_label: None,
+                ..Default::default()
+            },
+            PlayerStats {
+                player_id: "suspicious_player".to_string(),
+                shots_fired: shots2,
+                hits: hits2,
+                headshots: 50, // suspicious headshot count
+                shot_timestamps_ms: None,
+                training_label: None,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[cfg(feature = "dataframe")]
+    #[test]
+    fn test_analyze_to_arrow_round_trip() {
+        let bytes = analyze_to_arrow(create_test_stats()).unwrap();
+        let df = IpcReader::new(std::io::Cursor::new(bytes))
+            .finish()
+            .unwrap();
+        assert_eq!(df.height(), 2);
+        assert_eq!(
+            df.get_column_names(),
+            ["player_id", "suspicion_score", "flags"]
+        );
+        assert_eq!(
+            df.column("flags").unwrap().dtype(),
+            &DataType::List(Box::new(DataType::String))
+        );
+    }
+
+    #[cfg(feature = "dataframe")]
+    #[test]
+    fn test_missing_feature_column_error_names_column() {
+        let df = build_dataframe(&create_test_stats()).unwrap();
+        let error = df_to_ndarray(&df, &["aim_score"]).unwrap_err();
+        assert!(error.to_string().contains("'aim_score'"));
+        match error.downcast_ref::<error::NoCheatError>() {
+            Some(error::NoCheatError::Feature { column, stage, .. }) => {
+                assert_eq!(column, "aim_score");
+                assert_eq!(*stage, FeatureStage::Extract);
+            }
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[cfg(feature = "dataframe")]
+    #[test]
+    fn test_build_dataframe_columns() {
+        let stats = create_test_stats();
+        let df = build_dataframe(&stats).expect("DataFrame creation failed");
+
+        // Verify the DataFrame structure
+        assert_eq!(df.height(), 2);
+        assert_eq!(df.width(), 4);
+        assert!(df.column("player_id").is_ok());
+        assert!(df.column("shots").is_ok());
+        assert!(df.column("hits").is_ok());
+        assert!(df.column("headshots").is_ok());
+    }
+
+    #[cfg(feature = "dataframe")]
+    #[test]
+    fn test_build_dataframe_values() {
+        let stats = create_test_stats();
+        let df = build_dataframe(&stats).expect("DataFrame creation failed");
+
+        // Check specific values
+        let player_ids = df.column("player_id").unwrap();
+        // Using string conversion instead of direct utf8 access
+        let player_id_0 = player_ids.get(0).unwrap().to_string();
+        let player_id_1 = player_ids.get(1).unwrap().to_string();
+        assert!(player_id_0.contains("normal_player"));
+        assert!(player_id_1.contains("suspicious_player"));
+
+        let shots = df.column("shots").unwrap().u32().unwrap();
+        assert_eq!(shots.get(0), Some(100));
+        assert_eq!(shots.get(1), Some(150)); // 100 + 50
+
+        let hits = df.column("hits").unwrap().u32().unwrap();
+        assert_eq!(hits.get(0), Some(50));
+        assert_eq!(hits.get(1), Some(135)); // 90 + 45
+
+        let headshots = df.column("headshots").unwrap().u32().unwrap();
+        assert_eq!(headshots.get(0), Some(10));
+        assert_eq!(headshots.get(1), Some(50));
+    }
+
+    #[cfg(feature = "dataframe")]
+    #[test]
+    fn test_df_to_ndarray_conversion() {
+        let stats = create_test_stats();
+        let df = build_dataframe(&stats).expect("DataFrame creation failed");
+
+        // Create a test column
+        let df = df
+            .lazy()
+            .with_column(
+                (col("headshots").cast(DataType::Float32) / col("shots").cast(DataType::Float32))
+                    .alias("test_ratio"),
+            )
+            .collect()
+            .expect("Failed to compute test_ratio");
+
+        // Convert to ndarray
+        let features = df_to_ndarray(&df, &["test_ratio"]).expect("Failed to convert");
+
+        // Verify dimensions
+        assert_eq!(features.shape(), [2, 1]);
+
+        // Verify values with some tolerance for floating-point precision
+        let expected_normal = 10.0 / 100.0;
+        let expected_suspicious = 50.0 / 150.0;
+
+        let tolerance = 1e-5;
+        assert!((features[[0, 0]] - expected_normal).abs() < tolerance);
+        assert!((features[[1, 0]] - expected_suspicious).abs() < tolerance);
+    }
+
+    #[test]
+    fn test_train_model() {
+        // Create a temporary file path for the model
+        let temp_dir = std::env::temp_dir();
+        let model_path = temp_dir.join("test_model.bin");
+
+        // Create simple training data
+        let mut training_data = Vec::new();
+        let mut labels = Vec::new();
+
+        // Add a normal player
+        let mut shots = HashMap::new();
+        shots.insert("rifle".to_string(), 100);
+        let mut hits = HashMap::new();
+        hits.insert("rifle".to_string(), 50);
+
+        training_data.push(PlayerStats {
+            player_id: "normal_player".to_string(),
+            shots_fired: shots,
+            hits,
+            headshots: 10,
+            shot_timestamps_ms: None,
+            training_label: None,
+            ..Default::default()
+        });
+        labels.push(0.0);
+
+        // Add a cheating player
+        let mut shots = HashMap::new();
+        shots.insert("rifle".to_string(), 100);
+        let mut hits = HashMap::new();
+        hits.insert("rifle".to_string(), 95);
+
+        training_data.push(PlayerStats {
+            player_id: "cheater".to_string(),
+            shots_fired: shots,
+            hits,
+            headshots: 70,
+            shot_timestamps_ms: None,
+            training_label: None,
+            ..Default::default()
+        });
+        labels.push(1.0);
+
+        // Train the model
+        let result = train_model(training_data, labels, model_path.to_str().unwrap());
+        assert!(result.is_ok());
+
+        // Verify the model file exists
+        assert!(model_path.exists());
+
+        // Clean up
+        let _ = fs::remove_file(model_path);
+    }
+
+    #[test]
+    fn test_generate_default_model() {
+        // Create a temporary file path for the model
+        let temp_dir = std::env::temp_dir();
+        let model_path = temp_dir.join("default_model.bin");
+
+        // Generate the default model
+        let result = generate_default_model(model_path.to_str().unwrap());
+        assert!(result.is_ok());
+
+        // Verify the model file exists
+        assert!(model_path.exists());
+
+        // Clean up
+        let _ = fs::remove_file(model_path);
+    }
+    #[test]
+    fn test_set_model_path() {
+        // Create a temporary model file
+        let temp_dir = std::env::temp_dir();
+        let model_path = temp_dir.join("custom_model.bin");
+        let model_path_str = model_path.to_str().unwrap();
+
+        // Generate a model to use for testing
+        generate_default_model(model_path_str).expect("Failed to generate test model");
+
+        // Save the original model path to restore it later
+        let original_path = unsafe { CURRENT_MODEL_PATH };
+
+        // Call set_model_path using the FFI interface
+        let path_bytes = model_path_str.as_bytes();
+        let path_len = path_bytes.len();
+
+        let result = unsafe { set_model_path(path_bytes.as_ptr(), path_len) };
+
+        assert_eq!(
+            result, 0,
+            "Expected set_model_path to return success code 0"
+        );
+
+        // Verify the model path was updated - we need to be careful with mutable static
+        let current_path = unsafe { CURRENT_MODEL_PATH };
+        assert_eq!(
+            current_path, model_path_str,
+            "Model path was not updated correctly"
+        );
+
+        // Clean up
+        let _ = fs::remove_file(model_path);
+
+        // Restore the original path by calling set_model_path again
+        let orig_bytes = original_path.as_bytes();
+        unsafe {
+            set_model_path(orig_bytes.as_ptr(), orig_bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_train_model_from_ndjson_stream() {
+        let mut ndjson = String::new();
+        for hits in 30..100 {
+            let stats = PlayerStats {
+                player_id: format!("p{}", hits),
+                shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+                hits: HashMap::from([("rifle".to_string(), hits)]),
+                headshots: hits / 4,
+                training_label: Some(if hits >= 80 { 1.0 } else { 0.0 }),
+                ..Default::default()
+            };
+            ndjson.push_str(&serde_json::to_string(&stats).unwrap());
+            ndjson.push_str("\n\n");
+        }
+
+        let config = TrainingConfig {
+            trees: 11,
+            seed: Some(3),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        train_model_streaming_to_writer(ndjson.as_bytes(), &config, &mut buffer).unwrap();
+        let model = load_model_from_bytes(&buffer).unwrap();
+        assert_eq!(model.predict(&[0.95, 0.23]), 1.0);
+        assert_eq!(model.predict(&[0.45, 0.11]), 0.0);
+
+        let unlabeled = "{\"player_id\":\"p\",\"shots_fired\":{},\"hits\":{},\"headshots\":0}\n";
+        let error =
+            train_model_streaming_to_writer(unlabeled.as_bytes(), &config, Vec::new()).unwrap_err();
+        assert!(error.to_string().contains("line 1"));
+        assert!(train_model_streaming_to_writer("\n".as_bytes(), &config, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_epsilon_and_guard_strategies() {
+        let player = |shots: u32, hits: u32, headshots: u32| PlayerStats {
+            shots_fired: HashMap::from([("rifle".to_string(), shots)]),
+            hits: HashMap::from([("rifle".to_string(), hits)]),
+            headshots,
+            ..Default::default()
+        };
+        let stats = vec![player(100, 50, 10), player(0, 0, 0), player(40, 0, 0)];
+        let rows = |precision, zero_division| {
+            let rows = feature_rows_timed(
+                &stats,
+                precision,
+                Some(zero_division),
+                &mut Metrics::default(),
+            )
+            .unwrap();
+            // Both pipelines compute the same features
+            for (row, stats) in rows.iter().zip(&stats) {
+                assert_eq!(
+                    *row,
+                    FeatureRow::from_stats_using(stats, precision, zero_division)
+                );
+            }
+            rows
+        };
+
+        for precision in [FeaturePrecision::F32, FeaturePrecision::F64] {
+            // Nonzero denominators give the plain division
+            let divide = |numerator: u32, denominator: u32| match precision {
+                FeaturePrecision::F32 => (numerator as f32 / denominator as f32) as f64,
+                FeaturePrecision::F64 => numerator as f64 / denominator as f64,
+            };
+            let guard = rows(precision, ZeroDivision::Guard);
+            assert_eq!(guard[0].hit_rate, divide(50, 100));
+            assert_eq!(guard[0].headshot_rate, divide(10, 50));
+            assert_eq!((guard[1].hit_rate, guard[1].headshot_rate), (0.0, 0.0));
+            assert_eq!((guard[2].hit_rate, guard[2].headshot_rate), (0.0, 0.0));
+
+            // A small epsilon barely biases the ratios downwards
+            let epsilon = rows(precision, ZeroDivision::Epsilon(1e-3));
+            assert!(epsilon[0].hit_rate < guard[0].hit_rate);
+            assert!(guard[0].hit_rate - epsilon[0].hit_rate < 1e-5);
+            assert!(guard[0].headshot_rate - epsilon[0].headshot_rate < 1e-5);
+            assert_eq!((epsilon[1].hit_rate, epsilon[1].headshot_rate), (0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_balance_dataset_equalizes_classes() {
+        let (mut training_data, mut labels) = generate_synthetic_dataset();
+        training_data.truncate(60);
+        labels.truncate(60);
+        let count = |labels: &[f64], class: f64| labels.iter().filter(|&&l| l == class).count();
+
+        for (strategy, per_class) in [
+            (BalanceStrategy::Undersample, 10),
+            (BalanceStrategy::Oversample, 50),
+        ] {
+            let (balanced, balanced_labels) =
+                balance_dataset(training_data.clone(), labels.clone(), strategy, 7).unwrap();
+            assert_eq!(balanced.len(), balanced_labels.len());
+            assert_eq!(count(&balanced_labels, 0.0), per_class);
+            assert_eq!(count(&balanced_labels, 1.0), per_class);
+
+            let (again, _) =
+                balance_dataset(training_data.clone(), labels.clone(), strategy, 7).unwrap();
+            let ids = |stats: &[PlayerStats]| {
+                stats
+                    .iter()
+                    .map(|s| s.player_id.clone())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(ids(&again), ids(&balanced));
+        }
+
+        assert!(balance_dataset(
+            training_data[..5].to_vec(),
+            labels[..5].to_vec(),
+            BalanceStrategy::Undersample,
+            7
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_soft_labels_are_thresholded() {
+        let (training_data, labels) = generate_synthetic_dataset();
+        // Confidence-weighted labels: 0.1-0.4 for clean players, 0.6-0.9 for cheaters
+        let soft: Vec<f64> = labels
+            .iter()
+            .enumerate()
+            .map(|(i, &label)| label * 0.5 + 0.1 + (i % 4) as f64 * 0.1)
+            .collect();
+        let config = TrainingConfig {
+            trees: 11,
+            seed: Some(5),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        train_model_to_writer(training_data.clone(), soft, &config, &mut buffer).unwrap();
+
+        let model = load_model_from_bytes(&buffer).unwrap();
+        assert_eq!(model.classes(), &[0.0, 1.0]);
+        let (response, _) =
+            do_analysis(training_data, &model, &AnalysisOptions::default()).unwrap();
+        let correct = response
+            .results
+            .iter()
+            .zip(&labels)
+            .filter(|(result, &label)| (result.suspicion_score >= 0.5) == (label == 1.0))
+            .count();
+        assert!(correct * 10 >= labels.len() * 9);
+
+        let out_of_range = train_model_to_writer(
+            vec![PlayerStats::default()],
+            vec![1.5],
+            &config,
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+        assert!(out_of_range.to_string().contains("outside [0, 1]"));
+    }
+
+    #[test]
+    fn test_model_kind_controls_score_interpretation() {
+        let (training_data, labels) = generate_synthetic_dataset();
+        let soft: Vec<f64> = labels
+            .iter()
+            .enumerate()
+            .map(|(i, &label)| label * 0.6 + 0.1 + (i % 4) as f64 * 0.05)
+            .collect();
+        let train = |model_kind: ModelKind| {
+            let config = TrainingConfig {
+                trees: 11,
+                seed: Some(3),
+                model_kind,
+                ..Default::default()
+            };
+            let mut buffer = Vec::new();
+            train_model_to_writer(training_data.clone(), soft.clone(), &config, &mut buffer)
+                .unwrap();
+            load_model_from_bytes(&buffer).unwrap()
+        };
+
+        // Classifier: soft labels are thresholded and scores are votes
+        let classifier = train(ModelKind::Classifier);
+        assert_eq!(classifier.metadata.model_kind, ModelKind::Classifier);
+        let (response, _) = do_analysis(
+            training_data.clone(),
+            &classifier,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        assert!(response
+            .results
+            .iter()
+            .all(|r| r.suspicion_score == 0.0 || r.suspicion_score == 1.0));
+
+        // Regressor: scores are mean predictions of the soft labels, in [0, 1]
+        let regressor = train(ModelKind::Regressor);
+        assert_eq!(regressor.metadata.model_kind, ModelKind::Regressor);
+        assert!(!regressor.is_multiclass());
+        let (response, _) = do_analysis(
+            training_data.clone(),
+            &regressor,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        assert!(response
+            .results
+            .iter()
+            .all(|r| (0.0..=1.0).contains(&r.suspicion_score)));
+        assert!(response
+            .results
+            .iter()
+            .any(|r| r.suspicion_score > 0.0 && r.suspicion_score < 1.0));
+        for (result, &label) in response.results.iter().zip(&labels) {
+            assert_eq!(result.suspicion_score >= 0.5, label == 1.0);
+        }
+
+        // Vote-based outputs are rejected for regressors
+        let options = AnalysisOptions {
+            include_margin: true,
+            ..Default::default()
+        };
+        let error = do_analysis(training_data.clone(), &regressor, &options).unwrap_err();
+        assert!(error.to_string().contains("regressor"));
+        let config = TrainingConfig {
+            model_kind: ModelKind::Regressor,
+            calibrate: true,
+            ..Default::default()
+        };
+        assert!(train_model_to_writer(training_data, soft, &config, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_lenient_analysis_skips_invalid_entries() {
+        let analyzer = Analyzer::builder()
+            .model(fit_forest(&[[0.5, 0.2], [0.95, 0.7]], &[0.0, 1.0]))
+            .build()
+            .unwrap();
+        let json = br#"[
+            {"player_id": "first", "shots_fired": {"rifle": 100}, "hits": {"rifle": 50}, "headshots": 10},
+            {"player_id": "second", "shots_fired": {"rifle": "many"}, "hits": {}, "headshots": 0},
+            {"player_id": "third", "shots_fired": {"rifle": 100}, "hits": {"rifle": 95}, "headshots": 70}
+        ]"#;
+
+        let response = analyzer.analyze_json_lenient(json).unwrap();
+        let ids: Vec<&str> = response
+            .results
+            .iter()
+            .map(|r| r.player_id.as_str())
+            .collect();
+        assert_eq!(ids, ["first", "third"]);
+        assert_eq!(response.failed.len(), 1);
+        assert_eq!(response.failed[0].index, 1);
+        assert!(response.failed[0].error.contains("invalid type"));
+
+        // The strict parse rejects the whole batch, and a non-array is still an error
+        assert!(serde_json::from_slice::<Vec<PlayerStats>>(json).is_err());
+        assert!(analyzer.analyze_json_lenient(b"{}").is_err());
+    }
+
+    #[test]
+    fn test_non_finite_labels_are_rejected() {
+        let (training_data, mut labels) = generate_synthetic_dataset();
+        labels[3] = f64::NAN;
+        let error = train_model_to_writer(
+            training_data.clone(),
+            labels.clone(),
+            &TrainingConfig::default(),
+            &mut Vec::new(),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("sample 3"), "{}", error);
+        assert!(error.contains("NaN"), "{}", error);
+
+        labels[3] = 0.0;
+        labels[7] = f64::INFINITY;
+        let error = train_model_to_writer(
+            training_data,
+            labels,
+            &TrainingConfig::default(),
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("sample 7"), "{}", error);
+    }
+
+    #[test]
+    fn test_calibrated_probabilities_match_positive_rate() {
+        // Players cheat with a probability equal to their hit rate, so a
+        // majority vote alone cannot be a calibrated probability
+        let noisy = |seed: u64| {
+            let mut state = seed;
+            (0..400u32)
+                .map(|i| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    let hits = 20 + i % 61;
+                    let draw = (state >> 33) as f64 / (1u64 << 31) as f64;
+                    let stats = PlayerStats {
+                        player_id: format!("p{}", i),
+                        shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+                        hits: HashMap::from([("rifle".to_string(), hits)]),
+                        headshots: hits / 5,
+                        ..Default::default()
+                    };
+                    let label = if draw < hits as f64 / 100.0 { 1.0 } else { 0.0 };
+                    (stats, label)
+                })
+                .unzip::<_, _, Vec<PlayerStats>, Vec<f64>>()
+        };
+        let (training_data, labels) = noisy(1);
+        let mut buffer = Vec::new();
+        train_model_to_writer(
+            training_data,
+            labels,
+            &TrainingConfig {
+                trees: 21,
+                seed: Some(3),
+                calibrate: true,
+                ..Default::default()
+            },
+            &mut buffer,
+        )
+        .unwrap();
+        let model = load_model_from_bytes(&buffer).unwrap();
+        assert!(model.metadata.calibration.is_some());
+
+        let (validation, labels) = noisy(2);
+        let predictions: Vec<f64> = validation
+            .iter()
+            .map(|stats| {
+                let features = model
+                    .encode(stats, &inference::FeatureRow::from_stats(stats))
+                    .unwrap();
+                model.predict(&features)
+            })
+            .collect();
+        assert!(predictions.iter().all(|p| (0.0..=1.0).contains(p)));
+        // Still ranks a 80% hit rate above a 20% one
+        assert!(predictions[60] > predictions[0]);
+        let mean_prediction = predictions.iter().sum::<f64>() / predictions.len() as f64;
+        let positive_rate = labels.iter().sum::<f64>() / labels.len() as f64;
+        assert!(
+            (mean_prediction - positive_rate).abs() < 0.05,
+            "mean prediction {} vs positive rate {}",
+            mean_prediction,
+            positive_rate
+        );
+    }
+
+    #[test]
+    fn test_constant_feature_is_dropped() {
+        // Without damage reports, damage_per_shot is always 0.0
+        let training_data: Vec<PlayerStats> = (30..100)
+            .map(|hits| PlayerStats {
+                player_id: format!("p{}", hits),
+                shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+                hits: HashMap::from([("rifle".to_string(), hits)]),
+                headshots: hits / 4,
+                ..Default::default()
+            })
+            .collect();
+        let labels = (30..100)
+            .map(|hits| if hits >= 80 { 1.0 } else { 0.0 })
+            .collect();
+        let mut buffer = Vec::new();
+        train_model_to_writer(
+            training_data,
+            labels,
+            &TrainingConfig {
+                trees: 10,
+                seed: Some(1),
+                extra_features: vec!["damage_per_shot".to_string()],
+                min_feature_importance: Some(0.01),
+                ..Default::default()
+            },
+            &mut buffer,
+        )
+        .unwrap();
+
+        let model = load_model_from_bytes(&buffer).unwrap();
+        assert_eq!(model.metadata.dropped_inputs(), vec!["damage_per_shot"]);
+        assert_eq!(
+            model.metadata.input_names(),
+            vec!["hit_rate", "headshot_rate"]
+        );
+        let importances = model.feature_importances().unwrap();
+        assert_eq!(importances.len(), 2);
+
+        let stats = PlayerStats {
+            player_id: "cheater".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), 95)]),
+            headshots: 24,
+            ..Default::default()
+        };
+        let features = model
+            .encode(&stats, &FeatureRow::from_stats(&stats))
+            .unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(model.predict(&features), 1.0);
+    }
+
+    /// Fit a small forest directly from feature rows and (possibly out-of-range) labels
+    fn fit_forest(rows: &[[f64; 2]], labels: &[f64]) -> CheatModel {
+        use randomforest::criterion::Gini;
+        use randomforest::table::TableBuilder;
+        use randomforest::RandomForestClassifier;
+
+        let mut table_builder = TableBuilder::new();
+        for (row, label) in rows.iter().zip(labels) {
+            table_builder.add_row(row, *label).unwrap();
+        }
+        CheatModel::from_forest(RandomForestClassifier::fit(
+            Gini,
+            table_builder.build().unwrap(),
+        ))
+    }
+
+    #[test]
+    fn test_warm_start_learns_from_new_data() {
+        let player = |hits: u32| PlayerStats {
+            player_id: format!("p{}", hits),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), hits)]),
+            headshots: hits / 4,
+            ..Default::default()
+        };
+        // Subtle cheaters around 75% accuracy went undetected in the base data
+        let base_hits: Vec<u32> = (30..60).chain(70..80).chain(90..100).collect();
+        let base_labels = base_hits
+            .iter()
+            .map(|&h| if h >= 90 { 1.0 } else { 0.0 })
+            .collect();
+        let mut base = Vec::new();
+        train_model_to_writer(
+            base_hits.iter().map(|&h| player(h)).collect(),
+            base_labels,
+            &TrainingConfig {
+                trees: 20,
+                seed: Some(1),
+                ..Default::default()
+            },
+            &mut base,
+        )
+        .unwrap();
+        let base = load_model_from_bytes(&base).unwrap();
+
+        // Confirmed verdicts now label them as cheaters
+        let new_hits: Vec<u32> = (40..50).chain(70..80).collect();
+        let new_labels = new_hits
+            .iter()
+            .map(|&h| if h >= 70 { 1.0 } else { 0.0 })
+            .collect();
+        let mut warm = Vec::new();
+        train_model_warm_start_to_writer(
+            &base,
+            new_hits.iter().map(|&h| player(h)).collect(),
+            new_labels,
+            &TrainingConfig {
+                trees: 40,
+                seed: Some(2),
+                model_version: Some("warm".to_string()),
+                ..Default::default()
+            },
+            &mut warm,
+        )
+        .unwrap();
+        let warm = load_model_from_bytes(&warm).unwrap();
+        assert_eq!(warm.metadata.version, "warm");
+        assert_eq!(warm.forest().predict_individuals(&[0.5, 0.2]).count(), 60);
+
+        let cheater_share = |model: &CheatModel, hits: u32| {
+            let stats = player(hits);
+            let features = model
+                .metadata
+                .encode(&stats, &FeatureRow::from_stats(&stats))
+                .unwrap();
+            model.class_probabilities(&features)["1"]
+        };
+        for held_out in [73, 75, 77] {
+            assert!(cheater_share(&warm, held_out) > cheater_share(&base, held_out));
+        }
+    }
+
+    #[test]
+    fn test_scores_clamped_to_unit_range() {
+        // A model trained with a label of 2.0 produces raw scores above 1.0
+        let model = fit_forest(
+            &[[0.5, 0.2], [0.5, 0.2], [0.95, 0.7], [0.95, 0.7]],
+            &[0.0, 0.0, 2.0, 2.0],
+        );
+
+        let mut shots = HashMap::new();
+        shots.insert("rifle".to_string(), 100);
+        let mut hits = HashMap::new();
+        hits.insert("rifle".to_string(), 95);
+        let stats = vec![PlayerStats {
+            player_id: "cheater".to_string(),
+            shots_fired: shots,
+            hits,
+            headshots: 66,
+            shot_timestamps_ms: None,
+            training_label: None,
+            ..Default::default()
+        }];
+
+        let clamped = do_analysis(stats.clone(), &model, &AnalysisOptions::default())
+            .unwrap()
+            .0;
+        assert_eq!(clamped.results[0].suspicion_score, 1.0);
+
+        let raw_options = AnalysisOptions {
+            clamp_scores: false,
+            ..Default::default()
+        };
+        let raw = do_analysis(stats, &model, &raw_options).unwrap().0;
+        assert_eq!(raw.results[0].suspicion_score, 2.0);
+    }
+
+    #[test]
+    fn test_training_report_with_cross_validation() {
+        let (training_data, labels) = generate_synthetic_dataset();
+        let config = TrainingConfig {
+            trees: 11,
+            seed: Some(9),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        let report =
+            train_model_with_report(training_data, labels, &config, Some(3), &mut buffer).unwrap();
+        assert!(load_model_from_bytes(&buffer).is_ok());
+
+        let mut json = Vec::new();
+        report.write(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        for key in [
+            "model_version",
+            "class_counts",
+            "feature_names",
+            "hyperparameters",
+            "cross_validation",
+            "feature_importances",
+        ] {
+            assert!(json.get(key).is_some(), "missing key {}", key);
+        }
+        assert_eq!(json["class_counts"]["cheater"], 50);
+        assert_eq!(json["hyperparameters"]["trees"], 11);
+        assert_eq!(json["cross_validation"]["folds"], 3);
+        assert!(json["cross_validation"]["f1"].as_f64().unwrap() > 0.9);
+        assert!(json["feature_importances"].get("hit_rate").is_some());
+    }
+
+    #[test]
+    fn test_low_confidence_labels_weigh_less() {
+        let (mut training_data, mut labels) = generate_synthetic_dataset();
+        // Identical stats reviewed as clean twice and reported as a cheater three times
+        let disputed = PlayerStats {
+            player_id: "disputed".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), 70)]),
+            headshots: 21,
+            ..Default::default()
+        };
+        for label in [0.0, 0.0, 1.0, 1.0, 1.0] {
+            training_data.push(PlayerStats {
+                label_confidence: (label == 1.0).then_some(1.0),
+                ..disputed.clone()
+            });
+            labels.push(label);
+        }
+        let config = TrainingConfig {
+            trees: 21,
+            seed: Some(11),
+            ..Default::default()
+        };
+        let disputed_score = |confidence: f64| {
+            let mut training_data = training_data.clone();
+            for stats in training_data
+                .iter_mut()
+                .filter(|s| s.label_confidence.is_some())
+            {
+                stats.label_confidence = Some(confidence);
+            }
+            let mut buffer = Vec::new();
+            train_model_weighted(training_data, labels.clone(), &config, &mut buffer).unwrap();
+            let model = load_model_from_bytes(&buffer).unwrap();
+            do_analysis(vec![disputed.clone()], &model, &AnalysisOptions::default())
+                .unwrap()
+                .0
+                .results[0]
+                .suspicion_score
+        };
+
+        assert!(disputed_score(0.25) < disputed_score(1.0));
+
+        training_data[0].label_confidence = Some(1.5);
+        let out_of_range =
+            train_model_weighted(training_data, labels, &config, &mut Vec::new()).unwrap_err();
+        assert!(out_of_range.to_string().contains("must be in [0, 1]"));
+    }
+
+    #[test]
+    fn test_train_model_to_writer_in_memory() {
+        let stats = vec![
+            PlayerStats {
+                player_id: "normal".to_string(),
+                shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+                hits: HashMap::from([("rifle".to_string(), 50)]),
+                headshots: 10,
+                ..Default::default()
+            },
+            PlayerStats {
+                player_id: "cheater".to_string(),
+                shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+                hits: HashMap::from([("rifle".to_string(), 95)]),
+                headshots: 70,
+                ..Default::default()
+            },
+        ];
+        let config = TrainingConfig {
+            trees: 10,
+            seed: Some(7),
+            ..Default::default()
+        };
+
+        // Repeat the two samples so every bootstrap sample sees both classes
+        let training_data: Vec<PlayerStats> = stats.iter().cycle().take(20).cloned().collect();
+        let labels: Vec<f64> = [0.0, 1.0].iter().cycle().take(20).copied().collect();
+
+        let mut buffer = Vec::new();
+        train_model_to_writer(training_data, labels, &config, &mut buffer)
+            .expect("Training into a buffer failed");
+        assert!(!buffer.is_empty());
+
+        let model = load_model_from_bytes(&buffer).expect("Failed to load model from bytes");
+        let response = do_analysis(stats, &model, &AnalysisOptions::default())
+            .unwrap()
+            .0;
+        assert_eq!(response.results[0].suspicion_score, 0.0);
+        assert_eq!(response.results[1].suspicion_score, 1.0);
+
+        // Zero trees is rejected
+        let invalid = TrainingConfig {
+            trees: 0,
+            ..Default::default()
+        };
+        let result = train_model_to_writer(
+            vec![PlayerStats::default()],
+            vec![0.0],
+            &invalid,
+            Vec::new(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_aggregation_is_scoped_by_match() {
+        let round = |player_id: &str, match_id: Option<&str>, hits: u32| PlayerStats {
+            player_id: player_id.to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), hits)]),
+            match_id: match_id.map(str::to_string),
+            ..Default::default()
+        };
+        let records = aggregate_by_player(&[
+            round("p1", Some("m1"), 40),
+            round("p1", Some("m2"), 90),
+            round("p1", Some("m1"), 50),
+            round("p2", None, 30),
+            round("p2", None, 20),
+        ]);
+
+        let summary: Vec<(&str, Option<&str>, u32)> = records
+            .iter()
+            .map(|r| (r.player_id.as_str(), r.match_id.as_deref(), r.hits["rifle"]))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("p1", Some("m1"), 90),
+                ("p1", Some("m2"), 90),
+                ("p2", None, 50)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_windowed_features_use_recent_rounds() {
+        // 7 clean rounds followed by 3 rounds with 95% accuracy
+        let history: Vec<PlayerStats> = (0..10)
+            .map(|round| PlayerStats {
+                player_id: "player".to_string(),
+                shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+                hits: HashMap::from([("rifle".to_string(), if round < 7 { 50 } else { 95 })]),
+                headshots: 10,
+                shot_timestamps_ms: Some(vec![round].into()),
+                ..Default::default()
+            })
+            .collect();
+        let short_history = history[..2].to_vec();
+
+        let window = WindowConfig { size: 3 };
+        let recent = window.apply(&history).unwrap();
+        assert_eq!(recent.shots_fired["rifle"], 300);
+        assert_eq!(recent.hits["rifle"], 285);
+        assert_eq!(recent.headshots, 30);
+        assert_eq!(recent.shot_timestamps_ms, Some(vec![7, 8, 9].into()));
+
+        // Fewer rounds than the window: all rounds are used
+        let partial = window.apply(&short_history).unwrap();
+        assert_eq!(partial.shots_fired["rifle"], 200);
+        assert!(window.apply(&[]).is_none());
+
+        // The recent window is flagged while the full history is not
+        let model = fit_forest(&[[0.5, 0.2], [0.95, 0.7]], &[0.0, 1.0]);
+        let full = PlayerStats::aggregate(&history);
+        let response = do_analysis(vec![recent, full], &model, &AnalysisOptions::default())
+            .unwrap()
+            .0;
+        assert!(response.results[0]
+            .flags
+            .contains(&"HighHitRate".to_string()));
+        assert!(response.results[1].flags.is_empty());
+    }
+
+    #[test]
+    fn test_default_model_passes_selftest() {
+        let model_path = std::env::temp_dir().join("selftest_model.bin");
+        let model_path_str = model_path.to_str().unwrap();
+        generate_default_model(model_path_str).expect("Failed to generate model");
+
+        let report = selftest_model(model_path_str).expect("Selftest failed to run");
+        assert_eq!(report.checks.len(), 3);
+        assert!(report.passed(), "Selftest failed: {:?}", report);
+
+        let _ = fs::remove_file(model_path);
+
+        let missing = selftest_model("does/not/exist.bin").unwrap();
+        assert_eq!(missing.checks.len(), 1);
+        assert!(!missing.passed());
+    }
+
+    #[test]
+    fn test_default_model_created_in_nested_directory() {
+        let dir = std::env::temp_dir().join("nocheat_nested_model_test");
+        let _ = fs::remove_dir_all(&dir);
+        let model_path = dir.join("models").join("v1").join("cheat_model.bin");
+
+        generate_default_model(model_path.to_str().unwrap()).expect("Failed to generate model");
+        assert!(load_model(model_path.to_str().unwrap()).is_ok());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_ensure_default_model_keeps_existing_file() {
+        let dir = std::env::temp_dir().join("nocheat_ensure_model_test");
+        let _ = fs::remove_dir_all(&dir);
+        let model_path = dir.join("cheat_model.bin");
+        let model_path_str = model_path.to_str().unwrap();
+
+        ensure_default_model(model_path_str).expect("Failed to create model");
+        let original = fs::read(&model_path).unwrap();
+        assert!(load_model_from_bytes(&original).is_ok());
+
+        // Training is unseeded, so a regenerated model would differ
+        ensure_default_model(model_path_str).expect("Second call failed");
+        assert_eq!(fs::read(&model_path).unwrap(), original);
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_categorical_features_one_hot_encoded() {
+        // Identical accuracy everywhere: only the game mode separates the labels
+        let modes = ["casual", "ranked", "arcade"];
+        let training_data: Vec<PlayerStats> = (0..30)
+            .map(|i| PlayerStats {
+                player_id: format!("player_{}", i),
+                shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+                hits: HashMap::from([("rifle".to_string(), 50)]),
+                headshots: 10,
+                attributes: BTreeMap::from([("game_mode".to_string(), modes[i % 3].to_string())]),
+                ..Default::default()
+            })
+            .collect();
+        let labels: Vec<f64> = (0..30)
+            .map(|i| if modes[i % 3] == "ranked" { 1.0 } else { 0.0 })
+            .collect();
+        let config = TrainingConfig {
+            trees: 10,
+            seed: Some(7),
+            categorical_fields: vec!["game_mode".to_string()],
+            ..Default::default()
+        };
+
+        let mut buffer = Vec::new();
+        train_model_to_writer(training_data.clone(), labels, &config, &mut buffer).unwrap();
+        let model = load_model_from_bytes(&buffer).unwrap();
+
+        // Three categories produce three sorted one-hot columns
+        assert_eq!(
+            model.metadata.input_names(),
+            vec![
+                "hit_rate",
+                "headshot_rate",
+                "game_mode_arcade",
+                "game_mode_casual",
+                "game_mode_ranked"
+            ]
+        );
+        let row = FeatureRow::from_stats(&training_data[1]);
+        assert_eq!(
+            model.encode(&training_data[1], &row).unwrap()[2..],
+            [0.0, 0.0, 1.0]
+        );
+
+        // An unseen category encodes as all zeros
+        let mut unseen = training_data[1].clone();
+        unseen
+            .attributes
+            .insert("game_mode".to_string(), "tournament".to_string());
+        assert_eq!(model.encode(&unseen, &row).unwrap()[2..], [0.0, 0.0, 0.0]);
+
+        let response = do_analysis(
+            vec![training_data[0].clone(), training_data[1].clone(), unseen],
+            &model,
+            &AnalysisOptions::default(),
+        )
+        .unwrap()
+        .0;
+        assert_eq!(response.results[0].suspicion_score, 0.0);
+        assert_eq!(response.results[1].suspicion_score, 1.0);
+        assert_eq!(response.results.len(), 3);
+    }
+
+    #[test]
+    fn test_results_record_time_and_model_version() {
+        let (training_data, labels) = generate_synthetic_dataset();
+        let config = TrainingConfig {
+            trees: 10,
+            seed: Some(7),
+            model_version: Some("2024.06-test".to_string()),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        train_model_to_writer(training_data.clone(), labels, &config, &mut buffer).unwrap();
+        let model = load_model_from_bytes(&buffer).unwrap();
+
+        let before = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let response = do_analysis(
+            training_data[..2].to_vec(),
+            &model,
+            &AnalysisOptions::default(),
+        )
+        .unwrap()
+        .0;
+
+        for result in &response.results {
+            assert!(result.analyzed_at.unwrap() >= before);
+            assert_eq!(result.model_version.as_deref(), Some("2024.06-test"));
+        }
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains(r#""model_version":"2024.06-test""#));
+
+        // Bare forests report the legacy version
+        let legacy = fit_forest(&[[0.5, 0.2], [0.95, 0.7]], &[0.0, 1.0]);
+        let response = do_analysis(
+            training_data[..1].to_vec(),
+            &legacy,
+            &AnalysisOptions::default(),
+        )
+        .unwrap()
+        .0;
+        assert_eq!(
+            response.results[0].model_version.as_deref(),
+            Some(model::LEGACY_MODEL_VERSION)
+        );
     }
 
-    // Try to load the model to verify it works
-    match load_model(path_str) {
-        Ok(_) => {
-            // Update the global model path
-            let path_string = String::from(path_str);
-            let path_box: Box<str> = path_string.into_boxed_str();
-            CURRENT_MODEL_PATH = Box::leak(path_box);
-            0
+    #[test]
+    fn test_weapon_count_is_capped() {
+        let mut flooded = PlayerStats {
+            player_id: "flooded".to_string(),
+            headshots: 5,
+            ..Default::default()
+        };
+        for i in 0..10_000 {
+            flooded.shots_fired.insert(format!("weapon_{}", i), 1);
+            flooded.hits.insert(format!("weapon_{}", i), 1);
         }
-        Err(_) => -4,
+        assert_eq!(flooded.weapon_count(), 10_000);
+        let options = AnalysisOptions {
+            heuristic_fallback: true,
+            ..Default::default()
+        };
+
+        let missing = load_model("does/not/exist.bin").map(Arc::new);
+        let error = analyze_or_fallback(
+            vec![flooded.clone()],
+            missing,
+            &options,
+            &mut Metrics::default(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("more than the limit of 1024"));
+
+        let uncapped = AnalysisOptions {
+            max_weapons: None,
+            ..options
+        };
+        let missing = load_model("does/not/exist.bin").map(Arc::new);
+        let (response, _) =
+            analyze_or_fallback(vec![flooded], missing, &uncapped, &mut Metrics::default())
+                .unwrap();
+        assert_eq!(response.results.len(), 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashMap;
-    use std::fs;
+    #[test]
+    fn test_heuristic_fallback_without_model() {
+        let (samples, _) = generate_synthetic_dataset();
+        let cheater = PlayerStats {
+            player_id: "cheater".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), 95)]),
+            headshots: 70,
+            ..Default::default()
+        };
+        let stats = vec![samples[0].clone(), cheater];
 
-    fn create_test_stats() -> Vec<PlayerStats> {
-        let mut shots1 = HashMap::new();
-        shots1.insert("rifle".to_string(), 100);
-        let mut hits1 = HashMap::new();
-        hits1.insert("rifle".to_string(), 50);
+        // Without the option a missing model is an error
+        let missing = load_model("does/not/exist.bin").map(Arc::new);
+        assert!(analyze_or_fallback(
+            stats.clone(),
+            missing,
+            &AnalysisOptions::default(),
+            &mut Metrics::default()
+        )
+        .is_err());
 
-        let mut shots2 = HashMap::new();
-        shots2.insert("rifle".to_string(), 100);
-        shots2.insert("pistol".to_string(), 50);
-        let mut hits2 = HashMap::new();
-        hits2.insert("rifle".to_string(), 90); // suspicious hit rate
-        hits2.insert("pistol".to_string(), 45); // suspicious hit rate
+        let options = AnalysisOptions {
+            heuristic_fallback: true,
+            ..Default::default()
+        };
+        let missing = load_model("does/not/exist.bin").map(Arc::new);
+        let (response, _) =
+            analyze_or_fallback(stats, missing, &options, &mut Metrics::default()).unwrap();
 
-        vec![
-            PlayerStats {
-                player_id: "normal_player".to_string(),
-                shots_fired: shots1,
-                hits: hits1,
-                headshots: 10,
-                shot_timestamps_ms: None,
-                training_label: None,
-            },
-            PlayerStats {
-                player_id: "suspicious_player".to_string(),
-                shots_fired: shots2,
-                hits: hits2,
-                headshots: 50, // suspicious headshot count
-                shot_timestamps_ms: None,
-                training_label: None,
-            },
-        ]
+        let clean = &response.results[0];
+        assert_eq!(clean.suspicion_score, 0.0);
+        assert_eq!(clean.flags, vec!["HeuristicFallback".to_string()]);
+        assert_eq!(clean.model_version, None);
+
+        let cheater = &response.results[1];
+        assert_eq!(cheater.suspicion_score, 1.0);
+        assert!(cheater.flags.contains(&"HighHitRate".to_string()));
+        assert!(cheater.flags.contains(&"HeuristicFallback".to_string()));
     }
 
     #[test]
-    fn test_build_dataframe_columns() {
-        let stats = create_test_stats();
-        let df = build_dataframe(&stats).expect("DataFrame creation failed");
+    fn test_label_mismatch_detection() {
+        let model = fit_forest(&[[0.5, 0.2], [0.95, 0.7]], &[0.0, 1.0]);
+        let player = |player_id: &str, hits: u32, label: Option<f64>| PlayerStats {
+            player_id: player_id.to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), hits)]),
+            headshots: 10,
+            training_label: label,
+            ..Default::default()
+        };
+        let stats = vec![
+            player("clean_agrees", 50, Some(0.0)),
+            player("clean_labeled_cheater", 50, Some(1.0)),
+            player("unlabeled", 50, None),
+        ];
+        let has_mismatch = |response: &AnalysisResponse, i: usize| {
+            response.results[i]
+                .flags
+                .contains(&"LabelMismatch".to_string())
+        };
 
-        // Verify the DataFrame structure
-        assert_eq!(df.height(), 2);
-        assert_eq!(df.width(), 4);
-        assert!(df.column("player_id").is_ok());
-        assert!(df.column("shots").is_ok());
-        assert!(df.column("hits").is_ok());
-        assert!(df.column("headshots").is_ok());
+        // Labels are ignored by default
+        let response = do_analysis(stats.clone(), &model, &AnalysisOptions::default())
+            .unwrap()
+            .0;
+        assert!(!(0..3).any(|i| has_mismatch(&response, i)));
+
+        let options = AnalysisOptions {
+            check_labels: true,
+            ..Default::default()
+        };
+        let response = do_analysis(stats, &model, &options).unwrap().0;
+        assert!(!has_mismatch(&response, 0));
+        assert!(has_mismatch(&response, 1));
+        assert!(!has_mismatch(&response, 2));
     }
 
     #[test]
-    fn test_build_dataframe_values() {
-        let stats = create_test_stats();
-        let df = build_dataframe(&stats).expect("DataFrame creation failed");
+    fn test_whitelisted_player_not_scored() {
+        let model = fit_forest(&[[0.5, 0.2], [0.95, 0.7]], &[0.0, 1.0]);
+        let player = |player_id: &str| PlayerStats {
+            player_id: player_id.to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), 95)]),
+            headshots: 70,
+            ..Default::default()
+        };
+        let options = AnalysisOptions {
+            whitelist: ["pro_player".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
 
-        // Check specific values
-        let player_ids = df.column("player_id").unwrap();
-        // Using string conversion instead of direct utf8 access
-        let player_id_0 = player_ids.get(0).unwrap().to_string();
-        let player_id_1 = player_ids.get(1).unwrap().to_string();
-        assert!(player_id_0.contains("normal_player"));
-        assert!(player_id_1.contains("suspicious_player"));
+        let response = do_analysis(
+            vec![player("pro_player"), player("cheater")],
+            &model,
+            &options,
+        )
+        .unwrap()
+        .0;
+        assert_eq!(response.results[0].suspicion_score, 0.0);
+        assert_eq!(response.results[0].flags, vec!["Whitelisted".to_string()]);
+        assert_eq!(response.results[1].suspicion_score, 1.0);
+        assert!(response.results[1]
+            .flags
+            .contains(&"HighHitRate".to_string()));
+    }
 
-        let shots = df.column("shots").unwrap().u32().unwrap();
-        assert_eq!(shots.get(0), Some(100));
-        assert_eq!(shots.get(1), Some(150)); // 100 + 50
+    #[test]
+    fn test_impossible_hit_rate_flagged() {
+        let model = fit_forest(&[[0.5, 0.2], [0.95, 0.7]], &[0.0, 0.0]);
+        let stats = vec![PlayerStats {
+            player_id: "tampered".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), 110)]),
+            headshots: 10,
+            ..Default::default()
+        }];
 
-        let hits = df.column("hits").unwrap().u32().unwrap();
-        assert_eq!(hits.get(0), Some(50));
-        assert_eq!(hits.get(1), Some(135)); // 90 + 45
+        // Without the option the model alone decides
+        let response = do_analysis(stats.clone(), &model, &AnalysisOptions::default())
+            .unwrap()
+            .0;
+        assert!(!response.results[0]
+            .flags
+            .contains(&"ImpossibleHitRate".to_string()));
 
-        let headshots = df.column("headshots").unwrap().u32().unwrap();
-        assert_eq!(headshots.get(0), Some(10));
-        assert_eq!(headshots.get(1), Some(50));
+        let options = AnalysisOptions {
+            flag_impossible_hit_rate: true,
+            ..Default::default()
+        };
+        let response = do_analysis(stats, &model, &options).unwrap().0;
+        assert_eq!(response.results[0].suspicion_score, 1.0);
+        assert!(response.results[0]
+            .flags
+            .contains(&"ImpossibleHitRate".to_string()));
+        assert_eq!(response.results[0].model_version, None);
     }
 
+    #[cfg(feature = "dataframe")]
     #[test]
-    fn test_df_to_ndarray_conversion() {
-        let stats = create_test_stats();
-        let df = build_dataframe(&stats).expect("DataFrame creation failed");
+    fn test_export_features_npy() {
+        let dir = std::env::temp_dir().join("nocheat_npy_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("features.npy");
+        let (stats, _) = generate_synthetic_dataset();
 
-        // Create a test column
-        let df = df
-            .lazy()
-            .with_column(
-                (col("headshots").cast(DataType::Float32) / col("shots").cast(DataType::Float32))
-                    .alias("test_ratio"),
-            )
-            .collect()
-            .expect("Failed to compute test_ratio");
+        export_features_npy(&stats, path.to_str().unwrap()).unwrap();
 
-        // Convert to ndarray
-        let features = df_to_ndarray(&df, &["test_ratio"]).expect("Failed to convert");
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+        assert!(header.contains(&format!("'shape': ({}, 2)", stats.len())));
+        assert!(header.contains("'descr': '<f4'"));
 
-        // Verify dimensions
-        assert_eq!(features.shape(), [2, 1]);
+        let data = &bytes[10 + header_len..];
+        assert_eq!(data.len(), stats.len() * 2 * 4);
+        let first_hit_rate = f32::from_le_bytes(data[..4].try_into().unwrap());
+        assert_eq!(
+            first_hit_rate as f64,
+            FeatureRow::from_stats(&stats[0]).hit_rate
+        );
 
-        // Verify values with some tolerance for floating-point precision
-        let expected_normal = 10.0 / 100.0;
-        let expected_suspicious = 50.0 / 150.0;
+        let ids: Vec<String> =
+            serde_json::from_slice(&std::fs::read(dir.join("features.ids.json")).unwrap()).unwrap();
+        assert_eq!(ids.len(), stats.len());
+        assert_eq!(ids[0], stats[0].player_id);
 
-        let tolerance = 1e-5;
-        assert!((features[[0, 0]] - expected_normal).abs() < tolerance);
-        assert!((features[[1, 0]] - expected_suspicious).abs() < tolerance);
+        let _ = std::fs::remove_dir_all(dir);
     }
 
     #[test]
-    fn test_train_model() {
-        // Create a temporary file path for the model
-        let temp_dir = std::env::temp_dir();
-        let model_path = temp_dir.join("test_model.bin");
+    fn test_deduped_predictions_match_naive_path() {
+        use randomforest::criterion::Gini;
+        use randomforest::table::TableBuilder;
 
-        // Create simple training data
-        let mut training_data = Vec::new();
-        let mut labels = Vec::new();
+        // An odd number of trees, since tied votes are broken in arbitrary order
+        let mut table_builder = TableBuilder::new();
+        table_builder.add_row(&[0.5, 0.2], 0.0).unwrap();
+        table_builder.add_row(&[0.95, 0.7], 1.0).unwrap();
+        let forest = TrainingConfig {
+            trees: 101,
+            seed: Some(3),
+            ..Default::default()
+        }
+        .forest_options()
+        .unwrap()
+        .fit(Gini, table_builder.build().unwrap());
+        let model = CheatModel::from_forest(forest);
+        let (samples, _) = generate_synthetic_dataset();
+        // Every sample appears four times under different player_ids
+        let stats: Vec<PlayerStats> = (0..4)
+            .flat_map(|copy| {
+                samples.iter().map(move |s| PlayerStats {
+                    player_id: format!("{}_{}", s.player_id, copy),
+                    ..s.clone()
+                })
+            })
+            .collect();
 
-        // Add a normal player
-        let mut shots = HashMap::new();
-        shots.insert("rifle".to_string(), 100);
-        let mut hits = HashMap::new();
-        hits.insert("rifle".to_string(), 50);
+        let naive_options = AnalysisOptions {
+            dedupe_predictions: false,
+            ..Default::default()
+        };
+        let (naive, naive_cache) = do_analysis(stats.clone(), &model, &naive_options).unwrap();
+        let (deduped, cache) =
+            do_analysis(stats.clone(), &model, &AnalysisOptions::default()).unwrap();
+        // Inputs are deduplicated across the whole batch, however many threads score it
+        for max_threads in [Some(1), Some(4)] {
+            let options = AnalysisOptions {
+                max_threads,
+                ..Default::default()
+            };
+            let (_, threaded_cache) = do_analysis(stats.clone(), &model, &options).unwrap();
+            assert_eq!(threaded_cache, cache);
+        }
 
-        training_data.push(PlayerStats {
-            player_id: "normal_player".to_string(),
-            shots_fired: shots,
-            hits,
-            headshots: 10,
-            shot_timestamps_ms: None,
-            training_label: None,
-        });
-        labels.push(0.0);
+        assert_eq!(naive_cache, CacheStats::default());
+        assert_eq!(cache.lookups, naive.results.len());
+        assert!(cache.hit_rate() >= 0.75, "hit rate {}", cache.hit_rate());
 
-        // Add a cheating player
-        let mut shots = HashMap::new();
-        shots.insert("rifle".to_string(), 100);
-        let mut hits = HashMap::new();
-        hits.insert("rifle".to_string(), 95);
+        let strip =
+            |r: &types::PlayerResult| (r.player_id.clone(), r.suspicion_score, r.flags.clone());
+        assert_eq!(
+            naive.results.iter().map(strip).collect::<Vec<_>>(),
+            deduped.results.iter().map(strip).collect::<Vec<_>>()
+        );
+    }
 
-        training_data.push(PlayerStats {
-            player_id: "cheater".to_string(),
-            shots_fired: shots,
-            hits,
+    #[test]
+    fn test_class_probabilities_only_for_multiclass_models() {
+        let stats = vec![PlayerStats {
+            player_id: "player123".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), 95)]),
             headshots: 70,
-            shot_timestamps_ms: None,
-            training_label: None,
-        });
-        labels.push(1.0);
-
-        // Train the model
-        let result = train_model(training_data, labels, model_path.to_str().unwrap());
-        assert!(result.is_ok());
+            ..Default::default()
+        }];
 
-        // Verify the model file exists
-        assert!(model_path.exists());
+        let binary = fit_forest(&[[0.5, 0.2], [0.95, 0.7]], &[0.0, 1.0]);
+        let (response, _) =
+            do_analysis(stats.clone(), &binary, &AnalysisOptions::default()).unwrap();
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json["results"][0].get("class_probabilities").is_none());
 
-        // Clean up
-        let _ = fs::remove_file(model_path);
+        // 0 = legitimate, 1 = aimbot, 2 = triggerbot
+        let multiclass = fit_forest(&[[0.5, 0.2], [0.95, 0.7], [0.7, 0.9]], &[0.0, 1.0, 2.0]);
+        assert!(multiclass.is_multiclass());
+        let (response, _) = do_analysis(stats, &multiclass, &AnalysisOptions::default()).unwrap();
+        let json = serde_json::to_value(&response).unwrap();
+        let probabilities = json["results"][0]["class_probabilities"]
+            .as_object()
+            .unwrap();
+        assert_eq!(
+            probabilities.keys().collect::<Vec<_>>(),
+            vec!["0", "1", "2"]
+        );
+        let total: f64 = probabilities.values().map(|p| p.as_f64().unwrap()).sum();
+        assert!((total - 1.0).abs() < 1e-9);
     }
 
     #[test]
-    fn test_generate_default_model() {
-        // Create a temporary file path for the model
-        let temp_dir = std::env::temp_dir();
-        let model_path = temp_dir.join("default_model.bin");
+    fn test_load_model_retries_transient_failure() {
+        let dir = std::env::temp_dir().join("nocheat_retry_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.bin");
+        let path_str = path.to_str().unwrap();
 
-        // Generate the default model
-        let result = generate_default_model(model_path.to_str().unwrap());
-        assert!(result.is_ok());
+        let (training_data, labels) = generate_synthetic_dataset();
+        let config = TrainingConfig {
+            trees: 5,
+            seed: Some(7),
+            model_version: Some("retry-test".to_string()),
+            ..Default::default()
+        };
+        let mut model_bytes = Vec::new();
+        train_model_to_writer(training_data, labels, &config, &mut model_bytes).unwrap();
 
-        // Verify the model file exists
-        assert!(model_path.exists());
+        // The first read sees a half-written file, the writer finishes before the retry
+        fs::write(&path, &model_bytes[..model_bytes.len() / 2]).unwrap();
+        let policy = RetryPolicy {
+            attempts: 3,
+            initial_backoff: std::time::Duration::from_millis(1),
+        };
+        let mut attempts = 0;
+        let model = policy
+            .run(|| {
+                attempts += 1;
+                let result = load_model(path_str);
+                fs::write(&path, &model_bytes).unwrap();
+                result
+            })
+            .unwrap();
+        assert_eq!(attempts, 2);
+        assert_eq!(model.metadata.version, "retry-test");
 
-        // Clean up
-        let _ = fs::remove_file(model_path);
+        fs::write(&path, b"garbage").unwrap();
+        let error = load_model_with_retry(path_str, &policy).unwrap_err();
+        assert!(error.to_string().contains("attempt 3"), "{}", error);
+
+        // A hot reload of the corrupt file keeps the previous model and reports why
+        fs::write(&path, &model_bytes).unwrap();
+        let cache = RwLock::new(None);
+        let loaded = cached_model(&cache, path_str, &policy).unwrap();
+        assert!(cache
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .reload_error
+            .is_none());
+
+        fs::write(&path, b"garbage").unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let kept = cached_model(&cache, path_str, &policy).unwrap();
+        assert!(Arc::ptr_eq(&kept, &loaded));
+        let error = cache.read().unwrap().as_ref().unwrap().reload_error.clone();
+        assert!(error.unwrap().contains("attempt 3"));
+
+        let _ = fs::remove_dir_all(dir);
     }
-    #[test]
-    fn test_set_model_path() {
-        // Create a temporary model file
-        let temp_dir = std::env::temp_dir();
-        let model_path = temp_dir.join("custom_model.bin");
-        let model_path_str = model_path.to_str().unwrap();
 
-        // Generate a model to use for testing
-        generate_default_model(model_path_str).expect("Failed to generate test model");
+    static STREAMED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
 
-        // Save the original model path to restore it later
-        let original_path = unsafe { CURRENT_MODEL_PATH };
+    /// Held by tests comparing FFI output bytes, as `set_pretty_json` is global
+    static JSON_FORMAT: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
-        // Call set_model_path using the FFI interface
-        let path_bytes = model_path_str.as_bytes();
-        let path_len = path_bytes.len();
+    extern "C" fn collect_result(ptr: *const c_uchar, len: size_t) {
+        let json = unsafe { std::slice::from_raw_parts(ptr, len) };
+        STREAMED
+            .lock()
+            .unwrap()
+            .push(String::from_utf8(json.to_vec()).unwrap());
+    }
 
-        let result = unsafe { set_model_path(path_bytes.as_ptr(), path_len) };
+    #[test]
+    fn test_pretty_json_output() {
+        let response = AnalysisResponse {
+            results: vec![PlayerResult::new("player123", 0.9, Vec::new())],
+        };
+        let ffi_output = |pretty: c_int| {
+            let _format = JSON_FORMAT.lock().unwrap();
+            set_pretty_json(pretty);
+            let mut out_ptr = ptr::null_mut();
+            let mut out_len = 0;
+            assert_eq!(write_buffer(&response, &mut out_ptr, &mut out_len), 0);
+            set_pretty_json(0);
+            let json = unsafe { std::slice::from_raw_parts(out_ptr, out_len) }.to_vec();
+            unsafe { free_buffer(out_ptr, out_len) };
+            String::from_utf8(json).unwrap()
+        };
 
+        let pretty = ffi_output(1);
+        let compact = ffi_output(0);
+        assert!(pretty.contains('\n'));
+        assert!(!compact.contains('\n'));
         assert_eq!(
-            result, 0,
-            "Expected set_model_path to return success code 0"
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&compact).unwrap()
         );
 
-        // Verify the model path was updated - we need to be careful with mutable static
-        let current_path = unsafe { CURRENT_MODEL_PATH };
+        let output = options::JsonOutput {
+            pretty: true,
+            ..Default::default()
+        };
+        assert_eq!(response.to_json(&output).unwrap(), pretty.as_bytes());
+        let compact_json = response.to_json(&Default::default()).unwrap();
+        assert_eq!(compact_json, compact.as_bytes());
+    }
+
+    #[test]
+    fn test_batches_above_max_batch_are_rejected() {
+        let (stats, _) = generate_synthetic_dataset();
+        let batch = |players: usize| serde_json::to_vec(&stats[..players]).unwrap();
+        let analyze = |input: &[u8]| {
+            let mut out_ptr = ptr::null_mut();
+            let mut out_len = 0;
+            let code =
+                unsafe { analyze_round(input.as_ptr(), input.len(), &mut out_ptr, &mut out_len) };
+            unsafe { free_buffer(out_ptr, out_len) };
+            code
+        };
+
+        let lenient = |input: &[u8]| {
+            let mut out_ptr = ptr::null_mut();
+            let mut out_len = 0;
+            let code = unsafe {
+                analyze_round_lenient(input.as_ptr(), input.len(), &mut out_ptr, &mut out_len)
+            };
+            unsafe { free_buffer(out_ptr, out_len) };
+            code
+        };
+        // Invalid entries count towards the limit of the lenient entry point
+        let mut entries: Vec<serde_json::Value> = stats[..2]
+            .iter()
+            .map(|s| serde_json::to_value(s).unwrap())
+            .collect();
+        entries.push(serde_json::json!({ "steam_id": 7 }));
+        let invalid = serde_json::to_vec(&entries).unwrap();
+
+        set_max_batch(2);
+        let rejected = analyze(&batch(3));
+        let streamed =
+            unsafe { analyze_round_cb(batch(3).as_ptr(), batch(3).len(), Some(collect_result)) };
+        let lenient_rejected = lenient(&batch(3));
+        let invalid_rejected = lenient(&invalid);
+        let lenient_not_array = lenient(b"{}");
+        #[cfg(feature = "proto")]
+        let proto_rejected = {
+            use prost::Message;
+            let input = proto::PlayerStatsBatch {
+                players: stats[..3].iter().map(Into::into).collect(),
+            }
+            .encode_to_vec();
+            let mut out_ptr = ptr::null_mut();
+            let mut out_len = 0;
+            let code = unsafe {
+                analyze_round_proto(input.as_ptr(), input.len(), &mut out_ptr, &mut out_len)
+            };
+            unsafe { free_buffer(out_ptr, out_len) };
+            code
+        };
+        set_max_batch(0);
+        assert_eq!(rejected, -7);
+        assert_eq!(streamed, -7);
+        assert_eq!(lenient_rejected, -7);
+        assert_eq!(invalid_rejected, -7);
+        assert_eq!(lenient_not_array, -3);
+        #[cfg(feature = "proto")]
+        assert_eq!(proto_rejected, -7);
+        assert!(!exceeds_max_batch(3));
+
+        set_max_batch(3);
+        assert!(!exceeds_max_batch(3));
+        assert!(exceeds_max_batch(4));
+        set_max_batch(0);
+    }
+
+    #[test]
+    fn test_results_streamed_to_callback() {
+        let model = fit_forest(&[[0.5, 0.2], [0.95, 0.7]], &[0.0, 1.0]);
+        let (stats, _) = generate_synthetic_dataset();
+        let (response, _) =
+            do_analysis(stats.clone(), &model, &AnalysisOptions::default()).unwrap();
+
+        let _format = JSON_FORMAT.lock().unwrap();
+        assert_eq!(stream_results(&response, collect_result), 0);
+        let streamed = STREAMED.lock().unwrap();
+        assert_eq!(streamed.len(), stats.len());
+        for (json, result) in streamed.iter().zip(&response.results) {
+            assert_eq!(json, &serde_json::to_string(result).unwrap());
+        }
+
         assert_eq!(
-            current_path, model_path_str,
-            "Model path was not updated correctly"
+            unsafe { analyze_round_cb(ptr::null(), 0, Some(collect_result)) },
+            -1
         );
+        let input = b"not json";
+        assert_eq!(
+            unsafe { analyze_round_cb(input.as_ptr(), input.len(), Some(collect_result)) },
+            -2
+        );
+    }
 
-        // Clean up
-        let _ = fs::remove_file(model_path);
-
-        // Restore the original path by calling set_model_path again
-        let orig_bytes = original_path.as_bytes();
-        unsafe {
-            set_model_path(orig_bytes.as_ptr(), orig_bytes.len());
+    #[cfg(feature = "dataframe")]
+    #[test]
+    fn test_train_model_from_csv_with_schema() {
+        let mut csv = String::from("account,match_id,hit_rate,headshot_rate,is_cheater\n");
+        for i in 0..10 {
+            let step = i as f64 * 0.01;
+            csv += &format!("normal{},m1,{},{},0\n", i, 0.45 + step, 0.15 + step);
+            csv += &format!("cheater{},m1,{},{},1\n", i, 0.9 + step, 0.6 + step);
         }
+        let dir = std::env::temp_dir();
+        let csv_path = dir.join("test_schema_training.csv");
+        let model_path = dir.join("test_schema_model.bin");
+        fs::write(&csv_path, csv).unwrap();
+
+        let schema = SchemaSpec {
+            feature_cols: vec!["hit_rate".to_string(), "headshot_rate".to_string()],
+            label_col: "is_cheater".to_string(),
+            id_col: Some("account".to_string()),
+        };
+        let config = TrainingConfig {
+            seed: Some(7),
+            ..Default::default()
+        };
+        train_model_from_csv(&csv_path, &schema, &config, model_path.to_str().unwrap()).unwrap();
+        let model = load_model(model_path.to_str().unwrap()).unwrap();
+        assert_eq!(model.metadata.feature_names, schema.feature_cols);
+        assert_eq!(model.predict(&[0.95, 0.65]), 1.0);
+        assert_eq!(model.predict(&[0.5, 0.2]), 0.0);
+
+        let error = train_model_from_csv(
+            &csv_path,
+            &SchemaSpec {
+                label_col: "banned".to_string(),
+                ..schema
+            },
+            &config,
+            model_path.to_str().unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Training table is missing the label column 'banned'; its columns are: \
+             account, match_id, hit_rate, headshot_rate, is_cheater"
+        );
+
+        let _ = fs::remove_file(&csv_path);
+        let _ = fs::remove_file(&model_path);
     }
 }
@@ -0,0 +1,1458 @@
+//! Versioned model container.
+//!
+//! A model file written by `train_model` stores the RandomForest together with
+//! the metadata needed to encode inputs exactly as they were encoded during
+//! training:
+//!
+//! ```text
+//! magic "NOCHEAT\0" | u32 LE metadata length | metadata JSON | forest bytes
+//! ```
+//!
+//! Compact containers written by `CheatModel::serialize_compact` use the magic
+//! "NOCHEATC" and store the forest in the quantized encoding of the `forest`
+//! module. Files holding a bare serialized forest (as written by earlier
+//! versions of the crate) are still accepted and get legacy metadata.
+
+use anyhow::Result;
+use randomforest::RandomForestClassifier;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::sync::mpsc::RecvTimeoutError;
+
+use crate::error::NoCheatError;
+use crate::forest::ForestLayout;
+use crate::inference::{self, FeatureRow, FEATURE_NAMES};
+use crate::options::{AnalysisOptions, ModelLoadLimits};
+use crate::types::PlayerStats;
+
+/// Magic bytes at the start of every model container
+const MAGIC: &[u8; 8] = b"NOCHEAT\0";
+
+/// Magic bytes at the start of every compact model container
+const COMPACT_MAGIC: &[u8; 8] = b"NOCHEATC";
+
+/// Version reported for models stored without a container
+pub const LEGACY_MODEL_VERSION: &str = "legacy";
+
+/// How the forest of a model was trained, and so how to read its output.
+///
+/// Both kinds share the same serialized forest format, so the kind is stored
+/// in the model container; models without it are classifiers.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModelKind {
+    /// Trees vote for a class; the score is the majority vote, or a
+    /// probability when the model is calibrated
+    #[default]
+    Classifier,
+    /// Trees predict a cheating score; the score is their mean, clamped to [0, 1]
+    Regressor,
+}
+
+/// Platt scaling: a logistic mapping of a raw score to a calibrated probability.
+///
+/// The probability is `1 / (1 + exp(-(a * score + b)))`.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::model::PlattScaling;
+///
+/// let platt = PlattScaling::fit(&[0.1, 0.2, 0.8, 0.9], &[0.0, 0.0, 1.0, 1.0]).unwrap();
+/// assert!(platt.apply(0.9) > 0.5);
+/// assert!(platt.apply(0.1) < 0.5);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PlattScaling {
+    /// Slope of the logistic
+    pub a: f64,
+    /// Intercept of the logistic
+    pub b: f64,
+}
+
+impl PlattScaling {
+    /// Fit the logistic to scores and their 0/1 labels by maximum likelihood.
+    ///
+    /// Labels are smoothed towards 0.5 as in Platt's original method, so
+    /// perfectly separated scores still give finite parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `scores` - Raw scores, one per sample
+    /// * `labels` - Labels of the samples; 0.5 or more counts as positive
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PlattScaling>` - The fitted parameters, or an error when the
+    ///   inputs are empty or of different lengths
+    pub fn fit(scores: &[f64], labels: &[f64]) -> Result<Self> {
+        if scores.is_empty() || scores.len() != labels.len() {
+            return Err(anyhow::anyhow!(
+                "Calibration needs one label per score, got {} scores and {} labels",
+                scores.len(),
+                labels.len()
+            ));
+        }
+        let positives = labels
+            .iter()
+            .filter(|&&label| label >= inference::VERDICT_THRESHOLD as f64)
+            .count() as f64;
+        let negatives = labels.len() as f64 - positives;
+        let targets: Vec<f64> = labels
+            .iter()
+            .map(|&label| {
+                if label >= inference::VERDICT_THRESHOLD as f64 {
+                    (positives + 1.0) / (positives + 2.0)
+                } else {
+                    1.0 / (negatives + 2.0)
+                }
+            })
+            .collect();
+
+        let loss = |platt: &PlattScaling| -> f64 {
+            scores
+                .iter()
+                .zip(&targets)
+                .map(|(&score, &t)| {
+                    let p = platt.apply(score).clamp(1e-12, 1.0 - 1e-12);
+                    -(t * p.ln() + (1.0 - t) * (1.0 - p).ln())
+                })
+                .sum()
+        };
+
+        // Newton's method with step halving on the convex log loss
+        let mut platt = PlattScaling {
+            a: 0.0,
+            b: ((positives + 1.0) / (negatives + 1.0)).ln(),
+        };
+        let mut current = loss(&platt);
+        for _ in 0..100 {
+            let (mut ga, mut gb, mut haa, mut hab, mut hbb) = (0.0, 0.0, 1e-9, 0.0, 1e-9);
+            for (&score, &t) in scores.iter().zip(&targets) {
+                let p = platt.apply(score);
+                let w = p * (1.0 - p);
+                ga += (p - t) * score;
+                gb += p - t;
+                haa += w * score * score;
+                hab += w * score;
+                hbb += w;
+            }
+            let det = haa * hbb - hab * hab;
+            if det.abs() < f64::EPSILON {
+                break;
+            }
+            let da = (hbb * ga - hab * gb) / det;
+            let db = (haa * gb - hab * ga) / det;
+
+            let mut step = 1.0;
+            let mut improved = false;
+            while step > 1e-10 {
+                let candidate = PlattScaling {
+                    a: platt.a - step * da,
+                    b: platt.b - step * db,
+                };
+                let candidate_loss = loss(&candidate);
+                if candidate_loss <= current {
+                    improved = current - candidate_loss > 1e-12;
+                    platt = candidate;
+                    current = candidate_loss;
+                    break;
+                }
+                step /= 2.0;
+            }
+            if !improved {
+                break;
+            }
+        }
+        Ok(platt)
+    }
+
+    /// Calibrated probability of a raw score
+    pub fn apply(&self, score: f64) -> f64 {
+        1.0 / (1.0 + (-(self.a * score + self.b)).exp())
+    }
+}
+
+/// A categorical field one-hot encoded into the model input.
+///
+/// The field is looked up in `PlayerStats::attributes`. Every category of the
+/// vocabulary becomes one input column named `{field}_{category}`; a value that
+/// is missing or was not seen during training encodes as all zeros.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CategoricalFeature {
+    /// Name of the attribute holding the category
+    pub field: String,
+    /// Categories seen during training, in column order
+    pub categories: Vec<String>,
+}
+
+impl CategoricalFeature {
+    /// Names of the one-hot columns produced by this field
+    pub fn column_names(&self) -> Vec<String> {
+        self.categories
+            .iter()
+            .map(|category| format!("{}_{}", self.field, category))
+            .collect()
+    }
+
+    /// Append the one-hot encoding of a player's category to `features`
+    fn encode_into(&self, stats: &PlayerStats, features: &mut Vec<f64>) {
+        let value = stats.attributes.get(&self.field);
+        features.extend(self.categories.iter().map(|category| {
+            if value == Some(category) {
+                1.0
+            } else {
+                0.0
+            }
+        }));
+    }
+}
+
+/// Version of the weapon hash of `FeatureHashing`, bumped whenever the hash changes
+pub const FEATURE_HASH_VERSION: u32 = 1;
+
+/// Per-weapon hit rates hashed into a fixed number of input columns.
+///
+/// Weapon vocabularies differ between matches and patches, so one column per
+/// weapon cannot be aligned between training and inference. Every weapon name
+/// is instead hashed (64-bit FNV-1a mixed with `seed`) into one of `buckets`
+/// columns named `weapon_bucket_{i}`, each holding the sum of the hit rates of
+/// its weapons. The input width is constant whatever the weapons.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::model::FeatureHashing;
+///
+/// let hashing = FeatureHashing::new(8, 42);
+/// assert_eq!(hashing.bucket("rifle"), hashing.bucket("rifle"));
+/// assert!(hashing.bucket("rifle") < 8);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FeatureHashing {
+    /// Number of bucket columns
+    pub buckets: usize,
+    /// Seed mixed into the hash
+    pub seed: u64,
+    /// Version of the hash function (see `FEATURE_HASH_VERSION`)
+    pub version: u32,
+}
+
+impl FeatureHashing {
+    /// Hashing into `buckets` columns with the current hash version
+    pub fn new(buckets: usize, seed: u64) -> Self {
+        Self {
+            buckets,
+            seed,
+            version: FEATURE_HASH_VERSION,
+        }
+    }
+
+    /// Bucket of a weapon name
+    pub fn bucket(&self, weapon: &str) -> usize {
+        let mut hash = 0xcbf2_9ce4_8422_2325 ^ self.seed;
+        for byte in weapon.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        (hash % self.buckets.max(1) as u64) as usize
+    }
+
+    /// Names of the bucket columns
+    pub fn column_names(&self) -> Vec<String> {
+        (0..self.buckets)
+            .map(|i| format!("weapon_bucket_{}", i))
+            .collect()
+    }
+
+    /// Append the bucketed hit rates of a player to `features`
+    fn encode_into(&self, stats: &PlayerStats, features: &mut Vec<f64>) -> Result<()> {
+        if self.version != FEATURE_HASH_VERSION {
+            return Err(anyhow::anyhow!(
+                "Model uses weapon hash version {}, this build supports version {}",
+                self.version,
+                FEATURE_HASH_VERSION
+            ));
+        }
+        let mut buckets = vec![0.0; self.buckets];
+        // Sorted so the floating-point sums do not depend on map order
+        let mut weapons: Vec<(&String, &u32)> = stats.shots_fired.iter().collect();
+        weapons.sort();
+        for (weapon, &shots) in weapons {
+            if shots > 0 && self.buckets > 0 {
+                let hits = stats.hits.get(weapon).copied().unwrap_or(0);
+                buckets[self.bucket(weapon)] += hits as f64 / shots as f64;
+            }
+        }
+        features.extend(buckets);
+        Ok(())
+    }
+}
+
+/// Metadata stored alongside the forest in a model container.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ModelMetadata {
+    /// Version identifying the trained model
+    pub version: String,
+    /// Numeric features fed to the model, in input order
+    pub feature_names: Vec<String>,
+    /// Categorical features appended after the numeric ones, in input order
+    #[serde(default)]
+    pub categorical: Vec<CategoricalFeature>,
+    /// Optional inputs (see `inference::OPTIONAL_FEATURES`) whose presence is
+    /// appended after the categorical features as `{name}_present` columns
+    #[serde(default)]
+    pub optional_features: Vec<String>,
+    /// Input columns kept by feature selection, in input order; every column
+    /// is fed to the model when `None`
+    #[serde(default)]
+    pub selected_inputs: Option<Vec<String>>,
+    /// Mapping of the fraction of trees voting cheater to a calibrated
+    /// probability (see `TrainingConfig::calibrate`); predictions are the raw
+    /// forest output when `None`
+    #[serde(default)]
+    pub calibration: Option<PlattScaling>,
+    /// Hashed per-weapon hit rates appended after the presence indicators
+    #[serde(default)]
+    pub feature_hashing: Option<FeatureHashing>,
+    /// Whether the forest is a classifier or a regressor
+    #[serde(default)]
+    pub model_kind: ModelKind,
+}
+
+impl ModelMetadata {
+    /// Metadata of a model stored as a bare forest
+    pub fn legacy() -> Self {
+        Self {
+            version: LEGACY_MODEL_VERSION.to_string(),
+            feature_names: FEATURE_NAMES.iter().map(|name| name.to_string()).collect(),
+            categorical: Vec::new(),
+            optional_features: Vec::new(),
+            selected_inputs: None,
+            calibration: None,
+            feature_hashing: None,
+            model_kind: ModelKind::Classifier,
+        }
+    }
+
+    /// Names of the columns fed to the model, in input order
+    pub fn input_names(&self) -> Vec<String> {
+        match &self.selected_inputs {
+            Some(selected) => selected.clone(),
+            None => self.encoded_names(),
+        }
+    }
+
+    /// Encoded columns removed by feature selection, in input order
+    pub fn dropped_inputs(&self) -> Vec<String> {
+        match &self.selected_inputs {
+            Some(selected) => self
+                .encoded_names()
+                .into_iter()
+                .filter(|name| !selected.contains(name))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Optional inputs provided by some player of `stats` that the model does
+    /// not read, sorted by name.
+    ///
+    /// Attribute keys that are not a categorical field of the model are
+    /// reported as `attributes.{key}`. The rule-based checks may still use an
+    /// ignored input, e.g. timestamps for the `RoboticCadence` flag.
+    pub fn ignored_fields(&self, stats: &[PlayerStats]) -> Vec<String> {
+        let inputs = self.input_names();
+        let reads = |columns: &[String]| columns.iter().any(|column| inputs.contains(column));
+        let mut ignored = BTreeSet::new();
+        for stat in stats {
+            for input in &inference::OPTIONAL_INPUTS {
+                let read = input
+                    .columns
+                    .iter()
+                    .any(|column| inputs.iter().any(|name| name == column));
+                if (input.provided)(stat) && !read {
+                    ignored.insert(input.name.to_string());
+                }
+            }
+            for key in stat.attributes.keys() {
+                let read = self
+                    .categorical
+                    .iter()
+                    .any(|feature| feature.field == *key && reads(&feature.column_names()));
+                if !read {
+                    ignored.insert(format!("attributes.{}", key));
+                }
+            }
+        }
+        ignored.into_iter().collect()
+    }
+
+    /// Names of every encoded column before feature selection: numeric
+    /// features, categorical columns, presence indicators, then weapon buckets
+    fn encoded_names(&self) -> Vec<String> {
+        let mut names = self.feature_names.clone();
+        for feature in &self.categorical {
+            names.extend(feature.column_names());
+        }
+        names.extend(
+            self.optional_features
+                .iter()
+                .map(|name| format!("{}_present", name)),
+        );
+        if let Some(hashing) = &self.feature_hashing {
+            names.extend(hashing.column_names());
+        }
+        names
+    }
+
+    /// Build the model input of a player from its numeric features and attributes
+    pub fn encode(&self, stats: &PlayerStats, row: &FeatureRow) -> Result<Vec<f64>> {
+        let mut features = Vec::with_capacity(self.feature_names.len());
+        for name in &self.feature_names {
+            let value = row
+                .get(name)
+                .or_else(|| inference::extra_feature(stats, name))
+                .ok_or_else(|| anyhow::anyhow!("Model requires unknown feature: {}", name))?;
+            features.push(value);
+        }
+        for feature in &self.categorical {
+            feature.encode_into(stats, &mut features);
+        }
+        // 1.0 when the input was provided, so absence is not mistaken for a zero value
+        for name in &self.optional_features {
+            let present = inference::is_present(stats, name).ok_or_else(|| {
+                anyhow::anyhow!("Model requires unknown optional feature: {}", name)
+            })?;
+            features.push(if present { 1.0 } else { 0.0 });
+        }
+        if let Some(hashing) = &self.feature_hashing {
+            hashing.encode_into(stats, &mut features)?;
+        }
+        if let Some(selected) = &self.selected_inputs {
+            let names = self.encoded_names();
+            features = selected
+                .iter()
+                .map(|name| {
+                    names
+                        .iter()
+                        .position(|column| column == name)
+                        .map(|i| features[i])
+                        .ok_or_else(|| anyhow::anyhow!("Model requires unknown input: {}", name))
+                })
+                .collect::<Result<_>>()?;
+        }
+        Ok(features)
+    }
+}
+
+/// A trained RandomForest together with its metadata.
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::load_model;
+///
+/// let model = load_model("models/cheat_model.bin").expect("Failed to load model");
+/// println!("model {} uses {:?}", model.metadata.version, model.metadata.input_names());
+/// ```
+#[derive(Debug)]
+pub struct CheatModel {
+    /// Metadata describing how inputs are encoded
+    pub metadata: ModelMetadata,
+    forest: RandomForestClassifier,
+    classes: Vec<f64>,
+}
+
+impl CheatModel {
+    /// Wrap a trained forest with its metadata
+    pub fn new(forest: RandomForestClassifier, metadata: ModelMetadata) -> Self {
+        let mut bytes = Vec::new();
+        let classes = match forest.serialize(&mut bytes) {
+            Ok(()) => ForestLayout::parse(&bytes)
+                .map(|layout| layout.classes())
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        Self {
+            metadata,
+            forest,
+            classes,
+        }
+    }
+
+    /// Wrap a forest trained on the default features, without categorical inputs
+    pub fn from_forest(forest: RandomForestClassifier) -> Self {
+        Self::new(forest, ModelMetadata::legacy())
+    }
+
+    /// The underlying RandomForest
+    pub fn forest(&self) -> &RandomForestClassifier {
+        &self.forest
+    }
+
+    /// Load a model container, compact container or bare legacy forest from a
+    /// file, within the default `ModelLoadLimits`
+    pub fn load(path: &str) -> Result<Self> {
+        Self::load_with_limits(path, &ModelLoadLimits::default())
+    }
+
+    /// Load a model from a file, rejecting files above `limits.max_bytes` and
+    /// deserialization running longer than `limits.timeout`.
+    ///
+    /// Deserialization runs on a separate thread; when it times out, that
+    /// thread is left to finish in the background and its result is discarded.
+    /// Its work is bounded by the size of the file and the node and depth
+    /// limits of the forest parsers, so an abandoned load cannot run away.
+    pub fn load_with_limits(path: &str, limits: &ModelLoadLimits) -> Result<Self> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let too_large = |size| NoCheatError::ModelTooLarge {
+            size,
+            limit: limits.max_bytes,
+        };
+        if size > limits.max_bytes {
+            return Err(too_large(size).into());
+        }
+        // The file may grow after the size check, so never read past the limit
+        let mut bytes = Vec::new();
+        file.take(limits.max_bytes.saturating_add(1))
+            .read_to_end(&mut bytes)?;
+        if bytes.len() as u64 > limits.max_bytes {
+            return Err(too_large(bytes.len() as u64).into());
+        }
+
+        let Some(timeout) = limits.timeout else {
+            return Self::from_bytes(&bytes);
+        };
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(Self::from_bytes(&bytes));
+        });
+        receiver.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => NoCheatError::ModelLoadTimeout { timeout }.into(),
+            RecvTimeoutError::Disconnected => {
+                anyhow::anyhow!("Failed to deserialize model: deserialization thread panicked")
+            }
+        })?
+    }
+
+    /// Load a model container, compact container or bare legacy forest from an in-memory buffer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(COMPACT_MAGIC) {
+            return Self::deserialize_compact(bytes);
+        }
+        let (metadata, forest_bytes) = match bytes.strip_prefix(MAGIC.as_slice()) {
+            Some(rest) => read_metadata(rest)?,
+            None => (ModelMetadata::legacy(), bytes),
+        };
+
+        // Check the tree limits first, as the forest's own parser has none
+        ForestLayout::parse(forest_bytes)?;
+        let forest = RandomForestClassifier::deserialize(forest_bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to deserialize model: {}", e))?;
+        Ok(Self::new(forest, metadata))
+    }
+
+    /// Write the model container
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_metadata(&mut writer, MAGIC, &self.metadata)?;
+        self.forest
+            .serialize(writer)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize model: {}", e))
+    }
+
+    /// Write a compact model container for size-constrained deployments.
+    ///
+    /// Split thresholds and leaf values are quantized from `f64` to `f32` and
+    /// every distinct subtree is stored once, which typically shrinks a model
+    /// to a fraction of its full size. Predictions can differ from the full
+    /// model only for inputs lying within `f32` rounding of a threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the compact model
+    ///
+    /// # Returns
+    ///
+    /// * `Result<CompactReport>` - Sizes of the full and compact encodings
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::load_model;
+    /// use nocheat::model::CheatModel;
+    ///
+    /// let model = load_model("models/cheat_model.bin").expect("Failed to load model");
+    ///
+    /// let mut compact = Vec::new();
+    /// let report = model.serialize_compact(&mut compact).expect("Failed to write model");
+    /// println!("{} -> {} bytes ({:.0}% smaller)", report.full_bytes, report.compact_bytes, report.reduction() * 100.0);
+    ///
+    /// let restored = CheatModel::deserialize_compact(&compact).expect("Failed to read model");
+    /// ```
+    pub fn serialize_compact<W: Write>(&self, mut writer: W) -> Result<CompactReport> {
+        let mut full = Vec::new();
+        self.write(&mut full)?;
+        let mut forest_bytes = Vec::new();
+        self.forest.serialize(&mut forest_bytes)?;
+
+        let mut compact = Vec::new();
+        write_metadata(&mut compact, COMPACT_MAGIC, &self.metadata)?;
+        ForestLayout::parse(&forest_bytes)?.write_compact(&mut compact);
+        writer.write_all(&compact)?;
+
+        Ok(CompactReport {
+            full_bytes: full.len(),
+            compact_bytes: compact.len(),
+        })
+    }
+
+    /// Load a compact model container written by `serialize_compact`
+    pub fn deserialize_compact(bytes: &[u8]) -> Result<Self> {
+        let rest = bytes
+            .strip_prefix(COMPACT_MAGIC.as_slice())
+            .ok_or_else(|| anyhow::anyhow!("Not a compact model"))?;
+        let (metadata, compact) = read_metadata(rest)?;
+
+        let mut forest_bytes = Vec::new();
+        ForestLayout::parse_compact(compact)?.write(&mut forest_bytes);
+        let forest = RandomForestClassifier::deserialize(forest_bytes.as_slice())
+            .map_err(|e| anyhow::anyhow!("Failed to deserialize model: {}", e))?;
+        Ok(Self::new(forest, metadata))
+    }
+
+    /// Build the model input of a player from its numeric features and attributes
+    pub fn encode(&self, stats: &PlayerStats, row: &FeatureRow) -> Result<Vec<f64>> {
+        self.metadata.encode(stats, row)
+    }
+
+    /// Share of the forest's split nodes testing every input column.
+    ///
+    /// Columns are keyed by their name in `ModelMetadata::input_names` and the
+    /// shares sum to 1.0, unless the forest has no splits at all. A column
+    /// that is never split on, such as a constant one, has an importance of 0.0.
+    pub fn feature_importances(&self) -> Result<BTreeMap<String, f64>> {
+        let mut bytes = Vec::new();
+        self.forest
+            .serialize(&mut bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize model: {}", e))?;
+        let counts = ForestLayout::parse(&bytes)?.split_counts();
+        let total: usize = counts.iter().sum();
+        Ok(self
+            .metadata
+            .input_names()
+            .into_iter()
+            .zip(counts)
+            .map(|(name, count)| {
+                let share = if total == 0 {
+                    0.0
+                } else {
+                    count as f64 / total as f64
+                };
+                (name, share)
+            })
+            .collect())
+    }
+
+    /// Write the split rules of the first `max_trees` trees as indented text.
+    ///
+    /// Every split is printed as `if <input> <= <threshold>:` (`==` for
+    /// categorical inputs) using the names from `ModelMetadata::input_names`,
+    /// followed by the branch taken when the condition holds and an `else:`
+    /// branch. Leaves print the value the tree predicts.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the text dump
+    /// * `max_trees` - Maximum number of trees to print; the number of omitted
+    ///   trees is noted at the end
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::load_model;
+    ///
+    /// let model = load_model("models/cheat_model.bin").expect("Failed to load model");
+    /// model.dump_trees(std::io::stdout(), 2).expect("Failed to dump trees");
+    /// ```
+    pub fn dump_trees<W: Write>(&self, mut writer: W, max_trees: usize) -> Result<()> {
+        let mut bytes = Vec::new();
+        self.forest
+            .serialize(&mut bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize model: {}", e))?;
+        ForestLayout::parse(&bytes)?.write_text(
+            &mut writer,
+            &self.metadata.input_names(),
+            max_trees,
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Model output for an encoded input: the mean tree prediction clamped to
+    /// [0, 1] for a regressor, the calibrated probability of cheating when the
+    /// model carries a calibration, otherwise the raw forest prediction
+    pub fn predict(&self, features: &[f64]) -> f64 {
+        if self.metadata.model_kind == ModelKind::Regressor {
+            let (sum, total) = self
+                .forest
+                .predict_individuals(features)
+                .fold((0.0, 0), |(sum, total), value| (sum + value, total + 1));
+            if total == 0 {
+                return 0.0;
+            }
+            return (sum / total as f64).clamp(0.0, 1.0);
+        }
+        match &self.metadata.calibration {
+            Some(platt) => platt.apply(self.cheater_vote_share(features)),
+            None => self.forest.predict(features),
+        }
+    }
+
+    /// `predict` for a feature vector of any length, padded with `fill` or
+    /// truncated to the model's inputs (see `inference::align_features`).
+    ///
+    /// Also returns how the vector was aligned, so callers can report game
+    /// modes whose features do not match the model.
+    pub fn predict_aligned(
+        &self,
+        features: Vec<f32>,
+        fill: f32,
+    ) -> (f64, inference::FeatureAlignment) {
+        let expected_len = self.metadata.input_names().len();
+        let alignment = inference::FeatureAlignment::of(features.len(), expected_len);
+        let features: Vec<f64> = inference::align_features(features, expected_len, fill)
+            .into_iter()
+            .map(f64::from)
+            .collect();
+        (self.predict(&features), alignment)
+    }
+
+    /// Reject analysis options that need tree votes when the model is a regressor.
+    ///
+    /// `AnalysisOptions::include_margin` reports the share of trees voting
+    /// cheater, which is meaningless for trees predicting a score.
+    pub fn check_options(&self, options: &AnalysisOptions) -> Result<()> {
+        if self.metadata.model_kind == ModelKind::Regressor && options.include_margin {
+            return Err(anyhow::anyhow!(
+                "include_margin needs the tree votes of a classifier, but model {} is a regressor",
+                self.metadata.version
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that every input of the model can be computed from a sample payload.
+    ///
+    /// Meant as a pre-flight check at startup: a model trained with
+    /// `damage_per_shot` cannot score payloads without `damage_dealt`, which
+    /// would otherwise only surface as poor predictions or per-request errors.
+    /// Only the inputs the model reads are checked, so columns dropped by
+    /// feature selection do not need to be provided.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - A representative player record of the incoming payloads
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), IncompatibilityReport>` - Ok if every input is computable,
+    ///   otherwise the missing and unsupported inputs
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nocheat::load_model;
+    /// use nocheat::types::PlayerStats;
+    /// use std::collections::HashMap;
+    ///
+    /// let model = load_model("models/cheat_model.bin").expect("Failed to load model");
+    /// let sample = PlayerStats {
+    ///     shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+    ///     hits: HashMap::from([("rifle".to_string(), 50)]),
+    ///     ..Default::default()
+    /// };
+    /// if let Err(report) = model.check_compatibility(&sample) {
+    ///     eprintln!("Model cannot score these payloads: {}", report);
+    /// }
+    /// ```
+    pub fn check_compatibility(
+        &self,
+        sample: &PlayerStats,
+    ) -> std::result::Result<(), IncompatibilityReport> {
+        let inputs = self.metadata.input_names();
+        let reads = |column: &String| inputs.contains(column);
+        let mut report = IncompatibilityReport::default();
+
+        for name in self
+            .metadata
+            .feature_names
+            .iter()
+            .filter(|name| reads(name))
+        {
+            let computable = match name.as_str() {
+                "hit_rate" => !sample.shots_fired.is_empty(),
+                "headshot_rate" => !sample.hits.is_empty(),
+                "damage_per_shot" => sample.damage_dealt.is_some(),
+                "preaim_ratio" => {
+                    sample.preaimed_engagements.is_some() && sample.total_engagements.is_some()
+                }
+                _ => {
+                    report.unsupported.push(name.clone());
+                    continue;
+                }
+            };
+            if !computable {
+                report.missing.push(name.clone());
+            }
+        }
+        for feature in &self.metadata.categorical {
+            if feature.column_names().iter().any(reads)
+                && !sample.attributes.contains_key(&feature.field)
+            {
+                report.missing.push(format!("attributes.{}", feature.field));
+            }
+        }
+        for name in &self.metadata.optional_features {
+            if inference::is_present(sample, name).is_none() {
+                report.unsupported.push(format!("{}_present", name));
+            }
+        }
+        if let Some(hashing) = &self.metadata.feature_hashing {
+            if hashing.version != FEATURE_HASH_VERSION {
+                report
+                    .unsupported
+                    .push(format!("weapon hash version {}", hashing.version));
+            }
+        }
+        let encoded = self.metadata.encoded_names();
+        report.unsupported.extend(
+            inputs
+                .iter()
+                .filter(|name| !encoded.contains(name))
+                .cloned(),
+        );
+
+        if report.missing.is_empty() && report.unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(report)
+        }
+    }
+
+    /// Fraction of trees voting cheater (a label of 0.5 or more) for an encoded input
+    pub fn cheater_vote_share(&self, features: &[f64]) -> f64 {
+        let (positive, total) = self.cheater_votes(features);
+        if total == 0 {
+            return 0.0;
+        }
+        positive as f64 / total as f64
+    }
+
+    /// Number of trees voting cheater and total number of trees
+    fn cheater_votes(&self, features: &[f64]) -> (usize, usize) {
+        self.forest
+            .predict_individuals(features)
+            .fold((0, 0), |(positive, total), vote| {
+                (
+                    positive + (vote >= inference::VERDICT_THRESHOLD as f64) as usize,
+                    total + 1,
+                )
+            })
+    }
+
+    /// Labels the forest can predict, in ascending order
+    pub fn classes(&self) -> &[f64] {
+        &self.classes
+    }
+
+    /// Whether the model predicts more than the two cheater/legitimate classes
+    pub fn is_multiclass(&self) -> bool {
+        self.metadata.model_kind == ModelKind::Classifier && self.classes.len() > 2
+    }
+
+    /// Fraction of tree votes for every class of an encoded input.
+    ///
+    /// Classes are keyed by their label formatted as a string (e.g. `"2"`),
+    /// and every class of the model appears even when no tree voted for it.
+    pub fn class_probabilities(&self, features: &[f64]) -> BTreeMap<String, f64> {
+        let votes: Vec<f64> = self.forest.predict_individuals(features).collect();
+        self.classes
+            .iter()
+            .map(|class| {
+                let count = votes.iter().filter(|vote| *vote == class).count();
+                let share = if votes.is_empty() {
+                    0.0
+                } else {
+                    count as f64 / votes.len() as f64
+                };
+                (class.to_string(), share)
+            })
+            .collect()
+    }
+
+    /// Signed distance of an encoded input from the decision boundary.
+    ///
+    /// The fraction of trees voting cheater (a label of 0.5 or more) minus the
+    /// fraction voting legitimate, in [-1.0, 1.0]. Values near 0 are borderline
+    /// cases; the sign gives the side of the boundary.
+    pub fn predict_margin(&self, features: &[f64]) -> f64 {
+        let (positive, total) = self.cheater_votes(features);
+        if total == 0 {
+            return 0.0;
+        }
+        (2 * positive) as f64 / total as f64 - 1.0
+    }
+
+    /// Fraction of trees voting for the predicted class of an encoded input.
+    ///
+    /// Ranges from 1.0 when every tree agrees down to about 0.5 for a split
+    /// vote, so low values mark uncertain predictions.
+    pub fn confidence(&self, features: &[f64]) -> f64 {
+        let prediction = self.forest.predict(features);
+        let (agree, total) = self
+            .forest
+            .predict_individuals(features)
+            .fold((0, 0), |(agree, total), vote| {
+                (agree + (vote == prediction) as usize, total + 1)
+            });
+        if total == 0 {
+            return 0.0;
+        }
+        agree as f64 / total as f64
+    }
+}
+
+/// Inputs of a model that a sample payload cannot provide, produced by
+/// `CheatModel::check_compatibility`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncompatibilityReport {
+    /// Inputs the model reads whose source fields are absent from the sample
+    /// (e.g. `damage_per_shot` without `damage_dealt`, or `attributes.map`)
+    pub missing: Vec<String>,
+    /// Inputs this build does not know how to compute, typically from a model
+    /// trained by a newer version of the crate
+    pub unsupported: Vec<String>,
+}
+
+impl std::fmt::Display for IncompatibilityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if !self.missing.is_empty() {
+            parts.push(format!("missing inputs: {}", self.missing.join(", ")));
+        }
+        if !self.unsupported.is_empty() {
+            parts.push(format!(
+                "unsupported inputs: {}",
+                self.unsupported.join(", ")
+            ));
+        }
+        write!(
+            f,
+            "Model is incompatible with the payload ({})",
+            parts.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for IncompatibilityReport {}
+
+/// Size comparison produced by `CheatModel::serialize_compact`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactReport {
+    /// Size in bytes of the full-precision container
+    pub full_bytes: usize,
+    /// Size in bytes of the compact container
+    pub compact_bytes: usize,
+}
+
+impl CompactReport {
+    /// Fraction of the full size saved by the compact encoding
+    pub fn reduction(&self) -> f64 {
+        if self.full_bytes == 0 {
+            return 0.0;
+        }
+        1.0 - self.compact_bytes as f64 / self.full_bytes as f64
+    }
+}
+
+/// Quality metrics and settings of a training run, produced by
+/// `train_model_with_report`.
+///
+/// Serialized as JSON (see `TrainingReport::write`) it is a machine-readable
+/// artifact for CI, e.g. to fail a pipeline when the cross-validated F1 score
+/// drops below a threshold.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TrainingReport {
+    /// Version recorded in the trained model
+    pub model_version: String,
+    /// Number of training samples per class, keyed by `"clean"` and `"cheater"`
+    /// (labels at or above `TrainingConfig::label_cutoff` count as cheaters)
+    pub class_counts: BTreeMap<String, usize>,
+    /// Names of the model's input columns, in input order
+    pub feature_names: Vec<String>,
+    /// Hyperparameters the model was trained with
+    pub hyperparameters: Hyperparameters,
+    /// Cross-validated metrics, when cross-validation was run
+    pub cross_validation: Option<CrossValidation>,
+    /// Share of the forest's splits on each input (see `CheatModel::feature_importances`)
+    pub feature_importances: BTreeMap<String, f64>,
+}
+
+impl TrainingReport {
+    /// Write the report as pretty-printed JSON
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self)
+            .map_err(|e| anyhow::anyhow!("Failed to write training report: {}", e))
+    }
+}
+
+/// Hyperparameters recorded in a `TrainingReport`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Hyperparameters {
+    /// Number of decision trees in the forest
+    pub trees: usize,
+    /// Seed of the training run, if one was set
+    pub seed: Option<u64>,
+    /// Whether the forest is a classifier or a regressor
+    pub model_kind: ModelKind,
+    /// Cutoff turning soft labels into classes
+    pub label_cutoff: f64,
+    /// Whether the scores were calibrated with Platt scaling
+    pub calibrate: bool,
+    /// Importance below which input columns were dropped, if set
+    pub min_feature_importance: Option<f64>,
+}
+
+/// Metrics of k-fold cross-validation, produced by `cross_validate`.
+///
+/// Players scoring 0.5 or more count as predicted cheaters.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct CrossValidation {
+    /// Number of folds
+    pub folds: usize,
+    /// Fraction of held-out players classified correctly
+    pub accuracy: f64,
+    /// Fraction of predicted cheaters that are cheaters (0.0 when none is predicted)
+    pub precision: f64,
+    /// Fraction of cheaters that are predicted cheaters (0.0 when there is none)
+    pub recall: f64,
+    /// Harmonic mean of precision and recall
+    pub f1: f64,
+}
+
+/// Verdict changes between two models, produced by `model_diff`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffReport {
+    /// Number of players compared
+    pub total: usize,
+    /// Players below 0.5 under the old model and at or above 0.5 under the new one
+    pub clean_to_flagged: usize,
+    /// Players at or above 0.5 under the old model and below 0.5 under the new one
+    pub flagged_to_clean: usize,
+    /// Mean absolute change of the suspicion score
+    pub mean_abs_score_change: f32,
+}
+
+impl DiffReport {
+    /// Number of players whose verdict flipped in either direction
+    pub fn flipped(&self) -> usize {
+        self.clean_to_flagged + self.flagged_to_clean
+    }
+}
+
+/// Compare the predictions of two models on the same players.
+///
+/// Both models score every player with the default analysis options, and a
+/// player counts as flagged when the suspicion score is at least 0.5. Use it
+/// before rolling out a retrained model to see how many verdicts would flip.
+///
+/// # Arguments
+///
+/// * `old` - The model currently in use
+/// * `new` - The candidate model
+/// * `data` - Players to score with both models
+///
+/// # Returns
+///
+/// * `Result<DiffReport>` - Counts of flipped verdicts and the mean score change
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::model::model_diff;
+/// use nocheat::{generate_synthetic_dataset, load_model};
+///
+/// let old = load_model("models/cheat_model.bin").expect("Failed to load model");
+/// let new = load_model("models/retrained.bin").expect("Failed to load model");
+/// let (players, _) = generate_synthetic_dataset();
+///
+/// let report = model_diff(&old, &new, &players).expect("Diff failed");
+/// println!("{} of {} verdicts flipped", report.flipped(), report.total);
+/// ```
+pub fn model_diff(old: &CheatModel, new: &CheatModel, data: &[PlayerStats]) -> Result<DiffReport> {
+    let options = AnalysisOptions::default();
+    let mut report = DiffReport {
+        total: data.len(),
+        clean_to_flagged: 0,
+        flagged_to_clean: 0,
+        mean_abs_score_change: 0.0,
+    };
+
+    let mut total_change = 0.0;
+    for stats in data {
+        let row = FeatureRow::from_stats(stats);
+        let old_score = inference::score_row(old, stats, &row, &options)?.suspicion_score;
+        let new_score = inference::score_row(new, stats, &row, &options)?.suspicion_score;
+
+        match (
+            old_score >= inference::VERDICT_THRESHOLD,
+            new_score >= inference::VERDICT_THRESHOLD,
+        ) {
+            (false, true) => report.clean_to_flagged += 1,
+            (true, false) => report.flagged_to_clean += 1,
+            _ => {}
+        }
+        total_change += (new_score - old_score).abs();
+    }
+    if !data.is_empty() {
+        report.mean_abs_score_change = total_change / data.len() as f32;
+    }
+    Ok(report)
+}
+
+/// Write a container header: magic, metadata length and metadata JSON
+fn write_metadata<W: Write>(
+    writer: &mut W,
+    magic: &[u8; 8],
+    metadata: &ModelMetadata,
+) -> Result<()> {
+    let json = serde_json::to_vec(metadata)?;
+    writer.write_all(magic)?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&json)?;
+    Ok(())
+}
+
+/// Read the metadata following a container magic, returning it with the remaining bytes
+fn read_metadata(rest: &[u8]) -> Result<(ModelMetadata, &[u8])> {
+    if rest.len() < 4 {
+        return Err(anyhow::anyhow!("Truncated model header"));
+    }
+    let (len, rest) = rest.split_at(4);
+    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    if rest.len() < len {
+        return Err(anyhow::anyhow!("Truncated model metadata"));
+    }
+    let (json, payload) = rest.split_at(len);
+    let metadata = serde_json::from_slice(json)
+        .map_err(|e| anyhow::anyhow!("Invalid model metadata: {}", e))?;
+    Ok((metadata, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomforest::criterion::Gini;
+    use randomforest::table::TableBuilder;
+    use std::collections::HashMap;
+
+    fn tiny_forest() -> RandomForestClassifier {
+        let mut table_builder = TableBuilder::new();
+        for _ in 0..5 {
+            table_builder.add_row(&[0.5, 0.2], 0.0).unwrap();
+            table_builder.add_row(&[0.95, 0.7], 1.0).unwrap();
+        }
+        RandomForestClassifier::fit(Gini, table_builder.build().unwrap())
+    }
+
+    #[test]
+    fn test_compatibility_reports_missing_inputs() {
+        let mut feature_names = ModelMetadata::legacy().feature_names;
+        feature_names.push("damage_per_shot".to_string());
+        let metadata = ModelMetadata {
+            feature_names,
+            categorical: vec![CategoricalFeature {
+                field: "game_mode".to_string(),
+                categories: vec!["ranked".to_string()],
+            }],
+            ..ModelMetadata::legacy()
+        };
+        let model = CheatModel::new(tiny_forest(), metadata.clone());
+        let mut sample = PlayerStats {
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), 50)]),
+            ..Default::default()
+        };
+
+        let report = model.check_compatibility(&sample).unwrap_err();
+        assert_eq!(
+            report.missing,
+            vec!["damage_per_shot", "attributes.game_mode"]
+        );
+        assert!(report.unsupported.is_empty());
+        assert!(report.to_string().contains("damage_per_shot"));
+
+        sample.damage_dealt = Some(2500);
+        sample
+            .attributes
+            .insert("game_mode".to_string(), "ranked".to_string());
+        assert_eq!(model.check_compatibility(&sample), Ok(()));
+
+        let mut newer = metadata;
+        newer.feature_names.push("aim_smoothness".to_string());
+        let report = CheatModel::new(tiny_forest(), newer)
+            .check_compatibility(&sample)
+            .unwrap_err();
+        assert_eq!(report.unsupported, vec!["aim_smoothness"]);
+    }
+
+    #[test]
+    fn test_container_roundtrip() {
+        let metadata = ModelMetadata {
+            version: "test-1".to_string(),
+            categorical: vec![CategoricalFeature {
+                field: "game_mode".to_string(),
+                categories: vec!["casual".to_string(), "ranked".to_string()],
+            }],
+            ..ModelMetadata::legacy()
+        };
+        let model = CheatModel::new(tiny_forest(), metadata.clone());
+
+        let mut buffer = Vec::new();
+        model.write(&mut buffer).unwrap();
+        assert!(buffer.starts_with(MAGIC));
+
+        let loaded = CheatModel::from_bytes(&buffer).unwrap();
+        assert_eq!(loaded.metadata, metadata);
+        assert_eq!(
+            loaded.metadata.input_names(),
+            vec![
+                "hit_rate",
+                "headshot_rate",
+                "game_mode_casual",
+                "game_mode_ranked"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oversized_model_is_rejected_before_reading() {
+        let path = std::env::temp_dir().join("nocheat_oversized_model.bin");
+        std::fs::write(&path, vec![0xffu8; 4096]).unwrap();
+        let limits = ModelLoadLimits {
+            max_bytes: 1024,
+            ..Default::default()
+        };
+
+        let error = CheatModel::load_with_limits(path.to_str().unwrap(), &limits).unwrap_err();
+        match error.downcast_ref::<NoCheatError>() {
+            Some(NoCheatError::ModelTooLarge { size, limit }) => {
+                assert_eq!((*size, *limit), (4096, 1024));
+            }
+            _ => panic!("unexpected error: {}", error),
+        }
+
+        // Within the limit the garbage reaches deserialization instead
+        let error = CheatModel::load(path.to_str().unwrap()).unwrap_err();
+        assert!(error.downcast_ref::<NoCheatError>().is_none());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_deep_forest_is_rejected_within_the_time_limit() {
+        // A single tree of nested splits, far deeper than any trained forest
+        let mut bytes = vec![0, 1, 0, 0, 1];
+        for _ in 0..4096 {
+            bytes.push(1);
+            bytes.extend_from_slice(&0u16.to_be_bytes());
+            bytes.extend_from_slice(&0.5f64.to_be_bytes());
+        }
+        let path = std::env::temp_dir().join("nocheat_deep_model.bin");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let error = CheatModel::load(path.to_str().unwrap()).unwrap_err();
+        assert!(error.to_string().contains("depth"), "{}", error);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_legacy_forest_loads() {
+        let mut buffer = Vec::new();
+        tiny_forest().serialize(&mut buffer).unwrap();
+
+        let model = CheatModel::from_bytes(&buffer).unwrap();
+        assert_eq!(model.metadata, ModelMetadata::legacy());
+        assert_eq!(model.predict(&[0.95, 0.7]), 1.0);
+        assert!((0.5..=1.0).contains(&model.confidence(&[0.95, 0.7])));
+
+        assert!(CheatModel::from_bytes(b"NOCHEAT\0\xff\xff").is_err());
+    }
+
+    #[test]
+    fn test_compact_model_matches_full_precision() {
+        let (training_data, labels) = crate::generate_synthetic_dataset();
+        let config = crate::options::TrainingConfig {
+            trees: 50,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        crate::train_model_to_writer(training_data, labels, &config, &mut buffer).unwrap();
+        let model = CheatModel::from_bytes(&buffer).unwrap();
+
+        let mut compact = Vec::new();
+        let report = model.serialize_compact(&mut compact).unwrap();
+        assert_eq!(report.full_bytes, buffer.len());
+        assert_eq!(report.compact_bytes, compact.len());
+        assert!(report.reduction() > 0.5, "{:?}", report);
+
+        let restored = CheatModel::deserialize_compact(&compact).unwrap();
+        assert_eq!(restored.metadata, model.metadata);
+        // The generic loader recognizes compact containers too
+        assert!(CheatModel::from_bytes(&compact).is_ok());
+
+        // Compare predictions over a grid of feature values
+        let mut total_diff = 0.0;
+        let mut count = 0;
+        for i in 0..=40 {
+            for j in 0..=40 {
+                let features = [i as f64 / 40.0, j as f64 / 40.0];
+                total_diff += (model.predict(&features) - restored.predict(&features)).abs();
+                count += 1;
+            }
+        }
+        assert!(total_diff / count as f64 <= 0.01);
+    }
+
+    #[test]
+    fn test_model_diff() {
+        let (players, labels) = crate::generate_synthetic_dataset();
+        let train = |labels: Vec<f64>| {
+            let config = crate::options::TrainingConfig {
+                trees: 10,
+                seed: Some(7),
+                ..Default::default()
+            };
+            let mut buffer = Vec::new();
+            crate::train_model_to_writer(players.clone(), labels, &config, &mut buffer).unwrap();
+            CheatModel::from_bytes(&buffer).unwrap()
+        };
+        let model = train(labels.clone());
+
+        let same = model_diff(&model, &model, &players).unwrap();
+        assert_eq!(same.total, 100);
+        assert_eq!(same.flipped(), 0);
+        assert_eq!(same.mean_abs_score_change, 0.0);
+
+        // A model trained on inverted labels flips every verdict
+        let inverted = train(labels.iter().map(|l| 1.0 - l).collect());
+        let diff = model_diff(&model, &inverted, &players).unwrap();
+        assert_eq!(diff.clean_to_flagged, 50);
+        assert_eq!(diff.flagged_to_clean, 50);
+        assert_eq!(diff.mean_abs_score_change, 1.0);
+    }
+
+    #[test]
+    fn test_hashed_weapons_have_fixed_width() {
+        use std::collections::HashMap;
+
+        let metadata = ModelMetadata {
+            feature_hashing: Some(FeatureHashing::new(4, 7)),
+            ..ModelMetadata::legacy()
+        };
+        let player = |weapons: &[(&str, u32, u32)]| PlayerStats {
+            shots_fired: weapons
+                .iter()
+                .map(|(name, shots, _)| (name.to_string(), *shots))
+                .collect::<HashMap<_, _>>(),
+            hits: weapons
+                .iter()
+                .map(|(name, _, hits)| (name.to_string(), *hits))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        };
+        let rifles = player(&[("rifle", 100, 50), ("pistol", 20, 10)]);
+        let others = player(&[("sniper", 10, 9), ("smg", 200, 60), ("shotgun", 30, 12)]);
+
+        for stats in [&rifles, &others] {
+            let row = FeatureRow::from_stats(stats);
+            let encoded = metadata.encode(stats, &row).unwrap();
+            assert_eq!(encoded.len(), 2 + 4);
+            assert_eq!(encoded, metadata.encode(stats, &row).unwrap());
+            let bucket_total: f64 = encoded[2..].iter().sum();
+            let rate_total: f64 = stats
+                .shots_fired
+                .iter()
+                .map(|(weapon, &shots)| stats.hits[weapon] as f64 / shots as f64)
+                .sum();
+            assert!((bucket_total - rate_total).abs() < 1e-12);
+        }
+
+        let mut buffer = Vec::new();
+        CheatModel::new(tiny_forest(), metadata.clone())
+            .write(&mut buffer)
+            .unwrap();
+        let loaded = CheatModel::from_bytes(&buffer).unwrap();
+        assert_eq!(loaded.metadata.feature_hashing, metadata.feature_hashing);
+        assert_eq!(loaded.metadata.input_names()[2..].len(), 4);
+    }
+
+    #[test]
+    fn test_presence_indicators_are_encoded_and_persisted() {
+        let metadata = ModelMetadata {
+            optional_features: vec!["shot_timestamps_ms".to_string()],
+            ..ModelMetadata::legacy()
+        };
+        let without_timing = PlayerStats::default();
+        let with_timing = PlayerStats {
+            shot_timestamps_ms: Some(vec![0, 100, 200].into()),
+            ..Default::default()
+        };
+        let row = FeatureRow::from_stats(&without_timing);
+        assert_eq!(metadata.encode(&without_timing, &row).unwrap()[2], 0.0);
+        assert_eq!(metadata.encode(&with_timing, &row).unwrap()[2], 1.0);
+
+        let mut buffer = Vec::new();
+        CheatModel::new(tiny_forest(), metadata.clone())
+            .write(&mut buffer)
+            .unwrap();
+        let loaded = CheatModel::from_bytes(&buffer).unwrap();
+        assert_eq!(
+            loaded.metadata.input_names(),
+            vec!["hit_rate", "headshot_rate", "shot_timestamps_ms_present"]
+        );
+    }
+
+    #[test]
+    fn test_margin_ranks_strong_cheater_above_borderline() {
+        let model = CheatModel::from_forest(tiny_forest());
+        let strong = model.predict_margin(&[0.95, 0.7]);
+        let borderline = model.predict_margin(&[0.8, 0.3]);
+        assert!(strong > 0.0);
+        assert!(strong > borderline);
+        assert!(model.predict_margin(&[0.5, 0.2]) < 0.0);
+    }
+
+    #[test]
+    fn test_dump_trees_uses_feature_names() {
+        let metadata = ModelMetadata {
+            selected_inputs: Some(vec!["hit_rate".to_string(), "headshot_ratio".to_string()]),
+            ..ModelMetadata::legacy()
+        };
+        let model = CheatModel::new(tiny_forest(), metadata);
+
+        let mut dump = Vec::new();
+        model.dump_trees(&mut dump, 2).unwrap();
+        let dump = String::from_utf8(dump).unwrap();
+        assert!(dump.starts_with("tree 0:\n  if "));
+        assert!(dump.contains("hit_rate <= ") || dump.contains("headshot_ratio <= "));
+        assert!(dump.contains("predict 1"));
+        assert!(!dump.contains("tree 2:"));
+        assert!(dump.ends_with(" more trees\n"));
+    }
+}
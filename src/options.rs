@@ -0,0 +1,870 @@
+use anyhow::Result;
+use randomforest::{RandomForestClassifierOptions, RandomForestRegressorOptions};
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::inference::FeatureCache;
+use crate::model::{FeatureHashing, ModelKind};
+use crate::types::PlayerStats;
+
+/// Floating-point precision used to compute the engineered features.
+///
+/// The model always receives `f64` inputs. `F32` computes the ratios in `f32`
+/// and widens them, matching models trained by earlier versions of the crate;
+/// `F64` skips the `f32` round-trip, which keeps full precision for large shot
+/// counts and gives identical features on every platform. Analyze with the
+/// precision the model was trained with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FeaturePrecision {
+    /// Compute ratios in `f32` and widen them to `f64`
+    #[default]
+    F32,
+    /// Compute ratios directly in `f64`
+    F64,
+}
+
+/// How the features of model-scored players handle a zero denominator (no
+/// shots, or no hits).
+///
+/// `Guard` gives 0.0 for a zero denominator and the exact ratio otherwise.
+/// `Epsilon` adds a small constant to every denominator instead
+/// (`hits / (shots + eps)`), trading the branch for a slight downward bias of
+/// every ratio; keep it small relative to typical counts. With `Guard`, every
+/// player with shots and hits gets the same features as during training.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::options::ZeroDivision;
+///
+/// assert_eq!(ZeroDivision::Guard.ratio(5.0, 0.0), 0.0);
+/// assert_eq!(ZeroDivision::Guard.ratio(50.0, 100.0), 0.5);
+/// assert!(ZeroDivision::Epsilon(1e-3).ratio(50.0, 100.0) < 0.5);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ZeroDivision {
+    /// 0.0 when the denominator is zero
+    #[default]
+    Guard,
+    /// Add this constant to every denominator
+    Epsilon(f64),
+}
+
+impl ZeroDivision {
+    /// `numerator / denominator` under this strategy
+    pub fn ratio(self, numerator: f64, denominator: f64) -> f64 {
+        match self {
+            ZeroDivision::Guard if denominator == 0.0 => 0.0,
+            ZeroDivision::Guard => numerator / denominator,
+            ZeroDivision::Epsilon(eps) => numerator / (denominator + eps),
+        }
+    }
+
+    /// `numerator / denominator` under this strategy, computed in `f32`
+    pub fn ratio_f32(self, numerator: f32, denominator: f32) -> f32 {
+        match self {
+            ZeroDivision::Guard if denominator == 0.0 => 0.0,
+            ZeroDivision::Guard => numerator / denominator,
+            ZeroDivision::Epsilon(eps) => numerator / (denominator + eps as f32),
+        }
+    }
+}
+
+/// How `balance_dataset` equalizes the number of cheaters and legitimate players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceStrategy {
+    /// Randomly drop rows of the majority class
+    Undersample,
+    /// Randomly duplicate rows of the minority class
+    Oversample,
+}
+
+/// Options controlling how player statistics are analyzed.
+///
+/// The defaults reproduce the behavior of `analyze_stats`.
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyze_stats_with_options;
+/// use nocheat::options::AnalysisOptions;
+///
+/// // Keep the raw model output instead of clamping it to [0, 1]
+/// let options = AnalysisOptions {
+///     clamp_scores: false,
+///     ..Default::default()
+/// };
+///
+/// let response = analyze_stats_with_options(vec![], &options).expect("Analysis failed");
+/// assert!(response.results.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    /// Clamp every `suspicion_score` to the [0.0, 1.0] range.
+    ///
+    /// Enabled by default. Disable it to receive the raw model output, which may
+    /// fall outside that range for regression-style or multi-class forests.
+    pub clamp_scores: bool,
+    /// Thresholds used by the rule-based flags
+    pub thresholds: Thresholds,
+    /// Score players with the `Analyzable` heuristics when no model can be loaded.
+    ///
+    /// Disabled by default, in which case a missing or invalid model is an
+    /// error. When enabled, every result produced without a model carries the
+    /// `HeuristicFallback` flag.
+    pub heuristic_fallback: bool,
+    /// Compare the model's verdict with `PlayerStats::training_label` when present.
+    ///
+    /// Disabled by default so production traffic never needs labels. When
+    /// enabled, a player whose label disagrees with the verdict (suspicion
+    /// score at or above 0.5 means cheater) gets the `LabelMismatch` flag,
+    /// which is useful to validate a model against a labeled holdout set.
+    pub check_labels: bool,
+    /// Precision of the feature computation
+    pub feature_precision: FeaturePrecision,
+    /// Known-legitimate players (e.g. pros and content creators) that are never scored.
+    ///
+    /// Whitelisted players skip inference and get a suspicion score of 0.0
+    /// with the single flag `Whitelisted`.
+    pub whitelist: HashSet<String>,
+    /// Treat a hit rate above 1.0 as a confirmed cheat instead of scoring it.
+    ///
+    /// A weapon cannot register more hits than shots fired, so such stats can
+    /// only come from client-side manipulation. Disabled by default. When
+    /// enabled, these players skip the model and get a suspicion score of 1.0
+    /// with the `ImpossibleHitRate` flag.
+    pub flag_impossible_hit_rate: bool,
+    /// Run the model once per distinct feature vector of a batch.
+    ///
+    /// Enabled by default. Players with identical model inputs (common for
+    /// bot accounts) share a single prediction, which gives exactly the same
+    /// results as predicting every player separately.
+    pub dedupe_predictions: bool,
+    /// Report `PlayerResult::margin` for every model-scored player.
+    ///
+    /// Disabled by default. The margin ranks borderline cases that share the
+    /// same suspicion score.
+    pub include_margin: bool,
+    /// Number of players analyzed between two cancellation checks of
+    /// `Analyzer::analyze_cancellable`
+    pub chunk_size: usize,
+    /// Maximum number of threads scoring the players of a batch; `None` uses
+    /// every available core. Results are identical either way.
+    pub max_threads: Option<usize>,
+    /// Handling of zero denominators in the features of model-scored players
+    pub zero_division: ZeroDivision,
+    /// Suspicion score at or above which `PlayerResult::predicted_label` is
+    /// true (cheater)
+    pub label_threshold: f32,
+    /// Merge weapons whose names only differ in case or surrounding whitespace
+    /// before analysis (see `PlayerStats::normalize_weapons`).
+    ///
+    /// Disabled by default. Models with hashed weapon features should be
+    /// trained on stats normalized the same way.
+    pub normalize_weapons: bool,
+    /// Total shots below which a player's `PlayerResult::verdict` is
+    /// `Insufficient` regardless of the score. Defaults to 10.
+    pub min_shots: u32,
+    /// Performance expected by skill rating, used to compute
+    /// `PlayerResult::skill_adjusted_score`
+    pub skill_curve: SkillCurve,
+    /// Raise `TeamOutlier` on players whose suspicion score exceeds the mean
+    /// score of their teammates by more than this margin; disabled when
+    /// `None` (the default). See `collusion::flag_team_outliers`.
+    pub team_outlier_margin: Option<f32>,
+    /// Maximum number of distinct weapons (see `PlayerStats::weapon_count`)
+    /// a player may report; `None` disables the limit.
+    ///
+    /// A batch containing a player above the limit is rejected before any
+    /// per-weapon processing, so crafted input with thousands of fake weapon
+    /// keys cannot blow up feature computation. Defaults to 1024.
+    pub max_weapons: Option<usize>,
+    /// Feature rows shared between analyses, so players whose statistics did
+    /// not change skip feature computation; disabled when `None` (the
+    /// default). See `inference::FeatureCache` for its memory bounds.
+    pub feature_cache: Option<Arc<FeatureCache>>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            clamp_scores: true,
+            thresholds: Thresholds::default(),
+            heuristic_fallback: false,
+            check_labels: false,
+            feature_precision: FeaturePrecision::F32,
+            whitelist: HashSet::new(),
+            flag_impossible_hit_rate: false,
+            dedupe_predictions: true,
+            include_margin: false,
+            chunk_size: 10_000,
+            max_threads: None,
+            label_threshold: 0.5,
+            normalize_weapons: false,
+            min_shots: 10,
+            zero_division: ZeroDivision::Guard,
+            max_weapons: Some(1024),
+            skill_curve: SkillCurve::default(),
+            team_outlier_margin: None,
+            feature_cache: None,
+        }
+    }
+}
+
+/// Performance expected from a player of a given skill rating, relative to an
+/// average player.
+///
+/// Elite stats are normal for top-ranked players, so their suspicion score is
+/// divided by the expected performance of their rating. The curve is piecewise
+/// linear through `points`, sorted by rating, and constant before the first
+/// and after the last point. The default expects average performance (1.0)
+/// up to a rating of 1000, rising linearly to twice that at 3000.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::options::SkillCurve;
+///
+/// let curve = SkillCurve::default();
+/// assert_eq!(curve.expected(500.0), 1.0);
+/// assert_eq!(curve.expected(2000.0), 1.5);
+/// assert_eq!(curve.expected(5000.0), 2.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkillCurve {
+    /// `(rating, expected performance)` points, sorted by rating; expected
+    /// performances must be positive
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Default for SkillCurve {
+    fn default() -> Self {
+        Self {
+            points: vec![(1000.0, 1.0), (3000.0, 2.0)],
+        }
+    }
+}
+
+impl SkillCurve {
+    /// Expected performance at `rating`; 1.0 when the curve has no points
+    pub fn expected(&self, rating: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return 1.0;
+        };
+        if rating <= first.0 {
+            return first.1;
+        }
+        for pair in self.points.windows(2) {
+            let ((r0, e0), (r1, e1)) = (pair[0], pair[1]);
+            if rating <= r1 {
+                if r1 <= r0 {
+                    return e1;
+                }
+                return e0 + (e1 - e0) * (rating - r0) / (r1 - r0);
+            }
+        }
+        last.1
+    }
+}
+
+/// Read a whitelist file with one player_id per line.
+///
+/// Surrounding whitespace is trimmed, and blank lines and lines starting
+/// with `#` are ignored.
+///
+/// # Arguments
+///
+/// * `path` - Path to the whitelist file
+///
+/// # Returns
+///
+/// * `Result<HashSet<String>>` - The whitelisted player_ids
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::options::{load_whitelist, AnalysisOptions};
+///
+/// let options = AnalysisOptions {
+///     whitelist: load_whitelist("whitelist.txt").expect("Failed to read whitelist"),
+///     ..Default::default()
+/// };
+/// ```
+pub fn load_whitelist(path: &str) -> Result<HashSet<String>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Thresholds controlling when rule-based flags are raised.
+#[derive(Debug, Clone)]
+pub struct Thresholds {
+    /// Hit rate above which `HighHitRate` is raised
+    pub high_hit_rate: f32,
+    /// Headshot rate above which the heuristic fallback counts a check as fired;
+    /// also the threshold of weapon classes missing from `headshot_rate_by_class`
+    pub high_headshot_rate: f32,
+    /// Headshot rate thresholds per weapon class (e.g. `"sniper"`), used
+    /// instead of `high_headshot_rate` for players reporting
+    /// `PlayerStats::weapon_headshots`, which raise `HighHeadshotRate` with or
+    /// without a model. Headshots are common with snipers and
+    /// rare with shotguns, so one threshold cannot fit every weapon.
+    pub headshot_rate_by_class: HashMap<String, f32>,
+    /// Class of each weapon (e.g. `"awp"` → `"sniper"`); a weapon missing
+    /// here is a class of its own
+    pub weapon_classes: HashMap<String, String>,
+    /// Angular velocity (degrees per second) above which a view change counts as a snap
+    pub aim_snap_deg_per_sec: f32,
+    /// Window (milliseconds) before a hit in which snaps are looked for
+    pub aim_snap_window_ms: u64,
+    /// Number of snapped hits needed to raise `AimSnap`
+    pub aim_snap_min_events: usize,
+    /// Coefficient of variation of input intervals below which `RoboticCadence` is raised
+    pub robotic_cadence_max_cv: f32,
+    /// Number of input intervals needed before the cadence is evaluated
+    pub robotic_cadence_min_intervals: usize,
+    /// Damage per shot above which `HighDamageEfficiency` is raised.
+    ///
+    /// Depends on the weapon damage of the game; tune it to the damage a
+    /// skilled legitimate player deals per shot.
+    pub high_damage_per_shot: f32,
+    /// Fraction of pre-aimed engagements above which `PreAim` is raised.
+    ///
+    /// Skilled players pre-aim common angles, but only wallhacks put the
+    /// crosshair on most enemies before they are visible.
+    pub high_preaim_ratio: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            high_hit_rate: 0.8,
+            high_headshot_rate: 0.6,
+            headshot_rate_by_class: HashMap::new(),
+            weapon_classes: HashMap::new(),
+            aim_snap_deg_per_sec: 3600.0,
+            aim_snap_window_ms: 100,
+            aim_snap_min_events: 3,
+            robotic_cadence_max_cv: 0.05,
+            robotic_cadence_min_intervals: 20,
+            high_damage_per_shot: 60.0,
+            high_preaim_ratio: 0.6,
+        }
+    }
+}
+
+/// Region and platform of the players a segment-specific model and thresholds
+/// apply to (see `AnalyzerBuilder::segment`).
+///
+/// `None` matches any value, so `Segment::platform("console")` covers console
+/// players of every region. A player is routed to the most specific matching
+/// segment: region and platform first, then platform only, then region only.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Segment {
+    /// Region the segment applies to, or `None` for every region
+    pub region: Option<String>,
+    /// Platform the segment applies to, or `None` for every platform
+    pub platform: Option<String>,
+}
+
+impl Segment {
+    /// Segment of one platform in one region
+    pub fn new(region: &str, platform: &str) -> Self {
+        Self {
+            region: Some(region.to_string()),
+            platform: Some(platform.to_string()),
+        }
+    }
+
+    /// Segment of every platform in a region
+    pub fn region(region: &str) -> Self {
+        Self {
+            region: Some(region.to_string()),
+            platform: None,
+        }
+    }
+
+    /// Segment of a platform in every region
+    pub fn platform(platform: &str) -> Self {
+        Self {
+            region: None,
+            platform: Some(platform.to_string()),
+        }
+    }
+
+    /// Segments a player may be routed to, from the most to the least specific
+    pub(crate) fn candidates(stats: &PlayerStats) -> [Segment; 3] {
+        [
+            Segment {
+                region: stats.region.clone(),
+                platform: stats.platform.clone(),
+            },
+            Segment {
+                region: None,
+                platform: stats.platform.clone(),
+            },
+            Segment {
+                region: stats.region.clone(),
+                platform: None,
+            },
+        ]
+    }
+}
+
+/// Laplace noise making published aggregate counts epsilon-differentially private,
+/// see `AnalysisResponse::noisy_flag_histogram`.
+///
+/// `epsilon` is the privacy budget spent by one release: smaller values add
+/// more noise and protect individual players better. Budgets add up, so
+/// publishing k reports that include the same player spends `k * epsilon` of
+/// that player's privacy. Values from 0.1 to 1.0 are common for shared
+/// statistics.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::options::LaplaceNoise;
+///
+/// let noise = LaplaceNoise {
+///     epsilon: 0.5,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct LaplaceNoise {
+    /// Privacy budget of one release; must be positive. Defaults to 1.0.
+    pub epsilon: f64,
+    /// Seed of the noise, for tests only: published counts must use fresh
+    /// noise (`None`, the default), as anyone knowing the seed can remove it
+    pub seed: Option<u64>,
+}
+
+impl Default for LaplaceNoise {
+    fn default() -> Self {
+        Self {
+            epsilon: 1.0,
+            seed: None,
+        }
+    }
+}
+
+/// How `AnalysisResponse::to_json` writes `PlayerResult::flags`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlagEncoding {
+    /// An array of flag names (the default)
+    #[default]
+    Names,
+    /// A single integer with the bit `Flag::bit` set for every built-in
+    /// flag; flags not built into the crate are dropped
+    Bitmask,
+}
+
+/// Formatting of the JSON written by `AnalysisResponse::to_json`.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::options::{FlagEncoding, JsonOutput};
+///
+/// let output = JsonOutput {
+///     flags: FlagEncoding::Bitmask,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JsonOutput {
+    /// Encoding of the flags of every result
+    pub flags: FlagEncoding,
+    /// Indent the JSON over several lines for debugging; compact (the default)
+    /// otherwise
+    pub pretty: bool,
+}
+
+/// Bounds on loading a model file, guarding against huge or corrupt files.
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::load_model_with_limits;
+/// use nocheat::options::ModelLoadLimits;
+///
+/// let limits = ModelLoadLimits {
+///     max_bytes: 16 * 1024 * 1024,
+///     ..Default::default()
+/// };
+/// let model = load_model_with_limits("models/cheat_model.bin", &limits)
+///     .expect("Failed to load model");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModelLoadLimits {
+    /// Largest model file accepted, in bytes; larger files fail with
+    /// `NoCheatError::ModelTooLarge` before they are read
+    pub max_bytes: u64,
+    /// Longest time deserialization may take before failing with
+    /// `NoCheatError::ModelLoadTimeout`; `None` waits indefinitely
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ModelLoadLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 256 * 1024 * 1024,
+            timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+/// Retry schedule for operations that can fail transiently, such as loading a
+/// model file that is still being written.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::options::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy {
+///     attempts: 3,
+///     initial_backoff: Duration::from_millis(1),
+/// };
+///
+/// let mut calls = 0;
+/// let value = policy
+///     .run(|| {
+///         calls += 1;
+///         if calls < 2 {
+///             Err(anyhow::anyhow!("file is being written"))
+///         } else {
+///             Ok(42)
+///         }
+///     })
+///     .unwrap();
+/// assert_eq!((value, calls), (42, 2));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub attempts: u32,
+    /// Wait before the first retry; doubled after every further failure
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run `op` until it succeeds or every attempt has failed.
+    ///
+    /// Sleeps between attempts with exponential backoff. When every attempt
+    /// fails, the returned error lists the error of each attempt in order.
+    pub fn run<T>(&self, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut failures = Vec::new();
+        let mut backoff = self.initial_backoff;
+        for attempt in 1..=self.attempts.max(1) {
+            match op() {
+                Ok(value) => return Ok(value),
+                Err(e) => failures.push(format!("attempt {}: {}", attempt, e)),
+            }
+            if attempt < self.attempts {
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+        }
+        Err(anyhow::anyhow!(
+            "Failed after {} attempts ({})",
+            failures.len(),
+            failures.join("; ")
+        ))
+    }
+}
+
+/// Roles of the columns of a tabular training file (see
+/// `train_model_from_dataframe` and `train_model_from_csv`).
+///
+/// Each row of the table is one player. Feature columns hold precomputed
+/// features and must be features nocheat can compute from `PlayerStats` at
+/// analysis time (see `inference::FEATURE_NAMES` and
+/// `inference::EXTRA_FEATURES`); other columns, such as match metadata, are
+/// ignored.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::options::SchemaSpec;
+///
+/// let schema = SchemaSpec {
+///     label_col: "is_cheater".to_string(),
+///     ..Default::default()
+/// };
+/// assert!(schema
+///     .validate(&["player_id", "hit_rate", "headshot_rate", "is_cheater"])
+///     .is_ok());
+/// assert!(schema.validate(&["player_id", "hit_rate", "headshot_rate"]).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaSpec {
+    /// Columns fed to the model, in input order
+    pub feature_cols: Vec<String>,
+    /// Column of the labels in [0, 1] (1.0 for cheaters)
+    pub label_col: String,
+    /// Column identifying the player, used in error messages; `None` when the
+    /// table has no such column
+    pub id_col: Option<String>,
+}
+
+impl Default for SchemaSpec {
+    fn default() -> Self {
+        Self {
+            feature_cols: crate::inference::FEATURE_NAMES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            label_col: "label".to_string(),
+            id_col: Some("player_id".to_string()),
+        }
+    }
+}
+
+impl SchemaSpec {
+    /// Check the schema against the column names of a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - Names of the columns of the table
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An error listing every column of the schema missing
+    ///   from `columns`, or naming the first feature column that cannot be
+    ///   computed at analysis time
+    pub fn validate(&self, columns: &[&str]) -> Result<()> {
+        if self.feature_cols.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Schema must name at least one feature column"
+            ));
+        }
+        let known = |name: &str| {
+            crate::inference::FEATURE_NAMES.contains(&name)
+                || crate::inference::EXTRA_FEATURES.contains(&name)
+        };
+        if let Some(name) = self.feature_cols.iter().find(|name| !known(name)) {
+            return Err(anyhow::anyhow!(
+                "Feature column '{}' is not a feature nocheat can compute when analyzing; \
+                 expected one of {}, {}",
+                name,
+                crate::inference::FEATURE_NAMES.join(", "),
+                crate::inference::EXTRA_FEATURES.join(", ")
+            ));
+        }
+        if self.feature_cols.contains(&self.label_col) {
+            return Err(anyhow::anyhow!(
+                "Column '{}' cannot be both a feature and the label",
+                self.label_col
+            ));
+        }
+
+        let roles = self
+            .feature_cols
+            .iter()
+            .map(|name| ("feature", name))
+            .chain(std::iter::once(("label", &self.label_col)))
+            .chain(self.id_col.iter().map(|name| ("id", name)));
+        let missing: Vec<String> = roles
+            .filter(|(_, name)| !columns.contains(&name.as_str()))
+            .map(|(role, name)| format!("{} column '{}'", role, name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Training table is missing the {}; its columns are: {}",
+                missing.join(", "),
+                columns.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Hyperparameters used when training a model.
+///
+/// The defaults reproduce the behavior of `train_model`.
+#[derive(Debug, Clone)]
+pub struct TrainingConfig {
+    /// Number of decision trees in the forest
+    pub trees: usize,
+    /// Seed for reproducible training; a random seed is used when `None`
+    pub seed: Option<u64>,
+    /// Fit the trees in parallel
+    pub parallel: bool,
+    /// Attributes (keys of `PlayerStats::attributes`) one-hot encoded as extra features
+    pub categorical_fields: Vec<String>,
+    /// Optional inputs (see `inference::OPTIONAL_FEATURES`) given a `{name}_present`
+    /// indicator feature, so the model can learn that their absence is informative
+    pub optional_features: Vec<String>,
+    /// Extra numeric features (see `inference::EXTRA_FEATURES`) appended after
+    /// the default ones, such as `damage_per_shot` for games reporting damage
+    pub extra_features: Vec<String>,
+    /// Drop input columns whose share of the forest's splits (see
+    /// `CheatModel::feature_importances`) is below this cutoff and retrain
+    /// without them; every column is kept when `None`
+    pub min_feature_importance: Option<f64>,
+    /// Calibrate the model with Platt scaling, so that its scores are
+    /// probabilities of cheating rather than majority votes. Requires binary
+    /// labels and trains two extra forests for out-of-fold scores. Warm starts
+    /// drop the calibration of the extended model.
+    pub calibrate: bool,
+    /// Hash the per-weapon hit rates into a fixed number of extra input
+    /// columns, stored with the model (see `model::FeatureHashing`)
+    pub feature_hashing: Option<FeatureHashing>,
+    /// Cutoff turning soft labels (a confidence in [0, 1] that the player
+    /// cheats, e.g. 0.3 for "probably clean") into classes: labels at or above
+    /// it train as cheaters (1.0), labels below it as legitimate (0.0)
+    pub label_cutoff: f64,
+    /// Train a classifier on class labels, or a regressor predicting the
+    /// labels as scores; regressors keep soft labels as they are and cannot
+    /// be calibrated
+    pub model_kind: ModelKind,
+    /// Version recorded in the model container; derived from the training time when `None`
+    pub model_version: Option<String>,
+    /// Precision of the feature computation
+    pub feature_precision: FeaturePrecision,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        Self {
+            trees: 100,
+            seed: None,
+            parallel: false,
+            categorical_fields: Vec::new(),
+            optional_features: Vec::new(),
+            extra_features: Vec::new(),
+            min_feature_importance: None,
+            calibrate: false,
+            feature_hashing: None,
+            label_cutoff: 0.5,
+            model_kind: ModelKind::Classifier,
+            model_version: None,
+            feature_precision: FeaturePrecision::F32,
+        }
+    }
+}
+
+impl TrainingConfig {
+    /// Translate the config into RandomForest options
+    pub(crate) fn forest_options(&self) -> Result<RandomForestClassifierOptions> {
+        let trees = NonZeroUsize::new(self.trees)
+            .ok_or_else(|| anyhow::anyhow!("TrainingConfig.trees must be at least 1"))?;
+
+        let mut options = RandomForestClassifierOptions::new();
+        options.trees(trees);
+        if let Some(seed) = self.seed {
+            options.seed(seed);
+        }
+        if self.parallel {
+            options.parallel();
+        }
+        Ok(options)
+    }
+
+    /// Translate the config into RandomForest regressor options
+    pub(crate) fn regressor_options(&self) -> Result<RandomForestRegressorOptions> {
+        let trees = NonZeroUsize::new(self.trees)
+            .ok_or_else(|| anyhow::anyhow!("TrainingConfig.trees must be at least 1"))?;
+
+        let mut options = RandomForestRegressorOptions::new();
+        options.trees(trees);
+        if let Some(seed) = self.seed {
+            options.seed(seed);
+        }
+        if self.parallel {
+            options.parallel();
+        }
+        Ok(options)
+    }
+
+    /// Threshold labels at `label_cutoff`, rejecting labels outside [0, 1].
+    /// Labels of a regressor are only checked, not thresholded.
+    pub(crate) fn hard_labels(&self, labels: &[f64]) -> Result<Vec<f64>> {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(index, &label)| {
+                if !(0.0..=1.0).contains(&label) {
+                    return Err(anyhow::anyhow!(
+                        "Label {} of sample {} is outside [0, 1]",
+                        label,
+                        index
+                    ));
+                }
+                Ok(match self.model_kind {
+                    ModelKind::Regressor => label,
+                    ModelKind::Classifier if label >= self.label_cutoff => 1.0,
+                    ModelKind::Classifier => 0.0,
+                })
+            })
+            .collect()
+    }
+
+    /// Version to record in the trained model container
+    pub(crate) fn version(&self) -> String {
+        self.model_version.clone().unwrap_or_else(|| {
+            let trained_at = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            format!("{}-{}", env!("CARGO_PKG_VERSION"), trained_at)
+        })
+    }
+}
+
+/// Sliding window over a player's most recent rounds.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::options::WindowConfig;
+/// use nocheat::types::PlayerStats;
+///
+/// let history: Vec<PlayerStats> = (0..5)
+///     .map(|round| PlayerStats {
+///         player_id: "player123".to_string(),
+///         headshots: round,
+///         ..Default::default()
+///     })
+///     .collect();
+///
+/// // Only rounds 2, 3 and 4 are aggregated
+/// let window = WindowConfig { size: 3 }.apply(&history).unwrap();
+/// assert_eq!(window.headshots, 2 + 3 + 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    /// Number of most recent rounds aggregated into the feature basis
+    pub size: usize,
+}
+
+impl WindowConfig {
+    /// Aggregate the most recent `size` rounds of a history ordered oldest first.
+    ///
+    /// Players with fewer than `size` rounds use all of their rounds. Returns
+    /// `None` for an empty history or a window size of 0.
+    pub fn apply(&self, history: &[PlayerStats]) -> Option<PlayerStats> {
+        if self.size == 0 || history.is_empty() {
+            return None;
+        }
+        let start = history.len().saturating_sub(self.size);
+        Some(PlayerStats::aggregate(&history[start..]))
+    }
+}
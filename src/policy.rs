@@ -0,0 +1,212 @@
+use crate::types::PlayerResult;
+
+/// z-value of the 95% confidence level used by `decision_score`
+const WILSON_Z: f32 = 1.96;
+
+/// Recommended moderation action for a player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// No action needed
+    Allow,
+    /// Queue the player for manual review
+    Review,
+    /// Evidence is strong enough to ban
+    Ban,
+}
+
+/// Combine a suspicion score with the amount of evidence behind it.
+///
+/// The score is treated as a proportion observed over `total_shots` trials and
+/// shrunk to the lower bound of its 95% Wilson score interval:
+///
+/// ```text
+/// decision = (p + z²/2n - z * sqrt(p(1 - p)/n + z²/4n²)) / (1 + z²/n)
+/// ```
+///
+/// with `p` the suspicion score, `n` the number of shots and `z = 1.96`. The
+/// fewer shots a player fired, the wider the interval and the lower the
+/// resulting score, so a 0.9 on 1000 shots outranks a 0.9 on 10 shots.
+/// Players with no shots get a decision score of 0.0.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::policy::decision_score;
+///
+/// let low_volume = decision_score(0.9, 10);
+/// let high_volume = decision_score(0.9, 1000);
+/// assert!(low_volume < high_volume);
+/// assert!(high_volume < 0.9);
+/// ```
+pub fn decision_score(suspicion: f32, total_shots: u32) -> f32 {
+    if total_shots == 0 {
+        return 0.0;
+    }
+
+    let p = suspicion.clamp(0.0, 1.0);
+    let n = total_shots as f32;
+    let z2 = WILSON_Z * WILSON_Z;
+
+    let centre = p + z2 / (2.0 * n);
+    let margin = WILSON_Z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((centre - margin) / (1.0 + z2 / n)).max(0.0)
+}
+
+/// Thresholds mapping evidence-weighted scores to moderation actions.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::policy::{Action, Policy};
+/// use nocheat::types::PlayerResult;
+///
+/// let result = PlayerResult {
+///     player_id: "player123".to_string(),
+///     suspicion_score: 0.9,
+///     flags: vec!["HighHitRate".to_string()],
+///     ..Default::default()
+/// };
+///
+/// let policy = Policy::default();
+/// assert_eq!(policy.decide(&result, 1000), Action::Ban);
+/// assert_eq!(policy.decide(&result, 10), Action::Review);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Policy {
+    /// Decision score at or above which a player is queued for review
+    pub review_threshold: f32,
+    /// Decision score at or above which a player is banned
+    pub ban_threshold: f32,
+    /// Model confidence below which `decide_with_confidence` never recommends a ban
+    pub min_ban_confidence: f32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            review_threshold: 0.5,
+            ban_threshold: 0.8,
+            min_ban_confidence: 0.8,
+        }
+    }
+}
+
+impl Policy {
+    /// Decide on an action for a player's result, weighting it by the shots behind it.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The analysis result of the player
+    /// * `total_shots` - Total shots fired by the player across all weapons
+    ///
+    /// # Returns
+    ///
+    /// * `Action` - The recommended action based on `decision_score`
+    pub fn decide(&self, result: &PlayerResult, total_shots: u32) -> Action {
+        let score = decision_score(result.suspicion_score, total_shots);
+        if score >= self.ban_threshold {
+            Action::Ban
+        } else if score >= self.review_threshold {
+            Action::Review
+        } else {
+            Action::Allow
+        }
+    }
+
+    /// Decide on an action, downgrading a ban to a review when the model is uncertain.
+    ///
+    /// Banning on a prediction the trees disagree about risks wrongful bans,
+    /// so whenever `decide` would ban but `confidence` is below
+    /// `min_ban_confidence`, the player is queued for review instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The analysis result of the player
+    /// * `total_shots` - Total shots fired by the player across all weapons
+    /// * `confidence` - Vote agreement of the model, see `CheatModel::confidence`
+    ///
+    /// # Returns
+    ///
+    /// * `Action` - The recommended action, never `Ban` on low confidence
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::policy::{Action, Policy};
+    /// use nocheat::types::PlayerResult;
+    ///
+    /// let result = PlayerResult {
+    ///     player_id: "player123".to_string(),
+    ///     suspicion_score: 0.9,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let policy = Policy::default();
+    /// assert_eq!(policy.decide_with_confidence(&result, 1000, 0.95), Action::Ban);
+    /// assert_eq!(policy.decide_with_confidence(&result, 1000, 0.55), Action::Review);
+    /// ```
+    pub fn decide_with_confidence(
+        &self,
+        result: &PlayerResult,
+        total_shots: u32,
+        confidence: f32,
+    ) -> Action {
+        match self.decide(result, total_shots) {
+            Action::Ban if confidence < self.min_ban_confidence => Action::Review,
+            action => action,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_result(score: f32) -> PlayerResult {
+        PlayerResult {
+            player_id: "player".to_string(),
+            suspicion_score: score,
+            flags: vec![],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decision_score_shrinks_low_volume() {
+        let low = decision_score(0.9, 10);
+        let high = decision_score(0.9, 1000);
+
+        assert!((low - 0.596).abs() < 1e-3, "got {}", low);
+        assert!((high - 0.880).abs() < 1e-3, "got {}", high);
+        assert_eq!(decision_score(0.9, 0), 0.0);
+    }
+
+    #[test]
+    fn test_same_score_different_decisions() {
+        let policy = Policy::default();
+        let result = make_result(0.9);
+
+        assert_eq!(policy.decide(&result, 10), Action::Review);
+        assert_eq!(policy.decide(&result, 1000), Action::Ban);
+        assert_eq!(policy.decide(&make_result(0.1), 1000), Action::Allow);
+    }
+
+    #[test]
+    fn test_low_confidence_never_bans() {
+        let policy = Policy::default();
+        let result = make_result(0.95);
+
+        assert_eq!(
+            policy.decide_with_confidence(&result, 1000, 0.9),
+            Action::Ban
+        );
+        assert_eq!(
+            policy.decide_with_confidence(&result, 1000, 0.6),
+            Action::Review
+        );
+        assert_eq!(
+            policy.decide_with_confidence(&make_result(0.1), 1000, 0.6),
+            Action::Allow
+        );
+    }
+}
@@ -0,0 +1,395 @@
+//! Protocol Buffers encoding of the analysis API.
+//!
+//! The messages below are the `prost` types of `proto/nocheat.proto`, written
+//! out by hand so the crate builds without `protoc`. Keep both in sync. JSON
+//! stays the default format; this module is only compiled with the `proto`
+//! feature.
+
+use anyhow::Result;
+use prost::Message;
+use std::collections::HashMap;
+
+use crate::analyzer::Analyzer;
+use crate::types;
+
+/// Crosshair orientation recorded when a shot was fired
+#[derive(Clone, PartialEq, Message)]
+pub struct ViewAngleSample {
+    #[prost(uint64, tag = "1")]
+    pub timestamp_ms: u64,
+    #[prost(float, tag = "2")]
+    pub yaw: f32,
+    #[prost(float, tag = "3")]
+    pub pitch: f32,
+    #[prost(bool, tag = "4")]
+    pub hit: bool,
+}
+
+/// Timestamps of the shots of one weapon type
+#[derive(Clone, PartialEq, Message)]
+pub struct Timestamps {
+    #[prost(uint64, repeated, tag = "1")]
+    pub values: Vec<u64>,
+}
+
+/// Statistics of a single player; empty repeated fields mean "no data"
+#[derive(Clone, PartialEq, Message)]
+pub struct PlayerStats {
+    #[prost(string, tag = "1")]
+    pub player_id: String,
+    #[prost(map = "string, uint32", tag = "2")]
+    pub shots_fired: HashMap<String, u32>,
+    #[prost(map = "string, uint32", tag = "3")]
+    pub hits: HashMap<String, u32>,
+    #[prost(uint32, tag = "4")]
+    pub headshots: u32,
+    #[prost(uint64, repeated, tag = "5")]
+    pub shot_timestamps_ms: Vec<u64>,
+    #[prost(double, optional, tag = "6")]
+    pub training_label: Option<f64>,
+    #[prost(message, repeated, tag = "7")]
+    pub view_angles: Vec<ViewAngleSample>,
+    #[prost(map = "string, string", tag = "8")]
+    pub attributes: HashMap<String, String>,
+    #[prost(uint64, repeated, tag = "9")]
+    pub input_timestamps_ms: Vec<u64>,
+    #[prost(string, optional, tag = "10")]
+    pub match_id: Option<String>,
+    #[prost(uint32, optional, tag = "11")]
+    pub damage_dealt: Option<u32>,
+    #[prost(string, optional, tag = "12")]
+    pub replay_path: Option<String>,
+    #[prost(float, optional, tag = "13")]
+    pub skill_rating: Option<f32>,
+    #[prost(uint32, optional, tag = "14")]
+    pub preaimed_engagements: Option<u32>,
+    #[prost(uint32, optional, tag = "15")]
+    pub total_engagements: Option<u32>,
+    #[prost(map = "string, message", tag = "16")]
+    pub weapon_shot_timestamps_ms: HashMap<String, Timestamps>,
+    #[prost(string, optional, tag = "17")]
+    pub region: Option<String>,
+    #[prost(string, optional, tag = "18")]
+    pub platform: Option<String>,
+    #[prost(double, optional, tag = "19")]
+    pub label_confidence: Option<f64>,
+    #[prost(string, optional, tag = "20")]
+    pub team: Option<String>,
+    #[prost(map = "string, uint32", tag = "21")]
+    pub weapon_headshots: HashMap<String, u32>,
+}
+
+/// Request of `analyze_proto`: the players of one round
+#[derive(Clone, PartialEq, Message)]
+pub struct PlayerStatsBatch {
+    #[prost(message, repeated, tag = "1")]
+    pub players: Vec<PlayerStats>,
+}
+
+/// Why a flag fired, see `types::FlagDetail`
+#[derive(Clone, PartialEq, Message)]
+pub struct FlagDetail {
+    #[prost(string, tag = "1")]
+    pub flag: String,
+    #[prost(float, tag = "2")]
+    pub value: f32,
+    #[prost(float, tag = "3")]
+    pub threshold: f32,
+    #[prost(string, tag = "4")]
+    pub message: String,
+}
+
+/// Part of the hit rate above the threshold contributed by a weapon
+#[derive(Clone, PartialEq, Message)]
+pub struct WeaponContribution {
+    #[prost(string, tag = "1")]
+    pub weapon: String,
+    #[prost(float, tag = "2")]
+    pub contribution: f32,
+}
+
+/// Analysis result of a single player
+#[derive(Clone, PartialEq, Message)]
+pub struct PlayerResult {
+    #[prost(string, tag = "1")]
+    pub player_id: String,
+    #[prost(float, tag = "2")]
+    pub suspicion_score: f32,
+    #[prost(string, repeated, tag = "3")]
+    pub flags: Vec<String>,
+    #[prost(uint64, optional, tag = "4")]
+    pub analyzed_at: Option<u64>,
+    #[prost(string, optional, tag = "5")]
+    pub model_version: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub source: Option<String>,
+    #[prost(map = "string, double", tag = "7")]
+    pub class_probabilities: HashMap<String, f64>,
+    #[prost(double, optional, tag = "8")]
+    pub margin: Option<f64>,
+    #[prost(message, repeated, tag = "9")]
+    pub flag_details: Vec<FlagDetail>,
+    #[prost(float, optional, tag = "10")]
+    pub skill_adjusted_score: Option<f32>,
+    #[prost(bool, tag = "11")]
+    pub predicted_label: bool,
+    #[prost(string, tag = "12")]
+    pub verdict: String,
+    #[prost(message, repeated, tag = "13")]
+    pub top_weapons: Vec<WeaponContribution>,
+}
+
+/// Response of `analyze_proto`
+#[derive(Clone, PartialEq, Message)]
+pub struct AnalysisResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: Vec<PlayerResult>,
+}
+
+/// Wrap a repeated field, treating an empty one as absent
+fn non_empty<T>(values: Vec<T>) -> Option<Vec<T>> {
+    (!values.is_empty()).then_some(values)
+}
+
+/// Shot timestamps of a player, preferring the per-weapon ones when present
+fn shot_timestamps(
+    flat: Vec<u64>,
+    per_weapon: HashMap<String, Timestamps>,
+) -> Option<types::ShotTimestamps> {
+    if per_weapon.is_empty() {
+        return non_empty(flat).map(types::ShotTimestamps::Flat);
+    }
+    let per_weapon = per_weapon
+        .into_iter()
+        .map(|(weapon, timestamps)| (weapon, timestamps.values))
+        .collect();
+    Some(types::ShotTimestamps::PerWeapon(per_weapon))
+}
+
+impl From<PlayerStats> for types::PlayerStats {
+    fn from(stats: PlayerStats) -> Self {
+        types::PlayerStats {
+            player_id: stats.player_id,
+            shots_fired: stats.shots_fired,
+            hits: stats.hits,
+            headshots: stats.headshots,
+            shot_timestamps_ms: shot_timestamps(
+                stats.shot_timestamps_ms,
+                stats.weapon_shot_timestamps_ms,
+            ),
+            input_timestamps_ms: non_empty(stats.input_timestamps_ms),
+            training_label: stats.training_label,
+            label_confidence: stats.label_confidence,
+            view_angles: non_empty(
+                stats
+                    .view_angles
+                    .into_iter()
+                    .map(|s| types::ViewAngleSample {
+                        timestamp_ms: s.timestamp_ms,
+                        yaw: s.yaw,
+                        pitch: s.pitch,
+                        hit: s.hit,
+                    })
+                    .collect(),
+            ),
+            attributes: stats.attributes.into_iter().collect(),
+            match_id: stats.match_id,
+            damage_dealt: stats.damage_dealt,
+            replay_path: stats.replay_path,
+            skill_rating: stats.skill_rating,
+            preaimed_engagements: stats.preaimed_engagements,
+            total_engagements: stats.total_engagements,
+            region: stats.region,
+            platform: stats.platform,
+            team: stats.team,
+            weapon_headshots: stats.weapon_headshots,
+        }
+    }
+}
+
+impl From<&types::PlayerStats> for PlayerStats {
+    fn from(stats: &types::PlayerStats) -> Self {
+        PlayerStats {
+            player_id: stats.player_id.clone(),
+            shots_fired: stats.shots_fired.clone(),
+            hits: stats.hits.clone(),
+            headshots: stats.headshots,
+            shot_timestamps_ms: match &stats.shot_timestamps_ms {
+                Some(types::ShotTimestamps::Flat(timestamps)) => timestamps.clone(),
+                _ => Vec::new(),
+            },
+            weapon_shot_timestamps_ms: stats
+                .shot_timestamps_ms
+                .as_ref()
+                .and_then(types::ShotTimestamps::per_weapon)
+                .into_iter()
+                .flatten()
+                .map(|(weapon, values)| {
+                    let values = values.clone();
+                    (weapon.clone(), Timestamps { values })
+                })
+                .collect(),
+            training_label: stats.training_label,
+            label_confidence: stats.label_confidence,
+            view_angles: stats
+                .view_angles
+                .iter()
+                .flatten()
+                .map(|s| ViewAngleSample {
+                    timestamp_ms: s.timestamp_ms,
+                    yaw: s.yaw,
+                    pitch: s.pitch,
+                    hit: s.hit,
+                })
+                .collect(),
+            attributes: stats
+                .attributes
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            input_timestamps_ms: stats.input_timestamps_ms.clone().unwrap_or_default(),
+            match_id: stats.match_id.clone(),
+            damage_dealt: stats.damage_dealt,
+            replay_path: stats.replay_path.clone(),
+            skill_rating: stats.skill_rating,
+            preaimed_engagements: stats.preaimed_engagements,
+            total_engagements: stats.total_engagements,
+            region: stats.region.clone(),
+            platform: stats.platform.clone(),
+            team: stats.team.clone(),
+            weapon_headshots: stats.weapon_headshots.clone(),
+        }
+    }
+}
+
+impl From<types::PlayerResult> for PlayerResult {
+    fn from(result: types::PlayerResult) -> Self {
+        PlayerResult {
+            player_id: result.player_id,
+            suspicion_score: result.suspicion_score,
+            flags: result.flags,
+            analyzed_at: result.analyzed_at,
+            model_version: result.model_version,
+            source: result.source,
+            class_probabilities: result
+                .class_probabilities
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+            margin: result.margin,
+            flag_details: result
+                .flag_details
+                .into_iter()
+                .map(|detail| FlagDetail {
+                    flag: detail.flag.to_string(),
+                    value: detail.value,
+                    threshold: detail.threshold,
+                    message: detail.message,
+                })
+                .collect(),
+            skill_adjusted_score: result.skill_adjusted_score,
+            predicted_label: result.predicted_label,
+            verdict: result.verdict.as_str().to_string(),
+            top_weapons: result
+                .top_weapons
+                .into_iter()
+                .map(|(weapon, contribution)| WeaponContribution {
+                    weapon,
+                    contribution,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Analyze a protobuf-encoded `PlayerStatsBatch` with the current model.
+///
+/// # Arguments
+///
+/// * `input` - An encoded `PlayerStatsBatch`
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>>` - An encoded `AnalysisResponse`
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::proto::{analyze_proto, AnalysisResponse, PlayerStatsBatch};
+/// use prost::Message;
+///
+/// let request = PlayerStatsBatch { players: vec![] }.encode_to_vec();
+/// let response = AnalysisResponse::decode(analyze_proto(&request).unwrap().as_slice()).unwrap();
+/// assert!(response.results.is_empty());
+/// ```
+pub fn analyze_proto(input: &[u8]) -> Result<Vec<u8>> {
+    analyze_proto_with(&Analyzer::default(), input)
+}
+
+/// Analyze a protobuf-encoded `PlayerStatsBatch` with a configured analyzer
+pub fn analyze_proto_with(analyzer: &Analyzer, input: &[u8]) -> Result<Vec<u8>> {
+    analyze_decoded(analyzer, decode_batch(input)?)
+}
+
+/// Decode the players of a protobuf-encoded `PlayerStatsBatch`
+pub(crate) fn decode_batch(input: &[u8]) -> Result<Vec<types::PlayerStats>> {
+    let batch = PlayerStatsBatch::decode(input)
+        .map_err(|e| anyhow::anyhow!("Invalid protobuf input: {}", e))?;
+    Ok(batch.players.into_iter().map(Into::into).collect())
+}
+
+/// Analyze decoded players, returning an encoded `AnalysisResponse`
+pub(crate) fn analyze_decoded(
+    analyzer: &Analyzer,
+    stats: Vec<types::PlayerStats>,
+) -> Result<Vec<u8>> {
+    let response = analyzer.analyze(stats)?;
+    Ok(AnalysisResponse {
+        results: response.results.into_iter().map(Into::into).collect(),
+    }
+    .encode_to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proto_round_trip() {
+        let (training_data, labels) = crate::generate_synthetic_dataset();
+        let config = crate::options::TrainingConfig {
+            trees: 10,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        crate::train_model_to_writer(training_data.clone(), labels, &config, &mut buffer).unwrap();
+        let analyzer = Analyzer::builder()
+            .model(crate::load_model_from_bytes(&buffer).unwrap())
+            .build()
+            .unwrap();
+
+        let request = PlayerStatsBatch {
+            players: training_data.iter().map(PlayerStats::from).collect(),
+        };
+        let decoded: Vec<types::PlayerStats> =
+            request.players.iter().cloned().map(Into::into).collect();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&training_data).unwrap()
+        );
+
+        let output = analyze_proto_with(&analyzer, &request.encode_to_vec()).unwrap();
+        let response = AnalysisResponse::decode(output.as_slice()).unwrap();
+        let expected = analyzer.analyze(training_data).unwrap();
+        assert_eq!(response.results.len(), expected.results.len());
+        for (actual, expected) in response.results.iter().zip(&expected.results) {
+            assert_eq!(actual.player_id, expected.player_id);
+            assert_eq!(actual.suspicion_score, expected.suspicion_score);
+            assert_eq!(actual.flags, expected.flags);
+            assert_eq!(actual.model_version, expected.model_version);
+        }
+
+        assert!(analyze_proto_with(&analyzer, b"\xff\xff").is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! Per-shot events loaded on demand from replay files.
+//!
+//! Pipelines that keep only counts in `PlayerStats` can point
+//! `PlayerStats::replay_path` at a replay export holding every shot of the
+//! round. `Analyzer::analyze_with_replays` loads it to enable the timing
+//! checks (`AimSnap`, `RoboticCadence`) that counts alone cannot support.
+//!
+//! A replay is a text file with one shot per line:
+//!
+//! ```text
+//! # timestamp_ms,yaw,pitch,hit
+//! 1000,12.5,-3.0,0
+//! 1050,47.0,-2.5,1
+//! ```
+//!
+//! `hit` is `1` when the shot registered a hit and `0` otherwise. Blank lines
+//! and lines starting with `#` are ignored, and shots are expected in
+//! chronological order.
+//!
+//! `replay_path` arrives with the stats from untrusted clients, so replays are
+//! only read from inside a replay root directory chosen by the server, and
+//! never beyond `MAX_REPLAY_BYTES`.
+
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::types::{PlayerStats, ViewAngleSample};
+
+/// Parse the shots of a replay.
+///
+/// # Arguments
+///
+/// * `reader` - Source of the replay lines
+///
+/// # Returns
+///
+/// * `Result<Vec<ViewAngleSample>>` - Every shot in file order, or an error
+///   naming the first malformed line
+///
+/// # Example
+///
+/// ```
+/// use nocheat::replay::parse_replay;
+///
+/// let shots = parse_replay("# timestamp_ms,yaw,pitch,hit\n1000,12.5,-3.0,0\n1050,47.0,-2.5,1\n".as_bytes()).unwrap();
+/// assert_eq!(shots.len(), 2);
+/// assert!(shots[1].hit);
+/// ```
+pub fn parse_replay<R: BufRead>(reader: R) -> Result<Vec<ViewAngleSample>> {
+    let mut shots = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || anyhow::anyhow!("Invalid replay line {}: {}", index + 1, line);
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [timestamp_ms, yaw, pitch, hit] = fields.as_slice() else {
+            return Err(invalid());
+        };
+        shots.push(ViewAngleSample {
+            timestamp_ms: timestamp_ms.parse().map_err(|_| invalid())?,
+            yaw: yaw.parse().map_err(|_| invalid())?,
+            pitch: pitch.parse().map_err(|_| invalid())?,
+            hit: match *hit {
+                "0" => false,
+                "1" => true,
+                _ => return Err(invalid()),
+            },
+        });
+    }
+    Ok(shots)
+}
+
+/// Largest replay file read, in bytes
+pub const MAX_REPLAY_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Fill the per-shot inputs of a player from its replay.
+///
+/// `replay_path` is resolved against `replay_root`, and the resolved file,
+/// after following symlinks, must be a regular file inside that directory.
+/// `shot_timestamps_ms` and `view_angles` are only set when absent, so data
+/// sent with the stats always wins over the replay. Counts are left unchanged.
+///
+/// # Arguments
+///
+/// * `stats` - The player to complete
+/// * `replay_root` - Directory holding every replay the player may reference
+///
+/// # Returns
+///
+/// * `Result<bool>` - Whether a replay was loaded; `false` when the player has
+///   no `replay_path` or the file does not exist, and an error when the file
+///   lies outside `replay_root`, exceeds `MAX_REPLAY_BYTES`, or cannot be read
+///   or parsed
+pub fn attach_replay(stats: &mut PlayerStats, replay_root: &Path) -> Result<bool> {
+    let Some(path) = stats.replay_path.as_deref() else {
+        return Ok(false);
+    };
+    let resolved = replay_root.join(path);
+    if !resolved.exists() {
+        return Ok(false);
+    }
+    let root = replay_root.canonicalize()?;
+    let resolved = resolved.canonicalize()?;
+    if !resolved.starts_with(&root) {
+        return Err(anyhow::anyhow!(
+            "Replay {} lies outside the replay root {}",
+            path,
+            root.display()
+        ));
+    }
+    let file = std::fs::File::open(&resolved)?;
+    let metadata = file.metadata()?;
+    if !metadata.is_file() {
+        return Err(anyhow::anyhow!("Replay {} is not a regular file", path));
+    }
+    // The file may grow after the size check, so never read past the limit
+    let mut bytes = Vec::new();
+    file.take(MAX_REPLAY_BYTES + 1).read_to_end(&mut bytes)?;
+    if metadata.len() > MAX_REPLAY_BYTES || bytes.len() as u64 > MAX_REPLAY_BYTES {
+        return Err(anyhow::anyhow!(
+            "Replay {} exceeds the limit of {} bytes",
+            path,
+            MAX_REPLAY_BYTES
+        ));
+    }
+    let shots = parse_replay(BufReader::new(bytes.as_slice()))
+        .map_err(|e| anyhow::anyhow!("Failed to read replay {}: {}", path, e))?;
+
+    if stats.shot_timestamps_ms.is_none() {
+        stats.shot_timestamps_ms = Some(
+            shots
+                .iter()
+                .map(|s| s.timestamp_ms)
+                .collect::<Vec<_>>()
+                .into(),
+        );
+    }
+    if stats.view_angles.is_none() {
+        stats.view_angles = Some(shots);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed_replay_names_line() {
+        let error = parse_replay("1000,1.0,2.0,1\n\n1050,1.0,oops,0\n".as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("line 3"));
+        assert!(parse_replay("1000,1.0,2.0,yes\n".as_bytes()).is_err());
+        assert!(parse_replay("1000,1.0,2.0\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_replays_outside_root_are_rejected() {
+        let dir = std::env::temp_dir().join("nocheat_replay_root_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("replays");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(dir.join("secret.replay"), "1000,1.0,2.0,1\n").unwrap();
+        std::fs::write(root.join("round.replay"), "1000,1.0,2.0,1\n").unwrap();
+
+        let mut stats = PlayerStats {
+            replay_path: Some("round.replay".to_string()),
+            ..Default::default()
+        };
+        assert!(attach_replay(&mut stats, &root).unwrap());
+
+        for path in [
+            "../secret.replay".to_string(),
+            dir.join("secret.replay").to_str().unwrap().to_string(),
+            "/dev/zero".to_string(),
+        ] {
+            let mut stats = PlayerStats {
+                replay_path: Some(path.clone()),
+                ..Default::default()
+            };
+            let error = attach_replay(&mut stats, &root).unwrap_err();
+            assert!(error.to_string().contains("outside"), "{}: {}", path, error);
+            assert!(stats.view_angles.is_none());
+        }
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_oversized_replay_is_rejected() {
+        let root = std::env::temp_dir().join("nocheat_replay_size_test");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let file = std::fs::File::create(root.join("huge.replay")).unwrap();
+        file.set_len(MAX_REPLAY_BYTES + 1).unwrap();
+
+        let mut stats = PlayerStats {
+            replay_path: Some("huge.replay".to_string()),
+            ..Default::default()
+        };
+        let error = attach_replay(&mut stats, &root).unwrap_err();
+        assert!(error.to_string().contains("exceeds"), "{}", error);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::types::{PlayerResult, PlayerStats};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Number of independently locked shards of a `ReputationStore`
+const SHARDS: usize = 16;
+
+/// Human moderation verdict attached to a player after review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// A moderator confirmed the player was cheating (ban upheld)
+    ConfirmedCheater,
+    /// A moderator overturned the flag (player is legitimate)
+    Overturned,
+}
+
+impl Verdict {
+    /// Training label corresponding to this verdict (1.0 for cheater, 0.0 for legitimate)
+    pub fn label(self) -> f64 {
+        match self {
+            Verdict::ConfirmedCheater => 1.0,
+            Verdict::Overturned => 0.0,
+        }
+    }
+}
+
+/// Reputation record for a single player.
+#[derive(Debug, Clone)]
+pub struct PlayerReputation {
+    /// Unique identifier for the player
+    pub player_id: String,
+    /// Exponentially weighted moving average of the player's suspicion scores
+    pub score_ewma: f32,
+    /// Number of analyzed rounds recorded for this player
+    pub rounds: u32,
+    /// Raw stats of the most recent rounds, oldest first
+    pub history: Vec<PlayerStats>,
+    /// Moderator verdict, if the player has been reviewed
+    pub verdict: Option<Verdict>,
+}
+
+/// In-memory store tracking suspicion over time and moderator verdicts.
+///
+/// Every analyzed round is recorded together with its raw stats, so that once a
+/// moderator confirms or overturns a flag the stored rounds can be exported as
+/// labeled training data for the next `train_model` run.
+///
+/// # Concurrency
+///
+/// The store can be shared between request-handling threads (e.g. in an
+/// `Arc`): every method takes `&self`. Players are spread over independently
+/// locked shards, so threads recording different players rarely contend.
+/// Each `update` and `set_verdict` is atomic for its player, and concurrent
+/// updates of the same player are applied one after the other, so none is
+/// lost; their order is the order in which the threads acquire the lock.
+/// `len` and `export_labeled` lock one shard at a time and therefore see a
+/// consistent state per player, not a snapshot of the whole store.
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::reputation::{ReputationStore, Verdict};
+/// use nocheat::types::{PlayerResult, PlayerStats};
+/// use std::collections::HashMap;
+///
+/// let mut shots = HashMap::new();
+/// shots.insert("rifle".to_string(), 100);
+/// let mut hits = HashMap::new();
+/// hits.insert("rifle".to_string(), 95);
+///
+/// let stats = PlayerStats {
+///     player_id: "player123".to_string(),
+///     shots_fired: shots,
+///     hits: hits,
+///     headshots: 70,
+///     shot_timestamps_ms: None,
+///     training_label: None,
+///     ..Default::default()
+/// };
+/// let result = PlayerResult {
+///     player_id: "player123".to_string(),
+///     suspicion_score: 0.9,
+///     flags: vec!["HighHitRate".to_string()],
+///     ..Default::default()
+/// };
+///
+/// let store = ReputationStore::default();
+/// store.update(&stats, &result);
+/// store.set_verdict("player123", Verdict::ConfirmedCheater);
+///
+/// let (training_data, labels) = store.export_labeled();
+/// assert_eq!(training_data.len(), labels.len());
+/// ```
+#[derive(Debug)]
+pub struct ReputationStore {
+    alpha: f32,
+    max_history: usize,
+    shards: Vec<RwLock<HashMap<String, PlayerReputation>>>,
+}
+
+impl Default for ReputationStore {
+    fn default() -> Self {
+        Self::new(0.3, 20)
+    }
+}
+
+impl ReputationStore {
+    /// Create a new store.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - EWMA smoothing factor in (0, 1]; higher values favour recent rounds
+    /// * `max_history` - Maximum number of raw rounds kept per player
+    pub fn new(alpha: f32, max_history: usize) -> Self {
+        Self {
+            alpha: alpha.clamp(f32::EPSILON, 1.0),
+            max_history: max_history.max(1),
+            shards: (0..SHARDS).map(|_| RwLock::default()).collect(),
+        }
+    }
+
+    /// Shard holding a player
+    fn shard(&self, player_id: &str) -> &RwLock<HashMap<String, PlayerReputation>> {
+        let mut hasher = DefaultHasher::new();
+        player_id.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Lock a shard for reading; a panic of another thread while holding the
+    /// lock cannot leave a record half-written, so poisoning is ignored
+    fn read(
+        shard: &RwLock<HashMap<String, PlayerReputation>>,
+    ) -> RwLockReadGuard<'_, HashMap<String, PlayerReputation>> {
+        shard
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Lock a shard for writing, ignoring poisoning as in `read`
+    fn write(
+        shard: &RwLock<HashMap<String, PlayerReputation>>,
+    ) -> RwLockWriteGuard<'_, HashMap<String, PlayerReputation>> {
+        shard
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Record an analyzed round for a player, storing the raw stats alongside the score.
+    pub fn update(&self, stats: &PlayerStats, result: &PlayerResult) {
+        let alpha = self.alpha;
+        let max_history = self.max_history;
+        let mut players = Self::write(self.shard(&result.player_id));
+        let entry = players
+            .entry(result.player_id.clone())
+            .or_insert_with(|| PlayerReputation {
+                player_id: result.player_id.clone(),
+                score_ewma: result.suspicion_score,
+                rounds: 0,
+                history: Vec::new(),
+                verdict: None,
+            });
+
+        if entry.rounds > 0 {
+            entry.score_ewma = alpha * result.suspicion_score + (1.0 - alpha) * entry.score_ewma;
+        }
+        entry.rounds += 1;
+        entry.history.push(stats.clone());
+        if entry.history.len() > max_history {
+            let excess = entry.history.len() - max_history;
+            entry.history.drain(..excess);
+        }
+    }
+
+    /// Attach a moderator verdict to a player.
+    ///
+    /// Returns `false` if the player has never been recorded in the store.
+    pub fn set_verdict(&self, player_id: &str, verdict: Verdict) -> bool {
+        match Self::write(self.shard(player_id)).get_mut(player_id) {
+            Some(entry) => {
+                entry.verdict = Some(verdict);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Look up the reputation record of a player.
+    ///
+    /// Returns a copy, so the record does not change while it is inspected.
+    pub fn get(&self, player_id: &str) -> Option<PlayerReputation> {
+        Self::read(self.shard(player_id)).get(player_id).cloned()
+    }
+
+    /// Number of players tracked by the store
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| Self::read(shard).len())
+            .sum()
+    }
+
+    /// Whether the store tracks no players
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| Self::read(shard).is_empty())
+    }
+
+    /// Persist pending updates; call it on shutdown, as with `AuditSink::flush`.
+    ///
+    /// The store currently lives in memory only, so this always succeeds;
+    /// export the data to keep it across restarts.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Export every stored round of players with a moderator verdict as labeled training data.
+    ///
+    /// Players without a verdict are skipped. The output is ordered by player_id
+    /// (and by round within a player) so repeated exports are reproducible, and
+    /// can be passed straight to `train_model`.
+    pub fn export_labeled(&self) -> (Vec<PlayerStats>, Vec<f64>) {
+        let mut reviewed: Vec<PlayerReputation> = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                Self::read(shard)
+                    .values()
+                    .filter(|p| p.verdict.is_some())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        reviewed.sort_by(|a, b| a.player_id.cmp(&b.player_id));
+
+        let mut samples = Vec::new();
+        let mut labels = Vec::new();
+        for player in reviewed {
+            let label = player.verdict.map(Verdict::label).unwrap_or_default();
+            for stats in &player.history {
+                let mut sample = stats.clone();
+                sample.training_label = Some(label);
+                samples.push(sample);
+                labels.push(label);
+            }
+        }
+        (samples, labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_round(player_id: &str, hits: u32, headshots: u32) -> (PlayerStats, PlayerResult) {
+        let mut shots_fired = HashMap::new();
+        shots_fired.insert("rifle".to_string(), 100);
+        let mut hit_map = HashMap::new();
+        hit_map.insert("rifle".to_string(), hits);
+
+        let stats = PlayerStats {
+            player_id: player_id.to_string(),
+            shots_fired,
+            hits: hit_map,
+            headshots,
+            shot_timestamps_ms: None,
+            training_label: None,
+            ..Default::default()
+        };
+        let result = PlayerResult {
+            player_id: player_id.to_string(),
+            suspicion_score: hits as f32 / 100.0,
+            flags: vec![],
+            ..Default::default()
+        };
+        (stats, result)
+    }
+
+    #[test]
+    fn test_export_confirmed_cheater() {
+        let store = ReputationStore::default();
+        let (stats, result) = make_round("cheater", 95, 70);
+        store.update(&stats, &result);
+        let (stats, result) = make_round("unreviewed", 50, 10);
+        store.update(&stats, &result);
+
+        assert!(store.set_verdict("cheater", Verdict::ConfirmedCheater));
+
+        let (samples, labels) = store.export_labeled();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(labels, vec![1.0]);
+        assert_eq!(samples[0].player_id, "cheater");
+        assert_eq!(samples[0].training_label, Some(1.0));
+        assert_eq!(samples[0].headshots, 70);
+    }
+
+    #[test]
+    fn test_concurrent_updates_of_one_player() {
+        let store = std::sync::Arc::new(ReputationStore::new(0.05, 5));
+        let (stats, mut result) = make_round("player123", 0, 10);
+        store.update(&stats, &result);
+        result.suspicion_score = 1.0;
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let store = std::sync::Arc::clone(&store);
+                let (stats, result) = (stats.clone(), result.clone());
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        store.update(&stats, &result);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // Every score of 1.0 moves the average 5% closer to 1.0, in any order
+        let reputation = store.get("player123").unwrap();
+        assert_eq!(reputation.rounds, 81);
+        assert_eq!(reputation.history.len(), 5);
+        let expected = 1.0 - 0.95f32.powi(80);
+        assert!((reputation.score_ewma - expected).abs() < 1e-4);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_overturned_exports_clean_label() {
+        let store = ReputationStore::default();
+        let (stats, result) = make_round("pro_player", 90, 60);
+        store.update(&stats, &result);
+        store.update(&stats, &result);
+        store.set_verdict("pro_player", Verdict::Overturned);
+
+        let (samples, labels) = store.export_labeled();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(labels, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_verdict_for_unknown_player() {
+        let store = ReputationStore::default();
+        assert!(!store.set_verdict("ghost", Verdict::ConfirmedCheater));
+        assert!(store.export_labeled().0.is_empty());
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let store = ReputationStore::new(0.5, 3);
+        for hits in [10, 20, 30, 40, 50] {
+            let (stats, result) = make_round("player", hits, 5);
+            store.update(&stats, &result);
+        }
+
+        let record = store.get("player").unwrap();
+        assert_eq!(record.rounds, 5);
+        assert_eq!(record.history.len(), 3);
+        assert_eq!(*record.history[0].hits.get("rifle").unwrap(), 30);
+    }
+}
@@ -0,0 +1,211 @@
+//! Shadow deployment of a candidate model.
+//!
+//! A `ShadowAnalyzer` runs a candidate analyzer next to the live one on the
+//! same traffic. Only the live (primary) results are returned, so the
+//! candidate never affects a decision; its disagreements are written to an
+//! audit sink for review before it is promoted.
+
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::analyzer::Analyzer;
+use crate::audit::AuditSink;
+use crate::inference::VERDICT_THRESHOLD;
+use crate::model::DiffReport;
+use crate::types::{AnalysisResponse, PlayerResult, PlayerStats};
+
+/// Pair of analyzers where only `primary` acts on its results.
+///
+/// # Example
+///
+/// ```no_run
+/// use nocheat::analyzer::Analyzer;
+/// use nocheat::audit::JsonlAuditSink;
+/// use nocheat::shadow::ShadowAnalyzer;
+///
+/// let shadow = ShadowAnalyzer {
+///     primary: Analyzer::builder().model_path("models/cheat_model.bin").build().unwrap(),
+///     shadow: Analyzer::builder().model_path("models/cheat_model.next.bin").build().unwrap(),
+/// };
+/// let mut sink = JsonlAuditSink::open("audit/shadow.jsonl").expect("Failed to open audit log");
+///
+/// let outcome = shadow.analyze(&mut sink, vec![]).expect("Analysis failed");
+/// if let Ok(diff) = &outcome.shadow {
+///     println!("{} of {} verdicts would flip", diff.flipped(), diff.total);
+/// }
+/// let response = outcome.response; // act on the primary results only
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShadowAnalyzer {
+    /// Analyzer whose results are returned and acted upon
+    pub primary: Analyzer,
+    /// Candidate analyzer whose results are only recorded
+    pub shadow: Analyzer,
+}
+
+/// Results of `ShadowAnalyzer::analyze`
+#[derive(Debug)]
+pub struct ShadowResponse {
+    /// Results of the primary analyzer, unchanged
+    pub response: AnalysisResponse,
+    /// Verdict changes of the shadow analyzer relative to the primary one, or
+    /// the error of the shadow analyzer or the sink, which never fails the
+    /// primary analysis
+    pub shadow: Result<DiffReport>,
+}
+
+impl ShadowAnalyzer {
+    /// Analyze player statistics with both analyzers.
+    ///
+    /// Players are matched by `player_id`, so players missing from either
+    /// response (e.g. filtered by `only_flagged`) are not compared. The shadow
+    /// result of every player whose verdict (a score of at least 0.5) differs
+    /// from the primary one is recorded in `sink`; its `model_version` tells
+    /// which model produced it.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Destination of the disagreeing shadow results
+    /// * `stats` - A vector of PlayerStats structures containing data to analyze
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ShadowResponse>` - The primary results and the shadow outcome;
+    ///   an error only when the primary analysis fails
+    pub fn analyze(
+        &self,
+        sink: &mut dyn AuditSink,
+        stats: Vec<PlayerStats>,
+    ) -> Result<ShadowResponse> {
+        let shadow_response = self.shadow.analyze(stats.clone());
+        let response = self.primary.analyze(stats)?;
+        let shadow =
+            shadow_response.and_then(|shadow| record_disagreements(&response, &shadow, sink));
+        Ok(ShadowResponse { response, shadow })
+    }
+}
+
+/// Compare the shadow results with the primary ones, recording every flipped verdict
+fn record_disagreements(
+    primary: &AnalysisResponse,
+    shadow: &AnalysisResponse,
+    sink: &mut dyn AuditSink,
+) -> Result<DiffReport> {
+    let primary_results: HashMap<&str, &PlayerResult> = primary
+        .results
+        .iter()
+        .map(|result| (result.player_id.as_str(), result))
+        .collect();
+
+    let mut report = DiffReport {
+        total: 0,
+        clean_to_flagged: 0,
+        flagged_to_clean: 0,
+        mean_abs_score_change: 0.0,
+    };
+    let mut total_change = 0.0;
+    for shadow_result in &shadow.results {
+        let Some(primary_result) = primary_results.get(shadow_result.player_id.as_str()) else {
+            continue;
+        };
+        report.total += 1;
+        total_change += (shadow_result.suspicion_score - primary_result.suspicion_score).abs();
+
+        match (
+            primary_result.suspicion_score >= VERDICT_THRESHOLD,
+            shadow_result.suspicion_score >= VERDICT_THRESHOLD,
+        ) {
+            (false, true) => report.clean_to_flagged += 1,
+            (true, false) => report.flagged_to_clean += 1,
+            _ => continue,
+        }
+        sink.record(shadow_result)?;
+    }
+    if report.total > 0 {
+        report.mean_abs_score_change = total_change / report.total as f32;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::CheatModel;
+    use randomforest::criterion::Gini;
+    use randomforest::table::TableBuilder;
+    use randomforest::RandomForestClassifierOptions;
+    use std::collections::HashMap;
+
+    /// Model flagging every player whose hit rate is above `boundary`
+    fn model(boundary: f64, version: &str) -> CheatModel {
+        let mut table = TableBuilder::new();
+        for i in 0..20 {
+            let hit_rate = i as f64 / 20.0;
+            let label = if hit_rate > boundary { 1.0 } else { 0.0 };
+            table.add_row(&[hit_rate, 0.2], label).unwrap();
+        }
+        let forest = RandomForestClassifierOptions::new()
+            .seed(1)
+            .fit(Gini, table.build().unwrap());
+        let mut model = CheatModel::from_forest(forest);
+        model.metadata.version = version.to_string();
+        model
+    }
+
+    fn player(player_id: &str, hits: u32) -> PlayerStats {
+        PlayerStats {
+            player_id: player_id.to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), hits)]),
+            headshots: hits / 5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_primary_results_returned_and_shadow_disagreements_recorded() {
+        let primary = Analyzer::builder()
+            .model(model(0.9, "live"))
+            .build()
+            .unwrap();
+        let shadow = ShadowAnalyzer {
+            primary: primary.clone(),
+            shadow: Analyzer::builder()
+                .model(model(0.6, "next"))
+                .build()
+                .unwrap(),
+        };
+        let stats = vec![
+            player("clean", 40),
+            player("borderline", 75),
+            player("cheater", 97),
+        ];
+
+        let mut sink: Vec<PlayerResult> = Vec::new();
+        let outcome = shadow.analyze(&mut sink, stats.clone()).unwrap();
+
+        let expected = primary.analyze(stats).unwrap();
+        let scores = |response: &AnalysisResponse| {
+            response
+                .results
+                .iter()
+                .map(|r| {
+                    (
+                        r.player_id.clone(),
+                        r.suspicion_score,
+                        r.model_version.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(scores(&outcome.response), scores(&expected));
+
+        let diff = outcome.shadow.unwrap();
+        assert_eq!(diff.total, 3);
+        assert_eq!(diff.clean_to_flagged, 1);
+        assert_eq!(diff.flagged_to_clean, 0);
+        assert_eq!(sink.len(), 1);
+        assert_eq!(sink[0].player_id, "borderline");
+        assert_eq!(sink[0].model_version.as_deref(), Some("next"));
+    }
+}
@@ -1,5 +1,52 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::inference::VERDICT_THRESHOLD;
+use crate::options::{FlagEncoding, JsonOutput};
+
+/// Name of the JSON type of a value, for error messages
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
+
+/// Serialize a weapon map with its keys in sorted order.
+///
+/// `HashMap` iteration order is randomized per instance, so serializing it
+/// directly would make identical stats produce different JSON.
+fn serialize_sorted<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    let sorted: BTreeMap<&String, &V> = map.iter().collect();
+    sorted.serialize(serializer)
+}
+
+thread_local! {
+    /// Flag encoding of the `AnalysisResponse::to_json` call running on this thread
+    static FLAG_ENCODING: std::cell::Cell<FlagEncoding> =
+        const { std::cell::Cell::new(FlagEncoding::Names) };
+}
+
+/// Serialize the flags of a result as names, or as a bitmask inside
+/// `AnalysisResponse::to_json` with `FlagEncoding::Bitmask`
+fn serialize_flags<S: Serializer>(
+    flags: &[String],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match FLAG_ENCODING.with(|encoding| encoding.get()) {
+        FlagEncoding::Names => flags.serialize(serializer),
+        FlagEncoding::Bitmask => serializer.serialize_u32(Flag::bitmask(flags)),
+    }
+}
 
 /// Represents player statistics from a game round.
 ///
@@ -26,25 +73,644 @@ use std::collections::HashMap;
 ///     headshots: 10,
 ///     shot_timestamps_ms: None,
 ///     training_label: None,
+///     ..Default::default()
 /// };
 ///
 /// assert_eq!(player_stats.player_id, "player123");
 /// ```
-#[derive(Deserialize, Clone)]
+///
+/// When serialized, the weapon maps are written with their keys in sorted
+/// order so the same stats always produce byte-identical JSON.
+///
+/// When deserialized, unknown fields are ignored so that clients can add keys
+/// to their payloads without breaking analysis, while a missing `player_id`,
+/// `shots_fired`, `hits` or `headshots` is still an error.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct PlayerStats {
     /// Unique identifier for the player
     pub player_id: String,
     /// Number of shots fired per weapon type
+    #[serde(serialize_with = "serialize_sorted")]
     pub shots_fired: HashMap<String, u32>,
     /// Number of successful hits registered per weapon type
+    #[serde(serialize_with = "serialize_sorted")]
     pub hits: HashMap<String, u32>,
     /// Total number of headshots this round
     pub headshots: u32,
-    /// Optional raw shot timestamps in milliseconds (for timing analysis)
-    pub shot_timestamps_ms: Option<Vec<u64>>,
+    /// Optional number of headshots per weapon type, out of its `hits`.
+    ///
+    /// When present, the headshot rate check compares every weapon class
+    /// with its own threshold (see `Thresholds::headshot_rate_by_class`).
+    #[serde(
+        default,
+        serialize_with = "serialize_sorted",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub weapon_headshots: HashMap<String, u32>,
+    /// Optional raw shot timestamps in milliseconds (for timing analysis),
+    /// either one list for every shot or one list per weapon type
+    pub shot_timestamps_ms: Option<ShotTimestamps>,
+    /// Optional timestamps in milliseconds of every input (clicks, key presses,
+    /// commands), used for cadence analysis instead of `shot_timestamps_ms` when present
+    #[serde(default)]
+    pub input_timestamps_ms: Option<Vec<u64>>,
     /// Optional training label (1.0 for cheater, 0.0 for legitimate player)
     #[serde(default)]
     pub training_label: Option<f64>,
+    /// Optional confidence in `training_label`, in [0, 1] (defaults to 1.0).
+    ///
+    /// Used as the sample weight by `train_model_weighted`, so uncertain
+    /// labels (e.g. from unreviewed reports) influence the model less.
+    #[serde(default)]
+    pub label_confidence: Option<f64>,
+    /// Optional per-shot view angles (for aim snap detection)
+    #[serde(default)]
+    pub view_angles: Option<Vec<ViewAngleSample>>,
+    /// Optional categorical attributes of the round (e.g. map, game mode, region)
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    /// Optional identifier of the match (or session) the stats belong to.
+    ///
+    /// When present, aggregation and collusion detection only group records
+    /// sharing the same match_id.
+    #[serde(default)]
+    pub match_id: Option<String>,
+    /// Optional total damage dealt, for games that report it
+    #[serde(default)]
+    pub damage_dealt: Option<u32>,
+    /// Optional number of engagements in which the crosshair was already on
+    /// the enemy when it became visible
+    #[serde(default)]
+    pub preaimed_engagements: Option<u32>,
+    /// Optional total number of engagements (enemies becoming visible), the
+    /// denominator of `preaimed_engagements`
+    #[serde(default)]
+    pub total_engagements: Option<u32>,
+    /// Optional skill rating or rank of the player (e.g. MMR), used to compute
+    /// `PlayerResult::skill_adjusted_score`
+    #[serde(default)]
+    pub skill_rating: Option<f32>,
+    /// Optional path of a replay file holding every shot of the round, loaded
+    /// by `Analyzer::analyze_with_replays` relative to its replay root (see the
+    /// `replay` module for the format)
+    #[serde(default)]
+    pub replay_path: Option<String>,
+    /// Optional region the round was played in (e.g. "eu"), used to route the
+    /// player to a segment-specific model (see `options::Segment`)
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Optional platform of the player (e.g. "pc", "console"), used to route
+    /// the player to a segment-specific model (see `options::Segment`)
+    #[serde(default)]
+    pub platform: Option<String>,
+    /// Optional team of the player within its match, used to compare the
+    /// player with its teammates (see `AnalysisOptions::team_outlier_margin`)
+    #[serde(default)]
+    pub team: Option<String>,
+}
+
+impl PlayerStats {
+    /// Build player stats from an untyped JSON tree, for scripts and ETL jobs
+    /// that hold dynamic `serde_json::Value`s rather than typed records.
+    ///
+    /// Fields are read as in `serde_json::from_str`: unknown keys are
+    /// ignored and optional fields may be absent. A value of the wrong type
+    /// is reported together with the field holding it.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A JSON object with the fields of `PlayerStats`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PlayerStats>` - The stats, or an error naming the missing or
+    ///   mistyped field
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::PlayerStats;
+    /// use serde_json::json;
+    ///
+    /// let stats = PlayerStats::from_value(&json!({
+    ///     "player_id": "player123",
+    ///     "shots_fired": {"rifle": 100},
+    ///     "hits": {"rifle": 50},
+    ///     "headshots": 10,
+    /// }))
+    /// .unwrap();
+    /// assert_eq!(stats.hits["rifle"], 50);
+    ///
+    /// let error = PlayerStats::from_value(&json!({
+    ///     "player_id": "player123",
+    ///     "shots_fired": {"rifle": 100},
+    ///     "hits": {"rifle": 50},
+    ///     "headshots": "ten",
+    /// }))
+    /// .unwrap_err();
+    /// assert!(error.to_string().starts_with("field `headshots`"));
+    /// ```
+    pub fn from_value(value: &serde_json::Value) -> Result<Self> {
+        let Some(object) = value.as_object() else {
+            return Err(anyhow::anyhow!(
+                "player stats must be a JSON object, got {}",
+                json_type(value)
+            ));
+        };
+        Self::deserialize(value).map_err(|error| {
+            // serde does not say which field failed, so check each one on
+            // its own against otherwise default stats
+            let Ok(serde_json::Value::Object(defaults)) = serde_json::to_value(Self::default())
+            else {
+                return error.into();
+            };
+            for (field, field_value) in object {
+                let mut single = defaults.clone();
+                single.insert(field.clone(), field_value.clone());
+                if let Err(field_error) = Self::deserialize(&serde_json::Value::Object(single)) {
+                    return anyhow::anyhow!("field `{}`: {}", field, field_error);
+                }
+            }
+            error.into()
+        })
+    }
+
+    /// Accumulate a partial record of the same player into this one.
+    ///
+    /// Useful for streaming pipelines that receive a player's stats in several
+    /// messages (e.g. one per weapon). Merging is purely additive: per-weapon
+    /// shots and hits are added, headshots, damage and engagements are summed
+    /// (saturating at `u32::MAX`), and shot timestamps,
+    /// input timestamps and view-angle samples of `other` are appended after
+    /// those of `self`. The player_id, match_id, training_label and attributes
+    /// of `self` are kept unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The partial record to add
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::PlayerStats;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut stats = PlayerStats {
+    ///     player_id: "player123".to_string(),
+    ///     shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+    ///     hits: HashMap::from([("rifle".to_string(), 50)]),
+    ///     headshots: 10,
+    ///     ..Default::default()
+    /// };
+    /// let pistol = PlayerStats {
+    ///     player_id: "player123".to_string(),
+    ///     shots_fired: HashMap::from([("pistol".to_string(), 20)]),
+    ///     hits: HashMap::from([("pistol".to_string(), 15)]),
+    ///     headshots: 5,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// stats.merge_in(&pistol);
+    /// assert_eq!(stats.shots_fired["pistol"], 20);
+    /// assert_eq!(stats.headshots, 15);
+    /// ```
+    pub fn merge_in(&mut self, other: &PlayerStats) {
+        // Counts saturate at u32::MAX rather than overflowing
+        fn add(total: &mut u32, count: u32) {
+            *total = total.saturating_add(count);
+        }
+        for (weapon, count) in &other.shots_fired {
+            add(self.shots_fired.entry(weapon.clone()).or_insert(0), *count);
+        }
+        for (weapon, count) in &other.hits {
+            add(self.hits.entry(weapon.clone()).or_insert(0), *count);
+        }
+        add(&mut self.headshots, other.headshots);
+        for (weapon, count) in &other.weapon_headshots {
+            add(
+                self.weapon_headshots.entry(weapon.clone()).or_insert(0),
+                *count,
+            );
+        }
+        if let Some(damage) = other.damage_dealt {
+            add(self.damage_dealt.get_or_insert(0), damage);
+        }
+        if let Some(count) = other.preaimed_engagements {
+            add(self.preaimed_engagements.get_or_insert(0), count);
+        }
+        if let Some(count) = other.total_engagements {
+            add(self.total_engagements.get_or_insert(0), count);
+        }
+        if let Some(timestamps) = &other.shot_timestamps_ms {
+            match &mut self.shot_timestamps_ms {
+                Some(mine) => mine.extend(timestamps),
+                None => self.shot_timestamps_ms = Some(timestamps.clone()),
+            }
+        }
+        if let Some(timestamps) = &other.input_timestamps_ms {
+            self.input_timestamps_ms
+                .get_or_insert_with(Vec::new)
+                .extend(timestamps);
+        }
+        if let Some(samples) = &other.view_angles {
+            self.view_angles
+                .get_or_insert_with(Vec::new)
+                .extend(samples);
+        }
+    }
+
+    /// Sum several rounds of the same player into one record.
+    ///
+    /// Rounds are combined with `merge_in` in order, so per-weapon counts and
+    /// headshots are added and timestamps and view-angle samples are
+    /// concatenated in round order. The player_id, match_id and attributes of
+    /// the last round are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::PlayerStats;
+    ///
+    /// let rounds: Vec<PlayerStats> = (1..=3)
+    ///     .map(|round| PlayerStats {
+    ///         player_id: "player123".to_string(),
+    ///         headshots: round,
+    ///         ..Default::default()
+    ///     })
+    ///     .collect();
+    /// let total = PlayerStats::aggregate(&rounds);
+    /// assert_eq!(total.player_id, "player123");
+    /// assert_eq!(total.headshots, 6);
+    /// ```
+    pub fn aggregate(rounds: &[PlayerStats]) -> PlayerStats {
+        let mut total = PlayerStats {
+            player_id: rounds
+                .last()
+                .map(|r| r.player_id.clone())
+                .unwrap_or_default(),
+            attributes: rounds
+                .last()
+                .map(|r| r.attributes.clone())
+                .unwrap_or_default(),
+            match_id: rounds.last().and_then(|r| r.match_id.clone()),
+            ..Default::default()
+        };
+
+        for round in rounds {
+            total.merge_in(round);
+        }
+        total
+    }
+
+    /// Merge weapons whose names only differ in case or surrounding whitespace.
+    ///
+    /// Every weapon name of `shots_fired`, `hits` and per-weapon
+    /// `shot_timestamps_ms` is replaced by its `normalize_weapon` form, summing
+    /// the counts (and merging the timestamps) of names that collapse into one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::PlayerStats;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut stats = PlayerStats {
+    ///     shots_fired: HashMap::from([("AWP".to_string(), 10), ("awp".to_string(), 5)]),
+    ///     ..Default::default()
+    /// };
+    /// stats.normalize_weapons();
+    /// assert_eq!(stats.shots_fired, HashMap::from([("awp".to_string(), 15)]));
+    /// ```
+    pub fn normalize_weapons(&mut self) {
+        fn merge_counts(counts: HashMap<String, u32>) -> HashMap<String, u32> {
+            let mut merged = HashMap::with_capacity(counts.len());
+            for (weapon, count) in counts {
+                let total = merged.entry(normalize_weapon(&weapon)).or_insert(0u32);
+                *total = total.saturating_add(count);
+            }
+            merged
+        }
+
+        self.shots_fired = merge_counts(std::mem::take(&mut self.shots_fired));
+        self.hits = merge_counts(std::mem::take(&mut self.hits));
+        self.weapon_headshots = merge_counts(std::mem::take(&mut self.weapon_headshots));
+        if let Some(ShotTimestamps::PerWeapon(weapons)) = &mut self.shot_timestamps_ms {
+            let mut merged: HashMap<String, Vec<u64>> = HashMap::with_capacity(weapons.len());
+            for (weapon, timestamps) in std::mem::take(weapons) {
+                merged
+                    .entry(normalize_weapon(&weapon))
+                    .or_default()
+                    .extend(timestamps);
+            }
+            for timestamps in merged.values_mut() {
+                timestamps.sort_unstable();
+            }
+            *weapons = merged;
+        }
+    }
+
+    /// Number of distinct weapons in `shots_fired`, `hits` and `weapon_headshots`
+    pub fn weapon_count(&self) -> usize {
+        self.shots_fired.len()
+            + self
+                .hits
+                .keys()
+                .filter(|weapon| !self.shots_fired.contains_key(*weapon))
+                .count()
+            + self
+                .weapon_headshots
+                .keys()
+                .filter(|weapon| {
+                    !self.shots_fired.contains_key(*weapon) && !self.hits.contains_key(*weapon)
+                })
+                .count()
+    }
+}
+
+impl TryFrom<&serde_json::Value> for PlayerStats {
+    type Error = anyhow::Error;
+
+    /// See `PlayerStats::from_value`
+    fn try_from(value: &serde_json::Value) -> Result<Self> {
+        Self::from_value(value)
+    }
+}
+
+/// Canonical form of a weapon name: trimmed and lowercased, so that "AWP",
+/// "awp" and " Awp " name the same weapon
+pub fn normalize_weapon(weapon: &str) -> String {
+    weapon.trim().to_lowercase()
+}
+
+/// Crosshair orientation recorded when a shot was fired.
+///
+/// Samples are expected in chronological order. Angles are in degrees.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ViewAngleSample {
+    /// Time of the sample in milliseconds
+    pub timestamp_ms: u64,
+    /// Horizontal view angle in degrees
+    pub yaw: f32,
+    /// Vertical view angle in degrees
+    pub pitch: f32,
+    /// Whether the shot fired at this sample registered a hit
+    #[serde(default)]
+    pub hit: bool,
+}
+
+/// Shot timestamps in milliseconds, for timing analysis.
+///
+/// Deserializes from either a flat list (`[100, 250, 400]`, the original
+/// format) or a map from weapon type to that weapon's timestamps
+/// (`{"rifle": [100, 400], "sniper": [250]}`). Only the per-weapon form lets
+/// timing features tell a slow bolt-action from a fast SMG.
+///
+/// # Example
+///
+/// ```
+/// use nocheat::types::ShotTimestamps;
+///
+/// let flat: ShotTimestamps = serde_json::from_str("[100, 250, 400]").unwrap();
+/// assert_eq!(flat.per_weapon(), None);
+///
+/// let per_weapon: ShotTimestamps =
+///     serde_json::from_str(r#"{"rifle": [100, 400], "sniper": [250]}"#).unwrap();
+/// assert_eq!(per_weapon.all().as_ref(), &[100, 250, 400]);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ShotTimestamps {
+    /// Timestamps of every shot, whatever the weapon
+    Flat(Vec<u64>),
+    /// Timestamps of the shots of each weapon type
+    PerWeapon(#[serde(serialize_with = "serialize_sorted")] HashMap<String, Vec<u64>>),
+}
+
+impl ShotTimestamps {
+    /// Every timestamp; per-weapon timestamps are merged in chronological order
+    pub fn all(&self) -> Cow<'_, [u64]> {
+        match self {
+            ShotTimestamps::Flat(timestamps) => Cow::Borrowed(timestamps),
+            ShotTimestamps::PerWeapon(weapons) => {
+                let mut all: Vec<u64> = weapons.values().flatten().copied().collect();
+                all.sort_unstable();
+                Cow::Owned(all)
+            }
+        }
+    }
+
+    /// The timestamps of each weapon type, or `None` for a flat list
+    pub fn per_weapon(&self) -> Option<&HashMap<String, Vec<u64>>> {
+        match self {
+            ShotTimestamps::Flat(_) => None,
+            ShotTimestamps::PerWeapon(weapons) => Some(weapons),
+        }
+    }
+
+    /// Append the timestamps of `other`, keeping them per weapon when both are
+    /// per weapon and merging them into one chronological list otherwise
+    pub fn extend(&mut self, other: &ShotTimestamps) {
+        match (&mut *self, other) {
+            (ShotTimestamps::Flat(mine), ShotTimestamps::Flat(theirs)) => mine.extend(theirs),
+            (ShotTimestamps::PerWeapon(mine), ShotTimestamps::PerWeapon(theirs)) => {
+                for (weapon, timestamps) in theirs {
+                    mine.entry(weapon.clone()).or_default().extend(timestamps);
+                }
+            }
+            _ => {
+                let mut all = self.all().into_owned();
+                all.extend(other.all().iter());
+                all.sort_unstable();
+                *self = ShotTimestamps::Flat(all);
+            }
+        }
+    }
+}
+
+impl From<Vec<u64>> for ShotTimestamps {
+    fn from(timestamps: Vec<u64>) -> Self {
+        ShotTimestamps::Flat(timestamps)
+    }
+}
+
+/// Flag raised by the built-in analysis.
+///
+/// `PlayerResult::flags` stores flags by name so results stay plain JSON;
+/// `as_str` gives that name and `Flag::from_name` parses it back.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Flag {
+    /// Hit rate above `Thresholds::high_hit_rate`
+    HighHitRate,
+    /// Headshot rate of a weapon class above its threshold, for players
+    /// reporting `weapon_headshots`; the heuristic fallback also checks the
+    /// overall rate against `Thresholds::high_headshot_rate`
+    HighHeadshotRate,
+    /// Repeated humanly-implausible view snaps right before hits
+    AimSnap,
+    /// Input intervals too regular to be human
+    RoboticCadence,
+    /// Damage per shot above `Thresholds::high_damage_per_shot`
+    HighDamageEfficiency,
+    /// Pre-aimed engagement ratio above `Thresholds::high_preaim_ratio`
+    PreAim,
+    /// More hits than shots fired
+    ImpossibleHitRate,
+    /// Hit rate of a weapon far above that weapon's learned baseline
+    WeaponHitRateOutlier,
+    /// A feature far from the player's own history (see `baseline::analyze_vs_self`)
+    DeviationFromSelf,
+    /// The training label disagrees with the model's verdict
+    LabelMismatch,
+    /// The player is whitelisted and was not scored
+    Whitelisted,
+    /// The player was scored by the heuristics because no model was available
+    HeuristicFallback,
+    /// Suspicion score far above the mean of the player's teammates (see
+    /// `AnalysisOptions::team_outlier_margin`)
+    TeamOutlier,
+}
+
+impl Flag {
+    /// Every built-in flag
+    pub const ALL: [Flag; 13] = [
+        Flag::HighHitRate,
+        Flag::HighHeadshotRate,
+        Flag::AimSnap,
+        Flag::RoboticCadence,
+        Flag::HighDamageEfficiency,
+        Flag::PreAim,
+        Flag::ImpossibleHitRate,
+        Flag::WeaponHitRateOutlier,
+        Flag::DeviationFromSelf,
+        Flag::LabelMismatch,
+        Flag::Whitelisted,
+        Flag::HeuristicFallback,
+        Flag::TeamOutlier,
+    ];
+
+    /// Name of the flag as stored in `PlayerResult::flags`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Flag::HighHitRate => "HighHitRate",
+            Flag::HighHeadshotRate => "HighHeadshotRate",
+            Flag::AimSnap => "AimSnap",
+            Flag::RoboticCadence => "RoboticCadence",
+            Flag::HighDamageEfficiency => "HighDamageEfficiency",
+            Flag::PreAim => "PreAim",
+            Flag::ImpossibleHitRate => "ImpossibleHitRate",
+            Flag::WeaponHitRateOutlier => "WeaponHitRateOutlier",
+            Flag::DeviationFromSelf => "DeviationFromSelf",
+            Flag::LabelMismatch => "LabelMismatch",
+            Flag::Whitelisted => "Whitelisted",
+            Flag::HeuristicFallback => "HeuristicFallback",
+            Flag::TeamOutlier => "TeamOutlier",
+        }
+    }
+
+    /// Bit of the flag in a flag bitmask (see `FlagEncoding::Bitmask`).
+    ///
+    /// The bit is the position of the flag in `Flag::ALL`, which is stable:
+    /// new flags are only ever appended, so existing bits never move.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::Flag;
+    ///
+    /// assert_eq!(Flag::HighHitRate.bit(), 1);
+    /// assert_eq!(Flag::AimSnap.bit(), 1 << 2);
+    /// ```
+    pub fn bit(self) -> u32 {
+        let position = Flag::ALL.iter().position(|&flag| flag == self);
+        1 << position.expect("every flag is listed in Flag::ALL")
+    }
+
+    /// Bitmask of the built-in flags among `names`, ignoring any other name
+    pub fn bitmask(names: &[String]) -> u32 {
+        names
+            .iter()
+            .filter_map(|name| Flag::from_name(name))
+            .fold(0, |mask, flag| mask | flag.bit())
+    }
+
+    /// Parse a flag name, returning `None` for flags not built into the crate
+    pub fn from_name(name: &str) -> Option<Flag> {
+        Flag::ALL.into_iter().find(|flag| flag.as_str() == name)
+    }
+
+    /// Whether the flag only describes how a player was analyzed rather than suspicious behavior
+    pub fn is_informational(self) -> bool {
+        matches!(self, Flag::Whitelisted | Flag::HeuristicFallback)
+    }
+}
+
+impl std::fmt::Display for Flag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why a flag fired: the measured value and the threshold it crossed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FlagDetail {
+    /// The flag that fired
+    pub flag: Flag,
+    /// Value measured for the player, such as the hit rate for `HighHitRate`
+    pub value: f32,
+    /// Threshold the value crossed
+    pub threshold: f32,
+    /// Human-readable explanation for moderators
+    pub message: String,
+}
+
+impl FlagDetail {
+    /// Create the detail of a fired flag
+    pub fn new(flag: Flag, value: f32, threshold: f32, message: impl Into<String>) -> Self {
+        Self {
+            flag,
+            value,
+            threshold,
+            message: message.into(),
+        }
+    }
+}
+
+/// Coarse band of a suspicion score, see `PlayerResult::severity`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Score below 0.5
+    Low,
+    /// Score from 0.5 up to 0.8
+    Medium,
+    /// Score of 0.8 or more
+    High,
+}
+
+/// Three-way outcome of an analysis, reported in `PlayerResult::verdict`.
+///
+/// Not to be confused with `reputation::Verdict`, the verdict of a human
+/// moderator after review.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Assessment {
+    /// Enough data and no cheating predicted
+    #[default]
+    Clean,
+    /// Enough data and cheating predicted (`predicted_label` is true)
+    Suspicious,
+    /// Too little data for the score to mean anything, e.g. fewer shots than
+    /// `AnalysisOptions::min_shots`
+    Insufficient,
+}
+
+impl Assessment {
+    /// Name of the assessment, as serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Assessment::Clean => "Clean",
+            Assessment::Suspicious => "Suspicious",
+            Assessment::Insufficient => "Insufficient",
+        }
+    }
 }
 
 /// Analysis result for a single player.
@@ -61,19 +727,146 @@ pub struct PlayerStats {
 ///     player_id: "player123".to_string(),
 ///     suspicion_score: 0.75,
 ///     flags: vec!["HighHeadshotRatio".to_string()],
+///     ..Default::default()
 /// };
 ///
 /// assert!(result.suspicion_score > 0.7);
 /// assert!(result.flags.contains(&"HighHeadshotRatio".to_string()));
 /// ```
-#[derive(Serialize, Debug, PartialEq)]
+#[derive(Serialize, Clone, Debug, PartialEq, Default)]
 pub struct PlayerResult {
     /// Unique identifier for the player (same as in PlayerStats)
     pub player_id: String,
     /// Score between 0.0 and 1.0 indicating likelihood of cheating
     pub suspicion_score: f32,
+    /// Hard verdict: `suspicion_score >= AnalysisOptions::label_threshold`
+    /// (true for cheaters); always false for whitelisted players
+    pub predicted_label: bool,
+    /// `Insufficient` when the player is below the minimum-sample gate, so a
+    /// low score is not mistaken for a verified clean player; otherwise
+    /// `Suspicious` or `Clean` following `predicted_label`
+    pub verdict: Assessment,
     /// List of flags indicating specific suspicious behaviors
+    #[serde(serialize_with = "serialize_flags")]
     pub flags: Vec<String>,
+    /// Measured value and threshold of every flag backed by a measurement;
+    /// informational flags have no detail
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flag_details: Vec<FlagDetail>,
+    /// Weapons whose hit rate drove the `HighHitRate` flag, most to least,
+    /// with their part of the hit rate above the threshold (see
+    /// `inference::weapon_contributions`); empty when the flag did not fire
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub top_weapons: Vec<(String, f32)>,
+    /// Time of the analysis in milliseconds since the Unix epoch (for audit trails)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analyzed_at: Option<u64>,
+    /// Version of the model that produced the score (for audit trails)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_version: Option<String>,
+    /// File the player's stats were read from, set by `analyze_archive`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Share of tree votes per class label, only set for multi-class models
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_probabilities: Option<BTreeMap<String, f64>>,
+    /// Signed distance from the decision boundary (see `CheatModel::predict_margin`),
+    /// only set when `AnalysisOptions::include_margin` is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin: Option<f64>,
+    /// Suspicion score divided by the performance expected at the player's
+    /// `skill_rating` (see `AnalysisOptions::skill_curve`), only set for
+    /// scored players reporting a rating
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skill_adjusted_score: Option<f32>,
+}
+
+impl PlayerResult {
+    /// Result of a player with the given score and flags.
+    ///
+    /// `predicted_label` and `verdict` follow the score at the default
+    /// threshold of 0.5; every other field keeps its default. Handy for tests
+    /// and custom analyzers that would otherwise spell out every field.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::{Flag, PlayerResult, Assessment};
+    ///
+    /// let result = PlayerResult::new("player123", 0.9, vec![Flag::AimSnap]);
+    /// assert!(result.predicted_label);
+    /// assert_eq!(result.verdict, Assessment::Suspicious);
+    /// assert!(result.has_flag(Flag::AimSnap));
+    /// ```
+    pub fn new(player_id: impl Into<String>, suspicion_score: f32, flags: Vec<Flag>) -> Self {
+        let predicted_label = suspicion_score >= VERDICT_THRESHOLD;
+        Self {
+            player_id: player_id.into(),
+            suspicion_score,
+            predicted_label,
+            verdict: if predicted_label {
+                Assessment::Suspicious
+            } else {
+                Assessment::Clean
+            },
+            flags: flags.iter().map(|flag| flag.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Result of a clean player: a score of 0.0 and no flags
+    pub fn clean(player_id: impl Into<String>) -> Self {
+        Self::new(player_id, 0.0, Vec::new())
+    }
+
+    /// Whether the player needs attention: a suspicious-behavior flag or a score of 0.5 or more.
+    ///
+    /// Informational flags (`Whitelisted`, `HeuristicFallback`) do not count.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::{Flag, PlayerResult, Severity};
+    ///
+    /// let result = PlayerResult {
+    ///     player_id: "player123".to_string(),
+    ///     suspicion_score: 0.3,
+    ///     flags: vec![Flag::AimSnap.to_string()],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert!(result.is_flagged());
+    /// assert!(result.has_flag(Flag::AimSnap));
+    /// assert_eq!(result.severity(), Severity::Low);
+    /// ```
+    pub fn is_flagged(&self) -> bool {
+        self.suspicion_score >= VERDICT_THRESHOLD
+            || self
+                .flags
+                .iter()
+                .any(|name| !Flag::from_name(name).is_some_and(Flag::is_informational))
+    }
+
+    /// Band of the suspicion score: `High` from 0.8, `Medium` from 0.5, `Low` below
+    pub fn severity(&self) -> Severity {
+        if self.suspicion_score >= 0.8 {
+            Severity::High
+        } else if self.suspicion_score >= 0.5 {
+            Severity::Medium
+        } else {
+            Severity::Low
+        }
+    }
+
+    /// Whether the result carries the given flag
+    pub fn has_flag(&self, flag: Flag) -> bool {
+        self.flags.iter().any(|name| name == flag.as_str())
+    }
+
+    /// Detail of the given flag, if it fired with a measurement
+    pub fn flag_detail(&self, flag: Flag) -> Option<&FlagDetail> {
+        self.flag_details.iter().find(|detail| detail.flag == flag)
+    }
 }
 
 /// Response wrapper containing analysis results for multiple players.
@@ -89,11 +882,13 @@ pub struct PlayerResult {
 ///             player_id: "player123".to_string(),
 ///             suspicion_score: 0.75,
 ///             flags: vec!["HighHeadshotRatio".to_string()],
+///             ..Default::default()
 ///         },
 ///         PlayerResult {
 ///             player_id: "player456".to_string(),
 ///             suspicion_score: 0.2,
 ///             flags: vec![],
+///             ..Default::default()
 ///         }
 ///     ],
 /// };
@@ -101,15 +896,473 @@ pub struct PlayerResult {
 /// assert_eq!(response.results.len(), 2);
 /// assert!(response.results[0].suspicion_score > response.results[1].suspicion_score);
 /// ```
+///
+/// The JSON output is schema-stable: fields are always written in declaration
+/// order, results and flags keep their order, any map-valued field is written
+/// with sorted keys, and numbers are formatted independently of the system
+/// locale. The same response therefore always serializes to identical bytes.
 #[derive(Serialize, Debug, PartialEq)]
 pub struct AnalysisResponse {
     /// List of analysis results for all players
     pub results: Vec<PlayerResult>,
 }
 
+impl AnalysisResponse {
+    /// Combine two partial responses, e.g. from shards analyzing different player subsets.
+    ///
+    /// Results of `self` come first, followed by those of `other`. Duplicate
+    /// player_ids are kept as-is; use `try_merge` to reject them instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::{AnalysisResponse, PlayerResult};
+    ///
+    /// let shard_a = AnalysisResponse {
+    ///     results: vec![PlayerResult::new("player123", 0.75, vec![])],
+    /// };
+    /// let shard_b = AnalysisResponse {
+    ///     results: vec![PlayerResult::clean("player456")],
+    /// };
+    ///
+    /// let merged = shard_a.merge(shard_b);
+    /// assert_eq!(merged.results.len(), 2);
+    /// ```
+    pub fn merge(mut self, other: AnalysisResponse) -> AnalysisResponse {
+        self.results.extend(other.results);
+        self
+    }
+
+    /// Combine two partial responses, failing if a player_id appears in both
+    pub fn try_merge(self, other: AnalysisResponse) -> Result<AnalysisResponse> {
+        Self::try_merge_all(vec![self, other])
+    }
+
+    /// Serialize the response to JSON with the given formatting.
+    ///
+    /// With `FlagEncoding::Bitmask` the flags of every result are written as
+    /// one integer (see `Flag::bit` for the bit of each flag) instead of an
+    /// array of names, which saves bandwidth on high-volume outputs, and
+    /// `pretty` indents the JSON for debugging. The default output is
+    /// identical to `serde_json::to_vec`.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Formatting of the JSON
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>>` - The serialized response
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::options::{FlagEncoding, JsonOutput};
+    /// use nocheat::types::{AnalysisResponse, PlayerResult};
+    ///
+    /// let response = AnalysisResponse {
+    ///     results: vec![PlayerResult {
+    ///         flags: vec!["HighHitRate".to_string()],
+    ///         ..Default::default()
+    ///     }],
+    /// };
+    /// let output = JsonOutput {
+    ///     flags: FlagEncoding::Bitmask,
+    ///     ..Default::default()
+    /// };
+    /// let json = String::from_utf8(response.to_json(&output).unwrap()).unwrap();
+    /// assert!(json.contains("\"flags\":1"));
+    /// ```
+    pub fn to_json(&self, output: &JsonOutput) -> Result<Vec<u8>> {
+        let previous = FLAG_ENCODING.with(|encoding| encoding.replace(output.flags));
+        let json = if output.pretty {
+            serde_json::to_vec_pretty(self)
+        } else {
+            serde_json::to_vec(self)
+        };
+        FLAG_ENCODING.with(|encoding| encoding.set(previous));
+        json.map_err(|e| anyhow::anyhow!("Failed to serialize response: {}", e))
+    }
+
+    /// Order the results from most to least suspicious.
+    ///
+    /// The order is fully deterministic: players with equal scores are ordered
+    /// by player_id (lexicographically), and NaN scores are placed according
+    /// to `f32::total_cmp`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::{AnalysisResponse, PlayerResult};
+    ///
+    /// let result = |player_id: &str, score: f32| PlayerResult {
+    ///     player_id: player_id.to_string(),
+    ///     suspicion_score: score,
+    ///     ..Default::default()
+    /// };
+    /// let response = AnalysisResponse {
+    ///     results: vec![result("b", 0.5), result("c", 0.9), result("a", 0.5)],
+    /// };
+    ///
+    /// let ids: Vec<_> = response
+    ///     .sorted_by_suspicion()
+    ///     .results
+    ///     .into_iter()
+    ///     .map(|r| r.player_id)
+    ///     .collect();
+    /// assert_eq!(ids, vec!["c", "a", "b"]);
+    /// ```
+    pub fn sorted_by_suspicion(mut self) -> AnalysisResponse {
+        self.results.sort_by(|a, b| {
+            b.suspicion_score
+                .total_cmp(&a.suspicion_score)
+                .then_with(|| a.player_id.cmp(&b.player_id))
+        });
+        self
+    }
+
+    /// Concatenate any number of partial responses in order
+    pub fn merge_all(responses: Vec<AnalysisResponse>) -> AnalysisResponse {
+        responses.into_iter().fold(
+            AnalysisResponse { results: vec![] },
+            AnalysisResponse::merge,
+        )
+    }
+
+    /// Concatenate any number of partial responses, failing on the first duplicate player_id
+    pub fn try_merge_all(responses: Vec<AnalysisResponse>) -> Result<AnalysisResponse> {
+        let merged = Self::merge_all(responses);
+        let mut seen = HashSet::new();
+        for result in &merged.results {
+            if !seen.insert(result.player_id.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "Duplicate player_id in merged responses: {}",
+                    result.player_id
+                ));
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Number of occurrences of every built-in flag across all results.
+    ///
+    /// Flags not built into the crate are skipped; flags that never fired are absent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::{AnalysisResponse, Flag, PlayerResult};
+    ///
+    /// let result = |flags: &[Flag]| PlayerResult {
+    ///     flags: flags.iter().map(|f| f.to_string()).collect(),
+    ///     ..Default::default()
+    /// };
+    /// let response = AnalysisResponse {
+    ///     results: vec![result(&[Flag::HighHitRate, Flag::AimSnap]), result(&[Flag::HighHitRate])],
+    /// };
+    ///
+    /// let histogram = response.flag_histogram();
+    /// assert_eq!(histogram[&Flag::HighHitRate], 2);
+    /// assert_eq!(histogram[&Flag::AimSnap], 1);
+    /// ```
+    pub fn flag_histogram(&self) -> HashMap<Flag, usize> {
+        let mut histogram = HashMap::new();
+        for flag in self
+            .results
+            .iter()
+            .flat_map(|result| &result.flags)
+            .filter_map(|name| Flag::from_name(name))
+        {
+            *histogram.entry(flag).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// `flag_histogram` with Laplace noise, safe to share across organizations.
+    ///
+    /// Each player counts at most once per flag, so one player changes the
+    /// histogram by at most `Flag::ALL.len()` in total; noise of scale
+    /// `Flag::ALL.len() / epsilon` is added to every built-in flag, including
+    /// the ones that never fired, which makes the release
+    /// `noise.epsilon`-differentially private. Noisy counts are rounded and
+    /// floored at zero, which spends no extra budget.
+    ///
+    /// # Arguments
+    ///
+    /// * `noise` - Privacy budget and seed of the noise
+    ///
+    /// # Returns
+    ///
+    /// * `Result<HashMap<Flag, usize>>` - A noisy count for every built-in
+    ///   flag, or an error when `epsilon` is not a positive number
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::options::LaplaceNoise;
+    /// use nocheat::types::{AnalysisResponse, Flag};
+    ///
+    /// let response = AnalysisResponse { results: vec![] };
+    /// let histogram = response.noisy_flag_histogram(&LaplaceNoise::default()).unwrap();
+    /// assert_eq!(histogram.len(), Flag::ALL.len());
+    /// ```
+    pub fn noisy_flag_histogram(
+        &self,
+        noise: &crate::options::LaplaceNoise,
+    ) -> Result<HashMap<Flag, usize>> {
+        if !(noise.epsilon > 0.0 && noise.epsilon.is_finite()) {
+            return Err(anyhow::anyhow!(
+                "epsilon must be a positive number, got {}",
+                noise.epsilon
+            ));
+        }
+        let seed = noise.seed.unwrap_or_else(|| {
+            use std::hash::{BuildHasher, Hasher};
+            std::collections::hash_map::RandomState::new()
+                .build_hasher()
+                .finish()
+        });
+        let mut rng = crate::SplitMix64(seed);
+        let scale = Flag::ALL.len() as f64 / noise.epsilon;
+
+        let mut counts: HashMap<Flag, usize> = HashMap::new();
+        for result in &self.results {
+            let flags: HashSet<Flag> = result
+                .flags
+                .iter()
+                .filter_map(|name| Flag::from_name(name))
+                .collect();
+            for flag in flags {
+                *counts.entry(flag).or_insert(0) += 1;
+            }
+        }
+        // Draw in a fixed flag order so a seed always gives the same noise
+        Ok(Flag::ALL
+            .iter()
+            .map(|&flag| {
+                let count = counts.get(&flag).copied().unwrap_or(0);
+                // Inverse CDF of the Laplace distribution
+                let u = rng.unit() - 0.5;
+                let laplace = -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln();
+                let noisy = (count as f64 + laplace).round().max(0.0);
+                (flag, noisy as usize)
+            })
+            .collect())
+    }
+
+    /// Players whose suspicion score reaches each of `thresholds`.
+    ///
+    /// Lets operators compare moderation thresholds on the same batch without
+    /// scoring it again. Levels keep the order of `thresholds`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use nocheat::types::{AnalysisResponse, PlayerResult};
+    ///
+    /// let result = |player_id: &str, suspicion_score: f32| PlayerResult {
+    ///     player_id: player_id.to_string(),
+    ///     suspicion_score,
+    ///     ..Default::default()
+    /// };
+    /// let response = AnalysisResponse {
+    ///     results: vec![result("a", 0.95), result("b", 0.6), result("c", 0.1)],
+    /// };
+    ///
+    /// let sweep = response.sweep(&[0.5, 0.9]);
+    /// assert_eq!(sweep.levels[0].flagged_count, 2);
+    /// assert_eq!(sweep.levels[1].flagged_players, ["a"]);
+    /// ```
+    pub fn sweep(&self, thresholds: &[f32]) -> SweepResult {
+        let levels = thresholds
+            .iter()
+            .map(|&threshold| {
+                let flagged_players: Vec<String> = self
+                    .results
+                    .iter()
+                    .filter(|result| result.suspicion_score >= threshold)
+                    .map(|result| result.player_id.clone())
+                    .collect();
+                SweepLevel {
+                    threshold,
+                    flagged_count: flagged_players.len(),
+                    flagged_players,
+                }
+            })
+            .collect();
+        SweepResult { levels }
+    }
+}
+
+/// Players flagged at several suspicion thresholds, see `AnalysisResponse::sweep`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SweepResult {
+    /// One level per threshold, in the order the thresholds were given
+    pub levels: Vec<SweepLevel>,
+}
+
+/// Players flagged at one suspicion threshold
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SweepLevel {
+    /// Minimum suspicion score to be flagged
+    pub threshold: f32,
+    /// Number of players with a score of at least `threshold`
+    pub flagged_count: usize,
+    /// Ids of those players, in result order
+    pub flagged_players: Vec<String>,
+}
+
+/// Entry of a JSON batch that could not be parsed as `PlayerStats`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EntryError {
+    /// Position of the entry in the input array
+    pub index: usize,
+    /// Why the entry was rejected
+    pub error: String,
+}
+
+/// Results of a lenient analysis, see `Analyzer::analyze_json_lenient`
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct LenientResponse {
+    /// Results of the valid entries, in input order
+    pub results: Vec<PlayerResult>,
+    /// Entries that were skipped, in input order
+    pub failed: Vec<EntryError>,
+}
+
+/// Wall-clock time spent in each stage of an analysis, in milliseconds.
+///
+/// Returned by `Analyzer::analyze_timed`. `total_ms` covers the whole call, so
+/// it is slightly above the sum of the stages. Without the `dataframe`
+/// feature, and for players scored by the heuristic fallback, features are
+/// computed per player and `build_ms` is 0.0; the fallback reports its whole
+/// scoring time as `inference_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Metrics {
+    /// Building the player DataFrame
+    pub build_ms: f64,
+    /// Computing the engineered features
+    pub feature_ms: f64,
+    /// Scoring the players and evaluating their flags
+    pub inference_ms: f64,
+    /// The whole analysis
+    pub total_ms: f64,
+}
+
+impl Metrics {
+    /// Milliseconds elapsed since `start`
+    pub(crate) fn elapsed_ms(start: std::time::Instant) -> f64 {
+        start.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+/// Outcome of an analysis that can be cancelled by the caller
+#[derive(Debug, PartialEq)]
+pub enum AnalysisOutcome {
+    /// Every player was analyzed
+    Completed(AnalysisResponse),
+    /// The analysis was cancelled; holds the results of the chunks finished before
+    Cancelled(AnalysisResponse),
+}
+
+impl AnalysisOutcome {
+    /// Whether the analysis stopped before every player was analyzed
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, AnalysisOutcome::Cancelled(_))
+    }
+
+    /// The complete or partial response
+    pub fn into_response(self) -> AnalysisResponse {
+        match self {
+            AnalysisOutcome::Completed(response) | AnalysisOutcome::Cancelled(response) => response,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_stats_from_dynamic_value() {
+        let stats = PlayerStats::from_value(&json!({
+            "player_id": "player123",
+            "shots_fired": {"rifle": 100, "pistol": 20},
+            "hits": {"rifle": 50, "pistol": 15},
+            "headshots": 10,
+            "team": "blue",
+            "engine_build": "5.3.2",
+        }))
+        .unwrap();
+        assert_eq!(stats.player_id, "player123");
+        assert_eq!(stats.shots_fired["pistol"], 20);
+        assert_eq!(stats.headshots, 10);
+        assert_eq!(stats.team.as_deref(), Some("blue"));
+
+        let error = PlayerStats::from_value(&json!({
+            "player_id": "player123",
+            "shots_fired": {"rifle": 100},
+            "hits": {"rifle": "fifty"},
+            "headshots": 10,
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "field `hits`: invalid type: string \"fifty\", expected u32"
+        );
+
+        let error = PlayerStats::try_from(&json!(["player123"])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "player stats must be a JSON object, got an array"
+        );
+    }
+
+    #[test]
+    fn test_clean_result_has_no_flags() {
+        let clean = PlayerResult::clean("player123");
+        assert_eq!(clean.player_id, "player123");
+        assert_eq!(clean.suspicion_score, 0.0);
+        assert!(clean.flags.is_empty());
+        assert_eq!(clean.verdict, Assessment::Clean);
+        assert!(!clean.is_flagged());
+
+        let flagged = PlayerResult::new("player456", 0.4, vec![Flag::PreAim]);
+        assert_eq!(flagged.flags, vec!["PreAim".to_string()]);
+        assert!(!flagged.predicted_label);
+        assert!(flagged.is_flagged());
+    }
+
+    #[test]
+    fn test_flags_serialize_as_bitmask() {
+        let response = AnalysisResponse {
+            results: vec![PlayerResult {
+                player_id: "player123".to_string(),
+                flags: vec![
+                    "HighHitRate".to_string(),
+                    "RoboticCadence".to_string(),
+                    "HeuristicFallback".to_string(),
+                    "CustomRule".to_string(),
+                ],
+                ..Default::default()
+            }],
+        };
+
+        let names = response.to_json(&JsonOutput::default()).unwrap();
+        assert_eq!(names, serde_json::to_vec(&response).unwrap());
+
+        let output = JsonOutput {
+            flags: FlagEncoding::Bitmask,
+            ..Default::default()
+        };
+        let json: serde_json::Value =
+            serde_json::from_slice(&response.to_json(&output).unwrap()).unwrap();
+        // Bits 0, 3 and 11; the custom flag has no bit
+        assert_eq!(json["results"][0]["flags"], 0b1000_0000_1001);
+        // Plain serialization is unaffected afterwards
+        assert_eq!(serde_json::to_vec(&response).unwrap(), names);
+    }
 
     #[test]
     fn test_player_stats_creation() {
@@ -124,17 +1377,80 @@ mod tests {
         let stats = PlayerStats {
             player_id: "player123".to_string(),
             shots_fired: shots,
-            hits: hits,
+            hits,
             headshots: 10,
-            shot_timestamps_ms: Some(vec![100, 200, 300]),
+            shot_timestamps_ms: Some(vec![100, 200, 300].into()),
             training_label: None,
+            ..Default::default()
         };
 
         assert_eq!(stats.player_id, "player123");
         assert_eq!(*stats.shots_fired.get("rifle").unwrap(), 100);
         assert_eq!(*stats.hits.get("pistol").unwrap(), 15);
         assert_eq!(stats.headshots, 10);
-        assert_eq!(stats.shot_timestamps_ms.unwrap().len(), 3);
+        assert_eq!(stats.shot_timestamps_ms.unwrap().all().len(), 3);
+    }
+
+    #[test]
+    fn test_merge_partial_records() {
+        let mut stats = PlayerStats {
+            player_id: "player123".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+            hits: HashMap::from([("rifle".to_string(), 50)]),
+            headshots: 10,
+            shot_timestamps_ms: Some(vec![100, 200].into()),
+            ..Default::default()
+        };
+        stats.merge_in(&PlayerStats {
+            player_id: "player123".to_string(),
+            shots_fired: HashMap::from([("rifle".to_string(), 30), ("pistol".to_string(), 20)]),
+            hits: HashMap::from([("rifle".to_string(), 10), ("pistol".to_string(), 15)]),
+            headshots: 5,
+            shot_timestamps_ms: Some(vec![300].into()),
+            ..Default::default()
+        });
+
+        assert_eq!(stats.player_id, "player123");
+        assert_eq!(stats.shots_fired["rifle"], 130);
+        assert_eq!(stats.shots_fired["pistol"], 20);
+        assert_eq!(stats.hits["rifle"], 60);
+        assert_eq!(stats.hits["pistol"], 15);
+        assert_eq!(stats.headshots, 15);
+        assert_eq!(stats.shot_timestamps_ms, Some(vec![100, 200, 300].into()));
+        assert_eq!(stats.view_angles, None);
+    }
+
+    #[test]
+    fn test_merge_saturates_counts() {
+        let near_max = u32::MAX - 5;
+        let mut stats = PlayerStats {
+            shots_fired: HashMap::from([("rifle".to_string(), near_max)]),
+            hits: HashMap::from([("rifle".to_string(), near_max)]),
+            headshots: near_max,
+            weapon_headshots: HashMap::from([("rifle".to_string(), near_max)]),
+            damage_dealt: Some(near_max),
+            preaimed_engagements: Some(near_max),
+            total_engagements: Some(near_max),
+            ..Default::default()
+        };
+        stats.merge_in(&PlayerStats {
+            shots_fired: HashMap::from([("rifle".to_string(), 10)]),
+            hits: HashMap::from([("rifle".to_string(), 10)]),
+            headshots: 10,
+            weapon_headshots: HashMap::from([("rifle".to_string(), 10)]),
+            damage_dealt: Some(10),
+            preaimed_engagements: Some(10),
+            total_engagements: Some(10),
+            ..Default::default()
+        });
+
+        assert_eq!(stats.shots_fired["rifle"], u32::MAX);
+        assert_eq!(stats.hits["rifle"], u32::MAX);
+        assert_eq!(stats.headshots, u32::MAX);
+        assert_eq!(stats.weapon_headshots["rifle"], u32::MAX);
+        assert_eq!(stats.damage_dealt, Some(u32::MAX));
+        assert_eq!(stats.preaimed_engagements, Some(u32::MAX));
+        assert_eq!(stats.total_engagements, Some(u32::MAX));
     }
 
     #[test]
@@ -143,6 +1459,7 @@ mod tests {
             player_id: "player123".to_string(),
             suspicion_score: 0.75,
             flags: vec!["HighHeadshotRatio".to_string(), "AimSnap".to_string()],
+            ..Default::default()
         };
 
         assert_eq!(result.player_id, "player123");
@@ -151,6 +1468,179 @@ mod tests {
         assert!(result.flags.contains(&"HighHeadshotRatio".to_string()));
     }
 
+    #[test]
+    fn test_noisy_flag_histogram_stays_near_true_counts() {
+        let results = (0..1000)
+            .map(|i| PlayerResult {
+                player_id: format!("player{}", i),
+                flags: if i % 4 == 0 {
+                    vec![Flag::HighHitRate.to_string(), Flag::HighHitRate.to_string()]
+                } else {
+                    vec![]
+                },
+                ..Default::default()
+            })
+            .collect();
+        let response = AnalysisResponse { results };
+        let noise = |seed| crate::options::LaplaceNoise {
+            epsilon: 1.0,
+            seed: Some(seed),
+        };
+
+        // Noise scale is 12 at epsilon 1.0; 120 is ten scales away
+        let mut total = 0;
+        for seed in 0..200 {
+            let histogram = response.noisy_flag_histogram(&noise(seed)).unwrap();
+            assert_eq!(histogram.len(), Flag::ALL.len());
+            assert!(histogram[&Flag::HighHitRate].abs_diff(250) < 120);
+            assert!(histogram[&Flag::AimSnap] < 120);
+            total += histogram[&Flag::HighHitRate];
+        }
+        let mean = total as f64 / 200.0;
+        assert!((mean - 250.0).abs() < 5.0, "mean {}", mean);
+        assert_eq!(
+            response.noisy_flag_histogram(&noise(7)).unwrap(),
+            response.noisy_flag_histogram(&noise(7)).unwrap()
+        );
+        assert_ne!(
+            response.noisy_flag_histogram(&noise(7)).unwrap()[&Flag::HighHitRate],
+            250
+        );
+
+        let invalid = crate::options::LaplaceNoise {
+            epsilon: 0.0,
+            seed: None,
+        };
+        assert!(response.noisy_flag_histogram(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_weapon_names_merge_after_normalization() {
+        let mut stats = PlayerStats {
+            shots_fired: HashMap::from([
+                ("AWP".to_string(), 10),
+                ("awp".to_string(), 5),
+                ("Rifle".to_string(), 20),
+            ]),
+            hits: HashMap::from([("Awp ".to_string(), 4), ("awp".to_string(), 3)]),
+            shot_timestamps_ms: Some(ShotTimestamps::PerWeapon(HashMap::from([
+                ("AWP".to_string(), vec![100, 300]),
+                ("awp".to_string(), vec![200]),
+            ]))),
+            ..Default::default()
+        };
+        assert_eq!(stats.weapon_count(), 4);
+
+        stats.normalize_weapons();
+        assert_eq!(stats.weapon_count(), 2);
+        assert_eq!(stats.shots_fired["awp"], 15);
+        assert_eq!(stats.shots_fired["rifle"], 20);
+        assert_eq!(stats.hits, HashMap::from([("awp".to_string(), 7)]));
+        let timestamps = stats
+            .shot_timestamps_ms
+            .as_ref()
+            .unwrap()
+            .per_weapon()
+            .unwrap();
+        assert_eq!(timestamps["awp"], [100, 200, 300]);
+    }
+
+    #[test]
+    fn test_result_severity_bands() {
+        let result = |score: f32, flags: &[Flag]| PlayerResult {
+            player_id: "player123".to_string(),
+            suspicion_score: score,
+            flags: flags.iter().map(Flag::to_string).collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(result(0.0, &[]).severity(), Severity::Low);
+        assert_eq!(result(0.4999, &[]).severity(), Severity::Low);
+        assert_eq!(result(0.5, &[]).severity(), Severity::Medium);
+        assert_eq!(result(0.7999, &[]).severity(), Severity::Medium);
+        assert_eq!(result(0.8, &[]).severity(), Severity::High);
+        assert_eq!(result(1.0, &[]).severity(), Severity::High);
+
+        assert!(!result(0.4999, &[]).is_flagged());
+        assert!(result(0.5, &[]).is_flagged());
+        assert!(result(0.1, &[Flag::AimSnap]).is_flagged());
+        assert!(!result(0.0, &[Flag::Whitelisted]).is_flagged());
+        assert!(
+            result(0.1, &[Flag::HeuristicFallback, Flag::HighHitRate]).has_flag(Flag::HighHitRate)
+        );
+        assert!(!result(0.9, &[]).has_flag(Flag::HighHitRate));
+
+        for flag in Flag::ALL {
+            assert_eq!(Flag::from_name(flag.as_str()), Some(flag));
+        }
+    }
+
+    #[test]
+    fn test_sorted_by_suspicion_breaks_ties_by_player_id() {
+        let result = |player_id: &str, score: f32| PlayerResult {
+            player_id: player_id.to_string(),
+            suspicion_score: score,
+            ..Default::default()
+        };
+        let ids = |results: Vec<PlayerResult>| {
+            AnalysisResponse { results }
+                .sorted_by_suspicion()
+                .results
+                .into_iter()
+                .map(|r| r.player_id)
+                .collect::<Vec<_>>()
+        };
+
+        let expected = vec!["top", "alpha", "bravo", "charlie", "low"];
+        assert_eq!(
+            ids(vec![
+                result("charlie", 0.5),
+                result("low", 0.1),
+                result("alpha", 0.5),
+                result("top", 0.9),
+                result("bravo", 0.5),
+            ]),
+            expected
+        );
+        assert_eq!(
+            ids(vec![
+                result("bravo", 0.5),
+                result("top", 0.9),
+                result("alpha", 0.5),
+                result("low", 0.1),
+                result("charlie", 0.5),
+            ]),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_flag_histogram_counts_batch_flags() {
+        let result = |flags: &[&str]| PlayerResult {
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        };
+        let response = AnalysisResponse {
+            results: vec![
+                result(&["HighHitRate", "RoboticCadence"]),
+                result(&["HighHitRate"]),
+                result(&[]),
+                result(&["HighHitRate", "CustomRule"]),
+                result(&["RoboticCadence", "Whitelisted"]),
+            ],
+        };
+
+        let histogram = response.flag_histogram();
+        assert_eq!(
+            histogram,
+            HashMap::from([
+                (Flag::HighHitRate, 3),
+                (Flag::RoboticCadence, 2),
+                (Flag::Whitelisted, 1),
+            ])
+        );
+    }
+
     #[test]
     fn test_analysis_response_creation() {
         let response = AnalysisResponse {
@@ -159,11 +1649,13 @@ mod tests {
                     player_id: "player123".to_string(),
                     suspicion_score: 0.75,
                     flags: vec!["HighHeadshotRatio".to_string()],
+                    ..Default::default()
                 },
                 PlayerResult {
                     player_id: "player456".to_string(),
                     suspicion_score: 0.2,
                     flags: vec![],
+                    ..Default::default()
                 },
             ],
         };
@@ -172,4 +1664,129 @@ mod tests {
         assert_eq!(response.results[0].player_id, "player123");
         assert_eq!(response.results[1].player_id, "player456");
     }
+
+    fn make_result(player_id: &str, score: f32) -> PlayerResult {
+        PlayerResult {
+            player_id: player_id.to_string(),
+            suspicion_score: score,
+            flags: vec![],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_responses() {
+        let a = AnalysisResponse {
+            results: vec![make_result("p1", 0.1), make_result("p2", 0.9)],
+        };
+        let b = AnalysisResponse {
+            results: vec![make_result("p3", 0.5)],
+        };
+
+        let merged = a.try_merge(b).expect("No duplicates expected");
+        assert_eq!(merged.results.len(), 3);
+        assert_eq!(merged.results[2].player_id, "p3");
+
+        let all = AnalysisResponse::merge_all(vec![
+            merged,
+            AnalysisResponse { results: vec![] },
+            AnalysisResponse {
+                results: vec![make_result("p4", 0.0)],
+            },
+        ]);
+        assert_eq!(all.results.len(), 4);
+    }
+
+    #[test]
+    fn test_merge_detects_duplicates() {
+        let a = AnalysisResponse {
+            results: vec![make_result("p1", 0.1)],
+        };
+        let b = AnalysisResponse {
+            results: vec![make_result("p1", 0.8)],
+        };
+
+        let err = a.try_merge(b).unwrap_err();
+        assert!(err.to_string().contains("p1"));
+
+        // The lenient merge keeps both entries
+        let a = AnalysisResponse {
+            results: vec![make_result("p1", 0.1)],
+        };
+        let b = AnalysisResponse {
+            results: vec![make_result("p1", 0.8)],
+        };
+        assert_eq!(a.merge(b).results.len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored_but_required_fields_enforced() {
+        let stats: PlayerStats = serde_json::from_str(
+            r#"{"player_id":"p1","shots_fired":{"rifle":100},"hits":{"rifle":50},"headshots":10,"map_name":"dust2"}"#,
+        )
+        .unwrap();
+        assert_eq!(stats.player_id, "p1");
+        assert_eq!(stats.hits["rifle"], 50);
+
+        let error = serde_json::from_str::<PlayerStats>(
+            r#"{"player_id":"p1","shots_fired":{"rifle":100},"hits":{"rifle":50},"map_name":"dust2"}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("missing field `headshots`"));
+    }
+
+    #[test]
+    fn test_response_serialization_is_stable() {
+        let make_response = || AnalysisResponse {
+            results: vec![
+                PlayerResult {
+                    player_id: "player123".to_string(),
+                    suspicion_score: 0.75,
+                    flags: vec!["HighHitRate".to_string(), "AimSnap".to_string()],
+                    ..Default::default()
+                },
+                make_result("player456", 0.2),
+            ],
+        };
+
+        let first = serde_json::to_vec(&make_response()).unwrap();
+        let second = serde_json::to_vec(&make_response()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            String::from_utf8(first).unwrap(),
+            r#"{"results":[{"player_id":"player123","suspicion_score":0.75,"predicted_label":false,"verdict":"Clean","flags":["HighHitRate","AimSnap"]},{"player_id":"player456","suspicion_score":0.2,"predicted_label":false,"verdict":"Clean","flags":[]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_player_stats_serialization_sorts_weapons() {
+        let make_stats = |weapons: &[&str]| {
+            let mut shots = HashMap::new();
+            let mut hits = HashMap::new();
+            for w in weapons {
+                shots.insert(w.to_string(), 10);
+                hits.insert(w.to_string(), 5);
+            }
+            PlayerStats {
+                player_id: "player".to_string(),
+                shots_fired: shots,
+                hits,
+                headshots: 1,
+                shot_timestamps_ms: None,
+                training_label: None,
+                ..Default::default()
+            }
+        };
+
+        // Same content inserted in different orders into separately seeded maps
+        let a = make_stats(&["sniper", "pistol", "rifle", "smg", "shotgun", "knife"]);
+        let b = make_stats(&["knife", "shotgun", "smg", "rifle", "pistol", "sniper"]);
+
+        let json_a = serde_json::to_string(&a).unwrap();
+        let json_b = serde_json::to_string(&b).unwrap();
+        assert_eq!(json_a, json_b);
+        assert!(
+            json_a.starts_with(r#"{"player_id":"player","shots_fired":{"knife":10,"pistol":10"#)
+        );
+    }
 }
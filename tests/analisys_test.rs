@@ -1,9 +1,13 @@
 use nocheat::types::PlayerStats;
-use nocheat::{analyze_stats, build_dataframe, df_to_ndarray, generate_default_model, train_model};
+use nocheat::{analyze_stats, generate_default_model, train_model};
+#[cfg(feature = "dataframe")]
+use nocheat::{build_dataframe, df_to_ndarray};
+#[cfg(feature = "dataframe")]
 use polars::prelude::{col, DataType, IntoLazy};
 use std::collections::HashMap;
 use std::fs;
 
+#[cfg(feature = "dataframe")]
 fn make_dummy_stats() -> Vec<PlayerStats> {
     let mut shots = HashMap::new();
     shots.insert("rifle".to_string(), 100);
@@ -17,9 +21,11 @@ fn make_dummy_stats() -> Vec<PlayerStats> {
         headshots: 10,
         shot_timestamps_ms: None,
         training_label: None,
+        ..Default::default()
     }]
 }
 
+#[cfg(feature = "dataframe")]
 #[test]
 fn test_build_dataframe() {
     let stats = make_dummy_stats();
@@ -33,6 +39,7 @@ fn test_build_dataframe() {
     );
 }
 
+#[cfg(feature = "dataframe")]
 #[test]
 fn test_df_to_ndarray() {
     let stats = make_dummy_stats();
@@ -87,10 +94,11 @@ fn test_training_workflow() {
         training_data.push(PlayerStats {
             player_id: format!("normal_{}", i),
             shots_fired: shots,
-            hits: hits,
+            hits,
             headshots,
             shot_timestamps_ms: None,
             training_label: Some(0.0),
+            ..Default::default()
         });
 
         labels.push(0.0); // Not a cheater
@@ -116,10 +124,11 @@ fn test_training_workflow() {
         training_data.push(PlayerStats {
             player_id: format!("cheater_{}", i),
             shots_fired: shots,
-            hits: hits,
+            hits,
             headshots,
             shot_timestamps_ms: None,
             training_label: Some(1.0),
+            ..Default::default()
         });
 
         labels.push(1.0); // Labeled as a cheater
@@ -148,6 +157,7 @@ fn test_training_workflow() {
         headshots: 10, // 20% headshot ratio
         shot_timestamps_ms: None,
         training_label: None,
+        ..Default::default()
     };
 
     let mut test_suspicious = HashMap::new();
@@ -162,6 +172,7 @@ fn test_training_workflow() {
         headshots: 70, // 78% headshot ratio
         shot_timestamps_ms: None,
         training_label: None,
+        ..Default::default()
     };
 
     // Save the original model file path if it exists, so we can restore it after the test
@@ -257,10 +268,11 @@ fn test_generate_default_model() {
     let suspicious_player = PlayerStats {
         player_id: "suspicious".to_string(),
         shots_fired: shots,
-        hits: hits,
+        hits,
         headshots: 80, // 84% headshot ratio (very suspicious)
         shot_timestamps_ms: None,
         training_label: None,
+        ..Default::default()
     };
 
     // Save the original model file path if it exists, so we can restore it after the test
@@ -315,3 +327,47 @@ fn test_generate_default_model() {
     // Remove the test model file
     let _ = fs::remove_file(&model_path);
 }
+
+#[test]
+fn test_score_command_prints_flagged_players() {
+    let temp_dir = std::env::temp_dir();
+    let model_path = temp_dir.join("test_score_model.bin");
+    let stats_path = temp_dir.join("test_score_stats.json");
+    generate_default_model(model_path.to_str().unwrap()).expect("Failed to generate model");
+
+    let player = |player_id: &str, hit_count: u32, headshots: u32| PlayerStats {
+        player_id: player_id.to_string(),
+        shots_fired: HashMap::from([("rifle".to_string(), 100)]),
+        hits: HashMap::from([("rifle".to_string(), hit_count)]),
+        headshots,
+        ..Default::default()
+    };
+    let stats = vec![player("clean", 50, 8), player("cheater", 95, 80)];
+    fs::write(&stats_path, serde_json::to_string(&stats).unwrap()).unwrap();
+
+    let score = |extra: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_train"))
+            .arg("score")
+            .arg(&model_path)
+            .arg(&stats_path)
+            .args(extra)
+            .output()
+            .expect("Failed to run the score command")
+    };
+
+    // The cheater exceeds the default threshold
+    let output = score(&[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "stdout: {}", stdout);
+    assert!(stdout.contains("cheater"));
+    assert!(stdout.contains("HighHitRate"));
+    assert!(!stdout.contains("clean"));
+    assert!(stdout.contains("1 of 2 players flagged"));
+
+    // No score exceeds 1.0
+    let output = score(&["--threshold", "1.0"]);
+    assert!(output.status.success());
+
+    let _ = fs::remove_file(&model_path);
+    let _ = fs::remove_file(&stats_path);
+}